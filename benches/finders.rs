@@ -0,0 +1,37 @@
+//! The set finders and the partition solver on the trays of rummikub::testing, one group per
+//! scenario. Only the search is measured: the sets found are dropped without being printed.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rummikub::testing::{duplicates_fixture, long_run_fixture, partition_fixtures, valid_sets_fixtures};
+use rummikub::{best_partition, find_runs, find_same_numbers, valid_sets};
+
+fn valid_sets_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("valid_sets");
+    for fixture in valid_sets_fixtures() {
+        group.bench_function(fixture.name, |b| b.iter(|| valid_sets(black_box(&fixture.tray)).len()));
+    }
+    group.finish();
+}
+
+fn find_runs_bench(c: &mut Criterion) {
+    let fixture = long_run_fixture();
+    c.benchmark_group("find_runs")
+        .bench_function(fixture.name, |b| b.iter(|| find_runs(black_box(&fixture.tray)).len()));
+}
+
+fn find_same_numbers_bench(c: &mut Criterion) {
+    let fixture = duplicates_fixture();
+    c.benchmark_group("find_same_numbers")
+        .bench_function(fixture.name, |b| b.iter(|| find_same_numbers(black_box(&fixture.tray)).len()));
+}
+
+fn best_partition_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("best_partition");
+    for fixture in partition_fixtures() {
+        group.bench_function(fixture.name, |b| b.iter(|| best_partition(black_box(&fixture.tray))));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, valid_sets_bench, find_runs_bench, find_same_numbers_bench, best_partition_bench);
+criterion_main!(benches);
@@ -0,0 +1,50 @@
+/*
+ * C API of the rummikub crate, see src/ffi.rs.
+ *
+ * Every function returns a status, RUMMIKUB_OK (0) on success. After a failure,
+ * rummikub_last_error() describes what went wrong on the calling thread.
+ *
+ * Ownership:
+ *  - a tray from rummikub_parse_tray belongs to the caller until rummikub_tray_free;
+ *  - a string from rummikub_valid_sets belongs to the caller until rummikub_free;
+ *  - the string from rummikub_last_error belongs to the library; it stays valid as long as
+ *    the thread but the next call on the thread changes it.
+ * The free functions accept NULL and return RUMMIKUB_UNKNOWN_POINTER, instead of crashing,
+ * for pointers already freed or not made by this library.
+ */
+
+#ifndef RUMMIKUB_H
+#define RUMMIKUB_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum RummikubStatus {
+    RUMMIKUB_OK = 0,
+    RUMMIKUB_NULL_POINTER = 1,
+    RUMMIKUB_INVALID_UTF8 = 2,
+    RUMMIKUB_INVALID_TILE = 3,
+    RUMMIKUB_UNKNOWN_POINTER = 4,
+    RUMMIKUB_PANIC = 5
+} RummikubStatus;
+
+typedef struct RummikubTray RummikubTray;
+
+/* Reads a tray from tile codes like "B2 B3 B4 R7 W". On failure *tray_out is set to NULL. */
+RummikubStatus rummikub_parse_tray(const char *tiles, RummikubTray **tray_out);
+
+/* Stores in *json_out the valid sets of the tray as a NUL-terminated JSON string. */
+RummikubStatus rummikub_valid_sets(const RummikubTray *tray, char **json_out);
+
+RummikubStatus rummikub_tray_free(RummikubTray *tray);
+
+RummikubStatus rummikub_free(char *ptr);
+
+const char *rummikub_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,283 @@
+//! C API for embedding the set finder, declared in include/rummikub.h.
+//!
+//! Every function returns a [`RummikubStatus`] instead of panicking, and the message of the
+//! last error on the calling thread is available from [`rummikub_last_error`].
+//! Ownership is explicit: trays made by [`rummikub_parse_tray`] belong to the caller until
+//! passed to [`rummikub_tray_free`], and strings made by [`rummikub_valid_sets`] until passed
+//! to [`rummikub_free`]. Both free functions ignore NULL and refuse pointers that this library
+//! didn't hand out or that were already freed, so a double free is an error, not a crash.
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::{Mutex, MutexGuard};
+use crate::{sets_to_json, valid_sets, Card};
+
+/// Result of every call, 0 meaning success
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RummikubStatus {
+    Ok = 0,
+    NullPointer = 1,
+    InvalidUtf8 = 2,
+    InvalidTile = 3,
+    // The pointer was already freed or doesn't come from this library
+    UnknownPointer = 4,
+    Panic = 5
+}
+
+/// A tray read by rummikub_parse_tray, opaque on the C side
+pub struct RummikubTray {
+    cards: Vec<Card>
+}
+
+// Addresses of the trays and of the strings handed out and not freed yet
+static LIVE_TRAYS: Mutex<BTreeSet<usize>> = Mutex::new(BTreeSet::new());
+static LIVE_STRINGS: Mutex<BTreeSet<usize>> = Mutex::new(BTreeSet::new());
+
+// Longest error message kept, in bytes, longer ones are cut
+const ERROR_LEN: usize = 255;
+
+thread_local! {
+    // Overwritten in place so the pointers given by rummikub_last_error never dangle
+    static LAST_ERROR: RefCell<[u8; ERROR_LEN + 1]> = const { RefCell::new([0; ERROR_LEN + 1]) };
+}
+
+fn live(pointers: &'static Mutex<BTreeSet<usize>>) -> MutexGuard<'static, BTreeSet<usize>> {
+    // A panic while holding the lock can't leave the set half updated
+    pointers.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn set_last_error(message: &str) {
+    let mut len = message.len().min(ERROR_LEN);
+    while !message.is_char_boundary(len) {
+        len -= 1;
+    }
+    LAST_ERROR.with(|e| {
+        let mut buffer = e.borrow_mut();
+        buffer[..len].copy_from_slice(&message.as_bytes()[..len]);
+        buffer[len] = 0;
+    });
+}
+
+// Runs body, catching its panics and recording the error message of failures
+fn guard(body: impl FnOnce() -> Result<(), (RummikubStatus, String)>) -> RummikubStatus {
+    let result = panic::catch_unwind(AssertUnwindSafe(body))
+        .unwrap_or_else(|_| Err((RummikubStatus::Panic, "unexpected panic in the solver".to_string())));
+    match result {
+        Ok(()) => {
+            set_last_error("");
+            RummikubStatus::Ok
+        },
+        Err((status, message)) => {
+            set_last_error(&message);
+            status
+        }
+    }
+}
+
+fn null_pointer(name: &str) -> (RummikubStatus, String) {
+    (RummikubStatus::NullPointer, format!("{} is NULL", name))
+}
+
+/// Reads a tray from a NUL-terminated string of tile codes like "B2 B3 B4 R7 W" and stores
+/// a new tray in `*tray_out`, to be freed with rummikub_tray_free.
+///
+/// # Safety
+///
+/// `tiles` must be NULL or a NUL-terminated string and `tray_out` NULL or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rummikub_parse_tray(tiles: *const c_char, tray_out: *mut *mut RummikubTray) -> RummikubStatus {
+    guard(|| {
+        if tray_out.is_null() {
+            return Err(null_pointer("tray_out"));
+        }
+        *tray_out = ptr::null_mut();
+        if tiles.is_null() {
+            return Err(null_pointer("tiles"));
+        }
+
+        let tiles = CStr::from_ptr(tiles).to_str()
+            .map_err(|e| (RummikubStatus::InvalidUtf8, e.to_string()))?;
+        let cards = tiles.split_whitespace()
+            .map(|token| token.parse::<Card>())
+            .collect::<Result<Vec<Card>, _>>()
+            .map_err(|e| (RummikubStatus::InvalidTile, e.to_string()))?;
+
+        let tray = Box::into_raw(Box::new(RummikubTray { cards }));
+        live(&LIVE_TRAYS).insert(tray as usize);
+        *tray_out = tray;
+        Ok(())
+    })
+}
+
+/// Stores in `*json_out` the valid sets of a tray, as the NUL-terminated JSON written by
+/// sets_to_json, to be freed with rummikub_free.
+///
+/// # Safety
+///
+/// `tray` must be NULL or a pointer given by rummikub_parse_tray, and `json_out` NULL or
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rummikub_valid_sets(tray: *const RummikubTray, json_out: *mut *mut c_char) -> RummikubStatus {
+    guard(|| {
+        if json_out.is_null() {
+            return Err(null_pointer("json_out"));
+        }
+        *json_out = ptr::null_mut();
+        if tray.is_null() {
+            return Err(null_pointer("tray"));
+        }
+        if !live(&LIVE_TRAYS).contains(&(tray as usize)) {
+            return Err((RummikubStatus::UnknownPointer, "tray was freed or doesn't come from rummikub_parse_tray".to_string()));
+        }
+
+        let cards = &(*tray).cards;
+        let json = sets_to_json(cards, &valid_sets(cards), false);
+        // The JSON escapes control characters, so it has no NUL inside
+        let json = CString::new(json).map_err(|e| (RummikubStatus::Panic, e.to_string()))?.into_raw();
+        live(&LIVE_STRINGS).insert(json as usize);
+        *json_out = json;
+        Ok(())
+    })
+}
+
+/// Frees a tray from rummikub_parse_tray. NULL is ignored.
+///
+/// # Safety
+///
+/// `tray` must not be used after this call.
+#[no_mangle]
+pub unsafe extern "C" fn rummikub_tray_free(tray: *mut RummikubTray) -> RummikubStatus {
+    guard(|| {
+        if tray.is_null() {
+            return Ok(());
+        }
+        if !live(&LIVE_TRAYS).remove(&(tray as usize)) {
+            return Err((RummikubStatus::UnknownPointer, "tray was already freed or doesn't come from rummikub_parse_tray".to_string()));
+        }
+        drop(Box::from_raw(tray));
+        Ok(())
+    })
+}
+
+/// Frees a string from rummikub_valid_sets. NULL is ignored.
+///
+/// # Safety
+///
+/// `ptr` must not be used after this call.
+#[no_mangle]
+pub unsafe extern "C" fn rummikub_free(ptr: *mut c_char) -> RummikubStatus {
+    guard(|| {
+        if ptr.is_null() {
+            return Ok(());
+        }
+        if !live(&LIVE_STRINGS).remove(&(ptr as usize)) {
+            return Err((RummikubStatus::UnknownPointer, "pointer was already freed or doesn't come from rummikub_valid_sets".to_string()));
+        }
+        drop(CString::from_raw(ptr));
+        Ok(())
+    })
+}
+
+/// The message of the last failed call on this thread, or an empty string after a success.
+/// The string belongs to the library: the pointer stays valid as long as the thread, but the
+/// next call on the thread changes the message.
+#[no_mangle]
+pub extern "C" fn rummikub_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.as_ptr() as *const c_char)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(rummikub_last_error()) }.to_str().unwrap().to_string()
+    }
+
+    fn parse(tiles: &str) -> (RummikubStatus, *mut RummikubTray) {
+        let tiles = CString::new(tiles).unwrap();
+        let mut tray = ptr::null_mut();
+        let status = unsafe { rummikub_parse_tray(tiles.as_ptr(), &mut tray) };
+        (status, tray)
+    }
+
+    #[test]
+    fn valid_sets_round_trip_test() {
+        let (status, tray) = parse("R7 B7 K7 y2 W");
+        assert_eq!(status, RummikubStatus::Ok);
+
+        let mut json = ptr::null_mut();
+        assert_eq!(unsafe { rummikub_valid_sets(tray, &mut json) }, RummikubStatus::Ok);
+
+        let cards: Vec<Card> = "R7 B7 K7 Y2 W".split(' ').map(|c| c.parse().unwrap()).collect();
+        let output = unsafe { CStr::from_ptr(json) }.to_str().unwrap();
+        assert_eq!(output, sets_to_json(&cards, &valid_sets(&cards), false));
+
+        assert_eq!(unsafe { rummikub_free(json) }, RummikubStatus::Ok);
+        assert_eq!(unsafe { rummikub_tray_free(tray) }, RummikubStatus::Ok);
+    }
+
+    #[test]
+    fn invalid_input_test() {
+        let (status, tray) = parse("B2 Q4");
+        assert_eq!(status, RummikubStatus::InvalidTile);
+        assert!(tray.is_null());
+        assert!(last_error().contains("'Q4'"));
+
+        let long = format!("R1 {}", "é".repeat(200));
+        assert_eq!(parse(&long).0, RummikubStatus::InvalidTile);
+        assert!(last_error().len() <= ERROR_LEN);
+
+        let invalid_utf8 = [0xff_u8, 0];
+        let mut tray = ptr::null_mut();
+        let status = unsafe { rummikub_parse_tray(invalid_utf8.as_ptr() as *const c_char, &mut tray) };
+        assert_eq!(status, RummikubStatus::InvalidUtf8);
+
+        let (status, tray) = parse("B2");
+        assert_eq!(status, RummikubStatus::Ok);
+        assert_eq!(last_error(), "");
+        unsafe { rummikub_tray_free(tray) };
+    }
+
+    #[test]
+    fn null_pointers_test() {
+        let tiles = CString::new("B2").unwrap();
+        let mut tray = ptr::null_mut();
+        let mut json = ptr::null_mut();
+
+        unsafe {
+            assert_eq!(rummikub_parse_tray(ptr::null(), &mut tray), RummikubStatus::NullPointer);
+            assert_eq!(rummikub_parse_tray(tiles.as_ptr(), ptr::null_mut()), RummikubStatus::NullPointer);
+            assert_eq!(rummikub_valid_sets(ptr::null(), &mut json), RummikubStatus::NullPointer);
+            assert_eq!(rummikub_tray_free(ptr::null_mut()), RummikubStatus::Ok);
+            assert_eq!(rummikub_free(ptr::null_mut()), RummikubStatus::Ok);
+        }
+        assert!(json.is_null());
+    }
+
+    #[test]
+    fn double_free_test() {
+        let (_, tray) = parse("R1 R2 R3");
+        let mut json = ptr::null_mut();
+
+        unsafe {
+            assert_eq!(rummikub_valid_sets(tray, &mut json), RummikubStatus::Ok);
+            assert_eq!(rummikub_free(json), RummikubStatus::Ok);
+            assert_eq!(rummikub_free(json), RummikubStatus::UnknownPointer);
+
+            assert_eq!(rummikub_tray_free(tray), RummikubStatus::Ok);
+            assert_eq!(rummikub_tray_free(tray), RummikubStatus::UnknownPointer);
+            assert_eq!(rummikub_valid_sets(tray, &mut json), RummikubStatus::UnknownPointer);
+            // A tray isn't a string
+            let (_, other) = parse("W");
+            assert_eq!(rummikub_free(other as *mut c_char), RummikubStatus::UnknownPointer);
+            assert_eq!(rummikub_tray_free(other), RummikubStatus::Ok);
+        }
+    }
+
+}
@@ -0,0 +1,45 @@
+//! Finds the valid sets of a Rummikub tray: runs of consecutive numbers of the same color and
+//! groups of the same number in different colors, with wildcards standing for any tile.
+//! On top of that, the crate can split a tray into the sets playing the most tiles, look for
+//! a first meld worth 30 points and list the tiles a player is one draw away from using.
+//!
+//! Without the default std feature the crate is no_std and only needs alloc: the tiles, the
+//! set search, the partition solver and the other pure algorithms are there, the game, the
+//! bots and everything reading files or spawning threads are not.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+// The tests use the standard library whatever the features
+#[cfg(test)]
+#[macro_use]
+extern crate std;
+
+// What the standard prelude brings, for the modules that build without it
+mod prelude {
+    pub use alloc::string::{String, ToString};
+    pub use alloc::vec::Vec;
+    pub use alloc::{format, vec};
+}
+
+mod rummikub;
+
+pub use rummikub::*;
+
+#[cfg(feature = "std")]
+pub mod ffi;
+
+#[cfg(feature = "net")]
+pub mod net;
+
+pub mod testing;
+
+#[cfg(feature = "python")]
+mod python;
+
+#[cfg(feature = "server")]
+pub mod server;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -1,30 +1,614 @@
-mod rummikub;
+use std::fs::File;
+use std::io::{self, BufReader, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use clap::{Parser, Subcommand, ValueEnum};
+use rummikub::{
+    analyze_trays, best_partition, dead_tiles, display_meld, display_meld_with, load_position, load_tray, sets_to_csv, sets_to_json, tray_summary_csv,
+    render_html_with_sets, run_tournament, score_meld, simulate, solve_turn, trace_sets, valid_sets_with, validate_set, Card, Deck, DisplayTheme, GameState, SetOrder, SetSearchOptions,
+    SimulationReport, SortMode, Stat, StrategyKind, SystemClock, Table, TournamentReport, Tray, TrayLoadError, TrayStats, TurnClock
+};
 
-use rummikub::{Deck, valid_sets};
+mod repl;
+#[cfg(feature = "tui")]
+mod tui;
 
-fn main() {
+// Exit code for trays and deals that can't be read or done
+const USAGE_ERROR: u8 = 2;
+
+// With 14 tiles each, the deck has enough for 7 players
+const MAX_PLAYERS: usize = 7;
+
+// Against bots, the human takes a seat
+const MAX_BOTS: usize = MAX_PLAYERS - 1;
+
+#[derive(Parser)]
+#[command(about = "Finds the valid sets of a Rummikub tray")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Deals a tray to every player from a shuffled deck
+    Deal {
+        #[arg(long, default_value_t = 1)]
+        players: usize,
+        #[arg(long, default_value_t = 14)]
+        tray_size: usize,
+        /// Shuffles the deck the same way every time
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Writes the tiles as codes or symbols instead of their names
+        #[arg(long, value_enum)]
+        theme: Option<Theme>
+    },
+    /// Prints the valid sets of a tray, given as tile codes like "B2 B3 B4 R7 W". Without
+    /// tiles nor a file, the tray is read from the standard input.
+    Solve {
+        tiles: Vec<String>,
+        /// Reads the tray from a file, or from the standard input with "-"
+        #[arg(long, conflicts_with = "tiles")]
+        file: Option<PathBuf>,
+        /// Also prints the sets playing the most tiles
+        #[arg(long)]
+        partition: bool,
+        /// How the sets are sorted, by points by default in the text format and by kind
+        /// otherwise
+        #[arg(long, value_enum)]
+        order: Option<Order>,
+        #[arg(long, default_value_t = 3)]
+        min_len: usize,
+        /// Leaves out the sets that are part of a bigger one
+        #[arg(long)]
+        maximal: bool,
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+        /// Prints the JSON output on a single line
+        #[arg(long)]
+        compact: bool,
+        /// First column of the CSV rows
+        #[arg(long, default_value = "1")]
+        hand_id: String,
+        /// Prints every step of the set search on the standard error
+        #[arg(long)]
+        verbose: bool,
+        /// Writes the tiles as codes or symbols instead of their names
+        #[arg(long, value_enum)]
+        theme: Option<Theme>
+    },
+    /// Finds the turn playing the most tiles of a hand on a table, rearranging the table,
+    /// from a position written like load_position reads them
+    SolvePosition {
+        #[arg(long)]
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = PositionFormat::Text)]
+        format: PositionFormat,
+        /// Writes the tiles as codes or symbols instead of their names
+        #[arg(long, value_enum)]
+        theme: Option<Theme>
+    },
+    /// Starts a game at the prompt: draw tiles and play sets until your tray is empty. Built
+    /// with the tui feature, a terminal gets a full screen UI instead.
+    Play {
+        #[arg(long, default_value_t = 14, conflicts_with_all = ["bots", "players"])]
+        tray_size: usize,
+        /// Plays against this many bots, with the full rules, instead of alone
+        #[arg(long, default_value_t = 0, conflicts_with = "players")]
+        bots: usize,
+        /// Plays with the full rules between these players, passing the device around
+        #[arg(long, num_args = 2..)]
+        players: Vec<String>,
+        /// Shuffles the deck the same way every time
+        #[arg(long)]
+        seed: Option<u64>,
+        /// With bots or players, the seconds a turn can last, usually 60: once they run out,
+        /// the player draws and their turn is over
+        #[arg(long)]
+        turn_seconds: Option<u64>
+    },
+    /// Plays bots with different strategies against each other, two at a time, and prints
+    /// how well each strategy did
+    Tournament {
+        #[arg(required = true, num_args = 2..)]
+        strategies: Vec<StrategyName>,
+        /// Games played by every pair of strategies
+        #[arg(long, default_value_t = 100)]
+        games: usize,
+        /// Deals the same games every time
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Prints the report as JSON
+        #[arg(long)]
+        json: bool
+    },
+    /// Deals many random trays, each from a fresh deck, and prints statistics about them
+    Simulate {
+        #[arg(long, default_value_t = 10_000)]
+        hands: usize,
+        #[arg(long, default_value_t = 14)]
+        tray_size: usize,
+        /// Deals the same trays every time
+        #[arg(long)]
+        seed: Option<u64>,
+        #[arg(long, value_enum)]
+        stat: StatName,
+        /// Prints the report as JSON
+        #[arg(long)]
+        json: bool
+    },
+    /// Deals many random trays and prints how many sets they have, how many the best
+    /// partition lays down and how many dead tiles are left
+    Stats {
+        #[arg(long, default_value_t = 1000)]
+        samples: usize,
+        #[arg(long, default_value_t = 14)]
+        tray_size: usize,
+        /// Deals the same trays every time
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Prints the statistics as JSON
+        #[arg(long)]
+        json: bool
+    },
+    /// Serves the solver over HTTP: POST /solve, /partition and /validate-set
+    #[cfg(feature = "server")]
+    Serve {
+        #[arg(long, default_value_t = 8080)]
+        port: u16
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+    /// One row per set
+    Csv,
+    /// One row with the statistics of the tray
+    CsvSummary,
+    /// A page showing the tray, the tiles of a set outlined when it is hovered
+    Html
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum PositionFormat {
+    Text,
+    /// A page showing the position, the tiles of a meld of the table after the turn outlined
+    /// when it is hovered
+    Html
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Order {
+    Kind,
+    Length,
+    Points
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Theme {
+    /// A shape for every color, like ●7 and ■7
+    Colorblind,
+    /// The tile codes, like R7 and B7
+    Plain,
+    /// The tile codes in color
+    Ansi
+}
+
+impl From<Theme> for DisplayTheme {
+    fn from(theme: Theme) -> Self {
+        match theme {
+            Theme::Colorblind => DisplayTheme::colorblind(),
+            Theme::Plain => DisplayTheme::plain(),
+            Theme::Ansi => DisplayTheme::ansi()
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum StrategyName {
+    Greedy,
+    Random,
+    Hightile
+}
+
+impl From<StrategyName> for StrategyKind {
+    fn from(name: StrategyName) -> Self {
+        match name {
+            StrategyName::Greedy => StrategyKind::Greedy,
+            StrategyName::Random => StrategyKind::Random,
+            StrategyName::Hightile => StrategyKind::HighTile
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum StatName {
+    /// Whether the tray holds a 30 point opening
+    Opening30,
+    /// How many valid sets the tray has
+    SetsPerHand,
+    /// How many wildcards the tray holds
+    WildcardHands
+}
+
+impl From<StatName> for Stat {
+    fn from(name: StatName) -> Self {
+        match name {
+            StatName::Opening30 => Stat::Opening30,
+            StatName::SetsPerHand => Stat::SetsPerHand,
+            StatName::WildcardHands => Stat::WildcardHands
+        }
+    }
+}
+
+impl From<Order> for SetOrder {
+    fn from(order: Order) -> Self {
+        match order {
+            Order::Kind => SetOrder::Kind,
+            Order::Length => SetOrder::Length,
+            Order::Points => SetOrder::Points
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let result = match Cli::parse().command {
+        Some(Command::Deal { players, tray_size, seed, theme }) => deal(players, tray_size, seed, theme.map(DisplayTheme::from)),
+        Some(Command::Solve { tiles, file, partition, order, min_len, maximal, format, compact, hand_id, verbose, theme }) => {
+            let options = SetSearchOptions {
+                min_len,
+                maximal_only: maximal,
+                order: order.map(SetOrder::from).unwrap_or(if format == Format::Text { SetOrder::Points } else { SetOrder::Kind }),
+                ..SetSearchOptions::default()
+            };
+            let read = || read_tray(&tiles, file.as_deref()).inspect(|tray| {
+                if verbose {
+                    eprint!("{}", trace_sets(tray, &options));
+                }
+            });
+            match format {
+                Format::Text => read().map(|tray| solve(&tray, &options, partition, theme.map(DisplayTheme::from))),
+                _ if partition => Err("--partition only works with the text format".to_string()),
+                _ if theme.is_some() => Err("--theme only works with the text format".to_string()),
+                Format::Json => read().map(|tray| {
+                    println!("{}", sets_to_json(&tray, &valid_sets_with(&tray, &options), !compact));
+                }),
+                Format::Csv => read().and_then(|tray| {
+                    sets_to_csv(io::stdout(), &hand_id, &valid_sets_with(&tray, &options)).map_err(|e| e.to_string())
+                }),
+                Format::CsvSummary => read().and_then(|tray| {
+                    tray_summary_csv(io::stdout(), &hand_id, &tray).map_err(|e| e.to_string())
+                }),
+                Format::Html => read().map(|tray| {
+                    let sets: Vec<Vec<Card>> = valid_sets_with(&tray, &options).into_iter()
+                        .map(|set| set.into_iter().copied().collect())
+                        .collect();
+                    print!("{}", html_page(&render_html_with_sets(&Table::new(), &tray, &sets)));
+                })
+            }
+        },
+        Some(Command::SolvePosition { file, format: PositionFormat::Text, theme }) => solve_position(&file, theme.map(DisplayTheme::from)),
+        Some(Command::SolvePosition { theme: Some(_), .. }) => Err("--theme only works with the text format".to_string()),
+        Some(Command::SolvePosition { file, .. }) => solve_position_html(&file),
+        Some(Command::Play { players, seed, turn_seconds, .. }) if !players.is_empty() => play_hot_seat(&players, seed, turn_seconds),
+        Some(Command::Play { tray_size, bots: 0, seed, .. }) => play(tray_size, seed),
+        Some(Command::Play { bots, seed, turn_seconds, .. }) => play_with_bots(bots, seed, turn_seconds),
+        Some(Command::Tournament { strategies, games, seed, json }) => tournament(&strategies, games, seed, json),
+        Some(Command::Simulate { hands, tray_size, seed, stat, json }) => run_simulation(stat, hands, tray_size, seed, json),
+        Some(Command::Stats { samples, tray_size, seed, json }) => tray_stats(samples, tray_size, seed, json),
+        #[cfg(feature = "server")]
+        Some(Command::Serve { port }) => {
+            eprintln!("listening on port {}", port);
+            rummikub::server::serve(port).map_err(|e| e.to_string())
+        },
+        None => {
+            demo();
+            Ok(())
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            ExitCode::from(USAGE_ERROR)
+        }
+    }
+}
+
+fn deal(players: usize, tray_size: usize, seed: Option<u64>, theme: Option<DisplayTheme>) -> Result<(), String> {
+    let mut deck = new_deck(seed);
+    if players * tray_size > deck.len() {
+        return Err(format!(
+            "can't deal {} tiles to {} players: the deck has only {}", tray_size, players, deck.len()
+        ));
+    }
+
+    for player in 1..=players {
+        let tray = Tray::from(deck.pick_tray(tray_size)).sorted_view(SortMode::ByNumberThenColor);
+        println!("Player {}:", player);
+        print_cards(&tray, theme.as_ref());
+    }
+    Ok(())
+}
+
+fn play(tray_size: usize, seed: Option<u64>) -> Result<(), String> {
+    let deck = new_deck(seed);
+    if tray_size > deck.len() {
+        return Err(format!("can't deal {} tiles: the deck has only {}", tray_size, deck.len()));
+    }
+
+    let mut session = repl::Session::new(deck, tray_size);
+    #[cfg(feature = "tui")]
+    if io::stdin().is_terminal() && io::stdout().is_terminal() {
+        return tui::run(session).map_err(|e| e.to_string());
+    }
+    println!("Commands: draw, play <tiles>, sets, sort color|number, quit");
+    repl::run(&mut session, io::stdin().lock(), io::stdout()).map_err(|e| e.to_string())
+}
+
+fn play_with_bots(bots: usize, seed: Option<u64>, turn_seconds: Option<u64>) -> Result<(), String> {
+    if bots > MAX_BOTS {
+        return Err(format!("can't play against {} bots: at most {}", bots, MAX_BOTS));
+    }
+    let mut names = vec!["You".to_string()];
+    names.extend((1..=bots).map(|bot| format!("Bot {}", bot)));
+    let names: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+    let mut game = GameState::new_game(&names, seed.unwrap_or_else(rand::random));
+
+    println!("Commands: draw, play <tiles>, add <meld> <tile>, end, hint, sets, sort color|number, quit");
+    let mut clock = turn_clock(turn_seconds);
+    repl::run_with_bots(&mut game, io::stdin().lock(), io::stdout(), clock.as_mut()).map_err(|e| e.to_string())
+}
+
+fn play_hot_seat(players: &[String], seed: Option<u64>, turn_seconds: Option<u64>) -> Result<(), String> {
+    if players.len() > MAX_PLAYERS {
+        return Err(format!("can't play with {} players: at most {}", players.len(), MAX_PLAYERS));
+    }
+    let names: Vec<&str> = players.iter().map(|name| name.as_str()).collect();
+    let mut game = GameState::new_game(&names, seed.unwrap_or_else(rand::random));
+
+    println!("Commands: draw, play <tiles>, add <meld> <tile>, end, hint, sets, sort color|number, quit");
+    let mut clock = turn_clock(turn_seconds);
+    repl::run_hot_seat(&mut game, io::stdin().lock(), io::stdout(), clock.as_mut()).map_err(|e| e.to_string())
+}
+
+fn turn_clock(seconds: Option<u64>) -> Option<TurnClock> {
+    seconds.map(|seconds| TurnClock::new(Box::new(SystemClock::new()), seconds))
+}
+
+fn tournament(strategies: &[StrategyName], games: usize, seed: Option<u64>, json: bool) -> Result<(), String> {
+    let kinds: Vec<StrategyKind> = strategies.iter().map(|name| (*name).into()).collect();
+    let report = run_tournament(&kinds, games, seed.unwrap_or_else(rand::random))
+        .map_err(|e| format!("a bot played an illegal move: {}", e))?;
+    if json {
+        // Plain numbers and strings always serialize
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        print_report(&report);
+    }
+    Ok(())
+}
+
+fn print_report(report: &TournamentReport) {
+    println!("{} games per pairing, seed {}", report.games_per_pairing, report.seed);
+    println!("{:<10} {:>6} {:>6} {:>9} {:>12} {:>10} {:>7}", "strategy", "games", "wins", "win rate", "avg penalty", "avg turns", "rating");
+    for stats in &report.strategies {
+        println!(
+            "{:<10} {:>6} {:>6} {:>8.1}% {:>12.1} {:>10.1} {:>7.0}",
+            stats.name, stats.games, stats.wins, stats.win_rate * 100.0, stats.average_penalty, stats.average_turns, stats.rating
+        );
+    }
+    println!();
+    for pairing in &report.pairings {
+        println!(
+            "{} vs {}: {} - {}, {} unfinished",
+            pairing.first, pairing.second, pairing.first_wins, pairing.second_wins, pairing.unfinished
+        );
+    }
+}
+
+fn run_simulation(stat: StatName, hands: usize, tray_size: usize, seed: Option<u64>, json: bool) -> Result<(), String> {
+    let deck_len = Deck::new().len();
+    if tray_size > deck_len {
+        return Err(format!("can't deal {} tiles: the deck has only {}", tray_size, deck_len));
+    }
+    let report = simulate(stat.into(), hands, tray_size, seed.unwrap_or_else(rand::random));
+    if json {
+        // Plain numbers and strings always serialize
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        print_simulation(&report);
+    }
+    Ok(())
+}
+
+fn print_simulation(report: &SimulationReport) {
+    println!("{}: {} trays of {} tiles, seed {}", report.stat, report.hands, report.tray_size, report.seed);
+    println!("mean {:.4}, std dev {:.4}", report.mean, report.std_dev);
+    println!("95% confidence interval: {:.4} - {:.4}", report.confidence_low, report.confidence_high);
+    for bucket in &report.buckets {
+        let range = if bucket.low == bucket.high {
+            bucket.low.to_string()
+        } else {
+            format!("{}-{}", bucket.low, bucket.high)
+        };
+        let share = bucket.count as f64 / report.hands as f64 * 100.0;
+        println!("{:>7}: {:>7} {:>5.1}%", range, bucket.count, share);
+    }
+}
+
+fn tray_stats(samples: usize, tray_size: usize, seed: Option<u64>, json: bool) -> Result<(), String> {
+    let deck_len = Deck::new().len();
+    if tray_size > deck_len {
+        return Err(format!("can't deal {} tiles: the deck has only {}", tray_size, deck_len));
+    }
+    let stats = analyze_trays(samples, tray_size, seed.unwrap_or_else(rand::random));
+    if json {
+        // Plain numbers and strings always serialize
+        println!("{}", serde_json::to_string_pretty(&stats).unwrap());
+    } else {
+        print_tray_stats(&stats);
+    }
+    Ok(())
+}
+
+fn print_tray_stats(stats: &TrayStats) {
+    println!("{} trays of {} tiles, seed {}", stats.samples, stats.tray_size, stats.seed);
+    println!("valid sets: mean {:.2}, median {}", stats.mean_sets, stats.median_sets);
+    println!("at least one set: {:.1}%", stats.p_any_set * 100.0);
+    println!("sets in the best partition: mean {:.2}", stats.mean_partition_sets);
+    println!("dead tiles: mean {:.2}", stats.mean_dead_tiles);
+    println!("{:>7}  {:>7}", "sets", "trays");
+    for bucket in &stats.set_counts {
+        let share = bucket.count as f64 / stats.samples as f64 * 100.0;
+        println!("{:>7}: {:>7} {:>5.1}%", bucket.low, bucket.count, share);
+    }
+}
+
+fn new_deck(seed: Option<u64>) -> Deck {
+    match seed {
+        Some(seed) => Deck::new_seeded(seed),
+        None => Deck::new_shuffled()
+    }
+}
+
+fn read_tray(tiles: &[String], file: Option<&Path>) -> Result<Vec<Card>, String> {
+    if !tiles.is_empty() {
+        // Tiles can come as separate arguments or all in one
+        return tiles.iter()
+            .flat_map(|t| t.split_whitespace())
+            .map(|token| token.parse::<Card>().map_err(|e| e.to_string()))
+            .collect();
+    }
+
+    let (name, result) = match file {
+        Some(path) if path != Path::new("-") => {
+            let reader = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            (path.display().to_string(), load_tray(BufReader::new(reader)))
+        },
+        _ => {
+            if file.is_none() && io::stdin().is_terminal() {
+                return Err("no tiles given: pass them as arguments, with --file or through the standard input".to_string());
+            }
+            ("<stdin>".to_string(), load_tray(io::stdin().lock()))
+        }
+    };
+    result.map_err(|e| match e {
+        TrayLoadError::Io(error) => format!("{}: {}", name, error),
+        TrayLoadError::InvalidTile { line, error } => format!("{}:{}: {}", name, line, error)
+    })
+}
+
+// Prints the sets worth the most first, unless told otherwise, then a summary of what the
+// tray can do
+fn solve(tray: &[Card], options: &SetSearchOptions, partition: bool, theme: Option<DisplayTheme>) {
+    let sets = valid_sets_with(tray, options);
+    if sets.is_empty() {
+        println!("No plays possible: the tray makes no valid set");
+        return;
+    }
+    println!("Valid sets:");
+    print_sets(&sets, theme.as_ref());
+
+    let best = best_partition(tray);
+    if partition {
+        println!("Best partition:");
+        print_sets(&best.sets.iter().map(|s| s.iter().collect()).collect::<Vec<Vec<&Card>>>(), theme.as_ref());
+        println!("Left in hand:");
+        print_cards(&best.leftover, theme.as_ref());
+    }
+
+    let best_meld = sets.iter().map(|set| points(set)).max().unwrap_or(0);
+    let partition_points: u32 = best.sets.iter().map(|set| points(&set.iter().collect::<Vec<&Card>>())).sum();
+    println!("Summary:");
+    println!(" sets found: {}", sets.len());
+    println!(" best meld: {} points", best_meld);
+    println!(" best partition: {} tiles, {} points", best.played_count(), partition_points);
+    println!(" dead tiles: {}", dead_tiles(tray).len());
+}
+
+fn solve_position(path: &Path, theme: Option<DisplayTheme>) -> Result<(), String> {
+    let (table, hand) = read_position(path)?;
+    let Some(plan) = solve_turn(&table, &hand) else {
+        println!("No plays possible: no tile of the hand fits on the table");
+        return Ok(());
+    };
+    println!("Play:");
+    print_cards(&plan.played, theme.as_ref());
+    println!("Table after the turn:");
+    print_sets(&plan.table.melds().map(|(_, cards)| cards.iter().collect()).collect::<Vec<Vec<&Card>>>(), theme.as_ref());
+    println!("Left in hand:");
+    print_cards(&plan.hand, theme.as_ref());
+    Ok(())
+}
+
+// The position, with the melds of the table after the best turn as its sets
+fn solve_position_html(path: &Path) -> Result<(), String> {
+    let (table, hand) = read_position(path)?;
+    let sets: Vec<Vec<Card>> = solve_turn(&table, &hand)
+        .map(|plan| plan.table.melds().map(|(_, cards)| cards.to_vec()).collect())
+        .unwrap_or_default();
+    print!("{}", html_page(&render_html_with_sets(&table, &hand, &sets)));
+    Ok(())
+}
+
+fn read_position(path: &Path) -> Result<(Table, Vec<Card>), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    load_position(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+// A whole page around a snippet of render_html
+fn html_page(body: &str) -> String {
+    format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Rummikub</title>\n</head>\n<body>\n{}</body>\n</html>\n", body)
+}
+
+// Face value of a valid set, the wildcards worth what they stand for
+fn points(set: &[&Card]) -> u32 {
+    score_meld(&set.iter().map(|c| **c).collect::<Vec<Card>>()).unwrap_or(0)
+}
+
+// Deals a tray and shows what can be done with it
+fn demo() {
     let mut deck = Deck::new_shuffled();
-    let mut tray = deck.pick_tray(14);
+    let tray = deck.pick_tray(14);
 
     println!("Your tray:");
-    for c in &tray {
-        println!(" - {}", c);
-    }
-    
-    tray.sort();
+    print_cards(&tray, None);
+
+    let tray = Tray::from(tray).sorted_view(SortMode::ByNumberThenColor);
     println!("Your tray (sorted):");
-    for c in &tray {
-        println!(" - {}", c);
+    print_cards(&tray, None);
+
+    let options = SetSearchOptions { order: SetOrder::Points, ..SetSearchOptions::default() };
+    solve(&tray, &options, false, None);
+
+    println!("Dead tiles:");
+    for card in dead_tiles(&tray) {
+        println!(" - {}", card);
     }
+}
 
-    println!("Valid sets:");
-    let valid_sets = valid_sets(&tray);
-    for set in valid_sets {
-        print!(" -> ");
-        for card in set {
-            print!("{} ", card)
+// Without a theme, tiles are written by their names like "7 Red"
+fn print_cards(cards: &[Card], theme: Option<&DisplayTheme>) {
+    for c in cards {
+        match theme {
+            Some(theme) => println!(" - {}", theme.card(c)),
+            None => println!(" - {}", c)
+        }
+    }
+}
+
+// Every set with what it is worth and its kind
+fn print_sets(sets: &[Vec<&Card>], theme: Option<&DisplayTheme>) {
+    for set in sets {
+        let kind = validate_set(&set.iter().map(|c| **c).collect::<Vec<Card>>()).map(|kind| kind.name()).unwrap_or("invalid");
+        match theme {
+            Some(theme) => println!(" -> {} ({} points, {})", display_meld_with(set, theme), points(set), kind),
+            None => println!(" -> {} ({} points, {})", display_meld(set), points(set), kind)
         }
-        println!();
     }
-    
 }
@@ -0,0 +1,325 @@
+//! Plays a game over TCP, like on a LAN. The host owns the game: it checks and applies the
+//! moves its clients send, then tells every client what it can see, its own tray and what is
+//! public. Messages are JSON, each one preceded by its length as 4 big-endian bytes.
+//!
+//! A client opens with [`ClientMessage::Hello`], listing the protocol versions it speaks,
+//! and the host answers with the version they will use. When a client disconnects, the moves
+//! of its unfinished turn are taken back and its turns are skipped from then on, the host
+//! drawing for it like a player who can't play.
+
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc;
+use std::thread;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+use crate::{Card, GameState, MeldId, Move, MoveError, PlayerId};
+
+/// The version of the protocol this build speaks best
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Every version of the protocol this build speaks
+pub const SUPPORTED_VERSIONS: [u32; 1] = [1];
+
+// Messages longer than this are refused, so a broken peer can't make us allocate anything
+const MAX_FRAME_LEN: u32 = 1 << 20;
+
+/// What a client sends to the host
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClientMessage {
+    /// The first message of a client
+    Hello { name: String, versions: Vec<u32> },
+    /// A move for the turn of the client
+    Play(Move)
+}
+
+/// What the host sends to a client
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HostMessage {
+    /// The answer to Hello: the version both speak, and the seat of the client
+    Welcome { version: u32, player: PlayerId },
+    /// The answer to Hello when the host speaks none of the versions of the client
+    Rejected { reason: String },
+    /// The game as the client sees it, sent to everyone after every move
+    State(StateUpdate),
+    /// The move of the client was refused, the game didn't change
+    MoveRejected { reason: String },
+    /// The round is over
+    GameOver { winner: PlayerId, penalties: Vec<(PlayerId, i32)> }
+}
+
+/// One player as everyone sees them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublicPlayer {
+    pub name: String,
+    pub tile_count: usize,
+    pub has_melded: bool,
+    /// Whether the player is still connected
+    pub connected: bool
+}
+
+/// What a client can see of the game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateUpdate {
+    /// The seat of the client
+    pub player: PlayerId,
+    pub tray: Vec<Card>,
+    pub table: Vec<(MeldId, Vec<Card>)>,
+    pub deck_len: usize,
+    pub players: Vec<PublicPlayer>,
+    pub current_player: PlayerId,
+    pub turn_number: usize
+}
+
+/// Error of the host or of a client
+#[derive(Debug, Error)]
+pub enum NetError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("not a valid message: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("a message of {len} bytes is too long")]
+    FrameTooLong { len: u32 },
+    #[error("the host refused the connection: {0}")]
+    Rejected(String),
+    #[error("unexpected message")]
+    UnexpectedMessage
+}
+
+/// Writes a message with its length in front
+pub fn write_message(stream: &mut impl Write, message: &impl Serialize) -> Result<(), NetError> {
+    let bytes = serde_json::to_vec(message)?;
+    let len = u32::try_from(bytes.len()).ok().filter(|len| *len <= MAX_FRAME_LEN)
+        .ok_or(NetError::FrameTooLong { len: u32::MAX })?;
+    stream.write_all(&len.to_be_bytes())?;
+    stream.write_all(&bytes)?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Reads a message written by write_message
+pub fn read_message<T: DeserializeOwned>(stream: &mut impl Read) -> Result<T, NetError> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len);
+    if len > MAX_FRAME_LEN {
+        return Err(NetError::FrameTooLong { len });
+    }
+    let mut bytes = vec![0; len as usize];
+    stream.read_exact(&mut bytes)?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+/// The highest version both sides speak
+pub fn negotiate_version(offered: &[u32]) -> Option<u32> {
+    offered.iter().copied().filter(|version| SUPPORTED_VERSIONS.contains(version)).max()
+}
+
+// What the reader threads of the host pass on
+enum Incoming {
+    Message(PlayerId, ClientMessage),
+    Gone(PlayerId)
+}
+
+/// Listens for the clients and runs the game they play
+pub struct Host {
+    listener: TcpListener
+}
+
+impl Host {
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Host> {
+        Ok(Host { listener: TcpListener::bind(addr)? })
+    }
+
+    /// The address the clients connect to, with the port picked when binding to port 0
+    pub fn local_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Waits for players clients, then plays a game shuffled with seed until the round is
+    /// over or every client is gone. Clients speaking none of the supported versions are
+    /// turned away and don't take a seat. Returns the game as it ended.
+    pub fn run(self, players: usize, seed: u64) -> Result<GameState, NetError> {
+        let mut streams: Vec<TcpStream> = vec![];
+        let mut names: Vec<String> = vec![];
+        while streams.len() < players {
+            let (mut stream, _) = self.listener.accept()?;
+            // Whoever doesn't start with Hello isn't a client
+            let Ok(ClientMessage::Hello { name, versions }) = read_message(&mut stream) else {
+                continue;
+            };
+            match negotiate_version(&versions) {
+                Some(version) => {
+                    write_message(&mut stream, &HostMessage::Welcome { version, player: streams.len() })?;
+                    streams.push(stream);
+                    names.push(name);
+                },
+                None => {
+                    let reason = format!("the host speaks versions {:?}", SUPPORTED_VERSIONS);
+                    let _ = write_message(&mut stream, &HostMessage::Rejected { reason });
+                }
+            }
+        }
+
+        let (sender, incoming) = mpsc::channel();
+        for (player, stream) in streams.iter().enumerate() {
+            let mut reader = stream.try_clone()?;
+            let sender = sender.clone();
+            thread::spawn(move || {
+                while let Ok(message) = read_message(&mut reader) {
+                    if sender.send(Incoming::Message(player, message)).is_err() {
+                        return;
+                    }
+                }
+                let _ = sender.send(Incoming::Gone(player));
+            });
+        }
+        drop(sender);
+
+        let names: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+        let mut game = GameState::new_game(&names, seed);
+        let mut connected = vec![true; players];
+        broadcast(&game, &mut streams, &connected);
+        while game.round_result().is_none() && connected.contains(&true) {
+            if !connected[game.current_player_index()] {
+                game.skip_turn();
+                broadcast(&game, &mut streams, &connected);
+                continue;
+            }
+            let Ok(event) = incoming.recv() else {
+                break;
+            };
+            match event {
+                Incoming::Message(player, ClientMessage::Play(mv)) if player == game.current_player_index() => {
+                    match game.apply_move(mv) {
+                        Ok(_) => broadcast(&game, &mut streams, &connected),
+                        Err(error) => send(&mut streams[player], &mut connected[player], &HostMessage::MoveRejected { reason: error.to_string() })
+                    }
+                },
+                Incoming::Message(player, _) => {
+                    let reason = MoveError::NotYourTurn.to_string();
+                    send(&mut streams[player], &mut connected[player], &HostMessage::MoveRejected { reason });
+                },
+                Incoming::Gone(player) => connected[player] = false
+            }
+        }
+
+        if let Some(result) = game.round_result() {
+            let message = HostMessage::GameOver { winner: result.winner, penalties: result.penalties.clone() };
+            for (stream, connected) in streams.iter_mut().zip(&mut connected) {
+                send(stream, connected, &message);
+            }
+        }
+        Ok(game)
+    }
+}
+
+// Sends a message to a client, which counts as gone if it can't be reached. Its reader
+// thread notices too, so the host only has to stop writing to it.
+fn send(stream: &mut TcpStream, connected: &mut bool, message: &HostMessage) {
+    if *connected && write_message(stream, message).is_err() {
+        *connected = false;
+    }
+}
+
+fn broadcast(game: &GameState, streams: &mut [TcpStream], connected: &[bool]) {
+    for (player, stream) in streams.iter_mut().enumerate() {
+        let mut reachable = connected[player];
+        send(stream, &mut reachable, &HostMessage::State(state_for(game, player, connected)));
+    }
+}
+
+/// What the player can see of the game: their own tray and what is public
+pub fn state_for(game: &GameState, player: PlayerId, connected: &[bool]) -> StateUpdate {
+    let view = game.view_for(player);
+    StateUpdate {
+        player,
+        tray: view.tray.to_vec(),
+        table: view.table.melds().map(|(id, cards)| (id, cards.to_vec())).collect(),
+        deck_len: view.deck_len,
+        players: view.players.iter()
+            .enumerate()
+            .map(|(idx, summary)| PublicPlayer {
+                name: summary.name.to_string(),
+                tile_count: summary.tile_count,
+                has_melded: summary.has_melded,
+                connected: connected.get(idx).copied().unwrap_or(false)
+            })
+            .collect(),
+        current_player: game.current_player_index(),
+        turn_number: view.turn_number
+    }
+}
+
+/// A player connected to a host
+pub struct Client {
+    stream: TcpStream,
+    /// The seat of the player
+    pub player: PlayerId,
+    /// The version agreed on with the host
+    pub version: u32
+}
+
+impl Client {
+    /// Connects and says hello with every supported version
+    pub fn connect(addr: impl ToSocketAddrs, name: &str) -> Result<Client, NetError> {
+        Client::connect_with_versions(addr, name, &SUPPORTED_VERSIONS)
+    }
+
+    /// Connects offering only these versions of the protocol
+    pub fn connect_with_versions(addr: impl ToSocketAddrs, name: &str, versions: &[u32]) -> Result<Client, NetError> {
+        let mut stream = TcpStream::connect(addr)?;
+        write_message(&mut stream, &ClientMessage::Hello { name: name.to_string(), versions: versions.to_vec() })?;
+        match read_message(&mut stream)? {
+            HostMessage::Welcome { version, player } => Ok(Client { stream, player, version }),
+            HostMessage::Rejected { reason } => Err(NetError::Rejected(reason)),
+            _ => Err(NetError::UnexpectedMessage)
+        }
+    }
+
+    pub fn play(&mut self, mv: Move) -> Result<(), NetError> {
+        write_message(&mut self.stream, &ClientMessage::Play(mv))
+    }
+
+    /// Waits for the next message of the host
+    pub fn next_message(&mut self) -> Result<HostMessage, NetError> {
+        read_message(&mut self.stream)
+    }
+
+    /// Waits for the next state, skipping the other messages
+    pub fn next_state(&mut self) -> Result<StateUpdate, NetError> {
+        loop {
+            if let HostMessage::State(state) = self.next_message()? {
+                return Ok(state);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn frame_round_trip_test() {
+        let message = ClientMessage::Play(Move::PlayNewMeld(vec![Card::Wildcard]));
+        let mut bytes = vec![];
+        write_message(&mut bytes, &message).unwrap();
+
+        assert_eq!(u32::from_be_bytes(bytes[..4].try_into().unwrap()) as usize, bytes.len() - 4);
+        assert_eq!(read_message::<ClientMessage>(&mut bytes.as_slice()).unwrap(), message);
+
+        let too_long = (MAX_FRAME_LEN + 1).to_be_bytes();
+        assert!(matches!(read_message::<ClientMessage>(&mut too_long.as_slice()), Err(NetError::FrameTooLong { .. })));
+    }
+
+    #[test]
+    fn negotiate_version_test() {
+        assert_eq!(negotiate_version(&[0, 1, 7]), Some(1));
+        assert_eq!(negotiate_version(&[2]), None);
+        assert_eq!(negotiate_version(&[]), None);
+    }
+
+}
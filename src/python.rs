@@ -0,0 +1,207 @@
+//! Python bindings, built with the python feature into an extension module named rummikub
+//! (see pyproject.toml). Trays are lists of `Card`s or of tile codes like "B2" and "W", and
+//! codes that can't be read raise a `ValueError` with the parse message.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use crate::{Card, Color, Deck, ParseCardError};
+
+/// The colors of the numbered tiles, Green and Orange being those of the six-color variant
+#[pyclass(name = "Color", eq, eq_int, frozen, hash, from_py_object)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PyColor {
+    Red,
+    Blue,
+    Black,
+    Yellow,
+    Green,
+    Orange
+}
+
+impl From<Color> for PyColor {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Red => PyColor::Red,
+            Color::Blue => PyColor::Blue,
+            Color::Black => PyColor::Black,
+            Color::Yellow => PyColor::Yellow,
+            Color::Green => PyColor::Green,
+            Color::Orange => PyColor::Orange
+        }
+    }
+}
+
+impl From<PyColor> for Color {
+    fn from(color: PyColor) -> Self {
+        match color {
+            PyColor::Red => Color::Red,
+            PyColor::Blue => Color::Blue,
+            PyColor::Black => Color::Black,
+            PyColor::Yellow => Color::Yellow,
+            PyColor::Green => Color::Green,
+            PyColor::Orange => Color::Orange
+        }
+    }
+}
+
+/// A tile of the game, built from its code: Card("B2") or Card("W")
+#[pyclass(name = "Card", eq, ord, frozen, hash, from_py_object)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PyCard(pub Card);
+
+#[pymethods]
+impl PyCard {
+    #[new]
+    fn new(code: &str) -> PyResult<Self> {
+        code.parse().map(PyCard).map_err(value_error)
+    }
+
+    /// A numbered tile, raising ValueError unless the number is from 1 to 13
+    #[staticmethod]
+    fn numbered(number: i8, color: PyColor) -> PyResult<Self> {
+        Card::try_new(number, color.into()).map(PyCard).map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    #[getter]
+    fn number(&self) -> Option<i8> {
+        self.0.number().copied()
+    }
+
+    #[getter]
+    fn color(&self) -> Option<PyColor> {
+        self.0.color().map(|c| (*c).into())
+    }
+
+    #[getter]
+    fn is_wildcard(&self) -> bool {
+        self.0.is_wildcard()
+    }
+
+    #[getter]
+    fn code(&self) -> String {
+        self.0.code()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Card('{}')", self.0.code())
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// The 106 tiles of the game
+#[pyclass(name = "Deck")]
+pub struct PyDeck(Deck);
+
+#[pymethods]
+impl PyDeck {
+    #[staticmethod]
+    fn new_shuffled() -> Self {
+        PyDeck(Deck::new_shuffled())
+    }
+
+    #[staticmethod]
+    fn new_seeded(seed: u64) -> Self {
+        PyDeck(Deck::new_seeded(seed))
+    }
+
+    /// Draws num_cards tiles, or what is left of the deck
+    fn pick_tray(&mut self, num_cards: usize) -> Vec<PyCard> {
+        self.0.pick_tray(num_cards).into_iter().map(PyCard).collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.len()
+    }
+}
+
+// What a tray can be made of on the Python side
+#[derive(FromPyObject)]
+enum Tile {
+    Card(PyCard),
+    Code(String)
+}
+
+fn value_error(error: ParseCardError) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+fn tray_from_tiles(tiles: Vec<Tile>) -> Result<Vec<Card>, ParseCardError> {
+    tiles.into_iter()
+        .map(|tile| match tile {
+            Tile::Card(card) => Ok(card.0),
+            Tile::Code(code) => code.parse()
+        })
+        .collect()
+}
+
+fn sets_to_cards<'a>(sets: impl IntoIterator<Item = impl IntoIterator<Item = &'a Card>>) -> Vec<Vec<PyCard>> {
+    sets.into_iter().map(|set| set.into_iter().copied().map(PyCard).collect()).collect()
+}
+
+fn sets_to_codes<'a>(sets: impl IntoIterator<Item = impl IntoIterator<Item = &'a Card>>) -> Vec<Vec<String>> {
+    sets.into_iter().map(|set| set.into_iter().map(|c| c.code()).collect()).collect()
+}
+
+/// The valid sets of a tray, as lists of cards
+#[pyfunction]
+fn valid_sets(tray: Vec<Tile>) -> PyResult<Vec<Vec<PyCard>>> {
+    let tray = tray_from_tiles(tray).map_err(value_error)?;
+    Ok(sets_to_cards(crate::valid_sets(&tray)))
+}
+
+/// The valid sets of a tray, as lists of tile codes
+#[pyfunction]
+fn valid_set_codes(tray: Vec<Tile>) -> PyResult<Vec<Vec<String>>> {
+    let tray = tray_from_tiles(tray).map_err(value_error)?;
+    Ok(sets_to_codes(crate::valid_sets(&tray)))
+}
+
+/// The sets playing the most tiles of a tray and the cards left in hand, as a tuple
+#[pyfunction]
+fn best_partition(tray: Vec<Tile>) -> PyResult<(Vec<Vec<PyCard>>, Vec<PyCard>)> {
+    let tray = tray_from_tiles(tray).map_err(value_error)?;
+    let partition = crate::best_partition(&tray);
+    Ok((sets_to_cards(&partition.sets), partition.leftover.into_iter().map(PyCard).collect()))
+}
+
+#[pymodule]
+#[pyo3(name = "rummikub")]
+fn python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyColor>()?;
+    m.add_class::<PyCard>()?;
+    m.add_class::<PyDeck>()?;
+    m.add_function(wrap_pyfunction!(valid_sets, m)?)?;
+    m.add_function(wrap_pyfunction!(valid_set_codes, m)?)?;
+    m.add_function(wrap_pyfunction!(best_partition, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn tray_from_tiles_test() {
+        let tiles = vec![Tile::Code("b2".to_string()), Tile::Card(PyCard(Card::Wildcard))];
+
+        assert_eq!(tray_from_tiles(tiles), Ok(vec![Card::new(2, Color::Blue), Card::Wildcard]));
+
+        let error = tray_from_tiles(vec![Tile::Code("Q4".to_string())]).unwrap_err();
+        assert_eq!(error.token, "Q4");
+    }
+
+    #[test]
+    fn sets_conversion_test() {
+        let tray = [Card::new(7, Color::Red), Card::new(7, Color::Blue), Card::Wildcard];
+        let sets = vec![tray.iter().collect::<Vec<&Card>>()];
+
+        assert_eq!(sets_to_codes(sets.clone()), vec![vec!["R7", "B7", "W"]]);
+        assert!(sets_to_cards(sets)[0].iter().map(|c| c.0).eq(tray));
+        assert!(PyColor::from(Color::Black) == PyColor::Black && Color::from(PyColor::Yellow) == Color::Yellow);
+    }
+
+}
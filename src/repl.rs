@@ -0,0 +1,615 @@
+use std::io::{self, BufRead, Write};
+use rummikub::{
+    parse_meld, render_table, suggest_moves, valid_sets, validate_set, Card, Deck, GameState, GreedyStrategy, Move, MoveError, ScoredMove, SortMode,
+    Strategy, Tray, TurnClock
+};
+
+// How many moves hint prints
+const HINTS: usize = 3;
+
+// Clears the terminal and moves the cursor back to the top
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+// What the player can type at the prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Draw,
+    Play(Vec<Card>),
+    Sets,
+    Sort(SortMode),
+    // Against bots: add a tile to a meld of the table, named like #2, and end the turn
+    Add(String, Card),
+    End,
+    // Against bots: prints the best moves
+    Hint,
+    Quit
+}
+
+pub fn parse_action(line: &str) -> Result<Action, String> {
+    let mut words = line.split_whitespace();
+    let command = words.next().ok_or("type a command: draw, play, add, end, hint, sets, sort or quit")?;
+    let args: Vec<&str> = words.collect();
+
+    match (command.to_ascii_lowercase().as_str(), args.as_slice()) {
+        ("draw", []) => Ok(Action::Draw),
+        ("sets", []) => Ok(Action::Sets),
+        ("quit", []) => Ok(Action::Quit),
+        ("sort", ["color"]) => Ok(Action::Sort(SortMode::ByColorThenNumber)),
+        ("sort", ["number"]) => Ok(Action::Sort(SortMode::ByNumberThenColor)),
+        ("sort", _) => Err("usage: sort color|number".to_string()),
+        ("end", []) => Ok(Action::End),
+        ("hint", []) => Ok(Action::Hint),
+        ("add", [meld, tile]) => {
+            let label = if meld.starts_with('#') { meld.to_string() } else { format!("#{}", meld) };
+            tile.parse::<Card>().map(|card| Action::Add(label, card)).map_err(|e| e.to_string())
+        },
+        ("add", _) => Err("usage: add <meld> <tile>, like add #2 R5".to_string()),
+        ("play", []) => Err("usage: play <tiles>, like play B2 B3 B4, B2-B3-B4 or 7RBY for a group".to_string()),
+        ("play", [first, ..]) => {
+            // The meld goes on to the end of the line
+            let meld = &line[first.as_ptr() as usize - line.as_ptr() as usize..];
+            parse_meld(meld).map(Action::Play).map_err(|e| e.to_string())
+        },
+        ("draw" | "sets" | "end" | "hint" | "quit", _) => Err(format!("{} takes no arguments", command)),
+        _ => Err(format!("unknown command '{}': try draw, play, add, end, hint, sets, sort or quit", command))
+    }
+}
+
+// What happened after an action
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Drew(Card),
+    Played(Vec<Card>),
+    Sets(Vec<Vec<Card>>),
+    Sorted,
+    Rejected(String),
+    // The game is over
+    Won,
+    DeckEmpty,
+    Quit
+}
+
+impl Outcome {
+    pub fn ends_game(&self) -> bool {
+        matches!(self, Outcome::Won | Outcome::DeckEmpty | Outcome::Quit)
+    }
+}
+
+// A single player game: draw tiles and lay down sets until the tray is empty
+pub struct Session {
+    deck: Deck,
+    pub tray: Vec<Card>,
+    pub table: Vec<Vec<Card>>
+}
+
+impl Session {
+    pub fn new(mut deck: Deck, tray_size: usize) -> Session {
+        let tray = deck.pick_tray(tray_size);
+        Session { deck, tray, table: vec![] }
+    }
+
+    pub fn deck_len(&self) -> usize {
+        self.deck.len()
+    }
+
+    pub fn apply(&mut self, action: Action) -> Outcome {
+        match action {
+            Action::Draw => match self.deck.pick_card() {
+                Some(card) => {
+                    self.tray.push(card);
+                    Outcome::Drew(card)
+                },
+                None => Outcome::DeckEmpty
+            },
+            Action::Play(set) => self.play(set),
+            Action::Sets => Outcome::Sets(owned_sets(&self.tray)),
+            Action::Sort(mode) => {
+                self.tray = Tray::from(self.tray.clone()).sorted_view(mode);
+                Outcome::Sorted
+            },
+            Action::Add(..) | Action::End | Action::Hint =>
+                Outcome::Rejected("add, end and hint are for games against bots".to_string()),
+            Action::Quit => Outcome::Quit
+        }
+    }
+
+    // Takes exactly the played tiles from the tray, one copy each, if they form a valid set
+    fn play(&mut self, set: Vec<Card>) -> Outcome {
+        if let Err(error) = validate_set(&set) {
+            return Outcome::Rejected(format!("not a valid set: {}", error));
+        }
+        let mut tray = self.tray.clone();
+        for card in &set {
+            match tray.iter().position(|c| c == card) {
+                Some(idx) => {
+                    tray.remove(idx);
+                },
+                None => return Outcome::Rejected(format!("{} is not in your tray", card.code()))
+            }
+        }
+
+        self.tray = tray;
+        self.table.push(set.clone());
+        if self.tray.is_empty() {
+            Outcome::Won
+        } else {
+            Outcome::Played(set)
+        }
+    }
+}
+
+// Reads actions line by line until the game ends or the input is over
+pub fn run(session: &mut Session, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    print_tray(session, &mut output)?;
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let outcome = match parse_action(&line) {
+            Ok(action) => session.apply(action),
+            Err(message) => Outcome::Rejected(message)
+        };
+
+        match &outcome {
+            Outcome::Drew(card) => writeln!(output, "You drew {}", card)?,
+            Outcome::Played(set) => writeln!(output, "You played {}", cards_line(set))?,
+            Outcome::Sets(sets) if sets.is_empty() => writeln!(output, "No valid sets")?,
+            Outcome::Sets(sets) => {
+                for set in sets {
+                    writeln!(output, " -> {}", cards_line(set))?;
+                }
+            },
+            Outcome::Sorted => {},
+            Outcome::Rejected(message) => writeln!(output, "{}", message)?,
+            Outcome::Won => writeln!(output, "Your tray is empty: you won!")?,
+            Outcome::DeckEmpty => writeln!(output, "The deck is empty: game over")?,
+            Outcome::Quit => writeln!(output, "Bye")?
+        }
+        if outcome.ends_game() {
+            return Ok(());
+        }
+        if matches!(outcome, Outcome::Drew(_) | Outcome::Played(_) | Outcome::Sorted) {
+            print_tray(session, &mut output)?;
+        }
+    }
+    Ok(())
+}
+
+// Plays against bots, the human being the first player. Bots play their turns right away,
+// announcing their moves, and the human types actions like in a single player game, within
+// the time of the clock if there is one.
+pub fn run_with_bots(game: &mut GameState, input: impl BufRead, mut output: impl Write, mut clock: Option<&mut TurnClock>) -> io::Result<()> {
+    let mut bot = GreedyStrategy;
+    let mut sort_mode = SortMode::ByNumberThenColor;
+    let mut lines = input.lines();
+    while game.round_result().is_none() {
+        if game.current_player_index() != 0 {
+            let name = game.current_player().name().to_string();
+            let mv = bot.choose_move(&game.view_for(game.current_player_index()));
+            match game.apply_move(mv.clone()) {
+                Ok(_) => match mv {
+                    Move::Draw => writeln!(output, "{} draws a tile", name)?,
+                    Move::PlayNewMeld(cards) => writeln!(output, "{} plays {}", name, cards_line(&cards))?,
+                    Move::ExtendMeld { meld, card } => writeln!(output, "{} adds {} to {}", name, card, meld)?,
+                    Move::TakeDiscard => writeln!(output, "{} takes the discard", name)?,
+                    Move::Discard(card) => writeln!(output, "{} discards {}", name, card)?,
+                    Move::Mulligan(_) => writeln!(output, "{} takes a mulligan", name)?,
+                    Move::RetrieveWildcard { .. } | Move::EndTurn => {}
+                },
+                Err(error) => {
+                    writeln!(output, "{} is stuck: {}", name, error)?;
+                    return Ok(());
+                }
+            }
+            continue;
+        }
+
+        if !play_turn(game, &mut lines, &mut output, &mut sort_mode, clock.as_deref_mut())? {
+            return Ok(());
+        }
+    }
+    print_result(game, &mut output)
+}
+
+// Plays with everyone at the same device, passing it around. Between turns the screen is
+// cleared and the next player presses enter once they have the device, so that nobody sees
+// the tray of someone else. The clock, if there is one, starts once they press enter.
+pub fn run_hot_seat(game: &mut GameState, input: impl BufRead, mut output: impl Write, mut clock: Option<&mut TurnClock>) -> io::Result<()> {
+    let mut sort_modes = vec![SortMode::ByNumberThenColor; game.players().len()];
+    let mut lines = input.lines();
+    while game.round_result().is_none() {
+        let player = game.current_player_index();
+        write!(output, "{}", CLEAR_SCREEN)?;
+        writeln!(output, "Press enter when {} has the device", game.current_player().name())?;
+        match lines.next() {
+            Some(line) => line?,
+            None => return Ok(())
+        };
+        if !play_turn(game, &mut lines, &mut output, &mut sort_modes[player], clock.as_deref_mut())? {
+            return Ok(());
+        }
+    }
+    write!(output, "{}", CLEAR_SCREEN)?;
+    print_result(game, &mut output)
+}
+
+// Reads the actions of the current player until their turn is over, showing them their tray
+// and the table. Mistakes are told and the player types again. With a clock, the seconds left
+// are shown with the tray, and a line typed once they ran out ends the turn with a draw
+// instead. Returns false when the player quits or the input is over.
+fn play_turn(
+    game: &mut GameState,
+    lines: &mut impl Iterator<Item = io::Result<String>>,
+    output: &mut impl Write,
+    sort_mode: &mut SortMode,
+    mut clock: Option<&mut TurnClock>
+) -> io::Result<bool> {
+    let player = game.current_player_index();
+    if let Some(clock) = clock.as_deref_mut() {
+        clock.start_turn();
+    }
+    let mut show_tray = true;
+    loop {
+        if show_tray {
+            write!(output, "Table:\n{}", render_table(game.table()))?;
+            let tray = Tray::from(game.current_player().tray().to_vec());
+            writeln!(output, "Your tray ({} tiles left in the deck):", game.deck_len())?;
+            writeln!(output, " {}", tray.display_grouped(*sort_mode))?;
+            if let Some(clock) = &clock {
+                writeln!(output, "{} seconds left", clock.remaining().as_secs())?;
+            }
+            show_tray = false;
+        }
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => return Ok(false)
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if clock.as_deref_mut().is_some_and(|clock| clock.enforce(game)) {
+            writeln!(output, "Time is up: you draw a tile and your turn is over")?;
+            return Ok(true);
+        }
+        let action = match parse_action(&line) {
+            Ok(action) => action,
+            Err(message) => {
+                writeln!(output, "{}", message)?;
+                continue;
+            }
+        };
+
+        let played = match action {
+            // Drawing ends the turn
+            Action::Draw => match game.apply_move(Move::Draw) {
+                Ok(outcome) => {
+                    writeln!(output, "You drew {}", outcome.drawn.unwrap())?;
+                    game.apply_move(Move::EndTurn)
+                },
+                Err(error) => Err(error)
+            },
+            Action::Play(cards) => game.apply_move(Move::PlayNewMeld(cards)),
+            Action::Add(label, card) => {
+                let meld = game.table().melds().map(|(id, _)| id).find(|id| id.to_string() == label);
+                match meld {
+                    Some(meld) => game.apply_move(Move::ExtendMeld { meld, card }),
+                    None => {
+                        writeln!(output, "there is no meld {} on the table", label)?;
+                        continue;
+                    }
+                }
+            },
+            Action::End => game.apply_move(Move::EndTurn),
+            Action::Sets => {
+                let sets = owned_sets(game.current_player().tray());
+                if sets.is_empty() {
+                    writeln!(output, "No valid sets")?;
+                }
+                for set in sets {
+                    writeln!(output, " -> {}", cards_line(&set))?;
+                }
+                continue;
+            },
+            Action::Hint => {
+                let view = game.view_for(player);
+                for scored in suggest_moves(&view).iter().take(HINTS) {
+                    writeln!(output, " -> {}", describe_hint(scored))?;
+                }
+                continue;
+            },
+            Action::Sort(mode) => {
+                *sort_mode = mode;
+                show_tray = true;
+                continue;
+            },
+            Action::Quit => {
+                writeln!(output, "Bye")?;
+                return Ok(false);
+            }
+        };
+        match played {
+            Ok(_) if game.round_result().is_some() || game.current_player_index() != player => return Ok(true),
+            Ok(_) => show_tray = true,
+            Err(MoveError::DeckEmpty) => writeln!(output, "The deck is empty: type end to pass")?,
+            Err(error) => writeln!(output, "{}", error)?
+        }
+    }
+}
+
+// The winner of the round and the penalty of everyone
+fn print_result(game: &GameState, output: &mut impl Write) -> io::Result<()> {
+    let result = game.round_result().unwrap();
+    let name = |id: usize| game.players()[id].name().to_string();
+    writeln!(output, "{} won the round: +{}", name(result.winner), result.winner_gain)?;
+    for (player, penalty) in &result.penalties {
+        writeln!(output, "{}: {}", name(*player), penalty)?;
+    }
+    Ok(())
+}
+
+fn describe_hint(scored: &ScoredMove) -> String {
+    let mv = match &scored.mv {
+        Move::Draw => return "draw".to_string(),
+        Move::EndTurn => return "end".to_string(),
+        Move::TakeDiscard => return "take the discard".to_string(),
+        Move::Discard(card) => format!("discard {}", card.code()),
+        Move::Mulligan(_) => return "take a mulligan".to_string(),
+        Move::PlayNewMeld(cards) => format!("play {}", cards_line(cards)),
+        Move::ExtendMeld { meld, card } => format!("add {} to {}", card.code(), meld),
+        Move::RetrieveWildcard { meld, card } => format!("put {} in place of the wildcard of {}", card.code(), meld)
+    };
+    let tiles = if scored.tiles == 1 { "1 tile".to_string() } else { format!("{} tiles", scored.tiles) };
+    let out = if scored.goes_out { ", then you can go out" } else { "" };
+    format!("{}: {} points, {}{}", mv, scored.points, tiles, out)
+}
+
+fn owned_sets(tray: &[Card]) -> Vec<Vec<Card>> {
+    valid_sets(tray).into_iter()
+        .map(|s| s.into_iter().copied().collect())
+        .collect()
+}
+
+fn print_tray(session: &Session, output: &mut impl Write) -> io::Result<()> {
+    writeln!(output, "Your tray ({} tiles left in the deck):", session.deck_len())?;
+    writeln!(output, " {}", cards_line(&session.tray))
+}
+
+fn cards_line(cards: &[Card]) -> String {
+    cards.iter().map(|c| c.code()).collect::<Vec<String>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::io::Cursor;
+    use rummikub::{Color, ManualClock};
+    use super::*;
+
+    fn session(tray: Vec<Card>) -> Session {
+        let mut session = Session::new(Deck::new_seeded(1), 0);
+        session.tray = tray;
+        session
+    }
+
+    #[test]
+    fn parse_action_test() {
+        assert_eq!(parse_action("draw"), Ok(Action::Draw));
+        assert_eq!(parse_action("  SETS "), Ok(Action::Sets));
+        assert_eq!(parse_action("sort color"), Ok(Action::Sort(SortMode::ByColorThenNumber)));
+        assert_eq!(
+            parse_action("play B2 b3 W"),
+            Ok(Action::Play(vec![Card::new(2, Color::Blue), Card::new(3, Color::Blue), Card::Wildcard]))
+        );
+        assert_eq!(
+            parse_action("play 7RbW"),
+            Ok(Action::Play(vec![Card::new(7, Color::Red), Card::new(7, Color::Blue), Card::Wildcard]))
+        );
+        assert_eq!(parse_action("play R5-r3-R4"), parse_action("play R3 R4 R5"));
+        assert!(parse_action("play B2 X3").is_err());
+        assert_eq!(parse_action("play R3-7B"), Err("'7B' mixes a group like 7RBY with other tiles".to_string()));
+        assert!(parse_action("play").is_err());
+        assert!(parse_action("sort size").is_err());
+        assert!(parse_action("draw 2").is_err());
+        assert!(parse_action("jump").is_err());
+        assert_eq!(parse_action("add 2 r5"), Ok(Action::Add("#2".to_string(), Card::new(5, Color::Red))));
+        assert_eq!(parse_action("end"), Ok(Action::End));
+        assert_eq!(parse_action("hint"), Ok(Action::Hint));
+        assert!(parse_action("add #2").is_err());
+    }
+
+    #[test]
+    fn play_removes_exact_tiles_test() {
+        let mut session = session(vec![
+            Card::new(2, Color::Blue),
+            Card::new(3, Color::Blue),
+            Card::new(3, Color::Blue),
+            Card::new(4, Color::Blue),
+            Card::new(9, Color::Red)
+        ]);
+
+        let set = vec![Card::new(2, Color::Blue), Card::new(3, Color::Blue), Card::new(4, Color::Blue)];
+        assert_eq!(session.apply(Action::Play(set.clone())), Outcome::Played(set.clone()));
+
+        assert_eq!(session.tray, vec![Card::new(3, Color::Blue), Card::new(9, Color::Red)]);
+        assert_eq!(session.table, vec![set]);
+    }
+
+    #[test]
+    fn play_rejected_test() {
+        let tray = vec![
+            Card::new(2, Color::Blue),
+            Card::new(3, Color::Blue),
+            Card::new(5, Color::Blue),
+            Card::new(5, Color::Red)
+        ];
+        let mut session = session(tray.clone());
+
+        let invalid = vec![Card::new(2, Color::Blue), Card::new(3, Color::Blue), Card::new(5, Color::Blue)];
+        let missing = vec![Card::new(5, Color::Blue), Card::new(5, Color::Red), Card::new(5, Color::Black)];
+        let twice = vec![Card::new(2, Color::Blue), Card::new(2, Color::Blue), Card::new(2, Color::Red)];
+
+        assert!(matches!(session.apply(Action::Play(invalid)), Outcome::Rejected(_)));
+        assert!(matches!(session.apply(Action::Play(missing)), Outcome::Rejected(_)));
+        assert!(matches!(session.apply(Action::Play(twice)), Outcome::Rejected(_)));
+        assert_eq!(session.tray, tray);
+        assert!(session.table.is_empty());
+    }
+
+    #[test]
+    fn play_last_tiles_wins_test() {
+        let mut session = session(vec![Card::new(7, Color::Red), Card::new(7, Color::Blue), Card::Wildcard]);
+
+        let outcome = session.apply(Action::Play(vec![Card::new(7, Color::Red), Card::new(7, Color::Blue), Card::Wildcard]));
+
+        assert_eq!(outcome, Outcome::Won);
+        assert!(outcome.ends_game());
+    }
+
+    #[test]
+    fn draw_until_empty_test() {
+        let mut session = Session::new(Deck::new_seeded(3), 100);
+
+        for _ in 0..6 {
+            assert!(matches!(session.apply(Action::Draw), Outcome::Drew(_)));
+        }
+        assert_eq!(session.tray.len(), 106);
+        assert_eq!(session.apply(Action::Draw), Outcome::DeckEmpty);
+    }
+
+    #[test]
+    fn sort_test() {
+        let mut session = session(vec![
+            Card::Wildcard,
+            Card::new(3, Color::Blue),
+            Card::new(1, Color::Yellow),
+            Card::new(2, Color::Red)
+        ]);
+
+        session.apply(Action::Sort(SortMode::ByColorThenNumber));
+        assert_eq!(session.tray, vec![
+            Card::new(2, Color::Red),
+            Card::new(3, Color::Blue),
+            Card::new(1, Color::Yellow),
+            Card::Wildcard
+        ]);
+
+        session.apply(Action::Sort(SortMode::ByNumberThenColor));
+        assert_eq!(session.tray, vec![
+            Card::new(1, Color::Yellow),
+            Card::new(2, Color::Red),
+            Card::new(3, Color::Blue),
+            Card::Wildcard
+        ]);
+    }
+
+    #[test]
+    fn run_test() {
+        let mut session = session(vec![Card::new(1, Color::Black), Card::new(2, Color::Black), Card::new(3, Color::Black)]);
+        let input = Cursor::new("sets\nplay K1 K2\n\nplay K1 K2 K3\nsets\n");
+        let mut output = vec![];
+
+        run(&mut session, input, &mut output).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "\
+Your tray (106 tiles left in the deck):
+ K1 K2 K3
+ -> K1 K2 K3
+not a valid set: a set needs at least 3 tiles, this one has 2
+Your tray is empty: you won!
+");
+    }
+
+    #[test]
+    fn run_with_bots_test() {
+        let mut game = GameState::new_game(&["You", "Bot 1"], 7);
+        let input = Cursor::new("add #4 R1
+end
+draw
+quit
+");
+        let mut output = vec![];
+
+        run_with_bots(&mut game, input, &mut output, None).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("Table:\n(empty table)\nYour tray (78 tiles left in the deck):\n \
+            B1 | B2 K2 | Y3 | R4 | B5 | R6 B6 Y6 | R7 | R10 | R12 B12 | W\n"), "{}", output);
+        assert!(output.contains("there is no meld #4 on the table\nplay a tile or draw before ending your turn\nYou drew "));
+        assert!(output.contains("Bot 1 "));
+        assert!(output.ends_with("Bye\n"));
+        assert_eq!(game.current_player_index(), 0);
+    }
+
+    #[test]
+    fn hint_test() {
+        let mut game = GameState::new_game(&["You", "Bot 1"], 7);
+        let mut output = vec![];
+
+        run_with_bots(&mut game, Cursor::new("hint\nquit\n"), &mut output, None).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.ends_with("
+ -> play R12 B12 W: 36 points, 3 tiles
+ -> play R6 B6 Y6: 18 points, 3 tiles
+ -> draw
+Bye
+"), "{}", output);
+    }
+
+
+    #[test]
+    fn hot_seat_test() {
+        let mut game = GameState::new_game(&["Ann", "Bob"], 7);
+        let input = Cursor::new("\nplay R6 X6\nplay R4 B5 R7\ndraw\n\nquit\n");
+        let mut output = vec![];
+
+        run_hot_seat(&mut game, input, &mut output, None).unwrap();
+
+        // The tray of Ann is gone from the screen before Bob gets the device
+        let output = String::from_utf8(output).unwrap();
+        let screens: Vec<&str> = output.split(CLEAR_SCREEN).collect();
+        assert_eq!(screens.len(), 3, "{}", output);
+        assert!(screens[1].starts_with("Press enter when Ann has the device\nTable:\n(empty table)\n\
+            Your tray (78 tiles left in the deck):\n B1 | B2 K2 | Y3 | R4 | B5 | R6 B6 Y6 | R7 | R10 | R12 B12 | W\n"), "{}", output);
+        assert!(screens[1].contains("\nunknown tile 'X6'"), "{}", output);
+        assert!(screens[1].contains("\nnot a valid set"), "{}", output);
+        assert!(screens[1].contains("\nYou drew "));
+        assert!(screens[2].starts_with("Press enter when Bob has the device\nTable:\n"));
+        assert!(!screens[2].contains("R12 B12"));
+        assert!(screens[2].ends_with("Bye\n"));
+        assert_eq!(game.current_player_index(), 1);
+    }
+
+    #[test]
+    fn hot_seat_timeout_test() {
+        let mut game = GameState::new_game(&["Ann", "Bob"], 7);
+        // No time at all: whatever Ann types comes too late
+        let mut clock = TurnClock::new(Box::new(ManualClock::new()), 0);
+        let mut output = vec![];
+
+        run_hot_seat(&mut game, Cursor::new("\nplay R4 B5 R7\n\nquit\n"), &mut output, Some(&mut clock)).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let screens: Vec<&str> = output.split(CLEAR_SCREEN).collect();
+        assert!(screens[1].contains("\n0 seconds left\nTime is up: you draw a tile and your turn is over\n"), "{}", output);
+        assert!(screens[2].starts_with("Press enter when Bob has the device\n"), "{}", output);
+        assert_eq!(game.players()[0].tray().len(), 15);
+        assert!(game.events().iter().all(|event| event.timed_out));
+    }
+
+    #[test]
+    fn hot_seat_round_end_test() {
+        let mut game = GameState::new_game(&["Ann", "Bob"], 7);
+        let input = "\ndraw\n".repeat(78) + &"\nend\n".repeat(2);
+        let mut output = vec![];
+
+        run_hot_seat(&mut game, Cursor::new(input), &mut output, None).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let result = game.round_result().unwrap();
+        let last_screen = output.rsplit(CLEAR_SCREEN).next().unwrap();
+        assert!(last_screen.starts_with(&format!("{} won the round: +{}\n", game.players()[result.winner].name(), result.winner_gain)), "{}", last_screen);
+    }
+
+}
@@ -1,14 +1,174 @@
 use core::fmt;
-use std::collections::{BTreeMap, BTreeSet};
+use core::str::FromStr;
+use alloc::collections::{BTreeMap, BTreeSet};
 use rand::prelude::*;
 use iter_tools::Itertools;
+use thiserror::Error;
+use crate::prelude::*;
 
+mod almost;
+#[cfg(feature = "std")]
+mod audit;
+mod bitset;
+mod canonical;
+#[cfg(feature = "std")]
+mod clock;
+mod conflict;
+mod counts;
+mod diff;
+mod distinct;
+mod draws;
+#[cfg(feature = "std")]
+mod engine;
+#[cfg(feature = "std")]
+mod evaluate;
+#[cfg(feature = "std")]
+mod export;
+mod extension;
+#[cfg(feature = "std")]
+mod game;
+#[cfg(feature = "std")]
+mod hint;
+mod html;
+mod index;
+#[cfg(feature = "std")]
+mod inference;
+mod json;
+#[cfg(feature = "std")]
+mod loader;
+#[cfg(feature = "std")]
+mod lookahead;
+mod manipulation;
+#[cfg(feature = "std")]
+mod match_play;
+#[cfg(feature = "std")]
+mod notation;
+#[cfg(feature = "std")]
+mod observer;
+mod opening;
+mod parallel;
+mod partition;
+#[cfg(feature = "std")]
+mod penalty;
+mod position;
+mod render;
+#[cfg(feature = "std")]
+mod replay;
+mod rules;
+#[cfg(feature = "std")]
+mod save;
+mod shorthand;
+#[cfg(feature = "std")]
+mod simulate;
+#[cfg(feature = "std")]
+mod stats;
+#[cfg(feature = "std")]
+mod strategy;
+mod table;
+#[cfg(feature = "std")]
+mod teams;
+mod theme;
+#[cfg(feature = "std")]
+mod tournament;
+mod trace;
+mod tray;
+mod turn;
+mod twist;
+mod validation;
+mod verify;
+mod wildcards;
+mod wire;
+
+pub use almost::{find_almost_sets, AlmostSet};
+#[cfg(feature = "std")]
+pub use audit::{audit_turn, CheatError, TurnSummary};
+pub use canonical::{canonicalize_set, sets_equal, SetKey};
+#[cfg(feature = "std")]
+pub use clock::{Clock, ManualClock, SystemClock, TurnClock};
+pub use conflict::{set_conflicts, ConflictGraph};
+pub use counts::{CardCounts, MissingTiles};
+pub use diff::{diff_trays, TableDiff, TileMove, TrayDiff};
+pub use distinct::{distinct_sets, distinct_sets_with, DistinctSet};
+pub use draws::{draw_outcomes, p_improve};
+#[cfg(feature = "std")]
+pub use engine::{EngineCommand, EngineEvent, EnginePlayer, EngineView, GameEngine};
+#[cfg(feature = "std")]
+pub use evaluate::{evaluate_hand, HandScore, HandWeights};
+#[cfg(feature = "std")]
+pub use export::{sets_to_csv, tray_summary_csv};
+pub use extension::{find_extensions, Extension, ExtensionPosition};
+#[cfg(feature = "std")]
+pub use game::{GameEvent, GameState, Move, MoveError, MoveOutcome, Player, PlayerId, PlayerSummary, PlayerView, RoundEnd, RoundResult, SpectatorView, UndoError, TRAY_SIZE};
+#[cfg(feature = "std")]
+pub use hint::{suggest_moves, ScoredMove};
+pub use html::{render_html, render_html_with_sets};
+pub use index::{has_valid_set_from_index, valid_sets_from_index, TrayIndex};
+#[cfg(feature = "std")]
+pub use inference::Inference;
+pub use json::{partition_to_json, sets_to_json, validation_to_json, JSON_VERSION};
+#[cfg(feature = "std")]
+pub use loader::{load_tray, TrayLoadError};
+#[cfg(feature = "std")]
+pub use lookahead::{best_case_outs, min_draws_to_out, p_complete_within, p_out_within, DrawGoal};
+pub use manipulation::{generate_manipulations, ManipulationPlan, TableOp};
+#[cfg(feature = "std")]
+pub use match_play::{Match, MatchEnd, MatchStatus};
+#[cfg(feature = "std")]
+pub use notation::{load_notation, parse_notation, save_notation, write_notation, GameRecord, NotationError, SyntaxError};
+#[cfg(feature = "std")]
+pub use observer::{GameObserver, PublicDraw};
+pub use opening::{find_opening_melds, has_opening_meld};
+pub use partition::{best_partition, can_go_out, Partition};
+#[cfg(feature = "std")]
+pub use partition::{best_partition_with_budget, BudgetedPartition};
+#[cfg(feature = "std")]
+pub use penalty::{hand_penalties, hand_penalty, winner_bonus, WILDCARD_PENALTY};
+pub use position::{load_position, save_position, PositionError};
+pub use render::{render_table, render_table_colored, render_table_with};
+#[cfg(feature = "std")]
+pub use replay::{replay, replay_moves, verify_replay, ReplayError};
+pub use rules::{Mulligan, RulesConfig, ScoringVariant, VariantRules};
+#[cfg(feature = "std")]
+pub use save::{load_game, save_game, GameSave, SaveError, SavedPlayer, SAVE_VERSION};
+pub use shorthand::{parse_meld, parse_meld_with, MeldParseError};
+#[cfg(feature = "std")]
+pub use simulate::{analyze_trays, simulate, Bucket, SimulationReport, Stat, TrayStats};
+#[cfg(feature = "std")]
+pub use stats::{PlayerStats, StatsBook};
+#[cfg(feature = "std")]
+pub use strategy::{play_round, GreedyStrategy, HighTileDumper, RandomStrategy, Strategy};
+pub use table::{JokerError, MeldId, Table};
+#[cfg(feature = "std")]
+pub use teams::{TeamId, Teams};
+pub use theme::DisplayTheme;
+#[cfg(feature = "std")]
+pub use tournament::{run_tournament, PairingStats, StrategyKind, StrategyStats, TournamentReport};
+pub use trace::{trace_sets, SearchTrace, TraceEvent};
+pub use tray::{SortMode, Tray};
+pub use turn::{check_turn, solve_turn, TurnError, TurnPlan};
+pub use validation::{validate_set, validate_set_with, MeldKind, SetError};
+pub use verify::{verify_sets, verify_sets_with, VerifyError};
+pub use wildcards::{display_meld, display_meld_with, resolve_wildcards, resolve_wildcards_with, score_meld, score_meld_with};
+pub use wire::{decode_card, decode_meld, encode_card, encode_meld, WireError, WIRE_MAGIC, WIRE_VERSION};
+#[cfg(feature = "std")]
+pub use wire::{decode_game, encode_game};
+
+/// The colors of the numbered tiles: four in the standard game, six in the large-format one
 #[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash)]
 pub enum Color {
     Red,
     Blue,
     Black,
-    Yellow
+    Yellow,
+    Green,
+    Orange
+}
+
+impl Color {
+    /// The colors of the standard game, in the order used to sort tiles
+    pub const ALL: [Color; 4] = [Color::Red, Color::Blue, Color::Black, Color::Yellow];
+    /// The colors of the six-color variant, see RulesConfig::six_colors
+    pub const SIX: [Color; 6] = [Color::Red, Color::Blue, Color::Black, Color::Yellow, Color::Green, Color::Orange];
 }
 
 impl fmt::Display for Color {
@@ -17,84 +177,255 @@ impl fmt::Display for Color {
     }
 }
 
-#[derive(Eq, PartialEq, PartialOrd, Ord, Debug)]
+/// A tile of the game. Cards sort by number, then by color, with wildcards last and the
+/// jokers of the Twist rules after them.
+#[derive(Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, Debug)]
 pub enum Card {
     Numbered {
         number: i8,
         color: Color
     },
-    Wildcard
+    Wildcard,
+    /// With RulesConfig::twist_jokers, a joker standing for two tiles of a group
+    DoubleJoker,
+    /// With RulesConfig::twist_jokers, a joker in the middle of a run that reads the same
+    /// both ways around it, like 4 5 M 5 4
+    MirrorJoker
 }
 
 impl Card {
-    fn new(number: i8, color: Color) -> Card {
+    /// A numbered tile, for numbers known to be right like those of the deck. Debug builds
+    /// check that the number is at least 1, the highest one depending on the rules: tiles
+    /// read from users or other programs go through try_new instead.
+    pub fn new(number: i8, color: Color) -> Card {
+        debug_assert!(number >= 1, "tile number {} below 1", number);
         Card::Numbered { number, color }
     }
 
-    fn number(&self) -> Option<&i8> {
+    /// A numbered tile, failing unless the number is one of the standard game, 1 to 13
+    pub fn try_new(number: i8, color: Color) -> Result<Card, InvalidCard> {
+        Card::try_new_with(number, color, &RulesConfig::default())
+    }
+
+    /// Like try_new, with the numbers of the rules
+    pub fn try_new_with(number: i8, color: Color, rules: &RulesConfig) -> Result<Card, InvalidCard> {
+        if !rules.numbers().contains(&number) {
+            return Err(InvalidCard { number, min: rules.min_number, max: rules.max_number });
+        }
+        Ok(Card::Numbered { number, color })
+    }
+
+    /// The number of the tile, or None for wildcards and jokers
+    pub fn number(&self) -> Option<&i8> {
         match self {
             Card::Numbered { number, color: _ } => Some(number),
-            Card::Wildcard => None
+            _ => None
         }
     }
 
-    fn color(&self) -> Option<&Color> {
+    /// The color of the tile, or None for wildcards and jokers
+    pub fn color(&self) -> Option<&Color> {
         match self {
             Card::Numbered { number: _, color } => Some(color),
-            Card::Wildcard => None
+            _ => None
         }
     }
 
-    fn is_wildcard(&self) -> bool {
+    pub fn is_wildcard(&self) -> bool {
         *self == Card::Wildcard
     }
+
+    /// Whether the tile is one of the jokers of the Twist rules
+    pub fn is_twist_joker(&self) -> bool {
+        matches!(self, Card::DoubleJoker | Card::MirrorJoker)
+    }
+
+    /// The short form of the tile, as read by from_str
+    pub fn code(&self) -> String {
+        match self {
+            Card::Numbered { number, color } => {
+                let letter = match color {
+                    Color::Red => 'R',
+                    Color::Blue => 'B',
+                    Color::Black => 'K',
+                    Color::Yellow => 'Y',
+                    Color::Green => 'G',
+                    Color::Orange => 'O'
+                };
+                format!("{}{}", letter, number)
+            },
+            Card::Wildcard => "W".to_string(),
+            Card::DoubleJoker => "D".to_string(),
+            Card::MirrorJoker => "M".to_string()
+        }
+    }
 }
 
 impl fmt::Display for Card {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Card::Numbered {number, color} => write!(f, "{} {}", number, color),
-            Card::Wildcard => write!(f, "Wildcard")
+            Card::Wildcard => write!(f, "Wildcard"),
+            Card::DoubleJoker => write!(f, "Double joker"),
+            Card::MirrorJoker => write!(f, "Mirror joker")
         }
         
     }
 }
 
+/// Error returned by Card::try_new when the number is outside the rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("invalid tile number {number}: expected a number from {min} to {max}")]
+pub struct InvalidCard {
+    pub number: i8,
+    pub min: i8,
+    pub max: i8
+}
+
+/// Error returned when a tile code can't be read
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("unknown tile '{token}': expected a color (R, B, K, Y, G or O) followed by a number from {min} to {max}, or W for a wildcard")]
+pub struct ParseCardError {
+    pub token: String,
+    /// The numbers the tiles could have
+    pub min: i8,
+    pub max: i8
+}
+
+/// Reads the short form of a tile: its color letter and number (R7, B12, K1, Y13) or W for
+/// a wildcard, in upper or lower case
+impl FromStr for Card {
+    type Err = ParseCardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Card::parse_with(s, &RulesConfig::default())
+    }
+}
+
+impl Card {
+    /// Like from_str, with the numbers of the rules instead of 1 to 13, and D and M for the
+    /// jokers when the rules have twist_jokers
+    pub fn parse_with(s: &str, rules: &RulesConfig) -> Result<Card, ParseCardError> {
+        let error = || ParseCardError { token: s.to_string(), min: rules.min_number, max: rules.max_number };
+        let code = s.to_ascii_uppercase();
+        match code.as_str() {
+            "W" => return Ok(Card::Wildcard),
+            "D" if rules.twist_jokers => return Ok(Card::DoubleJoker),
+            "M" if rules.twist_jokers => return Ok(Card::MirrorJoker),
+            _ => {}
+        }
+
+        let mut chars = code.chars();
+        let color = match chars.next() {
+            Some('R') => Color::Red,
+            Some('B') => Color::Blue,
+            Some('K') => Color::Black,
+            Some('Y') => Color::Yellow,
+            Some('G') => Color::Green,
+            Some('O') => Color::Orange,
+            _ => return Err(error())
+        };
+        let digits = chars.as_str();
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return Err(error());
+        }
+        digits.parse::<i8>().ok()
+            .and_then(|number| Card::try_new_with(number, color, rules).ok())
+            .ok_or_else(error)
+    }
+}
 
+/// The 106 tiles of the game: two copies of every numbered tile and two wildcards
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Deck {
     cards: Vec<Card>
 }
 
+/// Error returned by Deck::standard_without
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DeckError {
+    /// The tile was seen more times than the deck holds it
+    #[error("{} was seen more times than the deck holds it", .0.code())]
+    TooManyCopies(Card)
+}
+
+impl Default for Deck {
+    fn default() -> Self {
+        Deck::new()
+    }
+}
+
 impl Deck {
+    /// A full deck, sorted
     pub fn new() -> Deck {
-        let mut cards = Vec::with_capacity(106);
-        for _ in 0..2 {
-            // Two sets of cards
-            for c in [Color::Red, Color::Blue, Color::Black, Color::Yellow] {
-                for num in 1..=13 {
+        Deck::with_rules(&RulesConfig::default())
+    }
+
+    /// A full deck with the colors and wildcards of the rules, sorted, and a joker of each
+    /// kind with twist_jokers
+    pub fn with_rules(rules: &RulesConfig) -> Deck {
+        let mut cards = Vec::with_capacity(2 * rules.number_count() * rules.colors.len() + rules.wildcards);
+        for copy in 0..2 {
+            // Two sets of cards, each followed by half of the wildcards
+            for &c in rules.colors {
+                for num in rules.numbers() {
                     cards.push(Card::new(num, c));
                 }
             }
-            cards.push(Card::Wildcard);
+            let wildcards = if copy == 0 { rules.wildcards.div_ceil(2) } else { rules.wildcards / 2 };
+            cards.extend(core::iter::repeat_n(Card::Wildcard, wildcards));
         }
+        if rules.twist_jokers {
+            cards.extend([Card::DoubleJoker, Card::MirrorJoker]);
+        }
+        Deck { cards }
+    }
+
+    /// A deck holding these tiles, in this order: the last one is drawn first
+    pub fn from_cards(cards: Vec<Card>) -> Deck {
         Deck { cards }
     }
 
+    /// A full deck, sorted, without one copy of every seen tile, like the tray and the table.
+    /// Seeing a tile more times than the deck holds it is an error.
+    pub fn standard_without(seen: &[Card]) -> Result<Deck, DeckError> {
+        let mut cards = Deck::new().cards;
+        let mut seen = CardCounts::from_cards(seen);
+        CardCounts::from_cards(&cards).subtract(&seen).map_err(|missing| DeckError::TooManyCopies(missing.tiles[0]))?;
+        cards.retain(|card| seen.remove(card).is_err());
+        Ok(Deck { cards })
+    }
+
+    #[cfg(feature = "std")]
     pub fn new_shuffled() -> Deck {
         let mut deck = Deck::new();
         deck.shuffle();
         deck
     }
 
+    /// Always shuffles the deck in the same way for the same seed
+    pub fn new_seeded(seed: u64) -> Deck {
+        let mut deck = Deck::new();
+        deck.shuffle_with(&mut StdRng::seed_from_u64(seed));
+        deck
+    }
+
+    #[cfg(feature = "std")]
     pub fn shuffle(&mut self) {
         let mut rng = rand::thread_rng();
-        self.cards.shuffle(&mut rng);
+        self.shuffle_with(&mut rng);
+    }
+
+    pub fn shuffle_with<R: Rng>(&mut self, rng: &mut R) {
+        self.cards.shuffle(rng);
     }
 
+    /// Draws a tile, returning None once the deck is empty
     pub fn pick_card(&mut self) -> Option<Card> {
         self.cards.pop()
     }
 
+    /// Draws num_cards tiles, or what is left of the deck
     pub fn pick_tray(&mut self, num_cards: usize) -> Vec<Card> {
         let mut tray = Vec::with_capacity(num_cards);
         for _ in 0..num_cards {
@@ -104,141 +435,657 @@ impl Deck {
         }
         tray
     }
+
+    /// A copy of the tiles left, the next one to be drawn first
+    pub fn snapshot(&self) -> Vec<Card> {
+        self.cards.iter().rev().copied().collect()
+    }
+
+    /// How many tiles are left to draw
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
 }
 
-fn get_wildcards(cards: &[Card]) -> Vec<&Card> {
-    cards.iter()
+fn get_wildcards<'a>(cards: impl IntoIterator<Item = &'a Card>) -> Vec<&'a Card> {
+    cards.into_iter()
         .filter(|c| c.is_wildcard())
         .collect()
 }
 
-fn create_permutations<'a>(set: &[&'a Card]) -> BTreeSet<Vec<&'a Card>> {
-    let mut permutations = BTreeSet::new();
-    if set.len() <= 4 {
-        permutations.insert(set.to_owned());
+// Every subset of the cards (keeping their order) which can be a group: between min_len
+// and max_len cards, or the whole set when it is shorter than that, with a numbered tile.
+// The subsets come sorted and each only once, the first limit of them. The cards must be
+// sorted, like a layer of group_layers followed by its wildcards, for the search to find
+// them in that order and stop at the limit.
+fn create_permutations<'a>(set: &[&'a Card], min_len: usize, max_len: usize, limit: Option<usize>) -> Vec<Vec<&'a Card>> {
+    let shortest = min_len.max(set.len().min(min_len.max(3)));
+    let lengths = (shortest, set.len().min(max_len));
+    let mut found = vec![];
+    extend_permutation(set, 0, &mut vec![], lengths, limit.unwrap_or(usize::MAX), &mut found);
+    found
+}
+
+// Adds the subsets starting with prefix and going on from start, in order. Equal tiles are
+// only tried once at each position, which leaves out the copies. False once the limit is
+// reached.
+fn extend_permutation<'a>(
+    set: &[&'a Card],
+    start: usize,
+    prefix: &mut Vec<&'a Card>,
+    (shortest, longest): (usize, usize),
+    limit: usize,
+    found: &mut Vec<Vec<&'a Card>>
+) -> bool {
+    if prefix.len() >= shortest {
+        note_candidate();
+        if prefix.iter().any(|c| !c.is_wildcard()) {
+            found.push(prefix.clone());
+        }
+    }
+    if found.len() >= limit {
+        return false;
     }
-    if set.len() > 3 {
-        for remove_idx in 0..set.len() {
-            let mut subset = set.to_owned();
-            subset.remove(remove_idx);
-            let mut subpermutations = create_permutations(&subset);
-            permutations.append(&mut subpermutations);
+    if prefix.len() < longest {
+        for idx in start..set.len() {
+            if idx > start && set[idx] == set[idx - 1] {
+                continue;
+            }
+            prefix.push(set[idx]);
+            let more = extend_permutation(set, idx + 1, prefix, (shortest, longest), limit, found);
+            prefix.pop();
+            if !more {
+                return false;
+            }
         }
     }
-    permutations
+    true
+}
+
+// The subsets of create_permutations one at a time, shortest first, so that has_valid_set
+// can stop at the first one
+fn permutations<'a, 'b>(set: &'b [&'a Card], min_len: usize, max_len: usize) -> impl Iterator<Item = Vec<&'a Card>> + 'b {
+    let shortest = min_len.max(set.len().min(min_len.max(3)));
+    (shortest..=set.len().min(max_len))
+        .flat_map(move |len| set.iter().copied().combinations(len))
+        .inspect(|_| note_candidate())
+        // With three wildcards, the subsets of the wildcards alone
+        .filter(|cards| cards.iter().any(|c| !c.is_wildcard()))
+}
+
+// How many candidate sets the searches of this thread looked at: windows of runs and subsets
+// of groups. Only kept in tests, to compare the searches.
+#[cfg(test)]
+std::thread_local! {
+    static CANDIDATES: core::cell::Cell<usize> = const { core::cell::Cell::new(0) };
+}
+
+fn note_candidate() {
+    #[cfg(test)]
+    CANDIDATES.with(|count| count.set(count.get() + 1));
 }
 
-fn create_run_windows<'a>(set: &[Option<&'a Card>], wildcards: &[&'a Card]) -> Vec<Vec<&'a Card>> {
+// The set has a slot for every number of the rules. With wrapping runs the numbers go on
+// from the highest to the lowest, so after the windows inside the set come the ones starting
+// near its end and going on from its start. Windows around the same tiles, with the wildcards
+// standing for other numbers, give the same run, which is only kept the first time.
+fn create_run_windows<'a>(set: &[Option<&'a Card>], wildcards: &[&'a Card], options: &SetSearchOptions) -> Vec<Vec<&'a Card>> {
     let mut found_windows = vec![];
-    for window_length in (3..=set.len()).rev() {
-        // println!("Trying window of length {}", window_length);
-        for start_idx in 0..=set.len()-window_length {
-            //println!("Checking subset from {} to {}", start_idx, window_length+start_idx);
-            let subwindow = &set[start_idx..window_length+start_idx];
-            let missing_cards = subwindow.iter()
-                .filter(|c| c.is_none())
-                .count();
-            if missing_cards <= wildcards.len() {
-                let mut tmp_wildcards = wildcards.to_owned();
-                let run: Vec<&Card> = subwindow.iter()
-                    .map(|c| match c {
-                        Some(card) => *card,
-                        None => tmp_wildcards.pop().unwrap()
-                    })
-                    .collect();
-                //println!("Found run: {:?}", run);
-                found_windows.push(run);
+    let mut wildcard_runs = BTreeSet::new();
+    for (start_idx, window_length) in run_windows(set.len(), options) {
+        if let Ok(run) = fill_window(set, start_idx, window_length, wildcards) {
+            // Without wildcards the window is the only one with these tiles
+            if run.iter().any(|c| c.is_wildcard()) && !wildcard_runs.insert(SetKey::of(&run)) {
+                continue;
+            }
+            found_windows.push(run);
+            if options.is_full(found_windows.len()) {
+                return found_windows;
             }
         }
-        
     }
     found_windows
 }
 
-fn find_runs(cards: &[Card]) -> Vec<Vec<&Card>> {
-    let wildcards = get_wildcards(cards);
+// The start and length of every window create_run_windows looks at, longest first
+fn run_windows(slots: usize, options: &SetSearchOptions) -> Vec<(usize, usize)> {
+    let mut windows = vec![];
+    for window_length in (options.min_run_len()..=slots).rev() {
+        let wrapping_starts = if options.rules.wrap_runs && window_length < slots {
+            slots-window_length+1..slots
+        } else {
+            0..0
+        };
+        for start_idx in (0..=slots-window_length).chain(wrapping_starts) {
+            windows.push((start_idx, window_length));
+        }
+    }
+    windows
+}
+
+// The run of a window, the missing numbers taking the wildcards from the last one. Fails with
+// the count of missing numbers when there are more of them than wildcards, or when the window
+// holds no tile of the set at all.
+fn fill_window<'a>(set: &[Option<&'a Card>], start_idx: usize, window_length: usize, wildcards: &[&'a Card]) -> Result<Vec<&'a Card>, usize> {
+    note_candidate();
+    let subwindow = || set.iter().cycle().skip(start_idx).take(window_length);
+    let missing = subwindow().filter(|c| c.is_none()).count();
+    if missing > wildcards.len() || missing == window_length {
+        return Err(missing);
+    }
+    let mut spare_wildcards = wildcards.iter().rev();
+    subwindow()
+        .map(|c| c.or_else(|| spare_wildcards.next().copied()))
+        .collect::<Option<Vec<&Card>>>()
+        .ok_or(missing)
+}
+
+/// Finds the runs of the tray: 3 or more consecutive numbers of the same color. Like the
+/// other finders, it takes the tiles from a slice, a Tray or any iterator of references.
+pub fn find_runs<'a>(cards: impl IntoIterator<Item = &'a Card>) -> Vec<Vec<&'a Card>> {
+    find_runs_of(&cards.into_iter().collect::<Vec<&Card>>(), &SetSearchOptions::default())
+}
+
+/// Same as find_runs, with options. With RulesConfig::twist_jokers, the mirror runs of the
+/// mirror joker of the tray come after the others.
+pub fn find_runs_with<'a>(cards: impl IntoIterator<Item = &'a Card>, options: &SetSearchOptions) -> Vec<Vec<&'a Card>> {
+    find_runs_of(&cards.into_iter().collect::<Vec<&Card>>(), options)
+}
+
+// The finders collect their tiles once, and the searches themselves aren't generic
+fn find_runs_of<'a>(cards: &[&'a Card], options: &SetSearchOptions) -> Vec<Vec<&'a Card>> {
+    let copies = group_copies(cards.iter().copied());
+    let mut runs = find_runs_in(&|number, color, copy| tile_copy(&copies, number, color, copy), &get_wildcards(cards.iter().copied()), options);
+    if options.rules.twist_jokers {
+        runs.extend(twist::mirror_runs(cards, options));
+        runs.truncate(options.max_results.unwrap_or(runs.len()));
+    }
+    sorted_if_canonical(runs, options)
+}
 
-    // Sort the cards by number and color (with Wildcards at the end)
-    //let mut sorted_cards: Vec<&Card> = cards.iter().collect();
-    //sorted_cards.sort();
+fn sorted_if_canonical<'a>(mut sets: Vec<Vec<&'a Card>>, options: &SetSearchOptions) -> Vec<Vec<&'a Card>> {
+    if options.canonical {
+        for set in &mut sets {
+            set.sort();
+        }
+    }
+    sets
+}
 
-    let mut sets: Vec<Vec<&Card>> = vec![];
-    // Group cards (excluding wildcards) by their color
-    let mut grouped_cards: BTreeMap<Color, BTreeSet<&Card>> = BTreeMap::new();
+// Groups the numbered cards by color and number, keeping every copy in the order they come in
+fn group_copies<'a>(cards: impl IntoIterator<Item = &'a Card>) -> BTreeMap<(Color, i8), Vec<&'a Card>> {
+    let mut copies: BTreeMap<(Color, i8), Vec<&Card>> = BTreeMap::new();
     for c in cards {
-        if let Card::Numbered { number: _, color } = c {
-            grouped_cards.entry(*color).or_default().insert(c);
+        if let Card::Numbered { number, color } = c {
+            copies.entry((*color, *number)).or_default().push(c);
         }
-        
     }
+    copies
+}
 
-    for cards in grouped_cards.values() {
-        // Take only groups with 3 cards or more (even with the help of wildcards)
-        if cards.len() + wildcards.len() >= 3 {
-            let mut all_nums_set: Vec<Option<&Card>> = Vec::with_capacity(13);
-            for num in 1..=13 {
-                let available_card = cards.iter().find(|c| c.number() == Some(&num)).cloned();
-                all_nums_set.push(available_card);
-            }
-            //println!("{:?}", all_nums_set);
+fn tile_copy<'a>(copies: &BTreeMap<(Color, i8), Vec<&'a Card>>, number: i8, color: Color, copy: usize) -> Option<&'a Card> {
+    copies.get(&(color, number)).and_then(|c| c.get(copy)).copied()
+}
 
-            let mut run_windows = create_run_windows(&all_nums_set, &wildcards);
-            sets.append(&mut run_windows);
+// The set searches look tiles up through a function returning the n-th copy of a tile, so
+// they can run on a slice as well as on a TrayIndex
+fn find_runs_in<'a, F>(tile: &F, wildcards: &[&'a Card], options: &SetSearchOptions) -> Vec<Vec<&'a Card>>
+    where F: Fn(i8, Color, usize) -> Option<&'a Card> + Sync {
+    // Take only groups with 3 cards or more (even with the help of wildcards)
+    let all_nums_sets: Vec<Vec<Option<&Card>>> = run_layers(tile, options).into_iter()
+        .filter(|(_, _, layer)| layer.iter().flatten().count() + wildcards.len() >= options.min_run_len())
+        .map(|(_, _, layer)| layer)
+        .collect();
+
+    collect_limited(&all_nums_sets, options, |all_nums_set, remaining| {
+        create_run_windows(all_nums_set, wildcards, remaining)
+    })
+}
+
+// Every color with a slot for every number, the n-th layer holding the n-th copy of each
+// number, so that every physical copy is used once. Stops at the first empty layer.
+fn run_layers<'a, F>(tile: &F, options: &SetSearchOptions) -> Vec<(Color, usize, Vec<Option<&'a Card>>)>
+    where F: Fn(i8, Color, usize) -> Option<&'a Card> {
+    let mut layers = vec![];
+    for &color in options.rules.colors {
+        for copy in 0.. {
+            let layer: Vec<Option<&Card>> = options.rules.numbers()
+                .map(|num| tile(num, color, copy))
+                .collect();
+            if layer.iter().all(|c| c.is_none()) {
+                break;
+            }
+            layers.push((color, copy, layer));
         }
+    }
+    layers
+}
+
+// Runs the search on every input until max_results sets are found. With the parallel
+// feature the inputs are searched at the same time and the results cut afterwards, which
+// gives the same sets since each search returns a prefix of its unbounded results.
+fn collect_limited<'a, T: Sync>(
+    inputs: &[T],
+    options: &SetSearchOptions,
+    search: impl Fn(&T, &SetSearchOptions) -> Vec<Vec<&'a Card>> + Sync
+) -> Vec<Vec<&'a Card>> {
+    if parallel::is_enabled() {
+        let mut sets: Vec<Vec<&Card>> = parallel::map(inputs, |input| search(input, options))
+            .into_iter()
+            .flatten()
+            .collect();
+        sets.truncate(options.max_results.unwrap_or(sets.len()));
+        return sets;
+    }
 
+    let mut sets = vec![];
+    for input in inputs {
+        sets.append(&mut search(input, &options.remaining_after(sets.len())));
+        if options.is_full(sets.len()) {
+            break;
+        }
     }
     sets
 }
 
-fn find_same_numbers(cards: &[Card]) -> Vec<Vec<&Card>> {
-    let wildcards = get_wildcards(cards);
+/// Finds the groups of the tray: 3 or 4 tiles with the same number and different colors
+pub fn find_same_numbers<'a>(cards: impl IntoIterator<Item = &'a Card>) -> Vec<Vec<&'a Card>> {
+    find_same_numbers_of(&cards.into_iter().collect::<Vec<&Card>>(), &SetSearchOptions::default())
+}
 
-    // Sort the cards by number and color (with Wildcards at the end)
-    let mut sorted_cards: Vec<&Card> = cards.iter().collect();
-    sorted_cards.sort();
+/// Same as find_same_numbers, with options. With RulesConfig::twist_jokers, the groups of the
+/// double joker of the tray come after the others.
+pub fn find_same_numbers_with<'a>(cards: impl IntoIterator<Item = &'a Card>, options: &SetSearchOptions) -> Vec<Vec<&'a Card>> {
+    find_same_numbers_of(&cards.into_iter().collect::<Vec<&Card>>(), options)
+}
 
-    let mut sets: BTreeSet<Vec<&Card>> = BTreeSet::new();
-    // Group cards (excluding wildcards) by their number
-    let grouped_cards = sorted_cards.into_iter()
-        .filter(|c| !c.is_wildcard())
-        .group_by(|c| c.number());
+fn find_same_numbers_of<'a>(cards: &[&'a Card], options: &SetSearchOptions) -> Vec<Vec<&'a Card>> {
+    let copies = group_copies(cards.iter().copied());
+    let mut groups = find_same_numbers_in(&|number, color, copy| tile_copy(&copies, number, color, copy), &get_wildcards(cards.iter().copied()), options);
+    if options.rules.twist_jokers {
+        groups.extend(twist::double_groups(cards, options));
+        groups.truncate(options.max_results.unwrap_or(groups.len()));
+    }
+    sorted_if_canonical(groups, options)
+}
 
-    for (_, cards) in &grouped_cards {
-        // Exclude cards with duplicated color
-        let mut cards: Vec<&Card> = cards.unique_by(|c| c.color()).collect();
+fn find_same_numbers_in<'a, F>(tile: &F, wildcards: &[&'a Card], options: &SetSearchOptions) -> Vec<Vec<&'a Card>>
+    where F: Fn(i8, Color, usize) -> Option<&'a Card> + Sync {
+    let mut candidate_groups: Vec<Vec<&Card>> = vec![];
+    for (_, _, mut cards) in group_layers(tile, options) {
         // Take only groups with 3 cards or more (even with the help of wildcards)
-        if cards.len() + wildcards.len() >= 3 {
+        if cards.len() + wildcards.len() >= options.min_len() {
             // Add wildcards to the set
-            for wildcard in &wildcards {
+            for wildcard in wildcards {
                 cards.push(wildcard)
             }
+            candidate_groups.push(cards);
+        }
+    }
+
+    collect_limited(&candidate_groups, options, |cards, remaining| {
+        // The group of cards + wildcards can be bigger than the rules allow, since
+        // create_permutations excludes blocks bigger than that
+        create_permutations(cards, remaining.min_len(), remaining.rules.group_limit(), remaining.max_results)
+    })
+}
+
+// Every number with its copies split in layers, so a group never contains the same color
+// twice but the second copies can still build a group of their own
+fn group_layers<'a, F>(tile: &F, options: &SetSearchOptions) -> Vec<(i8, usize, Vec<&'a Card>)>
+    where F: Fn(i8, Color, usize) -> Option<&'a Card> {
+    let mut all_layers = vec![];
+    for number in options.rules.numbers() {
+        let mut layers: Vec<Vec<&Card>> = vec![];
+        for &color in options.rules.colors {
+            for (layer, card) in (0..).map_while(|copy| tile(number, color, copy)).enumerate() {
+                if layers.len() <= layer {
+                    layers.push(vec![]);
+                }
+                layers[layer].push(card);
+            }
+        }
+        all_layers.extend(layers.into_iter().enumerate().map(|(copy, cards)| (number, copy, cards)));
+    }
+    all_layers
+}
+
+/// Limits what valid_sets_with looks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetSearchOptions {
+    /// Sets shorter than 3 tiles are never valid, so lower values behave like 3
+    pub min_len: usize,
+    /// Stops the search once this many sets are found
+    pub max_results: Option<usize>,
+    pub include_runs: bool,
+    pub include_groups: bool,
+    /// Drops the sets that can be extended into a bigger set of the same kind
+    pub maximal_only: bool,
+    pub order: SetOrder,
+    /// Sorts the tiles of every set found by find_runs_with and find_same_numbers_with, like
+    /// valid_sets always does
+    pub canonical: bool,
+    /// House rules the sets follow, like longer runs or runs going on from 13 to 1
+    pub rules: RulesConfig
+}
+
+impl Default for SetSearchOptions {
+    fn default() -> Self {
+        SetSearchOptions {
+            min_len: 3,
+            max_results: None,
+            include_runs: true,
+            include_groups: true,
+            maximal_only: false,
+            order: SetOrder::Kind,
+            canonical: false,
+            rules: RulesConfig::default()
+        }
+    }
+}
+
+/// How valid_sets_with sorts the sets it returns
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SetOrder {
+    /// Groups first, then runs, each by their first card and length
+    #[default]
+    Kind,
+    /// The longest sets first
+    Length,
+    /// The sets worth the most points first, wildcards counting as the number they stand for
+    Points
+}
+
+impl SetSearchOptions {
+    fn min_len(&self) -> usize {
+        self.min_len.max(3)
+    }
+
+    fn min_run_len(&self) -> usize {
+        self.min_len().max(self.rules.min_run_len)
+    }
+
+    fn is_full(&self, found: usize) -> bool {
+        self.max_results.is_some_and(|max| found >= max)
+    }
+
+    fn remaining_after(&self, found: usize) -> SetSearchOptions {
+        SetSearchOptions {
+            max_results: self.max_results.map(|max| max.saturating_sub(found)),
+            ..*self
+        }
+    }
+}
+
+/// Lists every run and group the tray can make, each physical tile at most once per set.
+/// Every set is sorted, and the list comes groups first, then runs. Sets with the same tiles
+/// come once for every copy they can be made from, distinct_sets lists them once instead.
+pub fn valid_sets<'a>(cards: impl IntoIterator<Item = &'a Card>) -> Vec<Vec<&'a Card>> {
+    valid_sets_of(&cards.into_iter().collect::<Vec<&Card>>(), &SetSearchOptions::default())
+}
+
+/// Same as valid_sets, restricted by the options
+pub fn valid_sets_with<'a>(cards: impl IntoIterator<Item = &'a Card>, options: &SetSearchOptions) -> Vec<Vec<&'a Card>> {
+    valid_sets_of(&cards.into_iter().collect::<Vec<&Card>>(), options)
+}
+
+fn valid_sets_of<'a>(cards: &[&'a Card], options: &SetSearchOptions) -> Vec<Vec<&'a Card>> {
+    let copies = group_copies(cards.iter().copied());
+    let sets = valid_sets_in(&|number, color, copy| tile_copy(&copies, number, color, copy), &get_wildcards(cards.iter().copied()), options);
+    #[cfg(feature = "verify")]
+    debug_assert_eq!(verify_sets_with(&cards.iter().map(|c| **c).collect::<Vec<Card>>(), &sets, &options.rules), Ok(()));
+    sets
+}
+
+fn valid_sets_in<'a, F>(tile: &F, wildcards: &[&'a Card], options: &SetSearchOptions) -> Vec<Vec<&'a Card>>
+    where F: Fn(i8, Color, usize) -> Option<&'a Card> + Sync {
+    if options.maximal_only || options.order != SetOrder::Kind {
+        // Which sets come first depends on all of them, so the limit applies at the end
+        let every_set = SetSearchOptions {
+            max_results: None,
+            maximal_only: false,
+            order: SetOrder::Kind,
+            ..*options
+        };
+        let mut sets = valid_sets_in(tile, wildcards, &every_set);
+        if options.maximal_only {
+            sets = keep_maximal(sets, &options.rules);
+        }
+        match options.order {
+            SetOrder::Kind => {},
+            SetOrder::Length => sets.sort_by_key(|set| core::cmp::Reverse(set.len())),
+            SetOrder::Points => sets.sort_by_cached_key(|set| core::cmp::Reverse(set_points_with(set, &options.rules)))
+        }
+        sets.truncate(options.max_results.unwrap_or(sets.len()));
+        return sets;
+    }
+
+    let mut search_options = *options;
+    loop {
+        let found = search_sets(tile, wildcards, &search_options);
+        let found_len = found.len();
+        let mut sets = canonicalize_sets(found, &options.rules);
+        match (options.max_results, search_options.max_results) {
+            // Some of the sets were duplicates: search a bit further to fill the gap
+            (Some(max), Some(searched)) if sets.len() < max && found_len == searched => {
+                search_options.max_results = Some(searched + max - sets.len());
+            }
+            _ => {
+                sets.truncate(options.max_results.unwrap_or(sets.len()));
+                return sets;
+            }
+        }
+    }
+}
+
+fn search_sets<'a, F>(tile: &F, wildcards: &[&'a Card], options: &SetSearchOptions) -> Vec<Vec<&'a Card>>
+    where F: Fn(i8, Color, usize) -> Option<&'a Card> + Sync {
+    let mut sets = vec![];
+    if options.include_groups && !options.is_full(sets.len()) {
+        sets.append(&mut find_same_numbers_in(tile, wildcards, options));
+    }
+    if options.include_runs && !options.is_full(sets.len()) {
+        sets.append(&mut find_runs_in(tile, wildcards, &options.remaining_after(sets.len())));
+    }
+    sets
+}
+
+/// How many sets valid_sets finds, without building them. A tray without wildcards can't
+/// make the same set twice, so its sets are counted from the tiles alone; with wildcards, only
+/// which tiles every set holds is kept, to count the sets made of the same ones once.
+pub fn count_valid_sets<'a>(cards: impl IntoIterator<Item = &'a Card>) -> usize {
+    count_valid_sets_with(cards, &SetSearchOptions::default())
+}
+
+/// Same as count_valid_sets, giving what valid_sets_with(cards, options).len() gives
+pub fn count_valid_sets_with<'a>(cards: impl IntoIterator<Item = &'a Card>, options: &SetSearchOptions) -> usize {
+    let cards: Vec<&Card> = cards.into_iter().collect();
+    let copies = group_copies(cards.iter().copied());
+    count_valid_sets_in(&|number, color, copy| tile_copy(&copies, number, color, copy), &get_wildcards(cards.iter().copied()), options)
+}
+
+fn count_valid_sets_in<'a, F>(tile: &F, wildcards: &[&'a Card], options: &SetSearchOptions) -> usize
+    where F: Fn(i8, Color, usize) -> Option<&'a Card> + Sync {
+    // Which sets are cut or dropped depends on the sets themselves
+    if options.max_results.is_some() || options.maximal_only {
+        return valid_sets_in(tile, wildcards, options).len();
+    }
+    if !wildcards.is_empty() {
+        let keys: BTreeSet<Vec<usize>> = search_sets(tile, wildcards, options).iter()
+            .map(|set| {
+                let mut key = physical_key(set);
+                key.sort();
+                key
+            })
+            .collect();
+        return keys.len();
+    }
+
+    let mut count = 0;
+    if options.include_groups {
+        for (_, _, cards) in group_layers(tile, options) {
+            let longest = cards.len().min(options.rules.group_limit());
+            count += (options.min_len()..=longest).map(|len| binomial(cards.len(), len)).sum::<usize>();
+        }
+    }
+    if options.include_runs {
+        for (_, _, layer) in run_layers(tile, options) {
+            count += run_windows(layer.len(), options).into_iter()
+                .filter(|(start_idx, window_length)| layer.iter().cycle().skip(*start_idx).take(*window_length).all(Option::is_some))
+                .count();
+        }
+    }
+    count
+}
+
+fn binomial(n: usize, k: usize) -> usize {
+    // Every partial product is itself a binomial, so the division is exact
+    (0..k).fold(1, |product, i| product * (n - i) / (i + 1))
+}
+
+/// Whether valid_sets finds any set, stopping at the first one, without looking at the other
+/// windows of runs or subsets of groups
+pub fn has_valid_set<'a>(cards: impl IntoIterator<Item = &'a Card>) -> bool {
+    has_valid_set_with(cards, &SetSearchOptions::default())
+}
+
+/// Same as has_valid_set, telling whether valid_sets_with(cards, options) finds any set
+pub fn has_valid_set_with<'a>(cards: impl IntoIterator<Item = &'a Card>, options: &SetSearchOptions) -> bool {
+    let cards: Vec<&Card> = cards.into_iter().collect();
+    let copies = group_copies(cards.iter().copied());
+    has_valid_set_in(&|number, color, copy| tile_copy(&copies, number, color, copy), &get_wildcards(cards.iter().copied()), options)
+}
 
-            // Compute permutation
-            // The group of cards + wildcards can be > 4, since create_permutations excludes blocks bigger than 4
-            let mut permutations = create_permutations(&cards);
-            sets.append(&mut permutations)
+// The maximal sets and the order don't matter: a tray with sets has maximal ones
+fn has_valid_set_in<'a, F>(tile: &F, wildcards: &[&'a Card], options: &SetSearchOptions) -> bool
+    where F: Fn(i8, Color, usize) -> Option<&'a Card> {
+    if options.max_results == Some(0) {
+        return false;
+    }
+    if options.include_groups {
+        for (_, _, mut cards) in group_layers(tile, options) {
+            if cards.len() + wildcards.len() < options.min_len() {
+                continue;
+            }
+            cards.extend(wildcards);
+            if permutations(&cards, options.min_len(), options.rules.group_limit()).next().is_some() {
+                return true;
+            }
         }
+    }
+    options.include_runs && run_layers(tile, options).iter().any(|(_, _, layer)| {
+        run_windows(layer.len(), options).into_iter()
+            .any(|(start_idx, window_length)| fill_window(layer, start_idx, window_length, wildcards).is_ok())
+    })
+}
 
+// Sorts the cards of every set (numbered cards by number and color, then wildcards), drops
+// sets made of the same cards and sorts the list by kind, then first card, then length.
+// Sets with the same values are kept when they use different copies of a numbered tile.
+fn canonicalize_sets<'a>(mut sets: Vec<Vec<&'a Card>>, rules: &RulesConfig) -> Vec<Vec<&'a Card>> {
+    for set in &mut sets {
+        set.sort();
     }
-    sets.into_iter().collect()
+    sets.sort_by_cached_key(|set| {
+        let kind = validation::validate_refs_with(set, rules).ok();
+        (kind, set.first().copied(), set.len(), set.clone(), physical_key(set))
+    });
+    sets.dedup_by_key(|set| physical_key(set));
+    sets
+}
+
+// Identifies the physical tiles of a sorted set; wildcards are interchangeable
+fn physical_key(set: &[&Card]) -> Vec<usize> {
+    set.iter()
+        .map(|c| if c.is_wildcard() { 0 } else { *c as *const Card as usize })
+        .collect()
+}
+
+/// Runs valid_sets on every tray, spreading the trays over the rayon pool with the parallel feature
+pub fn valid_sets_batch(trays: &[Vec<Card>]) -> Vec<Vec<Vec<Card>>> {
+    parallel::map(trays, |tray| {
+        valid_sets(tray).into_iter()
+            .map(|set| set.into_iter().copied().collect())
+            .collect()
+    })
+}
+
+/// Lists the tiles of the tray which don't belong to any valid set. Every copy of a tile
+/// is considered on its own, while wildcards are dead only when there are no sets at all.
+pub fn dead_tiles<'a>(cards: impl IntoIterator<Item = &'a Card>) -> Vec<&'a Card> {
+    let cards: Vec<&Card> = cards.into_iter().collect();
+    let sets = valid_sets_of(&cards, &SetSearchOptions::default());
+    cards.into_iter()
+        .filter(|card| {
+            if card.is_wildcard() {
+                sets.is_empty()
+            } else {
+                !sets.iter().flatten().any(|c| core::ptr::eq(*c, *card))
+            }
+        })
+        .collect()
+}
+
+// Tells whether all the physical tiles of set are also used by other (wildcards are interchangeable)
+fn is_subset_of(set: &[&Card], other: &[&Card]) -> bool {
+    let wildcards_count = |s: &[&Card]| s.iter().filter(|c| c.is_wildcard()).count();
+    wildcards_count(set) <= wildcards_count(other) &&
+        set.iter()
+            .filter(|c| !c.is_wildcard())
+            .all(|c| other.iter().any(|o| core::ptr::eq(*c, *o)))
 }
 
-pub fn valid_sets(cards: &[Card]) -> Vec<Vec<&Card>> {
-    let mut runs = find_runs(cards);
-    let mut same_numbers = find_same_numbers(cards);
-    same_numbers.append(&mut runs);
-    same_numbers
+/// Like valid_sets, but drops every set that can be extended into a bigger set of the same kind
+pub fn valid_sets_maximal<'a>(cards: impl IntoIterator<Item = &'a Card>) -> Vec<Vec<&'a Card>> {
+    valid_sets_of(&cards.into_iter().collect::<Vec<&Card>>(), &SetSearchOptions { maximal_only: true, ..SetSearchOptions::default() })
+}
+
+fn keep_maximal<'a>(sets: Vec<Vec<&'a Card>>, rules: &RulesConfig) -> Vec<Vec<&'a Card>> {
+    let kinds: Vec<Option<MeldKind>> = sets.iter()
+        .map(|s| validation::validate_refs_with(s, rules).ok())
+        .collect();
+
+    sets.iter()
+        .enumerate()
+        .filter(|(idx, set)| {
+            !sets.iter().enumerate().any(|(other_idx, other)| {
+                kinds[*idx] == kinds[other_idx] &&
+                    other.len() > set.len() &&
+                    is_subset_of(set, other)
+            })
+        })
+        .map(|(_, set)| set.clone())
+        .collect()
+}
+
+// Face value of a set, with wildcards worth the number they stand for
+#[cfg(feature = "std")]
+fn set_points(set: &[&Card]) -> u32 {
+    set_points_with(set, &RulesConfig::default())
+}
+
+// Same as set_points, for a set valid under house rules, 0 for the other ones
+fn set_points_with(set: &[&Card], rules: &RulesConfig) -> u32 {
+    if set.iter().any(|c| c.is_twist_joker()) {
+        return twist::twist_score(set, rules).unwrap_or(0);
+    }
+    resolve_wildcards_with(set, rules)
+        .map(|values| values.iter().map(|(number, _)| *number as u32).sum())
+        .unwrap_or(0)
 }
 
 #[cfg(test)]
 mod tests {
 
+    use alloc::borrow::ToOwned;
     use super::*;
 
-    fn equals_vec<T>(ref_slice: &[&T], struct_slice: &[T]) -> bool
-        where T : std::cmp::PartialEq {
-            ref_slice.len() == struct_slice.len() &&
-                ref_slice.iter().all(|&c| struct_slice.contains(c))
+    fn equals_vec(ref_slice: &[&Card], struct_slice: &[Card]) -> bool {
+        canonicalize_set(ref_slice) == canonicalize_set(&struct_slice.iter().collect::<Vec<&Card>>())
     }
 
     #[test]
@@ -524,6 +1371,8 @@ mod tests {
         ];
 
         let sets = find_runs(&test_tray);
+
+        // 2 3 W stands for 1 2 3 as well as 2 3 4, and is only found once
         
         let match_sets = vec![
             vec![
@@ -548,12 +1397,6 @@ mod tests {
                 Card::new(6, Color::Blue),
                 Card::new(7, Color::Blue)
             ],
-            vec![
-                Card::new(2, Color::Blue),
-                Card::new(3, Color::Blue),
-                Card::new(4, Color::Blue),
-                Card::Wildcard
-            ],
             vec![
                 Card::new(3, Color::Blue),
                 Card::new(4, Color::Blue),
@@ -596,13 +1439,7 @@ mod tests {
                 Card::new(2, Color::Blue),
                 Card::new(3, Color::Blue),
                 Card::Wildcard
-            ],
-            vec![
-                Card::Wildcard,
-                Card::new(3, Color::Blue),
-                Card::new(4, Color::Blue)
             ]
-
         ];
         
         println!("{:?}", sets);
@@ -686,38 +1523,10 @@ mod tests {
                 Card::new(6, Color::Blue),
                 Card::new(7, Color::Blue)
             ],
-            vec![
-                Card::Wildcard,
-                Card::new(2, Color::Blue),
-                Card::new(3, Color::Blue),
-                Card::Wildcard
-            ],
-            vec![
-                Card::Wildcard,
-                Card::new(6, Color::Blue),
-                Card::new(7, Color::Blue),
-                Card::Wildcard
-            ],
-            vec![
-                Card::new(6, Color::Blue),
-                Card::new(7, Color::Blue),
-                Card::Wildcard,
-                Card::Wildcard
-            ],
-            vec![
-                Card::Wildcard,
-                Card::new(2, Color::Blue),
-                Card::new(3, Color::Blue)
-            ],
             vec![
                 Card::new(7, Color::Blue),
                 Card::Wildcard,
                 Card::Wildcard
-            ],
-            vec![
-                Card::new(6, Color::Blue),
-                Card::new(7, Color::Blue),
-                Card::Wildcard
             ]
         ];
         
@@ -728,4 +1537,725 @@ mod tests {
         }
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn find_runs_2_wildcards_distinct_test() {
+        let test_tray = vec![
+            Card::new(2, Color::Blue),
+            Card::new(4, Color::Blue),
+            Card::new(5, Color::Blue),
+            Card::new(9, Color::Red),
+            Card::new(13, Color::Red),
+            Card::Wildcard,
+            Card::Wildcard
+        ];
+
+        let sets = find_runs(&test_tray);
+
+        let keys: BTreeSet<SetKey> = sets.iter().map(|s| SetKey::of(s)).collect();
+        assert_eq!(keys.len(), sets.len());
+        assert!(keys.contains(&SetKey::of(&[&Card::new(13, Color::Red), &Card::Wildcard, &Card::Wildcard])));
+        assert!(sets.iter().all(|s| validation::validate_refs(s) == Ok(MeldKind::Run)));
+    }
+
+    #[test]
+    fn valid_sets_maximal_test() {
+        let test_tray = vec![
+            Card::new(2, Color::Blue),
+            Card::new(3, Color::Blue),
+            Card::new(4, Color::Blue),
+            Card::new(5, Color::Red),
+            Card::new(6, Color::Blue),
+            Card::new(7, Color::Blue),
+            Card::Wildcard
+        ];
+
+        let sets = valid_sets_maximal(&test_tray);
+
+        let match_sets = vec![
+            vec![
+                Card::new(2, Color::Blue),
+                Card::new(3, Color::Blue),
+                Card::new(4, Color::Blue),
+                Card::Wildcard,
+                Card::new(6, Color::Blue),
+                Card::new(7, Color::Blue)
+            ]
+        ];
+
+        println!("{:?}", sets);
+        assert_eq!(sets.len(), match_sets.len());
+        for cur_match_set in match_sets {
+            assert!(sets.iter().any(|s| equals_vec(s, &cur_match_set)));
+        }
+    }
+
+    #[test]
+    fn valid_sets_maximal_with_wildcard_test() {
+        let test_tray = vec![
+            Card::new(2, Color::Blue),
+            Card::new(3, Color::Blue),
+            Card::new(4, Color::Blue),
+            Card::Wildcard
+        ];
+
+        let sets = valid_sets_maximal(&test_tray);
+
+        // A run without the wildcard can always be extended with it
+        let match_sets = vec![
+            vec![
+                Card::new(2, Color::Blue),
+                Card::new(3, Color::Blue),
+                Card::new(4, Color::Blue),
+                Card::Wildcard
+            ]
+        ];
+
+        println!("{:?}", sets);
+        assert_eq!(sets.len(), match_sets.len());
+        for cur_match_set in match_sets {
+            assert!(sets.iter().any(|s| equals_vec(s, &cur_match_set)));
+        }
+    }
+
+    fn search_test_tray() -> Vec<Card> {
+        vec![
+            Card::new(2, Color::Blue),
+            Card::new(3, Color::Blue),
+            Card::new(4, Color::Blue),
+            Card::new(6, Color::Blue),
+            Card::new(7, Color::Blue),
+            Card::new(4, Color::Red),
+            Card::new(4, Color::Yellow),
+            Card::Wildcard
+        ]
+    }
+
+    #[test]
+    fn valid_sets_with_min_len_test() {
+        let test_tray = search_test_tray();
+        let options = SetSearchOptions { min_len: 4, ..SetSearchOptions::default() };
+
+        let sets = valid_sets_with(&test_tray, &options);
+
+        assert!(!sets.is_empty());
+        assert!(sets.iter().all(|s| s.len() >= 4));
+        let expected = valid_sets(&test_tray).into_iter().filter(|s| s.len() >= 4).count();
+        assert_eq!(sets.len(), expected);
+    }
+
+    #[test]
+    fn valid_sets_with_min_len_clamped_test() {
+        let test_tray = search_test_tray();
+        let options = SetSearchOptions { min_len: 1, ..SetSearchOptions::default() };
+
+        assert_eq!(valid_sets_with(&test_tray, &options), valid_sets(&test_tray));
+    }
+
+    #[test]
+    fn valid_sets_with_house_rules_test() {
+        let test_tray = vec![
+            Card::new(1, Color::Red),
+            Card::new(12, Color::Red),
+            Card::new(13, Color::Red),
+            Card::new(4, Color::Blue),
+            Card::new(5, Color::Blue),
+            Card::new(6, Color::Blue),
+            Card::new(7, Color::Blue),
+            Card::new(9, Color::Black),
+            Card::new(10, Color::Black),
+            Card::new(11, Color::Black)
+        ];
+        let around = vec![&test_tray[0], &test_tray[1], &test_tray[2]];
+
+        assert!(!find_runs(&test_tray).contains(&around));
+        let wrapping = SetSearchOptions { rules: RulesConfig { wrap_runs: true, ..RulesConfig::default() }, ..SetSearchOptions::default() };
+        let runs = find_runs_with(&test_tray, &wrapping);
+        assert!(runs.iter().any(|run| run.len() == 3 && run.iter().all(|c| around.contains(c))));
+        assert!(valid_sets_with(&test_tray, &wrapping).contains(&around));
+
+        // Of the 4, 5 and 6 Blue, 5, 6 and 7 Blue and 9 to 11 Black runs, only 4 to 7 Blue is left
+        let long_runs = SetSearchOptions { rules: RulesConfig { min_run_len: 4, ..RulesConfig::default() }, ..SetSearchOptions::default() };
+        assert_eq!(valid_sets(&test_tray).iter().filter(|s| s.len() == 3).count(), 3);
+        assert_eq!(valid_sets_with(&test_tray, &long_runs), vec![test_tray[3..7].iter().collect::<Vec<&Card>>()]);
+    }
+
+    #[test]
+    fn valid_sets_with_kind_filter_test() {
+        let test_tray = search_test_tray();
+
+        let runs_only = SetSearchOptions { include_groups: false, ..SetSearchOptions::default() };
+        let runs = valid_sets_with(&test_tray, &runs_only);
+        assert_eq!(runs, canonicalize_sets(find_runs(&test_tray), &RulesConfig::default()));
+        assert!(runs.iter().all(|s| validation::validate_refs(s) == Ok(MeldKind::Run)));
+
+        let groups_only = SetSearchOptions { include_runs: false, ..SetSearchOptions::default() };
+        let groups = valid_sets_with(&test_tray, &groups_only);
+        assert_eq!(groups, canonicalize_sets(find_same_numbers(&test_tray), &RulesConfig::default()));
+        assert_eq!(groups.len(), 5);
+    }
+
+    #[test]
+    fn valid_sets_with_max_results_test() {
+        let test_tray = search_test_tray();
+        assert!(valid_sets(&test_tray).len() > 10);
+
+        for max_results in [0, 1, 3, 5, 10] {
+            let options = SetSearchOptions { max_results: Some(max_results), ..SetSearchOptions::default() };
+            assert_eq!(valid_sets_with(&test_tray, &options).len(), max_results);
+        }
+
+        let options = SetSearchOptions { max_results: Some(2), include_groups: false, ..SetSearchOptions::default() };
+        assert_eq!(create_run_windows_count(&test_tray, &options), 2);
+    }
+
+    // Counts the windows built for the blue cards alone, to check the search stops early
+    fn create_run_windows_count(cards: &[Card], options: &SetSearchOptions) -> usize {
+        let wildcards = get_wildcards(cards);
+        let all_nums_set: Vec<Option<&Card>> = (1..=13)
+            .map(|num| cards.iter().find(|c| c.color() == Some(&Color::Blue) && c.number() == Some(&num)))
+            .collect();
+        create_run_windows(&all_nums_set, &wildcards, options).len()
+    }
+
+    #[test]
+    fn valid_sets_deterministic_test() {
+        let test_tray = search_test_tray();
+        let cloned_tray = test_tray.clone();
+
+        let sets = format!("{:?}", valid_sets(&test_tray));
+        let cloned_sets = format!("{:?}", valid_sets(&cloned_tray));
+        assert_eq!(sets, cloned_sets);
+
+        let mut reversed_tray = test_tray.clone();
+        reversed_tray.reverse();
+        assert_eq!(sets, format!("{:?}", valid_sets(&reversed_tray)));
+    }
+
+    #[test]
+    fn valid_sets_ordering_test() {
+        let test_tray = search_test_tray();
+        let sets = valid_sets(&test_tray);
+
+        for set in &sets {
+            let mut sorted_set = set.clone();
+            sorted_set.sort();
+            assert_eq!(set, &sorted_set);
+        }
+        let kinds: Vec<MeldKind> = sets.iter()
+            .map(|s| validation::validate_refs(s).unwrap())
+            .collect();
+        assert!(kinds.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn valid_sets_dedup_wildcards_test() {
+        let test_tray = vec![
+            Card::new(5, Color::Red),
+            Card::Wildcard,
+            Card::Wildcard
+        ];
+
+        // Found as a group and as a run, whichever numbers the wildcards stand for
+        assert_eq!(find_same_numbers(&test_tray).len() + find_runs(&test_tray).len(), 2);
+
+        let sets = valid_sets(&test_tray);
+        assert_eq!(sets, vec![vec![&test_tray[0], &Card::Wildcard, &Card::Wildcard]]);
+    }
+
+    #[test]
+    fn find_runs_duplicated_tiles_test() {
+        let test_tray = vec![
+            Card::new(3, Color::Blue),
+            Card::new(4, Color::Blue),
+            Card::new(5, Color::Blue),
+            Card::new(3, Color::Blue),
+            Card::new(4, Color::Blue),
+            Card::new(5, Color::Blue)
+        ];
+
+        let sets = find_runs(&test_tray);
+
+        assert_eq!(sets.len(), 2);
+        assert!(sets.iter().all(|s| equals_vec(s, &test_tray[0..3])));
+        // The two runs don't share any physical tile
+        assert!(sets[0].iter().all(|c| !sets[1].iter().any(|o| std::ptr::eq(*c, *o))));
+
+        let sets = valid_sets(&test_tray);
+        assert_eq!(sets.len(), 2);
+        assert_eq!(sets[0], sets[1]);
+    }
+
+    #[test]
+    fn find_runs_partially_duplicated_tiles_test() {
+        let test_tray = vec![
+            Card::new(3, Color::Blue),
+            Card::new(4, Color::Blue),
+            Card::new(5, Color::Blue),
+            Card::new(6, Color::Blue),
+            Card::new(4, Color::Blue),
+            Card::new(5, Color::Blue),
+            Card::Wildcard
+        ];
+
+        let sets = find_runs(&test_tray);
+
+        // The second copies of 4 and 5 only make a run with the wildcard, standing for 3 or 6
+        let second_copies: Vec<&Vec<&Card>> = sets.iter()
+            .filter(|s| s.iter().any(|c| std::ptr::eq(*c, &test_tray[4])))
+            .collect();
+        let match_sets = vec![
+            vec![Card::Wildcard, Card::new(4, Color::Blue), Card::new(5, Color::Blue)]
+        ];
+        assert_eq!(second_copies.len(), match_sets.len());
+        for cur_match_set in match_sets {
+            assert!(second_copies.iter().any(|s| equals_vec(s, &cur_match_set)));
+        }
+        assert!(second_copies.iter().all(|s| s.iter().any(|c| std::ptr::eq(*c, &test_tray[5]))));
+    }
+
+    #[test]
+    fn find_same_numbers_duplicated_tiles_test() {
+        let test_tray = vec![
+            Card::new(7, Color::Red),
+            Card::new(7, Color::Red),
+            Card::new(7, Color::Blue),
+            Card::new(7, Color::Blue),
+            Card::new(7, Color::Yellow),
+            Card::new(7, Color::Yellow)
+        ];
+
+        let sets = find_same_numbers(&test_tray);
+
+        let group = vec![
+            Card::new(7, Color::Red),
+            Card::new(7, Color::Blue),
+            Card::new(7, Color::Yellow)
+        ];
+        assert_eq!(sets.len(), 2);
+        assert!(sets.iter().all(|s| equals_vec(s, &group)));
+        // Both groups can be played together
+        assert!(sets[0].iter().all(|c| !sets[1].iter().any(|o| std::ptr::eq(*c, *o))));
+        assert_eq!(valid_sets(&test_tray).len(), 2);
+    }
+
+    #[test]
+    fn find_same_numbers_partially_duplicated_tiles_test() {
+        let test_tray = vec![
+            Card::new(9, Color::Red),
+            Card::new(9, Color::Red),
+            Card::new(9, Color::Blue),
+            Card::new(9, Color::Yellow),
+            Card::Wildcard
+        ];
+
+        let sets = find_same_numbers(&test_tray);
+
+        // No group contains two red tiles, and the second red would need two wildcards
+        assert!(sets.iter().all(|s| s.iter().filter(|c| c.color() == Some(&Color::Red)).count() <= 1));
+        let second_copy_sets: Vec<&Vec<&Card>> = sets.iter()
+            .filter(|s| s.iter().any(|c| std::ptr::eq(*c, &test_tray[1])))
+            .collect();
+        assert!(second_copy_sets.is_empty());
+        assert_eq!(sets.len(), 5);
+    }
+
+    #[test]
+    fn find_same_numbers_max_results_test() {
+        // The four nines and two wildcards make a lot of groups
+        let test_tray = [Color::ALL.map(|color| Card::new(9, color)).to_vec(), vec![Card::Wildcard; 2]].concat();
+        let refs: Vec<&Card> = test_tray.iter().collect();
+        let first_two = SetSearchOptions { max_results: Some(2), ..SetSearchOptions::default() };
+
+        let groups = find_same_numbers_of(&refs, &SetSearchOptions::default());
+        let limited = find_same_numbers_of(&refs, &first_two);
+        assert_eq!(limited, groups[..2]);
+
+        // The search stops at the second group instead of listing them all first
+        let every = count_candidates(|| find_same_numbers_of(&refs, &SetSearchOptions::default()));
+        let two = count_candidates(|| find_same_numbers_of(&refs, &first_two));
+        assert!(two * 5 < every, "{} vs {} candidates", two, every);
+    }
+
+    #[test]
+    fn dead_tiles_test() {
+        let test_tray = vec![
+            Card::new(2, Color::Red),
+            Card::new(3, Color::Red),
+            Card::new(5, Color::Blue),
+            Card::new(5, Color::Yellow),
+            Card::new(13, Color::Black),
+            Card::Wildcard
+        ];
+
+        assert_eq!(dead_tiles(&test_tray), vec![&Card::new(13, Color::Black)]);
+    }
+
+    #[test]
+    fn dead_tiles_duplicates_test() {
+        let test_tray = vec![
+            Card::new(9, Color::Yellow),
+            Card::new(9, Color::Yellow),
+            Card::new(9, Color::Red),
+            Card::new(9, Color::Black)
+        ];
+
+        let dead = dead_tiles(&test_tray);
+
+        assert_eq!(dead, vec![&Card::new(9, Color::Yellow)]);
+        assert!(std::ptr::eq(dead[0], &test_tray[1]));
+    }
+
+    #[test]
+    fn dead_tiles_without_sets_test() {
+        let test_tray = vec![
+            Card::new(1, Color::Red),
+            Card::new(6, Color::Blue),
+            Card::Wildcard
+        ];
+
+        assert_eq!(dead_tiles(&test_tray).len(), 3);
+    }
+
+    #[test]
+    fn generic_input_test() {
+        let test_tray = vec![
+            Card::new(2, Color::Blue),
+            Card::new(3, Color::Blue),
+            Card::new(4, Color::Blue),
+            Card::new(4, Color::Red),
+            Card::new(4, Color::Black),
+            Card::new(9, Color::Yellow),
+            Card::Wildcard
+        ];
+        let slice: &[Card] = &test_tray;
+        let tray = Tray::from(test_tray.clone());
+        let expected = valid_sets(&test_tray);
+
+        assert_eq!(valid_sets(slice), expected);
+        assert_eq!(valid_sets(&tray), expected);
+        assert_eq!(find_runs(&tray), find_runs(slice));
+        assert_eq!(find_same_numbers(&tray), find_same_numbers(&test_tray));
+        assert_eq!(dead_tiles(&tray), vec![&Card::new(9, Color::Yellow)]);
+        assert_eq!(best_partition(&tray), best_partition(slice));
+        assert_eq!(best_partition(test_tray.clone()), best_partition(&test_tray));
+
+        // Leaving out the Y9 changes nothing, the sets still pointing into the tray
+        let adapter = valid_sets(tray.iter().filter(|c| c.number() != Some(&9)));
+        assert_eq!(adapter, expected);
+        assert!(adapter.iter().flatten().all(|c| tray.iter().any(|t| core::ptr::eq(*c, t))));
+        assert_eq!(best_partition(tray.iter().filter(|c| c.number() != Some(&9))).leftover, vec![]);
+        let refs: Vec<&Card> = test_tray.iter().collect();
+        assert_eq!(valid_sets_maximal(refs), valid_sets_maximal(slice));
+    }
+
+    #[test]
+    fn new_seeded_test() {
+        let mut deck = Deck::new_seeded(42);
+        let mut same_deck = Deck::new_seeded(42);
+        let mut other_deck = Deck::new_seeded(43);
+
+        let tray = deck.pick_tray(14);
+        assert_eq!(tray, same_deck.pick_tray(14));
+        assert_ne!(tray, other_deck.pick_tray(14));
+    }
+
+    #[test]
+    fn deck_snapshot_test() {
+        let deck = Deck::new_seeded(42);
+        let mut clone = deck.clone();
+        assert_eq!(deck, Deck::new_seeded(42));
+
+        let drawn = clone.pick_tray(3);
+        assert_eq!(deck.snapshot()[..3], drawn[..]);
+        assert_eq!(deck.snapshot()[3..], clone.snapshot()[..]);
+        assert_eq!(deck.len(), 106);
+        assert_ne!(deck, clone);
+    }
+
+    #[test]
+    fn valid_sets_batch_test() {
+        let trays: Vec<Vec<Card>> = (0..100)
+            .map(|seed| Deck::new_seeded(seed).pick_tray(14))
+            .collect();
+
+        let batch = valid_sets_batch(&trays);
+        let sequential: Vec<Vec<Vec<Card>>> = parallel::sequential(|| {
+            trays.iter()
+                .map(|tray| {
+                    valid_sets(tray).into_iter()
+                        .map(|set| set.into_iter().copied().collect())
+                        .collect()
+                })
+                .collect()
+        });
+
+        assert_eq!(batch, sequential);
+        assert!(batch.iter().any(|sets| !sets.is_empty()));
+    }
+
+    #[test]
+    fn valid_sets_sequential_limit_test() {
+        let test_tray = search_test_tray();
+        for max_results in [1, 4, 7] {
+            let options = SetSearchOptions { max_results: Some(max_results), ..SetSearchOptions::default() };
+            let sequential = parallel::sequential(|| valid_sets_with(&test_tray, &options));
+            assert_eq!(valid_sets_with(&test_tray, &options), sequential);
+        }
+    }
+
+    // The recursive version create_permutations used to be, kept to check the new one against
+    fn create_permutations_recursive<'a>(set: &[&'a Card], min_len: usize) -> BTreeSet<Vec<&'a Card>> {
+        let mut permutations = BTreeSet::new();
+        if set.len() <= 4 && set.len() >= min_len {
+            permutations.insert(set.to_owned());
+        }
+        if set.len() > min_len.max(3) {
+            for remove_idx in 0..set.len() {
+                let mut subset = set.to_owned();
+                subset.remove(remove_idx);
+                permutations.append(&mut create_permutations_recursive(&subset, min_len));
+            }
+        }
+        permutations
+    }
+
+    // Counts the allocations of the current thread, so that tests running in parallel don't interfere
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+            std::alloc::System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            std::alloc::System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    pub(super) fn count_allocations<T>(f: impl FnOnce() -> T) -> usize {
+        let before = ALLOCATIONS.with(|a| a.get());
+        drop(f());
+        ALLOCATIONS.with(|a| a.get()) - before
+    }
+
+    #[test]
+    fn create_permutations_matches_recursive_test() {
+        let cards = [
+            Card::new(2, Color::Red),
+            Card::new(2, Color::Blue),
+            Card::new(2, Color::Black),
+            Card::new(2, Color::Yellow),
+            Card::Wildcard,
+            Card::Wildcard,
+            Card::Wildcard
+        ];
+        let refs: Vec<&Card> = cards.iter().collect();
+
+        for len in 0..=refs.len() {
+            for min_len in 0..=5 {
+                // Unlike the recursive version, the new one leaves out the wildcards alone
+                let mut recursive = create_permutations_recursive(&refs[..len], min_len);
+                recursive.retain(|cards| cards.iter().any(|c| !c.is_wildcard()));
+                let recursive: Vec<Vec<&Card>> = recursive.into_iter().collect();
+                assert_eq!(create_permutations(&refs[..len], min_len, 4, None), recursive, "{} cards, min_len {}", len, min_len);
+            }
+        }
+    }
+
+    #[test]
+    fn create_permutations_allocations_test() {
+        // A group of 4 colors with 2 wildcards
+        let cards = [
+            Card::new(9, Color::Red),
+            Card::new(9, Color::Blue),
+            Card::new(9, Color::Black),
+            Card::new(9, Color::Yellow),
+            Card::Wildcard,
+            Card::Wildcard
+        ];
+        let refs: Vec<&Card> = cards.iter().collect();
+
+        let combinations = count_allocations(|| create_permutations(&refs, 3, 4, None));
+        let recursive = count_allocations(|| create_permutations_recursive(&refs, 3));
+
+        assert!(combinations * 5 < recursive, "{} vs {} allocations", combinations, recursive);
+    }
+
+    pub(super) fn count_candidates<T>(f: impl FnOnce() -> T) -> usize {
+        let before = CANDIDATES.with(|c| c.get());
+        drop(f());
+        CANDIDATES.with(|c| c.get()) - before
+    }
+
+    #[test]
+    fn count_and_has_valid_set_test() {
+        let no_wildcards: Vec<Card> = [(3, Color::Red), (4, Color::Red), (5, Color::Red), (5, Color::Red), (6, Color::Red), (5, Color::Blue), (5, Color::Black), (5, Color::Yellow), (13, Color::Blue), (1, Color::Blue)]
+            .into_iter()
+            .map(|(number, color)| Card::new(number, color))
+            .collect();
+        let with_wildcards = [no_wildcards.clone(), vec![Card::Wildcard, Card::Wildcard]].concat();
+        let trays = [search_test_tray(), no_wildcards, with_wildcards, vec![Card::new(2, Color::Red), Card::Wildcard], vec![]];
+        let options = [
+            SetSearchOptions::default(),
+            SetSearchOptions { min_len: 4, ..SetSearchOptions::default() },
+            SetSearchOptions { include_runs: false, ..SetSearchOptions::default() },
+            SetSearchOptions { include_groups: false, max_results: Some(2), ..SetSearchOptions::default() },
+            SetSearchOptions { maximal_only: true, ..SetSearchOptions::default() },
+            SetSearchOptions { rules: RulesConfig { wrap_runs: true, ..RulesConfig::default() }, ..SetSearchOptions::default() }
+        ];
+
+        for tray in &trays {
+            assert_eq!(count_valid_sets(tray), valid_sets(tray).len(), "{:?}", tray);
+            assert_eq!(has_valid_set(tray), !valid_sets(tray).is_empty(), "{:?}", tray);
+            for options in &options {
+                assert_eq!(count_valid_sets_with(tray, options), valid_sets_with(tray, options).len(), "{:?} {:?}", tray, options);
+                assert_eq!(has_valid_set_with(tray, options), !valid_sets_with(tray, options).is_empty(), "{:?} {:?}", tray, options);
+            }
+        }
+        assert!(!has_valid_set_with(&trays[0], &SetSearchOptions { max_results: Some(0), ..SetSearchOptions::default() }));
+    }
+
+    #[test]
+    fn has_valid_set_stops_early_test() {
+        let tray = [Card::new(1, Color::Red), Card::new(5, Color::Blue), Card::new(9, Color::Black), Card::new(13, Color::Yellow), Card::Wildcard, Card::Wildcard, Card::Wildcard];
+
+        let every_set = count_candidates(|| parallel::sequential(|| valid_sets(&tray)));
+        let first_set = count_candidates(|| has_valid_set(&tray));
+
+        assert!(has_valid_set(&tray));
+        // R1 W W is the first subset tried
+        assert_eq!(first_set, 1);
+        assert!(first_set * 100 < every_set, "{} vs {} candidates", first_set, every_set);
+    }
+
+    #[test]
+    fn standard_without_test() {
+        let mut deck = Deck::new_seeded(3);
+        let tray = deck.pick_tray(14);
+        let table = deck.pick_tray(20);
+
+        let mut without = Deck::standard_without(&[tray, table].concat()).unwrap();
+        assert_eq!(without.len(), 72);
+        let mut sorted_rest = deck.cards.clone();
+        sorted_rest.sort();
+        let mut rest = without.cards.clone();
+        rest.sort();
+        assert_eq!(rest, sorted_rest);
+
+        without.shuffle_with(&mut StdRng::seed_from_u64(5));
+        assert_eq!(without.pick_tray(72).len(), 72);
+        assert_eq!(Deck::from_cards(vec![Card::Wildcard, Card::new(2, Color::Red)]).pick_card(), Some(Card::new(2, Color::Red)));
+    }
+
+    #[test]
+    fn standard_without_third_copy_test() {
+        let seen = [Card::new(5, Color::Red), Card::Wildcard, Card::new(5, Color::Red), Card::new(5, Color::Red)];
+
+        let error = Deck::standard_without(&seen).unwrap_err();
+
+        assert_eq!(error, DeckError::TooManyCopies(Card::new(5, Color::Red)));
+        assert_eq!(error.to_string(), "R5 was seen more times than the deck holds it");
+        assert_eq!(Deck::standard_without(&seen[..3]).unwrap().len(), 103);
+    }
+
+    #[test]
+    fn six_color_deck_test() {
+        let six_colors = RulesConfig::six_colors();
+        let deck = Deck::with_rules(&six_colors);
+
+        assert_eq!(deck.cards.len(), 158);
+        assert_eq!(deck.cards.iter().filter(|c| c.color() == Some(&Color::Orange)).count(), 26);
+        assert_eq!(Deck::with_rules(&RulesConfig::default()), Deck::new());
+        for card in &deck.cards {
+            assert_eq!(card.code().parse(), Ok(*card));
+        }
+
+        let test_tray: Vec<Card> = Color::SIX[1..].iter().map(|c| Card::new(7, *c)).collect();
+        let options = SetSearchOptions { rules: six_colors, ..SetSearchOptions::default() };
+        assert!(valid_sets(&test_tray).iter().all(|s| s.len() <= 4));
+        assert!(valid_sets_with(&test_tray, &options).contains(&test_tray.iter().collect::<Vec<&Card>>()));
+    }
+
+    #[test]
+    fn number_range_test() {
+        let kids = RulesConfig { max_number: 10, ..RulesConfig::default() };
+        let extended = RulesConfig { max_number: 16, ..RulesConfig::default() };
+
+        assert_eq!(Deck::with_rules(&kids).cards.len(), 82);
+        assert_eq!(Deck::with_rules(&extended).cards.len(), 130);
+        assert!(Deck::with_rules(&kids).cards.iter().all(|c| c.number().is_none_or(|n| *n <= 10)));
+
+        let test_tray: Vec<Card> = (8..=16).map(|n| Card::new(n, Color::Red)).collect();
+        let top = |max: i8| test_tray.iter().filter(|c| c.number().is_some_and(|n| (max-2..=max).contains(n))).collect::<Vec<&Card>>();
+        let runs_with = |rules: RulesConfig| valid_sets_with(&test_tray, &SetSearchOptions { rules, ..SetSearchOptions::default() });
+        assert!(runs_with(extended).contains(&top(16)));
+        assert!(runs_with(extended).contains(&test_tray.iter().collect()));
+        assert!(runs_with(kids).contains(&top(10)));
+        assert!(runs_with(kids).iter().flatten().all(|c| c.number() <= Some(&10)));
+        assert!(valid_sets(&test_tray).iter().flatten().all(|c| c.number() <= Some(&13)));
+
+        assert_eq!(Card::parse_with("B16", &extended), Ok(Card::new(16, Color::Blue)));
+        assert_eq!("B16".parse::<Card>(), Err(ParseCardError { token: "B16".to_string(), min: 1, max: 13 }));
+        assert_eq!(
+            Card::parse_with("B11", &kids).unwrap_err().to_string(),
+            "unknown tile 'B11': expected a color (R, B, K, Y, G or O) followed by a number from 1 to 10, or W for a wildcard"
+        );
+    }
+
+    #[test]
+    fn parse_card_test() {
+        assert_eq!("B2".parse(), Ok(Card::new(2, Color::Blue)));
+        assert_eq!("k13".parse(), Ok(Card::new(13, Color::Black)));
+        assert_eq!("G4".parse(), Ok(Card::new(4, Color::Green)));
+        assert_eq!("w".parse(), Ok(Card::Wildcard));
+        for token in ["", "X3", "R", "R0", "Y14", "B2x", "B+2"] {
+            assert_eq!(token.parse::<Card>(), Err(ParseCardError { token: token.to_string(), min: 1, max: 13 }));
+        }
+
+        for card in Deck::new().cards {
+            assert_eq!(card.code().parse(), Ok(card));
+        }
+    }
+
+    #[test]
+    fn try_new_test() {
+        assert_eq!(Card::try_new(1, Color::Red), Ok(Card::new(1, Color::Red)));
+        assert_eq!(Card::try_new(13, Color::Yellow), Ok(Card::new(13, Color::Yellow)));
+        for number in [0, 14, -1, i8::MIN] {
+            let error = Card::try_new(number, Color::Blue).unwrap_err();
+            assert_eq!(error, InvalidCard { number, min: 1, max: 13 });
+            assert!(error.to_string().contains(&number.to_string()), "{}", error);
+        }
+
+        let extended = RulesConfig { max_number: 16, ..RulesConfig::default() };
+        assert_eq!(Card::try_new_with(16, Color::Red, &extended), Ok(Card::new(16, Color::Red)));
+        assert_eq!(Card::try_new_with(17, Color::Red, &extended), Err(InvalidCard { number: 17, min: 1, max: 16 }));
+    }
+
+    #[test]
+    fn valid_sets_with_order_test() {
+        let test_tray = search_test_tray();
+
+        let by_length = valid_sets_with(&test_tray, &SetSearchOptions { order: SetOrder::Length, ..SetSearchOptions::default() });
+        let by_points = valid_sets_with(&test_tray, &SetSearchOptions { order: SetOrder::Points, ..SetSearchOptions::default() });
+        let top_two = valid_sets_with(&test_tray, &SetSearchOptions {
+            order: SetOrder::Points,
+            max_results: Some(2),
+            ..SetSearchOptions::default()
+        });
+
+        assert_eq!(by_length.len(), valid_sets(&test_tray).len());
+        assert!(by_length.windows(2).all(|pair| pair[0].len() >= pair[1].len()));
+        assert!(by_points.windows(2).all(|pair| set_points_with(&pair[0], &RulesConfig::default()) >= set_points_with(&pair[1], &RulesConfig::default())));
+        assert_eq!(top_two, by_points[..2]);
+    }
+
+}
@@ -0,0 +1,146 @@
+use alloc::collections::BTreeSet;
+use super::{Card, Color};
+use crate::prelude::*;
+
+/// Two tiles of the hand and the tiles that would turn them into a valid set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlmostSet {
+    pub held: Vec<Card>,
+    pub missing: Vec<Card>
+}
+
+/// Finds the pairs of tiles that are one tile away from a 3-tile set. Wildcards are left out,
+/// since they already complete any pair, and so are the missing tiles already in the hand.
+pub fn find_almost_sets(cards: &[Card]) -> Vec<AlmostSet> {
+    let held: BTreeSet<(i8, Color)> = cards.iter()
+        .filter_map(|c| match c {
+            Card::Numbered { number, color } if (1..=13).contains(number) => Some((*number, *color)),
+            _ => None
+        })
+        .collect();
+    let held: Vec<(i8, Color)> = held.into_iter().collect();
+
+    let mut almost_sets = vec![];
+    for (idx, (number, color)) in held.iter().enumerate() {
+        for (other_number, other_color) in &held[idx+1..] {
+            let completions: Vec<(i8, Color)> = if color == other_color {
+                match other_number - number {
+                    1 => vec![(number - 1, *color), (other_number + 1, *color)],
+                    2 => vec![(number + 1, *color)],
+                    _ => vec![]
+                }
+            } else if number == other_number {
+                Color::ALL.iter()
+                    .filter(|c| *c != color && *c != other_color)
+                    .map(|c| (*number, *c))
+                    .collect()
+            } else {
+                vec![]
+            };
+
+            let missing: Vec<Card> = completions.into_iter()
+                .filter(|(n, c)| (1..=13).contains(n) && !held.contains(&(*n, *c)))
+                .map(|(n, c)| Card::new(n, c))
+                .collect();
+            if !missing.is_empty() {
+                almost_sets.push(AlmostSet {
+                    held: vec![Card::new(*number, *color), Card::new(*other_number, *other_color)],
+                    missing
+                });
+            }
+        }
+    }
+    almost_sets
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn almost_run_gap_test() {
+        let test_tray = vec![
+            Card::new(4, Color::Blue),
+            Card::new(6, Color::Blue)
+        ];
+
+        assert_eq!(find_almost_sets(&test_tray), vec![AlmostSet {
+            held: vec![Card::new(4, Color::Blue), Card::new(6, Color::Blue)],
+            missing: vec![Card::new(5, Color::Blue)]
+        }]);
+    }
+
+    #[test]
+    fn almost_run_ends_test() {
+        let test_tray = vec![
+            Card::new(5, Color::Blue),
+            Card::new(4, Color::Blue),
+            Card::new(12, Color::Red),
+            Card::new(13, Color::Red)
+        ];
+
+        assert_eq!(find_almost_sets(&test_tray), vec![
+            AlmostSet {
+                held: vec![Card::new(4, Color::Blue), Card::new(5, Color::Blue)],
+                missing: vec![Card::new(3, Color::Blue), Card::new(6, Color::Blue)]
+            },
+            AlmostSet {
+                held: vec![Card::new(12, Color::Red), Card::new(13, Color::Red)],
+                missing: vec![Card::new(11, Color::Red)]
+            }
+        ]);
+    }
+
+    #[test]
+    fn almost_group_test() {
+        let test_tray = vec![
+            Card::new(7, Color::Red),
+            Card::new(7, Color::Black)
+        ];
+
+        assert_eq!(find_almost_sets(&test_tray), vec![AlmostSet {
+            held: vec![Card::new(7, Color::Red), Card::new(7, Color::Black)],
+            missing: vec![Card::new(7, Color::Blue), Card::new(7, Color::Yellow)]
+        }]);
+    }
+
+    #[test]
+    fn almost_sets_duplicates_and_wildcards_test() {
+        let test_tray = vec![
+            Card::new(7, Color::Red),
+            Card::new(7, Color::Red),
+            Card::new(7, Color::Black),
+            Card::new(7, Color::Black),
+            Card::new(1, Color::Yellow),
+            Card::Wildcard
+        ];
+
+        let almost_sets = find_almost_sets(&test_tray);
+
+        assert_eq!(almost_sets.len(), 1);
+        assert_eq!(almost_sets[0].held, vec![Card::new(7, Color::Red), Card::new(7, Color::Black)]);
+    }
+
+    #[test]
+    fn almost_sets_already_complete_test() {
+        // 8 Blue is already held: only the ends of the run are still missing
+        let test_tray = vec![
+            Card::new(7, Color::Blue),
+            Card::new(8, Color::Blue),
+            Card::new(9, Color::Blue)
+        ];
+
+        assert_eq!(find_almost_sets(&test_tray), vec![
+            AlmostSet {
+                held: vec![Card::new(7, Color::Blue), Card::new(8, Color::Blue)],
+                missing: vec![Card::new(6, Color::Blue)]
+            },
+            AlmostSet {
+                held: vec![Card::new(8, Color::Blue), Card::new(9, Color::Blue)],
+                missing: vec![Card::new(10, Color::Blue)]
+            }
+        ]);
+    }
+
+}
@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+use thiserror::Error;
+use super::{check_turn, set_points_with, Card, GameState, PlayerId, Table, TurnError};
+
+/// How a claimed turn breaks the rules, see audit_turn
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum CheatError {
+    /// A tile showed up on the table without leaving the tray of the player
+    #[error("{} reached the table without leaving the tray", .0.code())]
+    TileConjured(Card),
+    /// A tile of the table is gone from it
+    #[error("{} left the table", .0.code())]
+    TileVanished(Card),
+    /// The player played a tile they didn't hold
+    #[error("{} was played without being in the tray", .0.code())]
+    PlayedUnownedTile(Card),
+    /// A wildcard left the table instead of being played again in the same turn
+    #[error("a wildcard was taken from the table and kept")]
+    IllegalJokerRetrieval,
+    /// The tiles add up, but the turn is still not legal, like one leaving an invalid meld
+    #[error("{0}")]
+    IllegalTurn(#[from] TurnError)
+}
+
+/// What audit_turn found the player did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TurnSummary {
+    /// The tiles that went from the tray to the table, sorted
+    pub played: Vec<Card>,
+    /// The tiles that reached the tray, sorted: the drawn tile when nothing was played
+    pub drawn: Vec<Card>,
+    /// The points the table gained, wildcards being worth the number they stand for
+    pub points: u32
+}
+
+fn counts<'a>(cards: impl IntoIterator<Item = &'a Card>) -> BTreeMap<Card, usize> {
+    let mut counts = BTreeMap::new();
+    for card in cards {
+        *counts.entry(*card).or_default() += 1;
+    }
+    counts
+}
+
+// The melds without their ids and order, to tell whether the table was rearranged
+fn sorted_melds(table: &Table) -> Vec<Vec<Card>> {
+    let mut melds: Vec<Vec<Card>> = table.melds()
+        .map(|(_, cards)| {
+            let mut cards = cards.to_vec();
+            cards.sort();
+            cards
+        })
+        .collect();
+    melds.sort();
+    melds
+}
+
+fn table_points(table: &Table) -> u32 {
+    table.melds().map(|(_, cards)| set_points_with(&cards.iter().collect::<Vec<&Card>>(), table.rules())).sum()
+}
+
+/// Checks the turn a client says the actor played, going from prev to claimed, for a host
+/// that can't trust its players. Beyond check_turn, the tiles are followed one by one: the
+/// table never loses a tile, wildcards included, and every tile it gains comes out of the
+/// tray the actor held in prev. A turn playing nothing must leave the table as it was.
+///
+/// Only the table and the tray of the actor are compared: the deck, the discard pile and the
+/// other trays stay with the host, who deals the draws. Panics if there is no such player in
+/// either game.
+pub fn audit_turn(prev: &GameState, claimed: &GameState, actor: PlayerId) -> Result<TurnSummary, CheatError> {
+    let hand_before = prev.players()[actor].tray();
+    let hand_after = claimed.players()[actor].tray();
+    let (table_before, table_after) = (counts(prev.table().melds().flat_map(|(_, cards)| cards)), counts(claimed.table().melds().flat_map(|(_, cards)| cards)));
+    let (held_before, held_after) = (counts(hand_before), counts(hand_after));
+    let count = |counts: &BTreeMap<Card, usize>, card: &Card| counts.get(card).copied().unwrap_or(0);
+
+    let mut played = vec![];
+    for card in table_before.keys().chain(table_after.keys()) {
+        let (before, after) = (count(&table_before, card), count(&table_after, card));
+        if after < before {
+            return Err(if card.is_wildcard() { CheatError::IllegalJokerRetrieval } else { CheatError::TileVanished(*card) });
+        }
+        // Both maps are walked, so the tiles of the second were already seen in the first
+        if after == before || played.contains(card) {
+            continue;
+        }
+        let held = count(&held_before, card);
+        if after - before > held {
+            return Err(CheatError::PlayedUnownedTile(*card));
+        }
+        if after - before > held - count(&held_after, card).min(held) {
+            return Err(CheatError::TileConjured(*card));
+        }
+        played.extend(core::iter::repeat_n(*card, after - before));
+    }
+
+    let mut drawn = vec![];
+    for (card, after) in &held_after {
+        drawn.extend(core::iter::repeat_n(*card, after.saturating_sub(count(&held_before, card))));
+    }
+    if played.is_empty() {
+        if sorted_melds(prev.table()) != sorted_melds(claimed.table()) {
+            return Err(TurnError::NothingPlayed.into());
+        }
+    } else {
+        check_turn(prev.table(), claimed.table(), hand_before, hand_after, prev.players()[actor].has_melded())?;
+    }
+    played.sort();
+    Ok(TurnSummary {
+        played,
+        drawn,
+        points: table_points(claimed.table()).saturating_sub(table_points(prev.table()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::{Color, Deck, GameSave, RulesConfig, SavedPlayer};
+
+    fn red(numbers: &[i8]) -> Vec<Card> {
+        numbers.iter().map(|n| Card::new(*n, Color::Red)).collect()
+    }
+
+    // Ann, who has melded, and Bo share an R6; the deck holds every other tile
+    fn game(table: &[Vec<Card>], ann: &[Card], bo: &[Card]) -> GameState {
+        let dealt: Vec<Card> = table.iter().flatten().chain(ann).chain(bo).copied().collect();
+        GameState::from_save(GameSave {
+            seed: None,
+            deck: Deck::standard_without(&dealt).unwrap().cards,
+            players: vec![
+                SavedPlayer { name: "Ann".to_string(), tray: ann.to_vec(), has_melded: true, took_mulligan: false },
+                SavedPlayer { name: "Bo".to_string(), tray: bo.to_vec(), has_melded: true, took_mulligan: false }
+            ],
+            table: table.to_vec(),
+            discards: vec![],
+            current_player: 0,
+            started: true,
+            rules: RulesConfig::default()
+        }).unwrap()
+    }
+
+    fn ann() -> Vec<Card> {
+        vec![Card::new(6, Color::Red), Card::new(10, Color::Black), Card::new(10, Color::Yellow), Card::new(4, Color::Blue), Card::new(2, Color::Yellow)]
+    }
+
+    fn table() -> Vec<Vec<Card>> {
+        let blue = vec![Card::new(1, Color::Blue), Card::new(2, Color::Blue), Card::new(3, Color::Blue)];
+        vec![red(&[7, 8, 9, 10]), blue, vec![Card::new(2, Color::Red), Card::new(2, Color::Black), Card::Wildcard]]
+    }
+
+    #[test]
+    fn legal_rearrangement_test() {
+        let prev = game(&table(), &ann(), &[Card::new(6, Color::Red)]);
+        // R10 leaves its run for a group of 10s, B4 extends the blue run and Y2 frees the
+        // wildcard, which goes to the end of the red run
+        let blue = vec![Card::new(1, Color::Blue), Card::new(2, Color::Blue), Card::new(3, Color::Blue), Card::new(4, Color::Blue)];
+        let claimed = game(&[
+            vec![Card::new(7, Color::Red), Card::new(8, Color::Red), Card::new(9, Color::Red), Card::Wildcard],
+            vec![Card::new(10, Color::Red), Card::new(10, Color::Black), Card::new(10, Color::Yellow)],
+            blue,
+            vec![Card::new(2, Color::Red), Card::new(2, Color::Black), Card::new(2, Color::Yellow)]
+        ], &ann()[..1], &[Card::new(6, Color::Red)]);
+
+        let summary = audit_turn(&prev, &claimed, 0).unwrap();
+        assert_eq!(summary.played, vec![Card::new(2, Color::Yellow), Card::new(4, Color::Blue), Card::new(10, Color::Black), Card::new(10, Color::Yellow)]);
+        assert!(summary.drawn.is_empty());
+        // The table gained 2 + 4 + 10 + 10, and the wildcard went from standing for a 2 to
+        // standing for R10
+        assert_eq!(summary.points, 34);
+
+        let drew = game(&table(), &[ann().as_slice(), &[Card::new(13, Color::Yellow)]].concat(), &[Card::new(6, Color::Red)]);
+        assert_eq!(audit_turn(&prev, &drew, 0), Ok(TurnSummary { played: vec![], drawn: vec![Card::new(13, Color::Yellow)], points: 0 }));
+    }
+
+    #[test]
+    fn cheat_variants_test() {
+        let prev = game(&table(), &ann(), &[Card::new(6, Color::Red)]);
+        let mut others = table();
+
+        // R10 goes back to the tray
+        others[0] = red(&[7, 8, 9]);
+        let vanished = game(&others, &[ann().as_slice(), &[Card::new(10, Color::Red)]].concat(), &[Card::new(6, Color::Red)]);
+        assert_eq!(audit_turn(&prev, &vanished, 0), Err(CheatError::TileVanished(Card::new(10, Color::Red))));
+
+        // R11 comes from the deck
+        others[0] = red(&[7, 8, 9, 10, 11]);
+        let unowned = game(&others, &ann(), &[Card::new(6, Color::Red)]);
+        assert_eq!(audit_turn(&prev, &unowned, 0), Err(CheatError::PlayedUnownedTile(Card::new(11, Color::Red))));
+
+        // The R6 of Bo is played while Ann keeps hers
+        others[0] = red(&[6, 7, 8, 9, 10]);
+        let conjured = game(&others, &ann(), &[]);
+        assert_eq!(audit_turn(&prev, &conjured, 0), Err(CheatError::TileConjured(Card::new(6, Color::Red))));
+
+        // Y2 takes the place of the wildcard, which stays in the tray
+        others[0] = red(&[7, 8, 9, 10]);
+        others[2] = vec![Card::new(2, Color::Red), Card::new(2, Color::Black), Card::new(2, Color::Yellow)];
+        let kept = game(&others, &[&ann()[..4], &[Card::Wildcard]].concat(), &[Card::new(6, Color::Red)]);
+        assert_eq!(audit_turn(&prev, &kept, 0), Err(CheatError::IllegalJokerRetrieval));
+
+        // Nothing is played, but the red run is cut in two
+        let long = game(&[red(&[7, 8, 9, 10, 11, 12, 13])], &ann(), &[Card::new(6, Color::Red)]);
+        let cut = game(&[red(&[7, 8, 9]), red(&[10, 11, 12, 13])], &ann(), &[Card::new(6, Color::Red)]);
+        assert_eq!(audit_turn(&long, &cut, 0), Err(CheatError::IllegalTurn(TurnError::NothingPlayed)));
+    }
+
+}
@@ -0,0 +1,434 @@
+use super::{Card, Color};
+use crate::prelude::*;
+
+// Packed tiles: the color index in the high bits, the number in the low ones
+pub(super) const WILDCARD: u8 = 0xFF;
+const NUMBER_BITS: u8 = 4;
+const NUMBER_MASK: u8 = (1 << NUMBER_BITS) - 1;
+
+// Trays are tracked with one bit per position, so bigger ones use the counts solver
+const MAX_POSITIONS: usize = 64;
+
+pub(super) fn pack(card: &Card) -> Option<u8> {
+    match card {
+        Card::Numbered { number, color } => {
+            if !(1..=13).contains(number) {
+                return None;
+            }
+            let color_idx = Color::ALL.iter().position(|c| c == color)? as u8;
+            Some(color_idx << NUMBER_BITS | *number as u8)
+        },
+        Card::Wildcard => Some(WILDCARD),
+        // The solvers play standard sets only
+        Card::DoubleJoker | Card::MirrorJoker => None
+    }
+}
+
+pub(super) fn unpack(code: u8) -> Option<Card> {
+    if code == WILDCARD {
+        return Some(Card::Wildcard);
+    }
+    let number = (code & NUMBER_MASK) as i8;
+    let color = *Color::ALL.get((code >> NUMBER_BITS) as usize)?;
+    if !(1..=13).contains(&number) {
+        return None;
+    }
+    Some(Card::new(number, color))
+}
+
+// The tiles of a tray as counts indexed by their packed code, plus the wildcards.
+// Tiles out of range can't belong to any set and are left out.
+pub(super) struct PackedTray {
+    pub(super) counts: [u8; 64],
+    pub(super) wildcards: u8
+}
+
+impl PackedTray {
+    // Returns None when the tray has too many numbered tiles for the bitset solver
+    pub(super) fn from_cards(cards: &[Card]) -> Option<PackedTray> {
+        let mut tray = PackedTray { counts: [0; 64], wildcards: 0 };
+        let mut numbered = 0;
+        for card in cards {
+            match pack(card) {
+                Some(WILDCARD) => tray.wildcards = tray.wildcards.checked_add(1)?,
+                Some(code) => {
+                    tray.counts[code as usize] += 1;
+                    numbered += 1;
+                },
+                None => {}
+            }
+        }
+        if numbered > MAX_POSITIONS {
+            return None;
+        }
+        Some(tray)
+    }
+
+    fn is_held(&self, code: u8) -> bool {
+        self.counts[code as usize] > 0
+    }
+}
+
+// Tiles sort by number first, then by color
+fn sort_key(code: u8) -> (u8, u8) {
+    (code & NUMBER_MASK, code >> NUMBER_BITS)
+}
+
+// A set the bitset solver can pick: its numbered tiles, lowest first, and its wildcards.
+// Tiles are kept inline, since a set never has more than 13 of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) struct PackedCandidate {
+    tiles: [u8; 13],
+    len: u8,
+    wildcards: u8
+}
+
+impl PackedCandidate {
+    fn new(codes: impl Iterator<Item = u8>, wildcards: usize) -> PackedCandidate {
+        let mut candidate = PackedCandidate { tiles: [0; 13], len: 0, wildcards: wildcards as u8 };
+        for code in codes {
+            candidate.tiles[candidate.len as usize] = code;
+            candidate.len += 1;
+        }
+        candidate
+    }
+
+    fn codes(&self) -> &[u8] {
+        &self.tiles[..self.len as usize]
+    }
+
+    fn size(&self) -> usize {
+        (self.len + self.wildcards) as usize
+    }
+
+    fn cards(&self) -> Vec<Card> {
+        self.codes().iter()
+            .filter_map(|code| unpack(*code))
+            .chain(core::iter::repeat_n(Card::Wildcard, self.wildcards as usize))
+            .collect()
+    }
+}
+
+// Same sets as partition::create_candidates, built straight from the packed codes
+pub(super) fn packed_candidates(tray: &PackedTray) -> Vec<PackedCandidate> {
+    let wildcards = tray.wildcards as usize;
+    let mut candidates = vec![];
+
+    // Runs: every window of a color, with wildcards for the missing tiles and maybe for held ones
+    for color_idx in 0..Color::ALL.len() as u8 {
+        for start in 1..=13u8 {
+            let mut held = [0u8; 13];
+            let mut held_len = 0;
+            let mut missing = 0;
+            for end in start..=13u8 {
+                let code = color_idx << NUMBER_BITS | end;
+                if tray.is_held(code) {
+                    held[held_len] = code;
+                    held_len += 1;
+                } else {
+                    missing += 1;
+                }
+                // Longer windows only miss more tiles
+                if missing > wildcards {
+                    break;
+                }
+                if end - start >= 2 {
+                    let len = (end - start + 1) as usize;
+                    push_swaps(&held[..held_len], 0, wildcards - missing, 0, len, &mut candidates);
+                }
+            }
+        }
+    }
+
+    // Groups: any subset of the held colors of a number, completed with wildcards
+    for number in 1..=13u8 {
+        let colors: Vec<u8> = (0..Color::ALL.len() as u8)
+            .map(|color_idx| color_idx << NUMBER_BITS | number)
+            .filter(|code| tray.is_held(*code))
+            .collect();
+        for mask in 1..(1u32 << colors.len()) {
+            let count = mask.count_ones() as usize;
+            for group_wildcards in 0..=wildcards.min(4) {
+                if (3..=4).contains(&(count + group_wildcards)) {
+                    let codes = (0..colors.len()).filter(|i| mask & (1 << i) != 0).map(|i| colors[i]);
+                    candidates.push(PackedCandidate::new(codes, group_wildcards));
+                }
+            }
+        }
+    }
+
+    // Wildcards at the ends of a run make the same set out of more than one window
+    candidates.sort_unstable();
+    candidates.dedup();
+    candidates
+}
+
+// Pushes the run made of the held tiles of a window, for every way of swapping at most
+// spare of them (from idx onwards) for wildcards
+fn push_swaps(
+    held: &[u8],
+    idx: usize,
+    spare: usize,
+    replaced: u32,
+    len: usize,
+    candidates: &mut Vec<PackedCandidate>
+) {
+    if idx == held.len() {
+        let codes = (0..held.len()).filter(|i| replaced & (1 << i) == 0).map(|i| held[i]);
+        let candidate = PackedCandidate::new(codes, 0);
+        if candidate.len > 0 {
+            candidates.push(PackedCandidate { wildcards: (len - candidate.len as usize) as u8, ..candidate });
+        }
+        return;
+    }
+    push_swaps(held, idx + 1, spare, replaced, len, candidates);
+    if spare > 0 {
+        push_swaps(held, idx + 1, spare - 1, replaced | 1 << idx, len, candidates);
+    }
+}
+
+// Branch and bound over the tray positions: every numbered tile has its own bit, and a set
+// is the mask of the positions it takes
+struct BitsetSolver<'a> {
+    candidates: &'a [PackedCandidate],
+    // Packed code of every position, lowest tiles first
+    positions: Vec<u8>,
+    // For every packed code, the positions holding a copy of it
+    copies: [u64; 64],
+    // For every packed code, the range of candidates starting with it
+    by_first: [(usize, usize); 64],
+    all: u64,
+    decided: u64,
+    wildcards: usize,
+    chosen: Vec<usize>,
+    played: usize,
+    best: Vec<usize>,
+    best_played: usize
+}
+
+impl<'a> BitsetSolver<'a> {
+    fn new(tray: &PackedTray, candidates: &'a [PackedCandidate]) -> BitsetSolver<'a> {
+        let mut order: Vec<u8> = (0..64u8).filter(|code| tray.is_held(*code)).collect();
+        order.sort_by_key(|code| sort_key(*code));
+        let mut positions = vec![];
+        let mut copies = [0u64; 64];
+        for code in order {
+            for _ in 0..tray.counts[code as usize] {
+                copies[code as usize] |= 1 << positions.len();
+                positions.push(code);
+            }
+        }
+
+        // Candidates are sorted, so the ones sharing their lowest tile are next to each other
+        let mut by_first = [(0, 0); 64];
+        for (idx, candidate) in candidates.iter().enumerate() {
+            let range = &mut by_first[candidate.codes()[0] as usize];
+            if range.0 == range.1 {
+                *range = (idx, idx);
+            }
+            range.1 = idx + 1;
+        }
+
+        let all = if positions.len() == MAX_POSITIONS { u64::MAX } else { (1 << positions.len()) - 1 };
+        BitsetSolver {
+            candidates,
+            positions,
+            copies,
+            by_first,
+            all,
+            decided: 0,
+            wildcards: tray.wildcards as usize,
+            chosen: vec![],
+            played: 0,
+            best: vec![],
+            best_played: 0
+        }
+    }
+
+    // The positions the candidate would take, picking the lowest free copy of each tile
+    fn mask_of(&self, candidate: &PackedCandidate) -> Option<u64> {
+        if candidate.wildcards as usize > self.wildcards {
+            return None;
+        }
+        let mut mask = 0;
+        for code in candidate.codes() {
+            let free = self.copies[*code as usize] & !self.decided;
+            if free == 0 {
+                return None;
+            }
+            mask |= free & free.wrapping_neg();
+        }
+        Some(mask)
+    }
+
+    fn take(&mut self, candidate_idx: usize, mask: u64) {
+        let candidate = &self.candidates[candidate_idx];
+        self.decided |= mask;
+        self.wildcards -= candidate.wildcards as usize;
+        self.played += candidate.size();
+        self.chosen.push(candidate_idx);
+    }
+
+    fn give_back(&mut self, mask: u64) {
+        let candidate = &self.candidates[self.chosen.pop().unwrap()];
+        self.decided &= !mask;
+        self.wildcards += candidate.wildcards as usize;
+        self.played -= candidate.size();
+    }
+
+    // The lowest tile not played nor kept yet, with its position
+    fn next_free(&self) -> Option<(u32, usize)> {
+        let free = self.all & !self.decided;
+        if free == 0 {
+            return None;
+        }
+        let position = free.trailing_zeros();
+        Some((position, self.positions[position as usize] as usize))
+    }
+
+    fn search(&mut self) {
+        if self.played > self.best_played {
+            self.best_played = self.played;
+            self.best = self.chosen.clone();
+        }
+        let remaining = (self.all & !self.decided).count_ones() as usize;
+        // Even playing every remaining tile can't beat the best partition found so far
+        if self.played + remaining + self.wildcards <= self.best_played {
+            return;
+        }
+        let (position, code) = match self.next_free() {
+            Some(next) => next,
+            None => return
+        };
+
+        let (first, last) = self.by_first[code];
+        for candidate_idx in first..last {
+            if let Some(mask) = self.mask_of(&self.candidates[candidate_idx]) {
+                self.take(candidate_idx, mask);
+                self.search();
+                self.give_back(mask);
+            }
+        }
+
+        // Keep this tile in the hand
+        self.decided |= 1 << position;
+        self.search();
+        self.decided &= !(1 << position);
+    }
+
+    // Looks only for partitions using every tile, stopping at the first one
+    fn search_complete(&mut self) -> bool {
+        let code = match self.next_free() {
+            Some((_, code)) => code,
+            None => {
+                // Every wildcard must end up in a set too
+                if self.wildcards == 0 {
+                    self.best = self.chosen.clone();
+                }
+                return self.wildcards == 0;
+            }
+        };
+
+        // The lowest tile left must belong to one of the sets starting with it
+        let (first, last) = self.by_first[code];
+        for candidate_idx in first..last {
+            if let Some(mask) = self.mask_of(&self.candidates[candidate_idx]) {
+                self.take(candidate_idx, mask);
+                let found = self.search_complete();
+                self.give_back(mask);
+                if found {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    fn best_cards(&self) -> Vec<Vec<Card>> {
+        self.best.iter().map(|idx| self.candidates[*idx].cards()).collect()
+    }
+}
+
+// The sets playing the most tiles of the tray
+pub(super) fn best_sets(tray: &PackedTray) -> Vec<Vec<Card>> {
+    let candidates = packed_candidates(tray);
+    let mut solver = BitsetSolver::new(tray, &candidates);
+    solver.search();
+    solver.best_cards()
+}
+
+// Sets playing every tile of the tray, if there are any
+pub(super) fn complete_sets(tray: &PackedTray) -> Option<Vec<Vec<Card>>> {
+    let candidates = packed_candidates(tray);
+    let mut solver = BitsetSolver::new(tray, &candidates);
+    if solver.search_complete() {
+        Some(solver.best_cards())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::collections::BTreeSet;
+    use super::*;
+    use super::super::partition::{create_candidates, TileCounts};
+
+    #[test]
+    fn pack_unpack_test() {
+        for number in 1..=13 {
+            for color in Color::ALL {
+                let card = Card::new(number, color);
+                assert_eq!(unpack(pack(&card).unwrap()), Some(card));
+            }
+        }
+        assert_eq!(pack(&Card::Wildcard), Some(WILDCARD));
+        assert_eq!(unpack(WILDCARD), Some(Card::Wildcard));
+        assert_eq!(pack(&Card::new(14, Color::Red)), None);
+    }
+
+    #[test]
+    fn packed_tray_test() {
+        let test_tray = vec![
+            Card::new(5, Color::Black),
+            Card::new(5, Color::Black),
+            Card::Numbered { number: 0, color: Color::Red },
+            Card::Wildcard
+        ];
+
+        let tray = PackedTray::from_cards(&test_tray).unwrap();
+
+        assert_eq!(tray.counts[pack(&Card::new(5, Color::Black)).unwrap() as usize], 2);
+        assert_eq!(tray.counts.iter().map(|c| *c as usize).sum::<usize>(), 2);
+        assert_eq!(tray.wildcards, 1);
+    }
+
+    #[test]
+    fn packed_candidates_match_test() {
+        let test_tray = vec![
+            Card::new(3, Color::Blue),
+            Card::new(4, Color::Blue),
+            Card::new(5, Color::Blue),
+            Card::new(7, Color::Blue),
+            Card::new(5, Color::Red),
+            Card::new(5, Color::Yellow),
+            Card::new(13, Color::Black),
+            Card::Wildcard,
+            Card::Wildcard
+        ];
+        let tiles = TileCounts::from_cards(&test_tray);
+
+        let packed: BTreeSet<Vec<Card>> = packed_candidates(&PackedTray::from_cards(&test_tray).unwrap())
+            .iter()
+            .map(|c| c.cards())
+            .collect();
+        let counted: BTreeSet<Vec<Card>> = create_candidates(&tiles.counts, tiles.wildcards)
+            .into_iter()
+            .map(|c| c.cards)
+            .collect();
+
+        assert_eq!(packed, counted);
+    }
+
+}
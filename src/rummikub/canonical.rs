@@ -0,0 +1,100 @@
+use super::Card;
+use crate::prelude::*;
+
+/// The tiles of a set in the order valid_sets gives them: numbered tiles by number and color,
+/// then the wildcards
+pub fn canonicalize_set(set: &[&Card]) -> Vec<Card> {
+    let mut cards: Vec<Card> = set.iter().map(|c| **c).collect();
+    cards.sort();
+    cards
+}
+
+/// Whether both sets hold the same tiles, in whatever order, a tile given twice needing two
+/// copies
+pub fn sets_equal(a: &[&Card], b: &[&Card]) -> bool {
+    a.len() == b.len() && canonicalize_set(a) == canonicalize_set(b)
+}
+
+/// Identifies a set by its tiles whatever their order, so the sets of find_runs and
+/// find_same_numbers can be deduplicated in a HashSet or a BTreeSet
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SetKey(Vec<Card>);
+
+impl SetKey {
+    pub fn of(set: &[&Card]) -> SetKey {
+        SetKey(canonicalize_set(set))
+    }
+
+    /// The tiles of the set, as canonicalize_set gives them
+    pub fn cards(&self) -> &[Card] {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::collections::BTreeSet;
+    use rand::prelude::*;
+    use super::*;
+    use super::super::{find_runs, find_runs_with, find_same_numbers, find_same_numbers_with, Color, Deck, SetSearchOptions};
+
+    #[test]
+    fn canonicalize_shuffled_sets_test() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..100 {
+            let len = rng.gen_range(1..=6);
+            let cards = Deck::new_seeded(rng.gen()).pick_tray(len);
+            let set: Vec<&Card> = cards.iter().collect();
+            let canonical = canonicalize_set(&set);
+
+            // Idempotent
+            assert_eq!(canonicalize_set(&canonical.iter().collect::<Vec<&Card>>()), canonical);
+            // Order-insensitive
+            let mut shuffled = set.clone();
+            shuffled.shuffle(&mut rng);
+            assert_eq!(canonicalize_set(&shuffled), canonical);
+            assert!(sets_equal(&set, &shuffled));
+            assert_eq!(SetKey::of(&shuffled), SetKey::of(&set));
+            assert!(canonical.windows(2).all(|pair| pair[0] <= pair[1]));
+        }
+    }
+
+    #[test]
+    fn sets_equal_test() {
+        let red = Card::new(5, Color::Red);
+        let blue = Card::new(5, Color::Blue);
+
+        assert_eq!(canonicalize_set(&[&Card::Wildcard, &blue, &red]), vec![red, blue, Card::Wildcard]);
+        assert!(sets_equal(&[&red, &blue, &Card::Wildcard], &[&Card::Wildcard, &blue, &red]));
+        // Copies count
+        assert!(!sets_equal(&[&red, &red, &blue], &[&red, &blue, &blue]));
+        assert!(!sets_equal(&[&red, &blue], &[&red, &blue, &blue]));
+    }
+
+    #[test]
+    fn dedup_across_finders_test() {
+        let tray = vec![
+            Card::new(5, Color::Red),
+            Card::new(6, Color::Red),
+            Card::new(5, Color::Blue),
+            Card::Wildcard,
+            Card::Wildcard
+        ];
+
+        let runs = find_runs(&tray);
+        let groups = find_same_numbers(&tray);
+        let keys: BTreeSet<SetKey> = runs.iter().chain(&groups).map(|set| SetKey::of(set)).collect();
+
+        // R5 W W is both a run and a group, and so is R6 W W
+        assert!(keys.len() < runs.len() + groups.len());
+        assert!(keys.contains(&SetKey::of(&[&Card::Wildcard, &tray[0], &Card::Wildcard])));
+        assert_eq!(keys.first().map(|key| key.cards()), Some(&[tray[0], tray[2], Card::Wildcard][..]));
+
+        let options = SetSearchOptions { canonical: true, ..SetSearchOptions::default() };
+        for set in find_runs_with(&tray, &options).iter().chain(&find_same_numbers_with(&tray, &options)) {
+            assert_eq!(set.iter().map(|c| **c).collect::<Vec<Card>>(), canonicalize_set(set));
+        }
+    }
+
+}
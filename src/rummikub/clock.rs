@@ -0,0 +1,153 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use super::GameState;
+
+/// Where a TurnClock reads the time from
+pub trait Clock {
+    /// The time gone by since a moment of the choice of the clock, never going back
+    fn now(&self) -> Duration;
+}
+
+/// The clock of the machine
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    start: Instant
+}
+
+impl SystemClock {
+    pub fn new() -> SystemClock {
+        SystemClock { start: Instant::now() }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A clock that only moves with advance, so that tests don't wait. Clones share the time.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    now: Rc<Cell<Duration>>
+}
+
+impl ManualClock {
+    pub fn new() -> ManualClock {
+        ManualClock::default()
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now.set(self.now.get() + by);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+/// The time limit of every turn, usually 60 seconds. Whoever runs the game calls start_turn
+/// when a turn starts and enforce before playing each move of the player.
+pub struct TurnClock {
+    clock: Box<dyn Clock>,
+    limit: Duration,
+    started: Duration
+}
+
+impl TurnClock {
+    /// A clock giving every turn seconds, started
+    pub fn new(clock: Box<dyn Clock>, seconds: u64) -> TurnClock {
+        let started = clock.now();
+        TurnClock { clock, limit: Duration::from_secs(seconds), started }
+    }
+
+    pub fn limit(&self) -> Duration {
+        self.limit
+    }
+
+    pub fn start_turn(&mut self) {
+        self.started = self.clock.now();
+    }
+
+    /// The time left in the turn, zero once it ran out
+    pub fn remaining(&self) -> Duration {
+        self.limit.saturating_sub(self.clock.now() - self.started)
+    }
+
+    pub fn expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// Once the time of the turn ran out, ends it with GameState::time_out and starts the
+    /// clock for the next one. Returns whether it did, the move the player submitted being
+    /// dropped then; a move submitted before is played as usual.
+    pub fn enforce(&mut self, game: &mut GameState) -> bool {
+        if !self.expired() {
+            return false;
+        }
+        game.time_out();
+        self.start_turn();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::Move;
+
+    const LIMIT: u64 = 60;
+
+    #[test]
+    fn timeout_draws_once_test() {
+        let mut game = GameState::new_game(&["Ann", "Bo"], 3);
+        let time = ManualClock::new();
+        let mut clock = TurnClock::new(Box::new(time.clone()), LIMIT);
+        let deck_len = game.deck_len();
+
+        time.advance(Duration::from_secs(LIMIT));
+        assert!(clock.enforce(&mut game));
+        assert_eq!(game.current_player_index(), 1);
+        assert_eq!(game.deck_len(), deck_len - 1);
+        assert_eq!(game.players()[0].tray().len(), 15);
+        assert_eq!(game.events().len(), 2);
+        assert!(game.events().iter().all(|event| event.player == 0 && event.timed_out));
+        assert_eq!(game.events()[0].mv, Move::Draw);
+        assert_eq!(game.events()[1].mv, Move::EndTurn);
+
+        // Bo gets a full turn
+        assert_eq!(clock.remaining(), Duration::from_secs(LIMIT));
+        assert!(!clock.enforce(&mut game));
+        assert_eq!(game.deck_len(), deck_len - 1);
+    }
+
+    #[test]
+    fn move_before_expiry_test() {
+        let mut game = GameState::new_game(&["Ann", "Bo"], 3);
+        let time = ManualClock::new();
+        let mut clock = TurnClock::new(Box::new(time.clone()), LIMIT);
+
+        time.advance(Duration::from_millis(LIMIT * 1000 - 1));
+        assert!(!clock.enforce(&mut game));
+        game.apply_move(Move::Draw).unwrap();
+        game.apply_move(Move::EndTurn).unwrap();
+        clock.start_turn();
+        assert_eq!(game.current_player_index(), 1);
+        assert!(game.events().iter().all(|event| !event.timed_out));
+
+        // The time of Ann doesn't count for Bo
+        time.advance(Duration::from_secs(1));
+        assert_eq!(clock.remaining(), Duration::from_secs(LIMIT - 1));
+    }
+
+}
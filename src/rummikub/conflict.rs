@@ -0,0 +1,162 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use super::Card;
+use crate::prelude::*;
+
+/// Which sets of a list can't be laid down together, see set_conflicts
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConflictGraph {
+    // For every set, the other sets it conflicts with, in increasing order
+    conflicts: Vec<Vec<usize>>
+}
+
+/// Finds the sets sharing a tile: two sets conflict when together they need more copies of a
+/// tile than the tray holds. Different copies of a duplicated tile, or two wildcards when
+/// the tray has both, can go to different sets.
+pub fn set_conflicts(tray: &[Card], sets: &[Vec<&Card>]) -> ConflictGraph {
+    let held = count(tray.iter());
+    let used: Vec<BTreeMap<Card, usize>> = sets.iter().map(|set| count(set.iter().copied())).collect();
+    let conflicts = (0..sets.len())
+        .map(|idx| {
+            (0..sets.len())
+                .filter(|other| *other != idx)
+                .filter(|other| {
+                    used[idx].iter().any(|(card, copies)| {
+                        copies + used[*other].get(card).unwrap_or(&0) > held.get(card).copied().unwrap_or(0)
+                    })
+                })
+                .collect()
+        })
+        .collect();
+    ConflictGraph { conflicts }
+}
+
+fn count<'a>(cards: impl Iterator<Item = &'a Card>) -> BTreeMap<Card, usize> {
+    let mut counts: BTreeMap<Card, usize> = BTreeMap::new();
+    for card in cards {
+        *counts.entry(*card).or_default() += 1;
+    }
+    counts
+}
+
+impl ConflictGraph {
+    /// The sets conflicting with the set at index set, none for an index out of the list
+    pub fn conflicts(&self, set: usize) -> &[usize] {
+        self.conflicts.get(set).map(|others| others.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn conflict(&self, a: usize, b: usize) -> bool {
+        self.conflicts(a).contains(&b)
+    }
+
+    /// How many sets the graph is made of
+    pub fn len(&self) -> usize {
+        self.conflicts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+
+    /// Every group of sets where no two conflict and which no other set can join, a single
+    /// empty group when there are no sets. Sets compatible two by two may still not fit all
+    /// together, like three sets asking for one of the two wildcards each. Every group is
+    /// sorted, and the groups come in order.
+    pub fn independent_groups(&self) -> Vec<Vec<usize>> {
+        let mut groups = vec![];
+        self.extend_group(&mut vec![], (0..self.len()).collect(), BTreeSet::new(), &mut groups);
+        groups.sort();
+        groups
+    }
+
+    // Bron–Kerbosch on the graph of the compatible sets: group can still take any set of
+    // candidates, and the ones of excluded were already tried
+    fn extend_group(&self, group: &mut Vec<usize>, mut candidates: BTreeSet<usize>, mut excluded: BTreeSet<usize>, groups: &mut Vec<Vec<usize>>) {
+        if candidates.is_empty() {
+            if excluded.is_empty() {
+                let mut found = group.clone();
+                found.sort();
+                groups.push(found);
+            }
+            return;
+        }
+        while let Some(set) = candidates.pop_first() {
+            let compatible = |other: &usize| !self.conflict(set, *other);
+            group.push(set);
+            self.extend_group(group, candidates.iter().copied().filter(compatible).collect(), excluded.iter().copied().filter(compatible).collect(), groups);
+            group.pop();
+            excluded.insert(set);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::{valid_sets, Color};
+
+    #[test]
+    fn wildcard_conflict_test() {
+        let tray = vec![
+            Card::new(4, Color::Red),
+            Card::new(5, Color::Red),
+            Card::new(9, Color::Blue),
+            Card::new(10, Color::Blue),
+            Card::Wildcard
+        ];
+        let sets = vec![
+            vec![&tray[0], &tray[1], &tray[4]],
+            vec![&tray[2], &tray[3], &tray[4]]
+        ];
+
+        let graph = set_conflicts(&tray, &sets);
+        assert!(graph.conflict(0, 1));
+        assert_eq!(graph.conflicts(1), &[0]);
+        assert_eq!(graph.independent_groups(), vec![vec![0], vec![1]]);
+    }
+
+    #[test]
+    fn duplicated_tile_test() {
+        let tray = vec![
+            Card::new(5, Color::Red),
+            Card::new(5, Color::Red),
+            Card::new(6, Color::Red),
+            Card::new(7, Color::Red),
+            Card::new(5, Color::Blue),
+            Card::new(5, Color::Black)
+        ];
+        let sets = valid_sets(&tray);
+        let run = sets.iter().position(|set| set.len() == 3 && set[1] == &tray[2]).unwrap();
+        let group = sets.iter().position(|set| set.len() == 3 && set[1] == &tray[4]).unwrap();
+
+        // The run and the group each take a copy of 5 Red
+        let graph = set_conflicts(&tray, &sets);
+        assert_eq!(graph.len(), sets.len());
+        assert!(!graph.conflict(run, group));
+        assert!(graph.independent_groups().contains(&vec![group.min(run), group.max(run)]));
+        assert_eq!(graph.conflicts(sets.len()), &[] as &[usize]);
+    }
+
+    #[test]
+    fn independent_groups_test() {
+        let tray = vec![
+            Card::new(1, Color::Black),
+            Card::new(2, Color::Black),
+            Card::new(3, Color::Black),
+            Card::new(4, Color::Black),
+            Card::new(8, Color::Yellow),
+            Card::new(9, Color::Yellow),
+            Card::new(10, Color::Yellow)
+        ];
+        let sets = vec![
+            vec![&tray[0], &tray[1], &tray[2]],
+            vec![&tray[1], &tray[2], &tray[3]],
+            vec![&tray[4], &tray[5], &tray[6]]
+        ];
+
+        let graph = set_conflicts(&tray, &sets);
+        assert_eq!(graph.independent_groups(), vec![vec![0, 2], vec![1, 2]]);
+        assert_eq!(set_conflicts(&tray, &[]).independent_groups(), vec![Vec::<usize>::new()]);
+    }
+
+}
@@ -0,0 +1,179 @@
+use alloc::collections::BTreeMap;
+use thiserror::Error;
+use super::{Card, Color};
+use crate::prelude::*;
+
+/// The tiles that were missing for CardCounts::remove or CardCounts::subtract, a tile
+/// missing twice being listed twice, sorted
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("missing {}", .tiles.iter().map(|card| card.code()).collect::<Vec<String>>().join(" "))]
+pub struct MissingTiles {
+    pub tiles: Vec<Card>
+}
+
+/// A bag of tiles: how many copies of every numbered tile it holds, and how many wildcards
+/// and jokers. The order of the tiles is lost, cards gives them back sorted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CardCounts {
+    numbered: BTreeMap<(i8, Color), usize>,
+    wildcards: usize,
+    double_jokers: usize,
+    mirror_jokers: usize
+}
+
+impl CardCounts {
+    pub fn new() -> CardCounts {
+        CardCounts::default()
+    }
+
+    pub fn from_cards(cards: &[Card]) -> CardCounts {
+        cards.iter().copied().collect()
+    }
+
+    pub fn add(&mut self, card: Card) {
+        *self.count_mut(card) += 1;
+    }
+
+    /// Takes out one copy of the card, failing when there is none left
+    pub fn remove(&mut self, card: &Card) -> Result<(), MissingTiles> {
+        if self.count(card) == 0 {
+            return Err(MissingTiles { tiles: vec![*card] });
+        }
+        *self.count_mut(*card) -= 1;
+        // Keeps equal bags equal, whether or not they once held the card
+        if let Card::Numbered { number, color } = card {
+            if self.numbered[&(*number, *color)] == 0 {
+                self.numbered.remove(&(*number, *color));
+            }
+        }
+        Ok(())
+    }
+
+    /// How many copies of the card there are
+    pub fn count(&self, card: &Card) -> usize {
+        match card {
+            Card::Numbered { number, color } => self.numbered.get(&(*number, *color)).copied().unwrap_or(0),
+            Card::Wildcard => self.wildcards,
+            Card::DoubleJoker => self.double_jokers,
+            Card::MirrorJoker => self.mirror_jokers
+        }
+    }
+
+    /// How many tiles there are, copies included
+    pub fn len(&self) -> usize {
+        self.numbered.values().sum::<usize>() + self.wildcards + self.double_jokers + self.mirror_jokers
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether every tile of other is here, as many times
+    pub fn contains(&self, other: &CardCounts) -> bool {
+        other.counts().all(|(card, count)| self.count(&card) >= count)
+    }
+
+    /// What is left once the tiles of other are taken out, or the tiles of other that aren't
+    /// here
+    pub fn subtract(&self, other: &CardCounts) -> Result<CardCounts, MissingTiles> {
+        let mut left = self.clone();
+        let mut missing = vec![];
+        for (card, count) in other.counts() {
+            let held = self.count(&card);
+            missing.extend(core::iter::repeat_n(card, count.saturating_sub(held)));
+            for _ in 0..count.min(held) {
+                let _ = left.remove(&card);
+            }
+        }
+        if missing.is_empty() { Ok(left) } else { Err(MissingTiles { tiles: missing }) }
+    }
+
+    /// Every tile with its number of copies, sorted like cards
+    pub fn counts(&self) -> impl Iterator<Item = (Card, usize)> + '_ {
+        self.numbered.iter()
+            .map(|(&(number, color), count)| (Card::new(number, color), *count))
+            .chain((self.wildcards > 0).then_some((Card::Wildcard, self.wildcards)))
+            .chain((self.double_jokers > 0).then_some((Card::DoubleJoker, self.double_jokers)))
+            .chain((self.mirror_jokers > 0).then_some((Card::MirrorJoker, self.mirror_jokers)))
+    }
+
+    /// Every tile as many times as it is held, numbered tiles by number and color, then the
+    /// wildcards and the jokers
+    pub fn cards(&self) -> impl Iterator<Item = Card> + '_ {
+        self.counts().flat_map(|(card, count)| core::iter::repeat_n(card, count))
+    }
+
+    pub fn to_vec(&self) -> Vec<Card> {
+        self.cards().collect()
+    }
+
+    fn count_mut(&mut self, card: Card) -> &mut usize {
+        match card {
+            Card::Numbered { number, color } => self.numbered.entry((number, color)).or_default(),
+            Card::Wildcard => &mut self.wildcards,
+            Card::DoubleJoker => &mut self.double_jokers,
+            Card::MirrorJoker => &mut self.mirror_jokers
+        }
+    }
+}
+
+impl FromIterator<Card> for CardCounts {
+    fn from_iter<I: IntoIterator<Item = Card>>(cards: I) -> CardCounts {
+        let mut counts = CardCounts::new();
+        for card in cards {
+            counts.add(card);
+        }
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::Deck;
+
+    #[test]
+    fn contains_with_copies_test() {
+        let red = Card::new(5, Color::Red);
+        let blue = Card::new(5, Color::Blue);
+        let bag = CardCounts::from_cards(&[red, blue, red, Card::Wildcard]);
+
+        assert_eq!(bag.count(&red), 2);
+        assert_eq!(bag.len(), 4);
+        assert!(bag.contains(&CardCounts::from_cards(&[red, red, Card::Wildcard])));
+        assert!(!bag.contains(&CardCounts::from_cards(&[blue, blue])));
+        assert!(!bag.contains(&CardCounts::from_cards(&[Card::Wildcard, Card::Wildcard])));
+        assert!(bag.contains(&CardCounts::new()));
+    }
+
+    #[test]
+    fn subtract_missing_test() {
+        let red = Card::new(5, Color::Red);
+        let blue = Card::new(5, Color::Blue);
+        let mut bag = CardCounts::from_cards(&[red, blue, Card::Wildcard]);
+
+        assert_eq!(bag.subtract(&CardCounts::from_cards(&[blue])), Ok(CardCounts::from_cards(&[red, Card::Wildcard])));
+        let error = bag.subtract(&CardCounts::from_cards(&[red, red, blue, Card::new(9, Color::Black)])).unwrap_err();
+        assert_eq!(error.tiles, vec![red, Card::new(9, Color::Black)]);
+        assert_eq!(error.to_string(), "missing R5 K9");
+
+        assert_eq!(bag.remove(&blue), Ok(()));
+        assert_eq!(bag.remove(&blue), Err(MissingTiles { tiles: vec![blue] }));
+        assert_eq!(bag, CardCounts::from_cards(&[Card::Wildcard, red]));
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let tray = Deck::new_seeded(4).pick_tray(30);
+        let counts = CardCounts::from_cards(&tray);
+
+        let mut sorted = tray.clone();
+        sorted.sort();
+        assert_eq!(counts.to_vec(), sorted);
+        assert_eq!(CardCounts::from_cards(&counts.to_vec()), counts);
+        assert_eq!(tray.iter().copied().collect::<CardCounts>(), counts);
+        assert_eq!(CardCounts::from_cards(&Deck::new().snapshot()).count(&Card::Wildcard), 2);
+    }
+
+}
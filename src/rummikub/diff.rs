@@ -0,0 +1,149 @@
+use super::{Card, CardCounts, MeldId, Table};
+use crate::prelude::*;
+
+/// The tiles that came in and went out of a hand, as diff_trays finds them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrayDiff {
+    /// The tiles of after that before didn't hold, in the order of after
+    pub gained: Vec<Card>,
+    /// The tiles of before that after doesn't hold, in the order of before
+    pub lost: Vec<Card>
+}
+
+impl TrayDiff {
+    pub fn is_empty(&self) -> bool {
+        self.gained.is_empty() && self.lost.is_empty()
+    }
+}
+
+/// The tiles gained and lost between two trays. Each copy of a tile counts: a tray going
+/// from two R5 to one lost a single R5, and tiles only moving around give an empty diff.
+pub fn diff_trays(before: &[Card], after: &[Card]) -> TrayDiff {
+    TrayDiff { gained: missing_from(before, after), lost: missing_from(after, before) }
+}
+
+// The tiles of cards, one copy at a time, that the others don't account for
+fn missing_from(others: &[Card], cards: &[Card]) -> Vec<Card> {
+    let mut left = CardCounts::from_cards(others);
+    cards.iter().filter(|card| left.remove(card).is_err()).copied().collect()
+}
+
+/// A tile taken from one meld and put in another, see TableDiff
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileMove {
+    pub card: Card,
+    pub from: MeldId,
+    pub to: MeldId
+}
+
+/// What changed between two tables, melds being matched by their ids, see Table::diff
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableDiff {
+    /// The melds only on the new table
+    pub added: Vec<MeldId>,
+    /// The melds only on the old table
+    pub removed: Vec<MeldId>,
+    /// The melds on both tables whose tiles changed, with the tiles they gained and lost
+    pub changed: Vec<(MeldId, TrayDiff)>,
+    /// The tiles that went from a meld of the old table to a meld of the new one. A tile
+    /// lost by a meld and gained by none went to a tray, and one gained by a meld and lost
+    /// by none came from a tray.
+    pub moved: Vec<TileMove>
+}
+
+impl TableDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+// The melds come in the order of their ids. A tile lost by several melds and gained by
+// several others is paired in that order: copies of a tile can't be told apart.
+pub(super) fn diff_tables(before: &Table, after: &Table) -> TableDiff {
+    let mut diff = TableDiff::default();
+    let mut lost: Vec<(MeldId, Card)> = vec![];
+    let mut gained: Vec<(MeldId, Card)> = vec![];
+    for (id, cards) in before.melds() {
+        match after.meld(id) {
+            Some(now) => {
+                let meld_diff = diff_trays(cards, now);
+                if !meld_diff.is_empty() {
+                    lost.extend(meld_diff.lost.iter().map(|card| (id, *card)));
+                    gained.extend(meld_diff.gained.iter().map(|card| (id, *card)));
+                    diff.changed.push((id, meld_diff));
+                }
+            },
+            None => {
+                lost.extend(cards.iter().map(|card| (id, *card)));
+                diff.removed.push(id);
+            }
+        }
+    }
+    for (id, cards) in after.melds().filter(|(id, _)| before.meld(*id).is_none()) {
+        gained.extend(cards.iter().map(|card| (id, *card)));
+        diff.added.push(id);
+    }
+    gained.sort_by_key(|(id, _)| *id);
+
+    for (from, card) in lost {
+        if let Some(idx) = gained.iter().position(|(to, c)| *c == card && *to != from) {
+            let (to, _) = gained.remove(idx);
+            diff.moved.push(TileMove { card, from, to });
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::Color;
+
+    fn red(numbers: &[i8]) -> Vec<Card> {
+        numbers.iter().map(|n| Card::new(*n, Color::Red)).collect()
+    }
+
+    #[test]
+    fn diff_trays_duplicates_test() {
+        let before = vec![Card::new(5, Color::Red), Card::new(5, Color::Red), Card::new(9, Color::Blue), Card::Wildcard];
+        let after = vec![Card::Wildcard, Card::new(5, Color::Red), Card::new(2, Color::Black), Card::new(2, Color::Black), Card::new(9, Color::Blue)];
+        let diff = diff_trays(&before, &after);
+        assert_eq!(diff.lost, vec![Card::new(5, Color::Red)]);
+        assert_eq!(diff.gained, vec![Card::new(2, Color::Black), Card::new(2, Color::Black)]);
+
+        // Sorting the tray changes nothing
+        let mut sorted = before.clone();
+        sorted.sort();
+        assert!(diff_trays(&before, &sorted).is_empty());
+        assert_eq!(diff_trays(&after, &before), TrayDiff { gained: diff.lost, lost: diff.gained });
+    }
+
+    #[test]
+    fn split_meld_test() {
+        let before = Table::from_melds(vec![red(&[1, 2, 3, 4, 5, 6]), red(&[7, 8, 9])]);
+        let mut after = before.clone();
+        let first = MeldId::from_number(0);
+        let second = after.add_meld(red(&[4, 5, 6])).unwrap();
+        for _ in 0..3 {
+            after.remove_card(first, 3);
+        }
+        after.append_to_meld(MeldId::from_number(1), Card::new(10, Color::Red));
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.added, vec![second]);
+        assert_eq!(diff.removed, vec![]);
+        assert_eq!(diff.changed, vec![
+            (first, TrayDiff { gained: vec![], lost: red(&[4, 5, 6]) }),
+            (MeldId::from_number(1), TrayDiff { gained: red(&[10]), lost: vec![] })
+        ]);
+        assert_eq!(diff.moved, red(&[4, 5, 6]).into_iter().map(|card| TileMove { card, from: first, to: second }).collect::<Vec<TileMove>>());
+
+        // Going back, the new meld is removed and its tiles go back to the first one
+        let back = after.diff(&before);
+        assert_eq!(back.removed, vec![second]);
+        assert_eq!(back.moved.len(), 3);
+        assert!(before.diff(&before.clone()).is_empty());
+    }
+
+}
@@ -0,0 +1,169 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use iter_tools::Itertools;
+use super::{create_run_windows, note_candidate, validation, Card, Color, RulesConfig, SetSearchOptions};
+use crate::prelude::*;
+
+/// A set of distinct_sets, with how many times the pool can make it out of different copies
+/// of its numbered tiles
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistinctSet {
+    /// The tiles of the set, sorted like the sets of valid_sets
+    pub cards: Vec<Card>,
+    /// How many times valid_sets lists a set with these tiles
+    pub copies: usize
+}
+
+/// Lists every run and group the pool can make once, however many copies of its tiles there
+/// are. The search works on how many copies of each tile the pool holds, not on the tiles
+/// themselves: with N numbers, C colors, W wildcards and at most L copies of a tile it
+/// checks at most L·C·N² run windows and L·N·2^C·(W+1) groups, so a large pool takes no
+/// longer than a small one holding the same tiles. The sets come in the order of valid_sets.
+pub fn distinct_sets(cards: &[Card]) -> Vec<DistinctSet> {
+    distinct_sets_with(cards, &RulesConfig::default())
+}
+
+/// Same as distinct_sets, with the sets following the rules
+pub fn distinct_sets_with(cards: &[Card], rules: &RulesConfig) -> Vec<DistinctSet> {
+    let mut counts: BTreeMap<(Color, i8), usize> = BTreeMap::new();
+    for card in cards {
+        if let Card::Numbered { number, color } = card {
+            *counts.entry((*color, *number)).or_default() += 1;
+        }
+    }
+    let count = |color: Color, number: i8| counts.get(&(color, number)).copied().unwrap_or(0);
+    let wildcards = vec![Card::Wildcard; cards.iter().filter(|c| c.is_wildcard()).count()];
+
+    // Like valid_sets, copy k of every tile only makes sets with the other k-th copies, so
+    // the copies of a set are the copy numbers k it can be made from
+    let mut found: BTreeMap<Vec<Card>, BTreeSet<usize>> = BTreeMap::new();
+    runs(rules, &count, &wildcards, &mut found);
+    groups(rules, &count, wildcards.len(), &mut found);
+
+    let mut sets: Vec<DistinctSet> = found.into_iter()
+        .map(|(cards, copies)| DistinctSet { cards, copies: copies.len() })
+        .collect();
+    sets.sort_by_cached_key(|set| {
+        let refs: Vec<&Card> = set.cards.iter().collect();
+        (validation::validate_refs_with(&refs, rules).ok(), set.cards.first().copied(), set.cards.len(), set.cards.clone())
+    });
+    sets
+}
+
+// The runs of every color, searched once for each pattern of the numbers the k-th copies cover
+fn runs(rules: &RulesConfig, count: &impl Fn(Color, i8) -> usize, wildcards: &[Card], found: &mut BTreeMap<Vec<Card>, BTreeSet<usize>>) {
+    let options = SetSearchOptions { rules: *rules, ..SetSearchOptions::default() };
+    let wildcards: Vec<&Card> = wildcards.iter().collect();
+    for &color in rules.colors {
+        let tiles: Vec<Card> = rules.numbers().map(|number| Card::new(number, color)).collect();
+        let most = rules.numbers().map(|number| count(color, number)).max().unwrap_or(0);
+        let mut patterns: BTreeMap<Vec<bool>, Vec<usize>> = BTreeMap::new();
+        for copy in 0..most {
+            let pattern: Vec<bool> = rules.numbers().map(|number| count(color, number) > copy).collect();
+            patterns.entry(pattern).or_default().push(copy);
+        }
+
+        for (pattern, copies) in patterns {
+            if pattern.iter().filter(|held| **held).count() + wildcards.len() < options.min_run_len() {
+                continue;
+            }
+            let slots: Vec<Option<&Card>> = tiles.iter().zip(&pattern)
+                .map(|(tile, held)| held.then_some(tile))
+                .collect();
+            for mut run in create_run_windows(&slots, &wildcards, &options) {
+                run.sort();
+                found.entry(run.into_iter().copied().collect()).or_default().extend(&copies);
+            }
+        }
+    }
+}
+
+// The groups of every number, from the colors the k-th copies cover and any number of wildcards
+fn groups(rules: &RulesConfig, count: &impl Fn(Color, i8) -> usize, wildcards: usize, found: &mut BTreeMap<Vec<Card>, BTreeSet<usize>>) {
+    let min_len = SetSearchOptions { rules: *rules, ..SetSearchOptions::default() }.min_len();
+    let limit = rules.group_limit();
+    for number in rules.numbers() {
+        let most = rules.colors.iter().map(|&color| count(color, number)).max().unwrap_or(0);
+        for copy in 0..most {
+            let layer: Vec<Card> = rules.colors.iter()
+                .filter(|&&color| count(color, number) > copy)
+                .map(|&color| Card::new(number, color))
+                .collect();
+            for len in 1..=layer.len().min(limit) {
+                for tiles in layer.iter().combinations(len) {
+                    for added in min_len.saturating_sub(len)..=wildcards.min(limit - len) {
+                        note_candidate();
+                        let mut group: Vec<Card> = tiles.iter().map(|c| **c).chain(vec![Card::Wildcard; added]).collect();
+                        group.sort();
+                        found.entry(group).or_default().insert(copy);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rand::prelude::*;
+    use super::*;
+    use super::super::{valid_sets, valid_sets_with, Deck};
+    use super::super::tests::count_candidates;
+
+    // The sets of valid_sets by value, each as many times as it is listed
+    fn listed(sets: Vec<Vec<&Card>>) -> Vec<Vec<Card>> {
+        let mut sets: Vec<Vec<Card>> = sets.into_iter().map(|set| set.into_iter().copied().collect()).collect();
+        sets.sort();
+        sets
+    }
+
+    fn expanded(sets: &[DistinctSet]) -> Vec<Vec<Card>> {
+        let mut sets: Vec<Vec<Card>> = sets.iter()
+            .flat_map(|set| std::iter::repeat_n(set.cards.clone(), set.copies))
+            .collect();
+        sets.sort();
+        sets
+    }
+
+    #[test]
+    fn same_sets_as_valid_sets_test() {
+        for seed in 0..20 {
+            let tray = Deck::new_seeded(seed).pick_tray(30);
+            assert_eq!(expanded(&distinct_sets(&tray)), listed(valid_sets(&tray)), "seed {}", seed);
+        }
+
+        let rules = RulesConfig { wrap_runs: true, min_run_len: 4, ..RulesConfig::six_colors() };
+        let mut deck = Deck::with_rules(&rules);
+        deck.shuffle_with(&mut StdRng::seed_from_u64(3));
+        let tray = deck.pick_tray(40);
+        let options = SetSearchOptions { rules, ..SetSearchOptions::default() };
+        assert_eq!(expanded(&distinct_sets_with(&tray, &rules)), listed(valid_sets_with(&tray, &options)));
+    }
+
+    #[test]
+    fn large_pool_test() {
+        // Two copies of the numbers up to 7 in every color, then R8, R9 and two wildcards
+        let mut pool: Vec<Card> = (0..2)
+            .flat_map(|_| Color::ALL.iter().flat_map(|&color| (1..=7).map(move |number| Card::new(number, color))))
+            .collect();
+        pool.extend([Card::new(8, Color::Red), Card::new(9, Color::Red), Card::Wildcard, Card::Wildcard]);
+        assert_eq!(pool.len(), 60);
+
+        let sets = distinct_sets(&pool);
+        // The bound of distinct_sets with 2 copies, 4 colors, 13 numbers and 2 wildcards
+        let candidates = count_candidates(|| distinct_sets(&pool));
+        assert!(candidates <= 2 * 4 * 13 * 13 + 2 * 13 * 16 * 3, "{} candidates", candidates);
+        // The same tiles once each, the second copies only doubling the search
+        let single: Vec<Card> = pool.iter().copied().unique().collect();
+        assert!(candidates <= 2 * count_candidates(|| distinct_sets(&single)), "{} candidates", candidates);
+
+        let copies = |cards: &[Card]| sets.iter().find(|set| set.cards == cards).map(|set| set.copies);
+        assert_eq!(copies(&[Card::new(1, Color::Red), Card::new(2, Color::Red), Card::new(3, Color::Red)]), Some(2));
+        // Only one copy of R8 and R9
+        assert_eq!(copies(&[Card::new(7, Color::Red), Card::new(8, Color::Red), Card::new(9, Color::Red)]), Some(1));
+        assert_eq!(copies(&[Card::new(8, Color::Red), Card::new(9, Color::Red), Card::new(10, Color::Red)]), None);
+        assert_eq!(sets.iter().map(|set| set.copies).sum::<usize>(), valid_sets(&pool).len());
+        assert!(sets.len() < valid_sets(&pool).len());
+    }
+
+}
@@ -0,0 +1,114 @@
+use alloc::collections::BTreeMap;
+use super::{find_almost_sets, Card, Deck, DeckError};
+use crate::prelude::*;
+
+/// Every tile that can still be drawn, with the chance of drawing it and whether it gives a
+/// new 3-tile set with the tray, see find_almost_sets. The tiles left are those of a full
+/// deck minus the tray and the seen tiles, like the table, so seen must not hold the tray
+/// again: with one 5 Blue seen, a single one is left. A drawn wildcard completes any pair
+/// of find_almost_sets. Tiles come sorted, wildcards last. Seeing more copies of a tile than
+/// the deck holds is an error, see Deck::standard_without.
+pub fn draw_outcomes(tray: &[Card], seen: &[Card]) -> Result<Vec<(Card, f64, bool)>, DeckError> {
+    let mut left: BTreeMap<Card, usize> = BTreeMap::new();
+    for card in Deck::standard_without(&[tray, seen].concat())?.cards {
+        *left.entry(card).or_default() += 1;
+    }
+    let total: usize = left.values().sum();
+
+    let almost_sets = find_almost_sets(tray);
+    let outcomes = left.into_iter()
+        .map(|(card, count)| {
+            let completes = if card.is_wildcard() {
+                !almost_sets.is_empty()
+            } else {
+                almost_sets.iter().any(|almost| almost.missing.contains(&card))
+            };
+            (card, count as f64 / total as f64, completes)
+        })
+        .collect();
+    Ok(outcomes)
+}
+
+/// The chance that the next draw gives a new set, summed over draw_outcomes
+pub fn p_improve(tray: &[Card], seen: &[Card]) -> Result<f64, DeckError> {
+    let outcomes = draw_outcomes(tray, seen)?;
+    Ok(outcomes.iter()
+        .filter(|(_, _, completes)| *completes)
+        .map(|(_, probability, _)| probability)
+        .sum())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::Color;
+
+    fn probability(outcomes: &[(Card, f64, bool)], card: Card) -> Option<f64> {
+        outcomes.iter().find(|(c, _, _)| *c == card).map(|(_, p, _)| *p)
+    }
+
+    #[test]
+    fn draw_outcomes_test() {
+        let test_tray = vec![
+            Card::new(4, Color::Blue),
+            Card::new(6, Color::Blue)
+        ];
+
+        let outcomes = draw_outcomes(&test_tray, &[]).unwrap();
+
+        // 52 tiles and a wildcard per copy of the deck, two of them in the tray
+        assert_eq!(outcomes.len(), 53);
+        assert_eq!(outcomes.last(), Some(&(Card::Wildcard, 2.0 / 104.0, true)));
+        assert_eq!(probability(&outcomes, Card::new(4, Color::Blue)), Some(1.0 / 104.0));
+        assert!(outcomes.contains(&(Card::new(5, Color::Blue), 2.0 / 104.0, true)));
+        assert!(outcomes.contains(&(Card::new(7, Color::Blue), 2.0 / 104.0, false)));
+        assert!((outcomes.iter().map(|(_, p, _)| p).sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!((p_improve(&test_tray, &[]).unwrap() - 4.0 / 104.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn seen_duplicates_test() {
+        let test_tray = vec![
+            Card::new(4, Color::Blue),
+            Card::new(6, Color::Blue)
+        ];
+        let one_seen = [Card::new(5, Color::Blue)];
+        let both_seen = [Card::new(5, Color::Blue), Card::new(5, Color::Blue), Card::Wildcard];
+
+        let outcomes = draw_outcomes(&test_tray, &one_seen).unwrap();
+        assert_eq!(probability(&outcomes, Card::new(5, Color::Blue)), Some(1.0 / 103.0));
+        assert!((p_improve(&test_tray, &one_seen).unwrap() - 3.0 / 103.0).abs() < 1e-9);
+
+        // Nothing is left of what was seen twice
+        let outcomes = draw_outcomes(&test_tray, &both_seen).unwrap();
+        assert_eq!(probability(&outcomes, Card::new(5, Color::Blue)), None);
+        assert!((p_improve(&test_tray, &both_seen).unwrap() - 1.0 / 101.0).abs() < 1e-9);
+
+        // A third copy can't have been seen
+        let third = [both_seen.as_slice(), &[Card::new(5, Color::Blue)]].concat();
+        assert_eq!(draw_outcomes(&test_tray, &third), Err(DeckError::TooManyCopies(Card::new(5, Color::Blue))));
+    }
+
+    #[test]
+    fn group_outcomes_test() {
+        let test_tray = vec![
+            Card::new(7, Color::Red),
+            Card::new(7, Color::Black),
+            Card::new(7, Color::Black),
+            Card::new(1, Color::Yellow)
+        ];
+        let seen = [Card::new(7, Color::Yellow)];
+
+        let completing: Vec<Card> = draw_outcomes(&test_tray, &seen).unwrap().into_iter()
+            .filter(|(_, _, completes)| *completes)
+            .map(|(card, _, _)| card)
+            .collect();
+
+        assert_eq!(completing, vec![Card::new(7, Color::Blue), Card::new(7, Color::Yellow), Card::Wildcard]);
+        // Two 7 Blue, one 7 Yellow and two wildcards out of 101 tiles
+        assert!((p_improve(&test_tray, &seen).unwrap() - 5.0 / 101.0).abs() < 1e-9);
+        assert_eq!(p_improve(&[], &[]), Ok(0.0));
+    }
+
+}
@@ -0,0 +1,320 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use super::{suggest_moves, Card, GameState, Move, MoveError, PlayerId, RoundResult, ScoredMove, Table};
+
+/// What the engine is asked to do, by any player, from any thread
+#[derive(Debug, Clone)]
+pub enum EngineCommand {
+    /// Sends the events of the player to events from now on, starting with the game as it
+    /// stands. Joining again replaces the previous sender.
+    Join { player: PlayerId, events: Sender<EngineEvent> },
+    SubmitMove { player: PlayerId, mv: Move },
+    /// Answered with EngineEvent::Hint, on the turn of the player only
+    RequestHint { player: PlayerId },
+    /// The player leaves the round: the moves of their unfinished turn are taken back and
+    /// their turns are skipped from then on
+    Resign { player: PlayerId }
+}
+
+/// What a player is told by the engine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineEvent {
+    /// Sent to everyone after every change
+    StateChanged(EngineView),
+    /// The command of the player was refused, the game didn't change
+    MoveRejected(MoveError),
+    /// The moves suggest_moves finds for the player, best first
+    Hint(Vec<ScoredMove>),
+    /// Sent to everyone once, when the round is over
+    RoundEnded(RoundResult)
+}
+
+/// One player as everyone sees them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnginePlayer {
+    pub name: String,
+    pub tile_count: usize,
+    pub has_melded: bool,
+    pub resigned: bool
+}
+
+/// The game as one player sees it, like GameState::view_for, owned so it can be sent to
+/// another thread
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineView {
+    pub player: PlayerId,
+    pub tray: Vec<Card>,
+    pub has_melded: bool,
+    pub table: Table,
+    pub deck_len: usize,
+    /// Every player, this one included
+    pub players: Vec<EnginePlayer>,
+    pub current_player: PlayerId,
+    /// See GameState::turn_number
+    pub turn_number: usize
+}
+
+/// Runs a game on its own thread, for user interfaces that would rather not lock a
+/// GameState: every player sends EngineCommand values through sender and gets EngineEvent
+/// values on the receiver they joined with. The engine thread alone touches the game, one
+/// command at a time, so commands sent at the same time from different threads are played
+/// one after the other.
+#[derive(Debug)]
+pub struct GameEngine {
+    commands: Sender<EngineCommand>,
+    thread: JoinHandle<GameState>
+}
+
+impl GameEngine {
+    /// Starts the engine on the game
+    pub fn spawn(game: GameState) -> GameEngine {
+        let (commands, received) = mpsc::channel();
+        let thread = thread::spawn(move || Engine::new(game).run(received));
+        GameEngine { commands, thread }
+    }
+
+    /// Where to send the commands, one clone per thread
+    pub fn sender(&self) -> Sender<EngineCommand> {
+        self.commands.clone()
+    }
+
+    /// Joins as the player, returning where their events arrive
+    pub fn join(&self, player: PlayerId) -> Receiver<EngineEvent> {
+        let (events, received) = mpsc::channel();
+        // The engine thread only stops once every sender is gone, this one included
+        let _ = self.commands.send(EngineCommand::Join { player, events });
+        received
+    }
+
+    /// Waits for every sender to be dropped and the commands already sent to be played, then
+    /// returns the game
+    pub fn shutdown(self) -> GameState {
+        drop(self.commands);
+        self.thread.join().unwrap()
+    }
+}
+
+// The state owned by the engine thread
+struct Engine {
+    game: GameState,
+    listeners: Vec<Option<Sender<EngineEvent>>>,
+    resigned: Vec<bool>
+}
+
+impl Engine {
+    fn new(game: GameState) -> Engine {
+        let players = game.players().len();
+        Engine { game, listeners: vec![None; players], resigned: vec![false; players] }
+    }
+
+    fn run(mut self, commands: Receiver<EngineCommand>) -> GameState {
+        for command in commands {
+            self.handle(command);
+        }
+        self.game
+    }
+
+    fn handle(&mut self, command: EngineCommand) {
+        let ended = self.game.round_result().is_some();
+        match command {
+            EngineCommand::Join { player, events } => {
+                if player < self.listeners.len() {
+                    self.listeners[player] = Some(events);
+                    self.send(player, EngineEvent::StateChanged(self.view(player)));
+                    if let Some(result) = self.game.round_result() {
+                        self.send(player, EngineEvent::RoundEnded(result.clone()));
+                    }
+                }
+                return;
+            },
+            EngineCommand::SubmitMove { player, mv } => {
+                let result = match mv {
+                    // Anyone can take their own mulligan, on the turn of someone else too
+                    Move::Mulligan(taker) if taker == player && self.resigned.get(player) == Some(&false) => self.game.apply_move(mv),
+                    Move::Mulligan(_) => Err(MoveError::NotYourTurn),
+                    _ => self.check_turn(player).and_then(|_| self.game.apply_move(mv))
+                };
+                if let Err(error) = result {
+                    self.send(player, EngineEvent::MoveRejected(error));
+                    return;
+                }
+            },
+            EngineCommand::RequestHint { player } => {
+                let event = match self.check_turn(player) {
+                    Ok(()) => EngineEvent::Hint(suggest_moves(&self.game.view_for(player))),
+                    Err(error) => EngineEvent::MoveRejected(error)
+                };
+                self.send(player, event);
+                return;
+            },
+            EngineCommand::Resign { player } => {
+                if player >= self.resigned.len() || self.resigned[player] {
+                    return;
+                }
+                self.resigned[player] = true;
+            }
+        }
+
+        while self.game.round_result().is_none() && self.resigned[self.game.current_player_index()] {
+            self.game.skip_turn();
+        }
+        for player in 0..self.listeners.len() {
+            self.send(player, EngineEvent::StateChanged(self.view(player)));
+        }
+        if let Some(result) = self.game.round_result().filter(|_| !ended) {
+            let result = result.clone();
+            for player in 0..self.listeners.len() {
+                self.send(player, EngineEvent::RoundEnded(result.clone()));
+            }
+        }
+    }
+
+    fn check_turn(&self, player: PlayerId) -> Result<(), MoveError> {
+        if self.game.round_result().is_some() {
+            Err(MoveError::RoundOver)
+        } else if player != self.game.current_player_index() || self.resigned.get(player) != Some(&false) {
+            Err(MoveError::NotYourTurn)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn view(&self, player: PlayerId) -> EngineView {
+        let view = self.game.view_for(player);
+        EngineView {
+            player,
+            tray: view.tray.to_vec(),
+            has_melded: view.has_melded,
+            table: view.table.clone(),
+            deck_len: view.deck_len,
+            players: view.players.iter()
+                .zip(&self.resigned)
+                .map(|(summary, resigned)| EnginePlayer {
+                    name: summary.name.to_string(),
+                    tile_count: summary.tile_count,
+                    has_melded: summary.has_melded,
+                    resigned: *resigned
+                })
+                .collect(),
+            current_player: self.game.current_player_index(),
+            turn_number: view.turn_number
+        }
+    }
+
+    // A player whose receiver is gone doesn't get events anymore, but can still send commands
+    fn send(&mut self, player: PlayerId, event: EngineEvent) {
+        let gone = self.listeners.get(player)
+            .and_then(|listener| listener.as_ref())
+            .is_some_and(|listener| listener.send(event).is_err());
+        if gone {
+            self.listeners[player] = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::time::Duration;
+    use super::*;
+
+    // The next event, failing instead of hanging when none comes
+    fn next(events: &Receiver<EngineEvent>) -> EngineEvent {
+        events.recv_timeout(Duration::from_secs(5)).unwrap()
+    }
+
+    fn next_state(events: &Receiver<EngineEvent>) -> EngineView {
+        loop {
+            if let EngineEvent::StateChanged(view) = next(events) {
+                return view;
+            }
+        }
+    }
+
+    #[test]
+    fn full_game_through_channels_test() {
+        let engine = GameEngine::spawn(GameState::new_game(&["Ann", "Bob"], 5));
+        let commands = engine.sender();
+        let events = [engine.join(0), engine.join(1)];
+        let first = next_state(&events[0]);
+        assert_eq!(first.tray.len(), 14);
+        assert_eq!(first.players[1].tile_count, 14);
+        assert_eq!(next_state(&events[1]).current_player, 0);
+
+        // Bob plays out of turn, then Ann ends the turn without drawing
+        commands.send(EngineCommand::SubmitMove { player: 1, mv: Move::Draw }).unwrap();
+        assert_eq!(next(&events[1]), EngineEvent::MoveRejected(MoveError::NotYourTurn));
+        commands.send(EngineCommand::SubmitMove { player: 1, mv: Move::Mulligan(0) }).unwrap();
+        assert_eq!(next(&events[1]), EngineEvent::MoveRejected(MoveError::NotYourTurn));
+        commands.send(EngineCommand::SubmitMove { player: 0, mv: Move::EndTurn }).unwrap();
+        assert_eq!(next(&events[0]), EngineEvent::MoveRejected(MoveError::MustDrawOrPlay));
+
+        commands.send(EngineCommand::RequestHint { player: 0 }).unwrap();
+        let EngineEvent::Hint(hints) = next(&events[0]) else {
+            panic!("expected a hint");
+        };
+        assert_eq!(hints.last().map(|hint| &hint.mv), Some(&Move::Draw));
+
+        // Both draw until the deck is empty, then pass until the round is over. The events of
+        // Ann are enough to follow the game.
+        commands.send(EngineCommand::SubmitMove { player: 0, mv: Move::Draw }).unwrap();
+        commands.send(EngineCommand::SubmitMove { player: 0, mv: Move::EndTurn }).unwrap();
+        let mut played_turn = Some(0);
+        let result = loop {
+            match next(&events[0]) {
+                EngineEvent::StateChanged(view) if played_turn != Some(view.turn_number) => {
+                    played_turn = Some(view.turn_number);
+                    let player = view.current_player;
+                    if view.deck_len > 0 {
+                        commands.send(EngineCommand::SubmitMove { player, mv: Move::Draw }).unwrap();
+                    }
+                    commands.send(EngineCommand::SubmitMove { player, mv: Move::EndTurn }).unwrap();
+                },
+                EngineEvent::RoundEnded(result) => break result,
+                _ => {}
+            }
+        };
+
+        drop(commands);
+        drop(events);
+        let game = engine.shutdown();
+        assert_eq!(game.round_result(), Some(&result));
+        assert_eq!(game.deck_len(), 0);
+    }
+
+    #[test]
+    fn resign_and_concurrent_moves_test() {
+        let engine = GameEngine::spawn(GameState::new_game(&["Ann", "Bob", "Cy"], 8));
+        let events = engine.join(0);
+        next_state(&events);
+
+        // Ann draws while Bob resigns, from two threads
+        let threads: Vec<_> = [
+            EngineCommand::SubmitMove { player: 0, mv: Move::Draw },
+            EngineCommand::Resign { player: 1 }
+        ].into_iter()
+            .map(|command| {
+                let commands = engine.sender();
+                thread::spawn(move || commands.send(command).unwrap())
+            })
+            .collect();
+        threads.into_iter().for_each(|thread| thread.join().unwrap());
+        next_state(&events);
+        next_state(&events);
+
+        // Ending Ann's turn skips the one of Bob, the engine drawing for them
+        engine.sender().send(EngineCommand::SubmitMove { player: 0, mv: Move::EndTurn }).unwrap();
+        let view = next_state(&events);
+        assert_eq!(view.current_player, 2);
+        assert_eq!(view.tray.len(), 15);
+        assert!(view.players[1].resigned);
+        assert_eq!(view.players[1].tile_count, 15);
+        assert_eq!(view.deck_len, 106 - 3 * 14 - 2);
+
+        engine.sender().send(EngineCommand::RequestHint { player: 1 }).unwrap();
+        let game = engine.shutdown();
+        assert_eq!(game.turn_number(), 2);
+        assert_eq!(game.events().len(), 4);
+    }
+
+}
@@ -0,0 +1,130 @@
+use super::{best_partition, dead_tiles, find_almost_sets, hand_penalty, score_meld, Card, CardCounts, Deck, DeckError};
+
+/// How much every component of a HandScore counts in its total
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HandWeights {
+    /// Per point of the best partition
+    pub playable_points: f64,
+    /// Per almost set sure to be completed by the next draw
+    pub almost_sets: f64,
+    /// Per wildcard held
+    pub wildcards: f64,
+    /// Per point of the dead tiles, taken off the total
+    pub dead_penalty: f64
+}
+
+impl Default for HandWeights {
+    fn default() -> Self {
+        HandWeights { playable_points: 1.0, almost_sets: 50.0, wildcards: 20.0, dead_penalty: 0.5 }
+    }
+}
+
+/// What evaluate_hand found in a tray, component by component
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandScore {
+    /// The points of the sets best_partition plays, see score_meld
+    pub playable_points: u32,
+    /// The chance of the next draw being one of the missing tiles, summed over the almost
+    /// sets of find_almost_sets
+    pub almost_sets: f64,
+    pub wildcards: usize,
+    /// The hand_penalty of the numbered tiles in no valid set, see dead_tiles. Wildcards are
+    /// left to their own component.
+    pub dead_penalty: u32,
+    /// The components weighted by HandWeights::default
+    pub total: f64
+}
+
+impl HandScore {
+    /// The total the components give with other weights
+    pub fn weighted(&self, weights: &HandWeights) -> f64 {
+        self.playable_points as f64 * weights.playable_points
+            + self.almost_sets * weights.almost_sets
+            + self.wildcards as f64 * weights.wildcards
+            - self.dead_penalty as f64 * weights.dead_penalty
+    }
+}
+
+/// How good the tray is before anything is played, the higher the better. The tiles left to
+/// draw are those of draw_outcomes, a full deck minus the tray and the seen tiles, so seeing
+/// more copies of a tile than the deck holds is an error.
+pub fn evaluate_hand(tray: &[Card], seen: &[Card]) -> Result<HandScore, DeckError> {
+    let left = CardCounts::from_cards(&Deck::standard_without(&[tray, seen].concat())?.cards);
+
+    let playable_points = best_partition(tray).sets.iter()
+        // best_partition only gives valid sets
+        .map(|set| score_meld(set).unwrap())
+        .sum();
+    let almost_sets = if left.is_empty() {
+        0.0
+    } else {
+        find_almost_sets(tray).iter()
+            .map(|almost| almost.missing.iter().map(|card| left.count(card)).sum::<usize>() as f64 / left.len() as f64)
+            .sum()
+    };
+    let dead: Vec<Card> = dead_tiles(tray).into_iter()
+        .filter(|card| !card.is_wildcard())
+        .copied()
+        .collect();
+
+    let mut score = HandScore {
+        playable_points,
+        almost_sets,
+        wildcards: tray.iter().filter(|card| card.is_wildcard()).count(),
+        dead_penalty: hand_penalty(&dead),
+        total: 0.0
+    };
+    score.total = score.weighted(&HandWeights::default());
+    Ok(score)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::Color;
+    use crate::testing::random_tray;
+
+    #[test]
+    fn opening_meld_scores_higher_test() {
+        // 30 points either way
+        let meld = vec![
+            Card::new(9, Color::Red),
+            Card::new(10, Color::Red),
+            Card::new(11, Color::Red)
+        ];
+        let scattered = vec![
+            Card::new(1, Color::Red),
+            Card::new(13, Color::Blue),
+            Card::new(8, Color::Black),
+            Card::new(8, Color::Yellow)
+        ];
+
+        let meld_score = evaluate_hand(&meld, &[]).unwrap();
+        let scattered_score = evaluate_hand(&scattered, &[]).unwrap();
+        assert_eq!(meld_score.playable_points, 30);
+        assert_eq!(meld_score.dead_penalty, 0);
+        assert_eq!(scattered_score.playable_points, 0);
+        assert_eq!(scattered_score.dead_penalty, 30);
+        assert!(meld_score.total > scattered_score.total);
+        // Unless only the almost sets count
+        let weights = HandWeights { playable_points: 0.0, almost_sets: 1.0, wildcards: 0.0, dead_penalty: 0.0 };
+        assert!(meld_score.weighted(&weights) < scattered_score.weighted(&weights));
+        assert!(evaluate_hand(&meld, &[Card::Wildcard; 3]).is_err());
+    }
+
+    #[test]
+    fn wildcard_never_lowers_test() {
+        for seed in 0..200 {
+            let tray = random_tray(seed, 10 + seed as usize % 5);
+            if tray.iter().filter(|card| card.is_wildcard()).count() == 2 {
+                continue;
+            }
+            let before = evaluate_hand(&tray, &[]).unwrap();
+            let after = evaluate_hand(&[tray.as_slice(), &[Card::Wildcard]].concat(), &[]).unwrap();
+            assert!(after.total >= before.total, "seed {}: {:?} then {:?}", seed, before, after);
+            assert_eq!(after.wildcards, before.wildcards + 1);
+        }
+    }
+
+}
@@ -0,0 +1,91 @@
+use std::io::{self, Write};
+use super::{best_partition, count_valid_sets, set_points, Card};
+use super::validation::validate_refs;
+
+/// Writes one CSV row per set, after a header: the hand id, the set kind (run or group),
+/// its length, its points and the codes of its cards joined by '-'. Invalid sets are left out.
+pub fn sets_to_csv(writer: impl Write, hand_id: &str, sets: &[Vec<&Card>]) -> io::Result<()> {
+    let mut csv = csv::Writer::from_writer(writer);
+    csv.write_record(["hand_id", "kind", "length", "points", "cards"])?;
+    for set in sets {
+        let kind = match validate_refs(set) {
+            Ok(kind) => kind,
+            Err(_) => continue
+        };
+        let codes: Vec<String> = set.iter().map(|c| c.code()).collect();
+        csv.write_record([
+            hand_id,
+            kind.name(),
+            &set.len().to_string(),
+            &set_points(set).to_string(),
+            &codes.join("-")
+        ])?;
+    }
+    csv.flush()
+}
+
+/// Writes a header and a CSV row with the aggregates of the hand: its tiles, its wildcards,
+/// how many valid sets it has and how many tiles the best partition plays
+pub fn tray_summary_csv(writer: impl Write, hand_id: &str, tray: &[Card]) -> io::Result<()> {
+    let mut csv = csv::Writer::from_writer(writer);
+    csv.write_record(["hand_id", "tiles", "wildcards", "sets", "best_partition"])?;
+    csv.write_record([
+        hand_id,
+        &tray.len().to_string(),
+        &tray.iter().filter(|c| c.is_wildcard()).count().to_string(),
+        &count_valid_sets(tray).to_string(),
+        &best_partition(tray).played_count().to_string()
+    ])?;
+    csv.flush()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::{valid_sets, Color};
+
+    fn test_tray() -> Vec<Card> {
+        vec![
+            Card::new(7, Color::Red),
+            Card::new(7, Color::Blue),
+            Card::new(7, Color::Black),
+            Card::new(8, Color::Red),
+            Card::new(9, Color::Red),
+            Card::Wildcard
+        ]
+    }
+
+    #[test]
+    fn sets_to_csv_test() {
+        let tray = test_tray();
+        let sets = vec![
+            vec![&tray[0], &tray[1], &tray[2]],
+            vec![&tray[0], &tray[3], &tray[4], &tray[5]],
+            vec![&tray[1], &tray[3]]
+        ];
+        let mut output = vec![];
+
+        sets_to_csv(&mut output, "game 1, hand 2", &sets).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "\
+hand_id,kind,length,points,cards
+\"game 1, hand 2\",group,3,21,R7-B7-K7
+\"game 1, hand 2\",run,4,34,R7-R8-R9-W
+");
+    }
+
+    #[test]
+    fn tray_summary_csv_test() {
+        let mut output = vec![];
+
+        tray_summary_csv(&mut output, "7", &test_tray()).unwrap();
+
+        let sets = valid_sets(&test_tray()).len();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("hand_id,tiles,wildcards,sets,best_partition\n7,6,1,{},6\n", sets)
+        );
+    }
+
+}
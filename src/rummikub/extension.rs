@@ -0,0 +1,125 @@
+use alloc::collections::BTreeSet;
+use super::{resolve_wildcards_with, validate_set_with, Card, MeldId, Table};
+use crate::prelude::*;
+
+/// Where a tile goes in the meld it extends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ExtensionPosition {
+    /// Below the lowest number of a run
+    Start,
+    /// Above the highest number of a run
+    End,
+    /// Anywhere in a group, as a color it is missing
+    Group
+}
+
+/// A tile of the tray that can be added to a meld of the table, see find_extensions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Extension {
+    pub meld_id: MeldId,
+    pub card: Card,
+    pub position: ExtensionPosition
+}
+
+/// Lists the tiles of the tray that can be added to a meld of the table on their own: the
+/// number right below or above a run, reading its wildcards like resolve_wildcards does, or
+/// a color missing from a group. Runs don't go past the numbers of the rules of the table,
+/// and groups past the size they allow. A wildcard extends every run at both ends it can and
+/// every group which isn't full. Extensions come by meld, then by tile.
+pub fn find_extensions(tray: &[Card], table: &Table) -> Vec<Extension> {
+    let rules = table.rules();
+    let tiles: BTreeSet<Card> = tray.iter().copied().collect();
+    let mut extensions = vec![];
+    for (meld_id, cards) in table.melds() {
+        let refs: Vec<&Card> = cards.iter().collect();
+        let values = resolve_wildcards_with(&refs, rules).unwrap_or_default();
+        let (Some((low, color)), Some((high, _))) = (values.iter().copied().min(), values.iter().copied().max()) else {
+            continue;
+        };
+
+        for card in &tiles {
+            let positions = if low == high {
+                let held = cards.iter().any(|c| c.number() == Some(&low) && c.color() == card.color());
+                let fits = card.is_wildcard() || (card.number() == Some(&low) && !held);
+                if fits && cards.len() < rules.group_limit() { vec![ExtensionPosition::Group] } else { vec![] }
+            } else {
+                let below = (low > rules.min_number).then_some((ExtensionPosition::Start, low - 1));
+                let above = (high < rules.max_number).then_some((ExtensionPosition::End, high + 1));
+                below.into_iter().chain(above)
+                    .filter(|(_, number)| card.is_wildcard() || *card == Card::new(*number, color))
+                    .map(|(position, _)| position)
+                    .collect()
+            };
+
+            for position in positions {
+                let mut extended = cards.to_vec();
+                extended.push(*card);
+                if validate_set_with(&extended, rules).is_ok() {
+                    extensions.push(Extension { meld_id, card: *card, position });
+                }
+            }
+        }
+    }
+    extensions
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::Color;
+
+    fn extensions_of(tray: &[Card], melds: Vec<Vec<Card>>) -> Vec<(Card, ExtensionPosition)> {
+        find_extensions(tray, &Table::from_melds(melds)).into_iter()
+            .map(|extension| (extension.card, extension.position))
+            .collect()
+    }
+
+    #[test]
+    fn group_extension_test() {
+        let group = vec![Card::new(7, Color::Red), Card::new(7, Color::Blue), Card::new(7, Color::Black)];
+        let tray = [Card::new(7, Color::Yellow), Card::new(7, Color::Red), Card::new(8, Color::Yellow)];
+
+        assert_eq!(extensions_of(&tray, vec![group.clone()]), vec![(Card::new(7, Color::Yellow), ExtensionPosition::Group)]);
+
+        // Nothing goes in a full group, not even a wildcard
+        let mut full = group;
+        full.push(Card::new(7, Color::Yellow));
+        assert_eq!(extensions_of(&[Card::new(7, Color::Yellow), Card::Wildcard], vec![full]), vec![]);
+    }
+
+    #[test]
+    fn run_extension_test() {
+        let run = vec![Card::new(2, Color::Blue), Card::new(3, Color::Blue), Card::new(4, Color::Blue)];
+        let tray = [Card::new(5, Color::Blue), Card::new(1, Color::Red), Card::new(6, Color::Blue)];
+
+        assert_eq!(extensions_of(&tray, vec![run.clone()]), vec![(Card::new(5, Color::Blue), ExtensionPosition::End)]);
+        assert_eq!(extensions_of(&[Card::new(1, Color::Blue)], vec![run]), vec![(Card::new(1, Color::Blue), ExtensionPosition::Start)]);
+
+        // The wildcard stands for 13, so nothing goes above
+        let top = vec![Card::new(11, Color::Red), Card::new(12, Color::Red), Card::Wildcard];
+        let tray = [Card::new(10, Color::Red), Card::new(13, Color::Red), Card::Wildcard];
+        assert_eq!(extensions_of(&tray, vec![top]), vec![
+            (Card::new(10, Color::Red), ExtensionPosition::Start),
+            (Card::Wildcard, ExtensionPosition::Start)
+        ]);
+    }
+
+    #[test]
+    fn extension_melds_test() {
+        let table = Table::from_melds(vec![
+            vec![Card::new(4, Color::Red), Card::Wildcard, Card::new(6, Color::Red)],
+            vec![Card::new(9, Color::Black), Card::new(9, Color::Yellow), Card::Wildcard]
+        ]);
+        let ids: Vec<MeldId> = table.melds().map(|(id, _)| id).collect();
+
+        assert_eq!(find_extensions(&[Card::new(9, Color::Red), Card::new(7, Color::Red), Card::Wildcard], &table), vec![
+            Extension { meld_id: ids[0], card: Card::new(7, Color::Red), position: ExtensionPosition::End },
+            Extension { meld_id: ids[0], card: Card::Wildcard, position: ExtensionPosition::Start },
+            Extension { meld_id: ids[0], card: Card::Wildcard, position: ExtensionPosition::End },
+            Extension { meld_id: ids[1], card: Card::new(9, Color::Red), position: ExtensionPosition::Group },
+            Extension { meld_id: ids[1], card: Card::Wildcard, position: ExtensionPosition::Group }
+        ]);
+    }
+
+}
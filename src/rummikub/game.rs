@@ -0,0 +1,1893 @@
+use std::collections::{BTreeSet, VecDeque};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use super::{diff_trays, hand_penalties, hand_penalty, has_valid_set_with, set_points_with, Card, CardCounts, Deck, GameObserver, GameSave, JokerError, MeldId, Mulligan, PublicDraw, RulesConfig, SaveError, SavedPlayer, ScoringVariant, SetError, SetSearchOptions, Table, TableDiff, Teams, Tray, TrayDiff, VariantRules, validate_set_with};
+use super::observer::Observers;
+
+/// Tiles dealt to every player at the start of a game
+pub const TRAY_SIZE: usize = 14;
+
+// Moves GameState::undo can go back by, unless set_history_depth says otherwise
+const HISTORY_DEPTH: usize = 100;
+
+/// Index of a player in GameState::players
+pub type PlayerId = usize;
+
+/// A player and the tiles in their tray
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Player {
+    name: String,
+    tray: Tray,
+    has_melded: bool
+}
+
+impl Player {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn tray(&self) -> &[Card] {
+        self.tray.as_ref()
+    }
+
+    /// Whether the player made their opening, after which they can use the melds on the table
+    pub fn has_melded(&self) -> bool {
+        self.has_melded
+    }
+}
+
+/// What everyone at the table can see of a player: their name, how many tiles they hold and
+/// whether they opened
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PlayerSummary<'a> {
+    pub name: &'a str,
+    pub tile_count: usize,
+    pub has_melded: bool
+}
+
+/// The game as one player sees it: their own tray and what is public. In the events, the
+/// tiles drawn by the other players are left out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlayerView<'a> {
+    pub player: PlayerId,
+    pub tray: &'a [Card],
+    pub has_melded: bool,
+    pub table: &'a Table,
+    pub deck_len: usize,
+    /// The discard pile, the last discard on top, empty unless playing DrawDiscard
+    pub discards: &'a [Card],
+    /// Every player, this one included
+    pub players: Vec<PlayerSummary<'a>>,
+    pub events: Vec<GameEvent>,
+    /// See GameState::turn_number
+    pub turn_number: usize
+}
+
+/// The game as someone watching it sees it, with no tray at all and no drawn tile in the
+/// events
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SpectatorView<'a> {
+    pub table: &'a Table,
+    pub deck_len: usize,
+    /// See PlayerView::discards
+    pub discards: &'a [Card],
+    pub players: Vec<PlayerSummary<'a>>,
+    pub events: Vec<GameEvent>,
+    /// See GameState::turn_number
+    pub turn_number: usize
+}
+
+/// Why a round is over, see RoundResult::end
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundEnd {
+    /// The winner has no tiles left
+    WentOut,
+    /// The deck ran out with nobody going out: every player in a row passed without playing,
+    /// or in VariantRules::DrawDiscard the last tile was drawn. The lowest tray wins, and the
+    /// players tied with it share the win, losing nothing; the winner is the first of them.
+    Stalemate
+}
+
+/// How a round ended: who won and what everyone scores
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoundResult {
+    pub winner: PlayerId,
+    pub end: RoundEnd,
+    /// What each of the other players scores, in player order: what they lose as negative
+    /// numbers, or with ScoringVariant::RaceTo the points against them
+    pub penalties: Vec<(PlayerId, i32)>,
+    /// What the winner gains: minus the sum of the penalties, twice that with
+    /// ScoringVariant::WinnerDouble, and nothing with ScoringVariant::RaceTo
+    pub winner_gain: i32,
+    /// The scoring the round was played with
+    pub scoring: ScoringVariant,
+    /// With teams, what every team scores by TeamId: what its players gain and lose added
+    /// up. Empty without teams.
+    pub team_scores: Vec<i32>
+}
+
+impl RoundResult {
+    // Everyone loses the value of their tray minus the value of the tray of the winner, which
+    // is empty unless the deck ran out. The teammates of the winner lose nothing.
+    fn score(players: &[Player], winner: PlayerId, scoring: ScoringVariant, teams: Option<&Teams>) -> RoundResult {
+        let winner_value = tray_value(&players[winner].tray);
+        let teammate = |id: PlayerId| teams.is_some_and(|teams| teams.team_of(id) == teams.team_of(winner));
+        let losses = (0..players.len())
+            .filter(|id| *id != winner)
+            .map(|id| (id, if teammate(id) { 0 } else { winner_value - tray_value(&players[id].tray) }));
+        let penalties: Vec<(PlayerId, i32)> = match scoring {
+            ScoringVariant::RaceTo(_) => losses.map(|(id, loss)| (id, -loss)).collect(),
+            _ => losses.collect()
+        };
+        let lost = -penalties.iter().map(|(_, penalty)| penalty).sum::<i32>();
+        let winner_gain = match scoring {
+            ScoringVariant::Standard => lost,
+            ScoringVariant::WinnerDouble => 2 * lost,
+            ScoringVariant::RaceTo(_) => 0
+        };
+        let mut team_scores = vec![];
+        if let Some(teams) = teams {
+            team_scores = vec![0; teams.names().len()];
+            team_scores[teams.team_of(winner)] += winner_gain;
+            for (id, penalty) in &penalties {
+                team_scores[teams.team_of(*id)] += penalty;
+            }
+        }
+        RoundResult { winner, end: RoundEnd::WentOut, penalties, winner_gain, scoring, team_scores }
+    }
+}
+
+fn tray_value(tray: &Tray) -> i32 {
+    hand_penalty(tray.as_ref()) as i32
+}
+
+/// Something the current player does on their turn
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Move {
+    Draw,
+    PlayNewMeld(Vec<Card>),
+    ExtendMeld { meld: MeldId, card: Card },
+    /// Swaps a tile of the tray for the wildcard standing for it in a meld. The wildcard goes
+    /// to the tray and has to be played in a new meld before the turn ends.
+    RetrieveWildcard { meld: MeldId, card: Card },
+    EndTurn,
+    /// Takes the top of the discard pile instead of drawing, in VariantRules::DrawDiscard
+    TakeDiscard,
+    /// Ends the turn throwing a tile on the discard pile, in VariantRules::DrawDiscard
+    Discard(Card),
+    /// The mulligan of the player, see GameState::mulligan. Unlike the other moves it can
+    /// be taken on the turn of someone else.
+    Mulligan(PlayerId)
+}
+
+/// A move played with GameState::apply_move, as recorded in GameState::events
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GameEvent {
+    pub player: PlayerId,
+    /// How many turns ended before this move, 0 for the first turn of the game
+    pub turn: usize,
+    pub mv: Move,
+    /// The tile drawn by Move::Draw
+    pub drawn: Option<Card>,
+    /// Whether the move was played for the player once their time ran out, see
+    /// GameState::time_out
+    pub timed_out: bool
+}
+
+/// What a move did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MoveOutcome {
+    /// The tile drawn by Move::Draw
+    pub drawn: Option<Card>,
+    /// The meld laid down by Move::PlayNewMeld
+    pub meld: Option<MeldId>,
+    /// Whether the player has no tiles left
+    pub gone_out: bool,
+    /// The tiles that came in and went out of the tray of the player
+    pub tray: TrayDiff,
+    /// How the move changed the table
+    pub table: TableDiff
+}
+
+/// Why a move was refused. A refused move leaves the game as it was.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MoveError {
+    #[error("{} is not in your tray", .0.code())]
+    NotInTray(Card),
+    #[error("not a valid set: {0}")]
+    InvalidMeld(#[source] SetError),
+    #[error("there is no meld {0} on the table")]
+    NoSuchMeld(MeldId),
+    #[error("{0}")]
+    Joker(#[source] JokerError),
+    #[error("play the wildcard you took back in a new meld first")]
+    WildcardNotReplayed,
+    // The melds on the table can't be touched before the opening
+    #[error("you can only lay down new melds until you open")]
+    NotOpened,
+    // The first melds of a player are worth less than the rules ask for
+    #[error("your opening is worth {points} points, it needs {needed}")]
+    OpeningTooLow { points: u32, needed: u32 },
+    // Drawing ends what a player can do on their turn
+    #[error("you already drew this turn, end it")]
+    AlreadyDrew,
+    // Players either draw or play
+    #[error("you already played this turn, you can't draw")]
+    AlreadyPlayed,
+    #[error("the deck is empty")]
+    DeckEmpty,
+    #[error("play a tile or draw before ending your turn")]
+    MustDrawOrPlay,
+    #[error("the round is over")]
+    RoundOver,
+    // GameState plays for the current player, only GameEngine and the net host know who
+    // sent a move
+    #[error("it is not your turn")]
+    NotYourTurn,
+    // The game was dealt to nobody
+    #[error("the game has no players")]
+    NoPlayers,
+    #[error("the rules don't allow a mulligan")]
+    NoMulligan,
+    // Mulligans are taken before the first move
+    #[error("the game already started")]
+    GameStarted,
+    #[error("you already took your mulligan")]
+    MulliganTaken,
+    // A mulligan only for trays without a valid set
+    #[error("your tray makes a valid set, keep it")]
+    TrayHasSets,
+    // TakeDiscard and Discard outside of VariantRules::DrawDiscard
+    #[error("the rules have no discard pile")]
+    NoDiscardPile,
+    #[error("the discard pile is empty")]
+    DiscardPileEmpty,
+    // In VariantRules::DrawDiscard turns start with a draw
+    #[error("draw a tile or take the discard first")]
+    MustDrawFirst,
+    // In VariantRules::DrawDiscard turns end with a discard
+    #[error("discard a tile to end your turn")]
+    MustDiscard
+}
+
+/// Why GameState::undo or GameState::redo did nothing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum UndoError {
+    #[error("there is no move to undo")]
+    NothingToUndo,
+    #[error("there is no undone move to redo")]
+    NothingToRedo
+}
+
+// What the current player did so far this turn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Turn {
+    played: bool,
+    drew: bool,
+    // Wildcards taken back from the table and not played again yet
+    retrieved_wildcards: usize,
+    // Points of the melds laid down this turn, counted until the player opens
+    opening_points: u32
+}
+
+// Everything a move can change, kept to undo it
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Snapshot {
+    deck: Deck,
+    discards: Vec<Card>,
+    players: Vec<Player>,
+    table: Table,
+    current_player: usize,
+    turn: Turn,
+    passes: usize,
+    round_result: Option<RoundResult>,
+    turn_number: usize,
+    events: Vec<GameEvent>,
+    started: bool,
+    mulligans: BTreeSet<PlayerId>
+}
+
+/// A game in progress: the deck to draw from, the players, the melds on the table and whose
+/// turn it is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameState {
+    deck: Deck,
+    // The discard pile of VariantRules::DrawDiscard, the last discard last
+    discards: Vec<Card>,
+    players: Vec<Player>,
+    table: Table,
+    current_player: usize,
+    turn: Turn,
+    // Turns in a row ended without playing once the deck is empty
+    passes: usize,
+    round_result: Option<RoundResult>,
+    // Turns ended so far
+    turn_number: usize,
+    events: Vec<GameEvent>,
+    // Whether a move other than a mulligan was played, after which the mulligans are over
+    started: bool,
+    seed: Option<u64>,
+    rules: RulesConfig,
+    // The players who took their mulligan
+    mulligans: BTreeSet<PlayerId>,
+    // The game before each move, the last move last, and the undone moves to redo
+    undo: VecDeque<Snapshot>,
+    redo: Vec<Snapshot>,
+    history_depth: usize,
+    observers: Observers,
+    teams: Option<Teams>
+}
+
+impl GameState {
+    /// Shuffles a deck with the seed and deals TRAY_SIZE tiles to every player, in order.
+    /// The deck runs out after 7 players, the next ones get what is left.
+    pub fn new_game(names: &[&str], seed: u64) -> GameState {
+        GameState::new_game_from(names, seed, 0)
+    }
+
+    /// Like new_game, with first_player playing first. Going past the last player starts over
+    /// from the first, so rounds can pass the first turn along by counting up.
+    pub fn new_game_from(names: &[&str], seed: u64, first_player: PlayerId) -> GameState {
+        GameState::new_game_with_rules(names, seed, first_player, RulesConfig::default())
+    }
+
+    /// Like new_game_from, played with house rules: the deck holds their wildcards, and melds
+    /// and openings are checked against them
+    pub fn new_game_with_rules(names: &[&str], seed: u64, first_player: PlayerId, rules: RulesConfig) -> GameState {
+        let mut deck = Deck::with_rules(&rules);
+        deck.shuffle_with(&mut StdRng::seed_from_u64(seed));
+        let players = names.iter()
+            .map(|name| Player { name: name.to_string(), tray: deck.pick_tray(TRAY_SIZE).into(), has_melded: false })
+            .collect();
+        GameState {
+            deck,
+            discards: vec![],
+            players,
+            table: Table::with_rules(rules),
+            current_player: first_player % names.len().max(1),
+            turn: Turn::default(),
+            passes: 0,
+            round_result: None,
+            turn_number: 0,
+            events: vec![],
+            started: false,
+            seed: Some(seed),
+            rules,
+            mulligans: BTreeSet::new(),
+            undo: VecDeque::new(),
+            redo: vec![],
+            history_depth: HISTORY_DEPTH,
+            observers: Observers::default(),
+            teams: None
+        }
+    }
+
+    /// Throws back the tray of the player into the deck, reshuffles it and deals them a new
+    /// one, when the rules allow a mulligan. Each player can take it once, before anyone
+    /// plays, and with only_without_sets only for a tray that makes no valid set. The deck is
+    /// reshuffled from the seed of the game, so a seeded game deals the same new trays.
+    /// The mulligan is played as Move::Mulligan, kept in the events and taken back by undo.
+    /// Panics if there is no such player.
+    pub fn mulligan(&mut self, player: PlayerId) -> Result<(), MoveError> {
+        self.apply_move(Move::Mulligan(player)).map(|_| ())
+    }
+
+    fn take_mulligan(&mut self, player: PlayerId) -> Result<(), MoveError> {
+        let Mulligan::OncePerPlayer { only_without_sets } = self.rules.mulligan else {
+            return Err(MoveError::NoMulligan);
+        };
+        if self.started || self.turn_number > 0 {
+            return Err(MoveError::GameStarted);
+        }
+        if self.mulligans.contains(&player) {
+            return Err(MoveError::MulliganTaken);
+        }
+        let options = SetSearchOptions { rules: self.rules, ..SetSearchOptions::default() };
+        if only_without_sets && has_valid_set_with(&self.players[player].tray, &options) {
+            return Err(MoveError::TrayHasSets);
+        }
+
+        self.deck.cards.extend(Vec::from(std::mem::take(&mut self.players[player].tray)));
+        let seed = self.seed.unwrap_or_default().wrapping_add(self.mulligans.len() as u64 + 1);
+        self.deck.shuffle_with(&mut StdRng::seed_from_u64(seed));
+        self.players[player].tray = self.deck.pick_tray(TRAY_SIZE).into();
+        self.mulligans.insert(player);
+        Ok(())
+    }
+
+    /// Picks who plays first the official way: every player draws a tile and the highest
+    /// number starts, the players tied for it drawing again until one is left. A player
+    /// drawing a wildcard puts it aside and draws again, wildcards don't count. The tiles then
+    /// go back, leaving the deck full and reshuffled for the deal. Returns 0 without players,
+    /// or when the deck doesn't hold two different numbers.
+    pub fn determine_first_player<R: Rng>(deck: &mut Deck, num_players: usize, rng: &mut R) -> PlayerId {
+        let mut drawn: Vec<Card> = vec![];
+        let mut contenders: Vec<PlayerId> = (0..num_players).collect();
+        // Without two different numbers the ties would never end
+        let numbers: BTreeSet<i8> = deck.cards.iter().filter_map(|card| card.number().copied()).collect();
+        if numbers.len() < 2 {
+            contenders.truncate(1);
+        }
+        while contenders.len() > 1 {
+            let numbers: Vec<i8> = contenders.iter()
+                .map(|_| loop {
+                    let card = deck.pick_card().unwrap_or_else(|| {
+                        // Everything was drawn aside, which only happens with a tiny deck
+                        deck.cards.append(&mut drawn);
+                        deck.shuffle_with(rng);
+                        deck.pick_card().expect("a deck to draw the first player from")
+                    });
+                    drawn.push(card);
+                    if let Some(number) = card.number() {
+                        break *number;
+                    }
+                })
+                .collect();
+            let highest = numbers.iter().max().copied().unwrap_or_default();
+            contenders = contenders.into_iter()
+                .zip(numbers)
+                .filter(|(_, number)| *number == highest)
+                .map(|(player, _)| player)
+                .collect();
+        }
+        deck.cards.append(&mut drawn);
+        deck.shuffle_with(rng);
+        contenders.first().copied().unwrap_or(0)
+    }
+
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    /// The players without their trays, for showing to everyone
+    pub fn player_summaries(&self) -> Vec<PlayerSummary<'_>> {
+        self.players.iter()
+            .map(|p| PlayerSummary { name: &p.name, tile_count: p.tray.len(), has_melded: p.has_melded })
+            .collect()
+    }
+
+    /// What the player can see of the game. Panics if there is no such player.
+    pub fn view_for(&self, player: PlayerId) -> PlayerView<'_> {
+        PlayerView {
+            player,
+            tray: self.players[player].tray.as_ref(),
+            has_melded: self.players[player].has_melded,
+            table: &self.table,
+            deck_len: self.deck.len(),
+            discards: &self.discards,
+            players: self.player_summaries(),
+            events: self.public_events(Some(player)),
+            turn_number: self.turn_number
+        }
+    }
+
+    /// What everyone can see of the game, the trays of all players being hidden
+    pub fn spectator_view(&self) -> SpectatorView<'_> {
+        SpectatorView {
+            table: &self.table,
+            deck_len: self.deck.len(),
+            discards: &self.discards,
+            players: self.player_summaries(),
+            events: self.public_events(None),
+            turn_number: self.turn_number
+        }
+    }
+
+    // The events with only the tiles drawn by the viewer, if any
+    fn public_events(&self, viewer: Option<PlayerId>) -> Vec<GameEvent> {
+        self.events.iter()
+            .map(|event| GameEvent { drawn: event.drawn.filter(|_| Some(event.player) == viewer), ..event.clone() })
+            .collect()
+    }
+
+    /// The player whose turn it is. Panics on a game without players.
+    pub fn current_player(&self) -> &Player {
+        &self.players[self.current_player]
+    }
+
+    /// Index in players of the one whose turn it is
+    pub fn current_player_index(&self) -> usize {
+        self.current_player
+    }
+
+    /// Passes the turn to the next player, going back to the first after the last
+    pub fn advance_turn(&mut self) {
+        self.turn = Turn::default();
+        self.turn_number += 1;
+        if !self.players.is_empty() {
+            self.current_player = (self.current_player + 1) % self.players.len();
+        }
+    }
+
+    /// Moves a tile from the deck to the tray of the current player, returning None once the
+    /// deck is empty. Panics on a game without players.
+    pub fn draw_for_current(&mut self) -> Option<Card> {
+        let card = self.deck.pick_card()?;
+        self.players[self.current_player].tray.push(card);
+        Some(card)
+    }
+
+    /// The moves played so far with apply_move and the mulligans, in order, see replay. A
+    /// resumed save starts with no events.
+    pub fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
+    /// How many turns ended so far, the turn of GameEvent
+    pub fn turn_number(&self) -> usize {
+        self.turn_number
+    }
+
+    /// The seed the deck was shuffled with, if known
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// The rules of the game, the standard ones unless it was started with new_game_with_rules
+    pub fn rules(&self) -> &RulesConfig {
+        &self.rules
+    }
+
+    /// How many tiles are left to draw
+    pub fn deck_len(&self) -> usize {
+        self.deck.len()
+    }
+
+    /// The discard pile of VariantRules::DrawDiscard, the last discard last
+    pub fn discards(&self) -> &[Card] {
+        &self.discards
+    }
+
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    /// How the round ended, once a player went out or, with the deck empty, every player
+    /// passed in a row. Then the player with the lowest tray wins, the first one in case of a
+    /// tie, and the others lose the difference between their tray and the winner's.
+    /// Tiles count their number and wildcards 30.
+    pub fn round_result(&self) -> Option<&RoundResult> {
+        self.round_result.as_ref()
+    }
+
+    /// The hand_penalty of the tray of every player, as it stands now
+    pub fn hand_penalties(&self) -> Vec<(PlayerId, u32)> {
+        hand_penalties(&self.players.iter().map(|player| player.tray()).collect::<Vec<&[Card]>>())
+    }
+
+    /// Plays a move for the current player, checking that they hold the tiles they play,
+    /// that the melds they lay down or extend stay valid and that they draw when they don't
+    /// play. Until a player opens they can only lay down new melds, and they can't end the
+    /// turn before these are worth 30 points. Going out ends the round, see round_result.
+    /// On error the game doesn't change. Panics on a game without players.
+    pub fn apply_move(&mut self, mv: Move) -> Result<MoveOutcome, MoveError> {
+        let before = self.snapshot();
+        let player = match mv {
+            Move::Mulligan(player) => player,
+            _ => self.current_player
+        };
+        let turn = self.turn_number;
+        let top_discard = self.discards.last().copied();
+        let mut outcome = self.play(mv.clone())?;
+        self.started |= !matches!(mv, Move::Mulligan(_));
+        outcome.tray = diff_trays(before.players[player].tray(), self.players[player].tray());
+        outcome.table = before.table.diff(&self.table);
+        if !self.observers.is_empty() {
+            self.notify(player, &mv, &outcome, top_discard);
+        }
+        self.events.push(GameEvent { player, turn, mv, drawn: outcome.drawn, timed_out: false });
+        if self.history_depth > 0 {
+            if self.undo.len() == self.history_depth {
+                self.undo.pop_front();
+            }
+            self.undo.push_back(before);
+        }
+        self.redo.clear();
+        Ok(outcome)
+    }
+
+    /// Has the observer told about every move apply_move plays from now on, right after the
+    /// move is made, in the order they subscribed. Refused moves, undo and redo are not told.
+    /// The observers only get what they are passed, so they can't change the game; one that
+    /// panics does it out of apply_move, the move being already played. Clones of the game
+    /// start without observers.
+    pub fn subscribe(&mut self, observer: Box<dyn GameObserver>) {
+        self.observers.push(observer);
+    }
+
+    fn notify(&self, player: PlayerId, mv: &Move, outcome: &MoveOutcome, top_discard: Option<Card>) {
+        for observer in self.observers.iter() {
+            match mv {
+                Move::Draw => observer.on_draw(player, &PublicDraw { deck_len: self.deck.len(), taken_discard: None }),
+                Move::TakeDiscard => observer.on_draw(player, &PublicDraw { deck_len: self.deck.len(), taken_discard: top_discard }),
+                Move::PlayNewMeld(cards) => {
+                    // Every new meld gets an id
+                    let meld = outcome.meld.unwrap();
+                    observer.on_meld_played(player, meld, self.table.meld(meld).unwrap_or(cards));
+                },
+                Move::ExtendMeld { meld, card } => observer.on_meld_extended(player, *meld, *card, self.table.meld(*meld).unwrap_or_default()),
+                Move::RetrieveWildcard { meld, card } => observer.on_joker_retrieved(player, *meld, *card),
+                Move::EndTurn | Move::Discard(_) => observer.on_turn_end(player),
+                // The new tray is only seen by the player
+                Move::Mulligan(_) => {}
+            }
+            if let Some(result) = &self.round_result {
+                observer.on_round_end(result);
+            }
+        }
+    }
+
+    /// Takes back the last move played with apply_move, a drawn tile going back on top of
+    /// the deck. Moves from earlier turns can be taken back as well, up to the history depth.
+    pub fn undo(&mut self) -> Result<(), UndoError> {
+        let before = self.undo.pop_back().ok_or(UndoError::NothingToUndo)?;
+        let after = self.snapshot();
+        self.restore(before);
+        self.redo.push(after);
+        Ok(())
+    }
+
+    /// Plays again the last move taken back by undo. Playing a move forgets the undone ones.
+    pub fn redo(&mut self) -> Result<(), UndoError> {
+        let after = self.redo.pop().ok_or(UndoError::NothingToRedo)?;
+        let before = self.snapshot();
+        self.restore(after);
+        self.undo.push_back(before);
+        Ok(())
+    }
+
+    /// Takes back the moves the current player played this turn, then draws for them and ends
+    /// the turn, passing when the deck is empty, or in VariantRules::DrawDiscard discarding
+    /// the drawn tile. For players who left or resigned.
+    pub fn skip_turn(&mut self) {
+        let (player, turn) = (self.current_player, self.turn_number);
+        while self.events.last().is_some_and(|event| event.player == player && event.turn == turn) {
+            if self.undo().is_err() {
+                break;
+            }
+        }
+        let drawn = self.apply_move(Move::Draw).ok().and_then(|outcome| outcome.drawn);
+        let ended = match (self.rules.variant, drawn) {
+            // Throwing back what was drawn
+            (VariantRules::DrawDiscard, Some(card)) => self.apply_move(Move::Discard(card)),
+            _ => self.apply_move(Move::EndTurn)
+        };
+        if ended.is_err() {
+            self.advance_turn();
+        }
+    }
+
+    /// Ends the turn of the current player once their time ran out, like skip_turn does, the
+    /// moves it plays for them being marked as timed out in the events
+    pub fn time_out(&mut self) {
+        let (player, turn) = (self.current_player, self.turn_number);
+        self.skip_turn();
+        for event in self.events.iter_mut().rev().take_while(|event| event.player == player && event.turn == turn) {
+            event.timed_out = true;
+        }
+    }
+
+    /// Has the players score in teams, see RoundResult::team_scores. Turns still go from
+    /// one player to the next, and every player only sees their own tray. Teams are not
+    /// saved with to_save. Panics unless the teams are made for the players of the game.
+    pub fn set_teams(&mut self, teams: Teams) {
+        assert_eq!(teams.player_count(), self.players.len(), "the teams are for another number of players");
+        self.teams = Some(teams);
+    }
+
+    pub fn teams(&self) -> Option<&Teams> {
+        self.teams.as_ref()
+    }
+
+    /// How many moves undo can take back, 100 by default. 0 turns undo off.
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.history_depth = depth;
+        while self.undo.len() > depth {
+            self.undo.pop_front();
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            deck: self.deck.clone(),
+            discards: self.discards.clone(),
+            players: self.players.clone(),
+            table: self.table.clone(),
+            current_player: self.current_player,
+            turn: self.turn,
+            passes: self.passes,
+            round_result: self.round_result.clone(),
+            turn_number: self.turn_number,
+            events: self.events.clone(),
+            started: self.started,
+            mulligans: self.mulligans.clone()
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.deck = snapshot.deck;
+        self.discards = snapshot.discards;
+        self.players = snapshot.players;
+        self.table = snapshot.table;
+        self.current_player = snapshot.current_player;
+        self.turn = snapshot.turn;
+        self.passes = snapshot.passes;
+        self.round_result = snapshot.round_result;
+        self.turn_number = snapshot.turn_number;
+        self.events = snapshot.events;
+        self.started = snapshot.started;
+        self.mulligans = snapshot.mulligans;
+    }
+
+    fn play(&mut self, mv: Move) -> Result<MoveOutcome, MoveError> {
+        if self.round_result.is_some() {
+            return Err(MoveError::RoundOver);
+        }
+        if self.players.is_empty() {
+            return Err(MoveError::NoPlayers);
+        }
+        let draw_discard = self.rules.variant == VariantRules::DrawDiscard;
+        let mut outcome = MoveOutcome::default();
+        match mv {
+            Move::Draw => {
+                if self.turn.drew {
+                    return Err(MoveError::AlreadyDrew);
+                }
+                if self.turn.played {
+                    return Err(MoveError::AlreadyPlayed);
+                }
+                outcome.drawn = Some(self.draw_for_current().ok_or(MoveError::DeckEmpty)?);
+                self.turn.drew = true;
+            },
+            Move::TakeDiscard => {
+                if !draw_discard {
+                    return Err(MoveError::NoDiscardPile);
+                }
+                if self.turn.drew {
+                    return Err(MoveError::AlreadyDrew);
+                }
+                let card = self.discards.pop().ok_or(MoveError::DiscardPileEmpty)?;
+                self.players[self.current_player].tray.push(card);
+                self.turn.drew = true;
+            },
+            Move::PlayNewMeld(cards) => {
+                self.check_can_play()?;
+                let tray = take_tiles(&self.players[self.current_player].tray, &cards)?;
+                let wildcards = cards.iter().filter(|c| c.is_wildcard()).count();
+                let points = set_points_with(&cards.iter().collect::<Vec<&Card>>(), &self.rules);
+                outcome.meld = Some(self.table.add_meld(cards).map_err(MoveError::InvalidMeld)?);
+                self.players[self.current_player].tray = tray;
+                self.turn.played = true;
+                self.turn.retrieved_wildcards = self.turn.retrieved_wildcards.saturating_sub(wildcards);
+                self.turn.opening_points += points;
+            },
+            Move::ExtendMeld { meld, card } => {
+                self.check_can_play()?;
+                if !self.players[self.current_player].has_melded {
+                    return Err(MoveError::NotOpened);
+                }
+                let mut extended = self.table.meld(meld).ok_or(MoveError::NoSuchMeld(meld))?.to_vec();
+                let tray = take_tiles(&self.players[self.current_player].tray, &[card])?;
+                extended.push(card);
+                validate_set_with(&extended, &self.rules).map_err(MoveError::InvalidMeld)?;
+
+                self.table.append_to_meld(meld, card);
+                self.players[self.current_player].tray = tray;
+                self.turn.played = true;
+            },
+            Move::RetrieveWildcard { meld, card } => {
+                self.check_can_play()?;
+                if !self.players[self.current_player].has_melded {
+                    return Err(MoveError::NotOpened);
+                }
+                let mut tray = take_tiles(&self.players[self.current_player].tray, &[card])?;
+                let wildcard = self.table.replace_wildcard(meld, card).map_err(MoveError::Joker)?;
+
+                tray.push(wildcard);
+                self.players[self.current_player].tray = tray;
+                self.turn.played = true;
+                self.turn.retrieved_wildcards += 1;
+            },
+            Move::EndTurn => {
+                if draw_discard {
+                    return Err(MoveError::MustDiscard);
+                }
+                if self.turn.retrieved_wildcards > 0 {
+                    return Err(MoveError::WildcardNotReplayed);
+                }
+                // With an empty deck, players who can't play pass
+                if !self.turn.played && !self.turn.drew && !self.deck.is_empty() {
+                    return Err(MoveError::MustDrawOrPlay);
+                }
+                self.check_opening()?;
+
+                self.passes = if !self.turn.played && self.deck.is_empty() { self.passes + 1 } else { 0 };
+                if self.passes >= self.players.len() {
+                    self.end_with_lowest_tray();
+                    return Ok(outcome);
+                }
+                self.advance_turn();
+                return Ok(outcome);
+            },
+            Move::Discard(card) => {
+                if !draw_discard {
+                    return Err(MoveError::NoDiscardPile);
+                }
+                if !self.turn.drew {
+                    return Err(MoveError::MustDrawFirst);
+                }
+                if self.turn.retrieved_wildcards > 0 {
+                    return Err(MoveError::WildcardNotReplayed);
+                }
+                let tray = take_tiles(&self.players[self.current_player].tray, &[card])?;
+                self.check_opening()?;
+
+                self.players[self.current_player].tray = tray;
+                self.discards.push(card);
+                if self.players[self.current_player].tray.is_empty() {
+                    outcome.gone_out = true;
+                    self.round_result = Some(RoundResult::score(&self.players, self.current_player, self.rules.scoring, self.teams.as_ref()));
+                } else if self.deck.is_empty() {
+                    self.end_with_lowest_tray();
+                } else {
+                    self.advance_turn();
+                }
+                return Ok(outcome);
+            },
+            Move::Mulligan(player) => {
+                self.take_mulligan(player)?;
+                return Ok(outcome);
+            }
+        }
+
+        let player = &mut self.players[self.current_player];
+        // Going out on the opening turn needs the opening points as well
+        outcome.gone_out = player.tray.is_empty() && (player.has_melded || self.turn.opening_points >= self.rules.opening_points);
+        if outcome.gone_out {
+            player.has_melded = true;
+            self.round_result = Some(RoundResult::score(&self.players, self.current_player, self.rules.scoring, self.teams.as_ref()));
+        }
+        Ok(outcome)
+    }
+
+    // Melds are laid down instead of drawing, or in VariantRules::DrawDiscard after drawing
+    fn check_can_play(&self) -> Result<(), MoveError> {
+        match self.rules.variant {
+            VariantRules::Standard if self.turn.drew => Err(MoveError::AlreadyDrew),
+            VariantRules::DrawDiscard if !self.turn.drew => Err(MoveError::MustDrawFirst),
+            _ => Ok(())
+        }
+    }
+
+    // A player ending the turn they laid down their first melds on opens, if they are worth
+    // enough
+    fn check_opening(&mut self) -> Result<(), MoveError> {
+        let player = &mut self.players[self.current_player];
+        if self.turn.played && !player.has_melded {
+            if self.turn.opening_points < self.rules.opening_points {
+                return Err(MoveError::OpeningTooLow { points: self.turn.opening_points, needed: self.rules.opening_points });
+            }
+            player.has_melded = true;
+        }
+        Ok(())
+    }
+
+    // The players tied with the winner lose what they hold minus what the winner holds: nothing
+    fn end_with_lowest_tray(&mut self) {
+        let values = self.players.iter().map(|p| tray_value(&p.tray));
+        // min_by_key keeps the first of the lowest on a tie
+        let (winner, _) = values.enumerate().min_by_key(|(_, value)| *value).unwrap();
+        let result = RoundResult::score(&self.players, winner, self.rules.scoring, self.teams.as_ref());
+        self.round_result = Some(RoundResult { end: RoundEnd::Stalemate, ..result });
+    }
+
+    /// Everything needed to resume this game later, see save_game
+    pub fn to_save(&self) -> GameSave {
+        GameSave {
+            seed: self.seed,
+            deck: self.deck.cards.clone(),
+            players: self.players.iter()
+                .enumerate()
+                .map(|(id, p)| SavedPlayer {
+                    name: p.name.clone(),
+                    tray: p.tray.clone().into(),
+                    has_melded: p.has_melded,
+                    took_mulligan: self.mulligans.contains(&id)
+                })
+                .collect(),
+            table: self.table.melds().map(|(_, cards)| cards.to_vec()).collect(),
+            discards: self.discards.clone(),
+            current_player: self.current_player,
+            started: self.started || self.turn_number > 0,
+            rules: self.rules
+        }
+    }
+
+    /// A hash of what the players can act on: the save of to_save, the number of turns played and whether the round is over. Games in the same state get the
+    /// same hash on every machine and with every build, the log of events and the undo history
+    /// being left out.
+    pub fn state_hash(&self) -> u64 {
+        let state = HashedState {
+            save: self.to_save(),
+            turn_number: self.turn_number,
+            round_over: self.round_result.is_some()
+        };
+        // Strings, numbers and lists always serialize
+        fnv1a(serde_json::to_string(&state).unwrap().as_bytes())
+    }
+
+    /// Resumes a saved game, once GameSave::validate accepts it
+    pub fn from_save(save: GameSave) -> Result<GameState, SaveError> {
+        save.validate()?;
+        let mut table = Table::with_rules(save.rules);
+        for (meld, cards) in save.table.iter().enumerate() {
+            table.add_meld(cards.clone()).map_err(|error| SaveError::InvalidMeld { meld, error })?;
+        }
+        let deck = save.deck();
+        let mulligans = save.players.iter().enumerate().filter(|(_, p)| p.took_mulligan).map(|(id, _)| id).collect();
+        let players: Vec<Player> = save.players.into_iter()
+            .map(|p| Player { name: p.name, tray: p.tray.into(), has_melded: p.has_melded })
+            .collect();
+        // A player went out before the save
+        let round_result = players.iter()
+            .position(|p| p.tray.is_empty())
+            .map(|winner| RoundResult::score(&players, winner, save.rules.scoring, None));
+        Ok(GameState {
+            deck,
+            discards: save.discards,
+            players,
+            table,
+            current_player: save.current_player,
+            turn: Turn::default(),
+            passes: 0,
+            round_result,
+            turn_number: 0,
+            events: vec![],
+            started: save.started,
+            seed: save.seed,
+            rules: save.rules,
+            mulligans,
+            undo: VecDeque::new(),
+            redo: vec![],
+            history_depth: HISTORY_DEPTH,
+            observers: Observers::default(),
+            teams: None
+        })
+    }
+}
+
+// What state_hash hashes, as JSON
+#[derive(Serialize)]
+struct HashedState {
+    save: GameSave,
+    turn_number: usize,
+    round_over: bool
+}
+
+// 64-bit FNV-1a, which unlike the hashers of std gives the same hash everywhere
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    bytes.iter().fold(OFFSET, |hash, byte| (hash ^ *byte as u64).wrapping_mul(PRIME))
+}
+
+// The tray without the played tiles, one copy taken for each
+fn take_tiles(tray: &Tray, played: &[Card]) -> Result<Tray, MoveError> {
+    let mut played = CardCounts::from_cards(played);
+    CardCounts::from_cards(tray.as_ref()).subtract(&played).map_err(|missing| MoveError::NotInTray(missing.tiles[0]))?;
+    // The first copies go, the tray keeping its order
+    let mut cards = Vec::from(tray.clone());
+    cards.retain(|card| played.remove(card).is_err());
+    Ok(Tray::from(cards))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::{play_round, Color, GreedyStrategy, Strategy};
+
+    // A two player game where Ann and Bo hold the given trays, the other tiles being in the
+    // deck. Both already opened, unless told otherwise.
+    fn game_with_trays(ann: &[Card], bo: &[Card]) -> GameState {
+        game_with_hands(&["Ann", "Bo"], &[ann, bo])
+    }
+
+    // Every player holds their hand and has melded
+    fn game_with_hands(names: &[&str], hands: &[&[Card]]) -> GameState {
+        let mut game = GameState::new_game(names, 9);
+        let mut cards = game.deck.cards.clone();
+        for player in &mut game.players {
+            cards.extend(Vec::from(std::mem::take(&mut player.tray)));
+        }
+        for (player, tray) in game.players.iter_mut().zip(hands) {
+            for card in *tray {
+                let idx = cards.iter().position(|c| c == card).unwrap();
+                player.tray.push(cards.remove(idx));
+            }
+            player.has_melded = true;
+        }
+        game.deck = Deck { cards };
+        game
+    }
+
+    fn game_with_tray(tray: &[Card]) -> GameState {
+        game_with_trays(tray, &[])
+    }
+
+    fn total_tiles(game: &GameState) -> usize {
+        let table: usize = game.table.melds().map(|(_, cards)| cards.len()).sum();
+        game.deck_len() + table + game.players.iter().map(|p| p.tray.len()).sum::<usize>()
+    }
+
+    #[test]
+    fn determine_first_player_test() {
+        // Drawn from the end: Ann 7, Bo a wildcard then 12, Cy 12, then Bo 2 and Cy 9
+        let mut deck = Deck::from_cards(vec![
+            Card::new(9, Color::Blue),
+            Card::new(2, Color::Yellow),
+            Card::new(12, Color::Black),
+            Card::new(12, Color::Red),
+            Card::Wildcard,
+            Card::new(7, Color::Blue)
+        ]);
+        let mut rng = StdRng::seed_from_u64(1);
+
+        assert_eq!(GameState::determine_first_player(&mut deck, 3, &mut rng), 2);
+        assert_eq!(deck.len(), 6);
+        assert_eq!(GameState::determine_first_player(&mut deck, 1, &mut rng), 0);
+        assert_eq!(GameState::determine_first_player(&mut deck, 0, &mut rng), 0);
+        assert_eq!(GameState::determine_first_player(&mut Deck::from_cards(vec![Card::Wildcard]), 2, &mut rng), 0);
+
+        let mut deck = Deck::new_seeded(4);
+        let before = deck.clone();
+        let first = GameState::determine_first_player(&mut deck, 4, &mut StdRng::seed_from_u64(4));
+        assert_eq!(first, GameState::determine_first_player(&mut before.clone(), 4, &mut StdRng::seed_from_u64(4)));
+        assert!(first < 4);
+        assert_eq!(deck.len(), 106);
+        assert_ne!(deck, before);
+        let mut sorted = deck.snapshot();
+        sorted.sort();
+        let mut full = before.snapshot();
+        full.sort();
+        assert_eq!(sorted, full);
+    }
+
+    #[test]
+    fn new_game_test() {
+        let game = GameState::new_game(&["Ann", "Bo", "Cy"], 9);
+
+        assert_eq!(game.players().len(), 3);
+        assert!(game.players().iter().all(|p| p.tray().len() == 14));
+        assert_eq!(game.deck_len(), 64);
+        assert_eq!(game.current_player().name(), "Ann");
+        assert_eq!(game.player_summaries()[2], PlayerSummary { name: "Cy", tile_count: 14, has_melded: false });
+        assert_eq!(GameState::new_game(&["Ann", "Bo", "Cy"], 9).players(), game.players());
+    }
+
+    #[test]
+    fn advance_turn_test() {
+        let mut game = GameState::new_game(&["Ann", "Bo", "Cy"], 9);
+
+        game.advance_turn();
+        assert_eq!(game.current_player().name(), "Bo");
+        game.advance_turn();
+        game.advance_turn();
+        assert_eq!(game.current_player_index(), 0);
+    }
+
+    #[test]
+    fn draw_for_current_test() {
+        let mut game = GameState::new_game(&["Ann", "Bo"], 9);
+        game.advance_turn();
+
+        let card = game.draw_for_current().unwrap();
+
+        assert_eq!(game.deck_len(), 77);
+        assert_eq!(game.players()[0].tray().len(), 14);
+        assert_eq!(game.players()[1].tray().len(), 15);
+        assert_eq!(game.players()[1].tray().last(), Some(&card));
+    }
+
+    #[test]
+    fn draw_from_empty_deck_test() {
+        let mut game = GameState::new_game(&["Ann", "Bo", "Cy", "Di", "Ed", "Flo", "Gus"], 9);
+
+        for _ in 0..8 {
+            assert!(game.draw_for_current().is_some());
+        }
+        assert_eq!(game.draw_for_current(), None);
+        assert_eq!(game.current_player().tray().len(), 22);
+    }
+
+    #[test]
+    fn save_round_trip_test() {
+        let mut game = GameState::new_game(&["Ann", "Bo"], 3);
+        game.advance_turn();
+        game.draw_for_current();
+
+        let resumed = GameState::from_save(game.to_save()).unwrap();
+
+        assert_eq!(resumed.players(), game.players());
+        assert_eq!(resumed.current_player_index(), 1);
+        assert_eq!(resumed.deck_len(), game.deck_len());
+        assert_eq!(resumed.to_save(), game.to_save());
+    }
+
+    #[test]
+    fn save_round_trip_with_rules_test() {
+        let rules = RulesConfig { wildcards: 4, wrap_runs: true, opening_points: 40, scoring: ScoringVariant::RaceTo(200), ..RulesConfig::default() };
+        let game = GameState::new_game_with_rules(&["Ann", "Bo"], 4, 0, rules);
+
+        let save = GameSave::from_json(&game.to_save().to_json()).unwrap();
+        assert_eq!(save.rules, rules);
+        let resumed = GameState::from_save(save).unwrap();
+        assert_eq!(resumed.rules(), &rules);
+        assert_eq!(resumed.table().rules(), &rules);
+        assert_eq!(resumed.deck_len(), game.deck_len());
+        assert_eq!(resumed.to_save(), game.to_save());
+    }
+
+    #[test]
+    fn play_new_meld_test() {
+        let run = vec![Card::new(4, Color::Red), Card::new(5, Color::Red), Card::new(6, Color::Red)];
+        let mut game = game_with_tray(&[run.clone(), vec![Card::new(9, Color::Blue)]].concat());
+
+        let outcome = game.apply_move(Move::PlayNewMeld(run.clone())).unwrap();
+
+        assert!(!outcome.gone_out);
+        assert_eq!(game.table().meld(outcome.meld.unwrap()), Some(run.as_slice()));
+        assert_eq!(game.current_player().tray(), &[Card::new(9, Color::Blue)]);
+        assert_eq!(total_tiles(&game), 106);
+    }
+
+    #[test]
+    fn play_tiles_not_held_test() {
+        let mut game = game_with_tray(&[Card::new(7, Color::Red), Card::new(7, Color::Blue), Card::new(2, Color::Black)]);
+        let tray = game.current_player().tray().to_vec();
+
+        let missing = game.apply_move(Move::PlayNewMeld(vec![Card::new(7, Color::Red), Card::new(7, Color::Blue), Card::new(7, Color::Yellow)]));
+        let twice = game.apply_move(Move::PlayNewMeld(vec![Card::new(7, Color::Red), Card::new(7, Color::Red), Card::new(7, Color::Blue)]));
+        let invalid = game.apply_move(Move::PlayNewMeld(vec![Card::new(7, Color::Red), Card::new(7, Color::Blue), Card::new(2, Color::Black)]));
+
+        assert_eq!(missing, Err(MoveError::NotInTray(Card::new(7, Color::Yellow))));
+        assert_eq!(twice, Err(MoveError::NotInTray(Card::new(7, Color::Red))));
+        assert!(matches!(invalid, Err(MoveError::InvalidMeld(_))));
+        assert_eq!(game.current_player().tray(), tray.as_slice());
+        assert!(game.table().is_empty());
+    }
+
+    #[test]
+    fn extend_group_test() {
+        let group = vec![Card::new(8, Color::Red), Card::new(8, Color::Blue), Card::new(8, Color::Black)];
+        let mut game = game_with_tray(&[group.clone(), vec![Card::new(8, Color::Yellow), Card::Wildcard, Card::new(1, Color::Red)]].concat());
+        let meld = game.apply_move(Move::PlayNewMeld(group)).unwrap().meld.unwrap();
+
+        game.apply_move(Move::ExtendMeld { meld, card: Card::new(8, Color::Yellow) }).unwrap();
+        // A group has at most 4 tiles, even with a wildcard
+        let fifth = game.apply_move(Move::ExtendMeld { meld, card: Card::Wildcard });
+
+        assert_eq!(fifth, Err(MoveError::InvalidMeld(SetError::GroupTooLarge { len: 5, max: 4 })));
+        assert_eq!(game.table().meld(meld).unwrap().len(), 4);
+        assert_eq!(game.current_player().tray(), &[Card::Wildcard, Card::new(1, Color::Red)]);
+        assert_eq!(
+            game.apply_move(Move::ExtendMeld { meld, card: Card::new(9, Color::Red) }),
+            Err(MoveError::NotInTray(Card::new(9, Color::Red)))
+        );
+    }
+
+    #[test]
+    fn extend_run_until_out_test() {
+        let run = vec![Card::new(10, Color::Yellow), Card::new(11, Color::Yellow), Card::new(12, Color::Yellow)];
+        let mut game = game_with_tray(&[run.clone(), vec![Card::new(13, Color::Yellow)]].concat());
+        let meld = game.apply_move(Move::PlayNewMeld(run)).unwrap().meld.unwrap();
+
+        // An id the table of the game never handed out
+        let unknown = game.table.clone().add_meld(vec![Card::new(1, Color::Red), Card::new(2, Color::Red), Card::new(3, Color::Red)]).unwrap();
+        assert_eq!(game.apply_move(Move::ExtendMeld { meld: unknown, card: Card::Wildcard }), Err(MoveError::NoSuchMeld(unknown)));
+        let outcome = game.apply_move(Move::ExtendMeld { meld, card: Card::new(13, Color::Yellow) }).unwrap();
+
+        assert!(outcome.gone_out);
+        assert_eq!(game.round_result().map(|r| r.winner), Some(0));
+        assert_eq!(game.apply_move(Move::EndTurn), Err(MoveError::RoundOver));
+    }
+
+    #[test]
+    fn end_turn_test() {
+        let mut game = game_with_tray(&[Card::new(1, Color::Red)]);
+
+        assert_eq!(game.apply_move(Move::EndTurn), Err(MoveError::MustDrawOrPlay));
+        assert_eq!(game.current_player_index(), 0);
+
+        let drawn = game.apply_move(Move::Draw).unwrap().drawn.unwrap();
+        assert_eq!(game.current_player().tray(), &[Card::new(1, Color::Red), drawn]);
+        assert_eq!(game.apply_move(Move::Draw), Err(MoveError::AlreadyDrew));
+        assert!(matches!(game.apply_move(Move::PlayNewMeld(vec![drawn])), Err(MoveError::AlreadyDrew)));
+
+        game.apply_move(Move::EndTurn).unwrap();
+        assert_eq!(game.current_player_index(), 1);
+        assert_eq!(game.apply_move(Move::EndTurn), Err(MoveError::MustDrawOrPlay));
+        assert_eq!(total_tiles(&game), 106);
+    }
+
+    #[test]
+    fn draw_after_playing_test() {
+        let set = vec![Card::new(3, Color::Red), Card::new(3, Color::Blue), Card::new(3, Color::Black)];
+        let mut game = game_with_tray(&[set.clone(), vec![Card::new(1, Color::Red)]].concat());
+        game.apply_move(Move::PlayNewMeld(set)).unwrap();
+
+        assert_eq!(game.apply_move(Move::Draw), Err(MoveError::AlreadyPlayed));
+        assert!(game.apply_move(Move::EndTurn).is_ok());
+    }
+
+    #[test]
+    fn retrieve_wildcard_test() {
+        let run = vec![Card::new(4, Color::Red), Card::Wildcard, Card::new(6, Color::Red)];
+        let bo = [Card::new(5, Color::Red), Card::new(11, Color::Blue), Card::new(11, Color::Black)];
+        let mut game = game_with_trays(&[run.clone(), vec![Card::new(1, Color::Black)]].concat(), &bo);
+        let meld = game.apply_move(Move::PlayNewMeld(run)).unwrap().meld.unwrap();
+        game.apply_move(Move::EndTurn).unwrap();
+
+        let wrong = game.apply_move(Move::RetrieveWildcard { meld, card: Card::new(11, Color::Blue) });
+        assert_eq!(wrong, Err(MoveError::Joker(JokerError::WrongTile { meld, replacement: Card::new(11, Color::Blue) })));
+
+        game.apply_move(Move::RetrieveWildcard { meld, card: Card::new(5, Color::Red) }).unwrap();
+        assert_eq!(game.current_player().tray(), &[Card::new(11, Color::Blue), Card::new(11, Color::Black), Card::Wildcard]);
+        assert!(game.table().is_valid());
+
+        let replayed = vec![Card::new(11, Color::Blue), Card::new(11, Color::Black), Card::Wildcard];
+        let outcome = game.apply_move(Move::PlayNewMeld(replayed)).unwrap();
+        assert!(outcome.gone_out);
+        assert_eq!(game.round_result().map(|r| r.winner), Some(1));
+        assert_eq!(total_tiles(&game), 106);
+    }
+
+    #[test]
+    fn retrieved_wildcard_kept_test() {
+        let group = vec![Card::new(2, Color::Red), Card::new(2, Color::Blue), Card::Wildcard];
+        let mut game = game_with_trays(&[group.clone(), vec![Card::new(1, Color::Black)]].concat(), &[Card::new(2, Color::Yellow), Card::new(9, Color::Red)]);
+        let meld = game.apply_move(Move::PlayNewMeld(group)).unwrap().meld.unwrap();
+        game.apply_move(Move::EndTurn).unwrap();
+
+        game.apply_move(Move::RetrieveWildcard { meld, card: Card::new(2, Color::Yellow) }).unwrap();
+
+        assert_eq!(game.apply_move(Move::EndTurn), Err(MoveError::WildcardNotReplayed));
+        assert_eq!(game.apply_move(Move::Draw), Err(MoveError::AlreadyPlayed));
+        assert_eq!(game.current_player_index(), 1);
+    }
+
+    #[test]
+    fn team_round_test() {
+        let run = vec![Card::new(4, Color::Red), Card::new(5, Color::Red), Card::new(6, Color::Red)];
+        let hands: [&[Card]; 4] = [&run, &[Card::new(9, Color::Blue)], &[Card::new(12, Color::Black), Card::Wildcard], &[Card::new(2, Color::Yellow), Card::new(3, Color::Yellow)]];
+        let mut game = game_with_hands(&["Ann", "Bo", "Cy", "Di"], &hands);
+        game.set_teams(Teams::partners());
+
+        assert!(game.apply_move(Move::PlayNewMeld(run)).unwrap().gone_out);
+        let result = game.round_result().unwrap();
+        // Cy plays with Ann, so the 42 points of the tray of Cy don't count
+        assert_eq!(result.penalties, vec![(1, -9), (2, 0), (3, -5)]);
+        assert_eq!(result.winner_gain, 14);
+        assert_eq!(result.team_scores, vec![14, -14]);
+
+        // The view of Ann shows no other tray
+        let view = game.view_for(0);
+        assert!(view.tray.is_empty());
+        assert_eq!(view.players[2].tile_count, 2);
+    }
+
+    #[test]
+    fn move_outcome_diffs_test() {
+        let run = vec![Card::new(4, Color::Red), Card::Wildcard, Card::new(6, Color::Red)];
+        let bo = [Card::new(5, Color::Red), Card::new(9, Color::Blue)];
+        let mut game = game_with_trays(&[run.clone(), vec![Card::new(1, Color::Black)]].concat(), &bo);
+        let played = game.apply_move(Move::PlayNewMeld(run.clone())).unwrap();
+        let meld = played.meld.unwrap();
+        assert_eq!(played.tray, TrayDiff { gained: vec![], lost: run });
+        assert_eq!(played.table.added, vec![meld]);
+        assert!(played.table.moved.is_empty());
+        let ended = game.apply_move(Move::EndTurn).unwrap();
+        assert!(ended.tray.is_empty() && ended.table.is_empty());
+
+        // The wildcard goes to the tray and R5 comes in its place
+        let retrieved = game.apply_move(Move::RetrieveWildcard { meld, card: Card::new(5, Color::Red) }).unwrap();
+        assert_eq!(retrieved.tray, TrayDiff { gained: vec![Card::Wildcard], lost: vec![Card::new(5, Color::Red)] });
+        assert_eq!(retrieved.table.changed, vec![(meld, TrayDiff { gained: vec![Card::new(5, Color::Red)], lost: vec![Card::Wildcard] })]);
+        assert!(retrieved.table.moved.is_empty());
+
+        game.undo().unwrap();
+        let drawn = game.apply_move(Move::Draw).unwrap();
+        assert_eq!(drawn.tray.gained, vec![drawn.drawn.unwrap()]);
+        assert_eq!(drawn.table, TableDiff::default());
+    }
+
+    #[test]
+    fn opening_too_low_test() {
+        // 9 9 W is worth 27, and the meld on the table can't help
+        let group = vec![Card::new(9, Color::Red), Card::new(9, Color::Blue), Card::Wildcard];
+        let mut game = game_with_trays(&[group.clone(), vec![Card::new(4, Color::Yellow)]].concat(), &[]);
+        game.players[0].has_melded = false;
+        let meld = game.table.add_meld(vec![Card::new(1, Color::Yellow), Card::new(2, Color::Yellow), Card::new(3, Color::Yellow)]).unwrap();
+
+        game.apply_move(Move::PlayNewMeld(group)).unwrap();
+
+        assert_eq!(game.apply_move(Move::EndTurn), Err(MoveError::OpeningTooLow { points: 27, needed: 30 }));
+        assert_eq!(game.apply_move(Move::ExtendMeld { meld, card: Card::new(4, Color::Yellow) }), Err(MoveError::NotOpened));
+        assert!(!game.current_player().has_melded());
+    }
+
+    #[test]
+    fn move_error_display_test() {
+        let meld = Table::from_melds(vec![vec![]]).melds().next().unwrap().0;
+        let messages = [
+            (MoveError::NotInTray(Card::new(5, Color::Blue)), "B5 is not in your tray"),
+            (MoveError::InvalidMeld(SetError::MixedNumbersInGroup), "not a valid set: all the tiles of a group must have the same number"),
+            (MoveError::NoSuchMeld(meld), "there is no meld #0 on the table"),
+            (MoveError::Joker(JokerError::NoWildcard(meld)), "meld #0 has no wildcard"),
+            (MoveError::WildcardNotReplayed, "play the wildcard you took back in a new meld first"),
+            (MoveError::NotOpened, "you can only lay down new melds until you open"),
+            (MoveError::OpeningTooLow { points: 27, needed: 30 }, "your opening is worth 27 points, it needs 30"),
+            (MoveError::AlreadyDrew, "you already drew this turn, end it"),
+            (MoveError::AlreadyPlayed, "you already played this turn, you can't draw"),
+            (MoveError::DeckEmpty, "the deck is empty"),
+            (MoveError::MustDrawOrPlay, "play a tile or draw before ending your turn"),
+            (MoveError::RoundOver, "the round is over"),
+            (MoveError::NoPlayers, "the game has no players")
+        ];
+
+        for (error, message) in messages {
+            assert_eq!(error.to_string(), message);
+        }
+        assert_eq!(UndoError::NothingToUndo.to_string(), "there is no move to undo");
+        assert_eq!(UndoError::NothingToRedo.to_string(), "there is no undone move to redo");
+        let source = std::error::Error::source(&MoveError::InvalidMeld(SetError::AllWildcards)).unwrap();
+        assert_eq!(source.to_string(), SetError::AllWildcards.to_string());
+    }
+
+    #[test]
+    fn no_players_test() {
+        // Indexing the players used to panic
+        let mut game = GameState::new_game(&[], 9);
+
+        assert_eq!(game.apply_move(Move::Draw), Err(MoveError::NoPlayers));
+        assert_eq!(game.apply_move(Move::EndTurn), Err(MoveError::NoPlayers));
+        assert_eq!(game.deck_len(), 106);
+    }
+
+    #[test]
+    fn house_opening_test() {
+        let run: Vec<Card> = (5..=9).map(|n| Card::new(n, Color::Red)).collect();
+        let mut game = game_with_tray(&[run.clone(), vec![Card::new(4, Color::Yellow)]].concat());
+        game.players[0].has_melded = false;
+        game.rules = RulesConfig { opening_points: 40, ..RulesConfig::default() };
+
+        game.apply_move(Move::PlayNewMeld(run)).unwrap();
+
+        let refused = game.apply_move(Move::EndTurn).unwrap_err();
+        assert_eq!(refused, MoveError::OpeningTooLow { points: 35, needed: 40 });
+        assert_eq!(refused.to_string(), "your opening is worth 35 points, it needs 40");
+        assert!(!game.current_player().has_melded());
+    }
+
+    #[test]
+    fn opening_above_13_test() {
+        let run: Vec<Card> = (14..=16).map(|n| Card::new(n, Color::Red)).collect();
+        let mut game = GameState::new_game_with_rules(&["Ann", "Bo"], 9, 0, RulesConfig { max_number: 16, ..RulesConfig::default() });
+        game.players[0].tray = [run.clone(), vec![Card::new(4, Color::Yellow)]].concat().into();
+
+        // R14 R15 R16 is worth 45
+        game.apply_move(Move::PlayNewMeld(run)).unwrap();
+        game.apply_move(Move::EndTurn).unwrap();
+        assert!(game.players[0].has_melded());
+    }
+
+    #[test]
+    fn opening_with_five_tile_group_test() {
+        let group = vec![Card::new(10, Color::Red), Card::new(10, Color::Blue), Card::new(10, Color::Green), Card::new(10, Color::Orange), Card::Wildcard];
+        let mut game = GameState::new_game_with_rules(&["Ann", "Bo"], 9, 0, RulesConfig::six_colors());
+        game.players[0].tray = [group.clone(), vec![Card::new(4, Color::Yellow)]].concat().into();
+
+        game.apply_move(Move::PlayNewMeld(group)).unwrap();
+        game.apply_move(Move::EndTurn).unwrap();
+        assert!(game.players[0].has_melded());
+    }
+
+    #[test]
+    fn new_game_with_rules_test() {
+        let rules = RulesConfig { wildcards: 4, min_run_len: 4, ..RulesConfig::default() };
+        let game = GameState::new_game_with_rules(&["Ann", "Bo"], 9, 1, rules);
+
+        assert_eq!(game.rules(), &rules);
+        assert_eq!(total_tiles(&game), 108);
+        assert_eq!(game.current_player().name(), "Bo");
+        assert_eq!(GameState::new_game_with_rules(&["Ann", "Bo"], 9, 0, RulesConfig::default()), GameState::new_game(&["Ann", "Bo"], 9));
+    }
+
+    #[test]
+    fn opening_with_two_melds_test() {
+        let run = vec![Card::new(1, Color::Black), Card::new(2, Color::Black), Card::new(3, Color::Black)];
+        let group = vec![Card::new(8, Color::Red), Card::new(8, Color::Blue), Card::new(8, Color::Yellow)];
+        let mut game = game_with_trays(&[run.clone(), group.clone(), vec![Card::new(4, Color::Black)]].concat(), &[]);
+        game.players[0].has_melded = false;
+
+        let meld = game.apply_move(Move::PlayNewMeld(run)).unwrap().meld.unwrap();
+        game.apply_move(Move::PlayNewMeld(group)).unwrap();
+        // Not on the turn of the opening
+        assert_eq!(game.apply_move(Move::ExtendMeld { meld, card: Card::new(4, Color::Black) }), Err(MoveError::NotOpened));
+        game.apply_move(Move::EndTurn).unwrap();
+        assert!(game.players()[0].has_melded());
+
+        game.apply_move(Move::Draw).unwrap();
+        game.apply_move(Move::EndTurn).unwrap();
+        game.apply_move(Move::ExtendMeld { meld, card: Card::new(4, Color::Black) }).unwrap();
+        assert_eq!(game.table().meld(meld).unwrap().len(), 4);
+        assert_eq!(GameState::from_save(game.to_save()).unwrap().players(), game.players());
+    }
+
+    #[test]
+    fn going_out_scores_round_test() {
+        let run = vec![Card::new(11, Color::Blue), Card::new(12, Color::Blue), Card::new(13, Color::Blue)];
+        let mut game = game_with_trays(&run, &[Card::Wildcard, Card::new(5, Color::Red), Card::new(13, Color::Black)]);
+        assert_eq!(game.round_result(), None);
+
+        game.apply_move(Move::PlayNewMeld(run)).unwrap();
+
+        let result = game.round_result().unwrap().clone();
+        assert_eq!(result.winner, 0);
+        assert_eq!(result.penalties, vec![(1, -48)]);
+        assert_eq!(result.winner_gain, 48);
+        assert_eq!(game.hand_penalties(), vec![(0, 0), (1, 48)]);
+        assert_eq!(game.apply_move(Move::Draw), Err(MoveError::RoundOver));
+        assert_eq!(GameState::from_save(game.to_save()).unwrap().round_result(), Some(&result));
+    }
+
+    #[test]
+    fn scoring_variants_test() {
+        let run = vec![Card::new(11, Color::Blue), Card::new(12, Color::Blue), Card::new(13, Color::Blue)];
+        // Bo is left holding 48
+        let result = |scoring| {
+            let mut game = game_with_trays(&run, &[Card::Wildcard, Card::new(5, Color::Red), Card::new(13, Color::Black)]);
+            game.rules.scoring = scoring;
+            game.apply_move(Move::PlayNewMeld(run.clone())).unwrap();
+            game.round_result().unwrap().clone()
+        };
+
+        let standard = result(ScoringVariant::Standard);
+        assert_eq!((standard.penalties, standard.winner_gain), (vec![(1, -48)], 48));
+        let double = result(ScoringVariant::WinnerDouble);
+        assert_eq!((double.penalties, double.winner_gain), (vec![(1, -48)], 96));
+        let race = result(ScoringVariant::RaceTo(100));
+        assert_eq!((race.penalties, race.winner_gain), (vec![(1, 48)], 0));
+        assert_eq!(race.scoring, ScoringVariant::RaceTo(100));
+    }
+
+    // Writes down every callback it gets
+    struct Recorder(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+    impl GameObserver for Recorder {
+        fn on_draw(&self, player: PlayerId, draw: &PublicDraw) {
+            self.0.lock().unwrap().push(format!("{} draws {:?}", player, draw.taken_discard));
+        }
+
+        fn on_meld_played(&self, player: PlayerId, meld: MeldId, cards: &[Card]) {
+            self.0.lock().unwrap().push(format!("{} plays {} with {} tiles", player, meld, cards.len()));
+        }
+
+        fn on_meld_extended(&self, player: PlayerId, meld: MeldId, card: Card, cards: &[Card]) {
+            self.0.lock().unwrap().push(format!("{} adds {} to {}, now {} tiles", player, card.code(), meld, cards.len()));
+        }
+
+        fn on_turn_end(&self, player: PlayerId) {
+            self.0.lock().unwrap().push(format!("{} ends", player));
+        }
+
+        fn on_round_end(&self, result: &RoundResult) {
+            self.0.lock().unwrap().push(format!("{} wins {}", result.winner, result.winner_gain));
+        }
+    }
+
+    #[test]
+    fn observer_callbacks_test() {
+        let red = vec![Card::new(5, Color::Red), Card::new(6, Color::Red), Card::new(7, Color::Red)];
+        let black = vec![Card::new(1, Color::Black), Card::new(2, Color::Black), Card::new(3, Color::Black)];
+        let mut game = game_with_trays(&[red.clone(), vec![Card::new(8, Color::Red)], black.clone()].concat(), &[Card::new(9, Color::Blue)]);
+        let calls = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        game.subscribe(Box::new(Recorder(calls.clone())));
+
+        game.apply_move(Move::PlayNewMeld(red)).unwrap();
+        let meld = game.table().melds().next().unwrap().0;
+        game.apply_move(Move::ExtendMeld { meld, card: Card::new(8, Color::Red) }).unwrap();
+        // Refused moves are not told
+        assert!(game.apply_move(Move::Draw).is_err());
+        game.apply_move(Move::EndTurn).unwrap();
+        game.apply_move(Move::Draw).unwrap();
+        game.apply_move(Move::EndTurn).unwrap();
+        // A copy plays without the observer
+        game.clone().apply_move(Move::Draw).unwrap();
+        game.apply_move(Move::PlayNewMeld(black)).unwrap();
+
+        assert_eq!(game.players()[1].tray().len(), 2);
+        assert_eq!(*calls.lock().unwrap(), vec![
+            format!("0 plays {} with 3 tiles", meld),
+            format!("0 adds R8 to {}, now 4 tiles", meld),
+            "0 ends".to_string(),
+            "1 draws None".to_string(),
+            "1 ends".to_string(),
+            format!("0 plays {} with 3 tiles", game.table().melds().nth(1).unwrap().0),
+            format!("0 wins {}", game.round_result().unwrap().winner_gain)
+        ]);
+    }
+
+    #[test]
+    fn empty_deck_stalemate_test() {
+        let mut game = game_with_trays(&[Card::new(2, Color::Red), Card::new(9, Color::Blue)], &[Card::new(4, Color::Black), Card::new(6, Color::Black)]);
+        game.deck = Deck { cards: vec![Card::new(1, Color::Yellow)] };
+
+        // Ann draws the last tile, then nobody can play
+        game.apply_move(Move::Draw).unwrap();
+        game.apply_move(Move::EndTurn).unwrap();
+        assert_eq!(game.round_result(), None);
+        game.apply_move(Move::EndTurn).unwrap();
+
+        // Ann holds 12 and Bo 10
+        let result = game.round_result().unwrap();
+        assert_eq!(result.winner, 1);
+        assert_eq!(result.penalties, vec![(0, -2)]);
+        assert_eq!(result.winner_gain, 2);
+        assert_eq!(result.end, RoundEnd::Stalemate);
+    }
+
+    #[test]
+    fn stalemate_tie_test() {
+        // Ann holds 12, Bo and Cy 7 each, and no tile fits anywhere
+        let hands: [&[Card]; 3] = [
+            &[Card::new(10, Color::Red), Card::new(2, Color::Black)],
+            &[Card::new(3, Color::Blue), Card::new(4, Color::Yellow)],
+            &[Card::new(5, Color::Black), Card::new(2, Color::Red)]
+        ];
+        let mut game = game_with_hands(&["Ann", "Bo", "Cy"], &hands);
+        game.deck = Deck { cards: vec![] };
+        let mut bots = game.clone();
+
+        assert_eq!(game.apply_move(Move::Draw), Err(MoveError::DeckEmpty));
+        game.apply_move(Move::EndTurn).unwrap();
+        game.apply_move(Move::EndTurn).unwrap();
+        assert_eq!(game.round_result(), None);
+        game.apply_move(Move::EndTurn).unwrap();
+
+        // Bo comes first of the two lowest and Cy, tied, loses nothing
+        let result = game.round_result().unwrap().clone();
+        assert_eq!(result.end, RoundEnd::Stalemate);
+        assert_eq!(result.winner, 1);
+        assert_eq!(result.penalties, vec![(0, -5), (2, 0)]);
+        assert_eq!(result.winner_gain, 5);
+        assert_eq!(game.apply_move(Move::EndTurn), Err(MoveError::RoundOver));
+
+        // Bots pass as well instead of playing on forever
+        let mut strategies: Vec<Box<dyn Strategy>> = (0..3).map(|_| Box::new(GreedyStrategy) as Box<dyn Strategy>).collect();
+        assert_eq!(play_round(&mut bots, &mut strategies, 100), Ok(Some(result)));
+    }
+
+    #[test]
+    fn undo_and_replay_test() {
+        let run = vec![Card::new(5, Color::Red), Card::new(6, Color::Red), Card::new(7, Color::Red), Card::new(8, Color::Red)];
+        let mut game = game_with_tray(&[run.clone(), vec![Card::new(9, Color::Red)]].concat());
+        let start = game.clone();
+
+        let meld = game.apply_move(Move::PlayNewMeld(run.clone())).unwrap().meld.unwrap();
+        game.apply_move(Move::ExtendMeld { meld, card: Card::new(9, Color::Red) }).unwrap();
+        let played = game.clone();
+
+        game.undo().unwrap();
+        assert_eq!(game.current_player().tray(), &[Card::new(9, Color::Red)]);
+        game.undo().unwrap();
+        assert_eq!(game.current_player().tray().len(), 5);
+        assert!(game.table().is_empty());
+        assert_eq!(game.undo(), Err(UndoError::NothingToUndo));
+
+        game.redo().unwrap();
+        game.redo().unwrap();
+        assert_eq!(game, played);
+        assert_eq!(game.redo(), Err(UndoError::NothingToRedo));
+
+        game.undo().unwrap();
+        game.undo().unwrap();
+        game.apply_move(Move::PlayNewMeld(run)).unwrap();
+        game.apply_move(Move::ExtendMeld { meld, card: Card::new(9, Color::Red) }).unwrap();
+        assert_eq!(game, played);
+        game.undo().unwrap();
+        game.undo().unwrap();
+        assert_eq!(game.undo.len(), start.undo.len());
+        assert_eq!(game.snapshot(), start.snapshot());
+    }
+
+    #[test]
+    fn undo_draw_test() {
+        let mut game = GameState::new_game(&["Ann", "Bo"], 9);
+        let deck = game.deck.clone();
+
+        let drawn = game.apply_move(Move::Draw).unwrap().drawn;
+        game.undo().unwrap();
+        assert_eq!(game.deck, deck);
+        assert_eq!(game.apply_move(Move::Draw).unwrap().drawn, drawn);
+    }
+
+    // Every tile of the game, wherever it is
+    fn all_tiles(game: &GameState) -> CardCounts {
+        let mut cards = game.deck.cards.clone();
+        cards.extend(game.table.melds().flat_map(|(_, cards)| cards.to_vec()));
+        cards.extend(game.players.iter().flat_map(|p| p.tray().to_vec()));
+        CardCounts::from_cards(&cards)
+    }
+
+    #[test]
+    fn mulligan_test() {
+        let rules = RulesConfig { mulligan: Mulligan::OncePerPlayer { only_without_sets: false }, ..RulesConfig::default() };
+        let mut game = GameState::new_game_with_rules(&["Ann", "Bo"], 9, 0, rules);
+        let dealt = game.players()[1].tray().to_vec();
+
+        assert_eq!(game.mulligan(1), Ok(()));
+        assert_eq!(game.players()[1].tray().len(), TRAY_SIZE);
+        assert_ne!(game.players()[1].tray(), dealt.as_slice());
+        assert_eq!(total_tiles(&game), 106);
+        assert_eq!(all_tiles(&game), CardCounts::from_cards(&Deck::new().cards));
+        assert_eq!(game.mulligan(1), Err(MoveError::MulliganTaken));
+
+        game.apply_move(Move::Draw).unwrap();
+        assert_eq!(game.mulligan(0), Err(MoveError::GameStarted));
+        assert_eq!(GameState::new_game(&["Ann", "Bo"], 9).mulligan(0), Err(MoveError::NoMulligan));
+    }
+
+    #[test]
+    fn mulligan_undo_test() {
+        let rules = RulesConfig { mulligan: Mulligan::OncePerPlayer { only_without_sets: false }, ..RulesConfig::default() };
+        let mut game = GameState::new_game_with_rules(&["Ann", "Bo"], 9, 0, rules);
+        let dealt = game.clone();
+
+        game.mulligan(1).unwrap();
+        assert_eq!(game.events(), &[GameEvent { player: 1, turn: 0, mv: Move::Mulligan(1), drawn: None, timed_out: false }]);
+        let mulliganed = game.players()[1].tray().to_vec();
+        game.undo().unwrap();
+        assert_eq!(game.to_save(), dealt.to_save());
+        assert!(game.events().is_empty());
+
+        // Taken again, it deals the same tray
+        game.mulligan(1).unwrap();
+        assert_eq!(game.players()[1].tray(), mulliganed.as_slice());
+        game.apply_move(Move::Draw).unwrap();
+        game.undo().unwrap();
+        assert_eq!(game.mulligan(0), Ok(()));
+    }
+
+    #[test]
+    fn mulligan_save_test() {
+        let rules = RulesConfig { mulligan: Mulligan::OncePerPlayer { only_without_sets: false }, ..RulesConfig::default() };
+        let mut game = GameState::new_game_with_rules(&["Ann", "Bo", "Cy"], 9, 0, rules);
+        game.mulligan(1).unwrap();
+
+        let mut resumed = GameState::from_save(GameSave::from_json(&game.to_save().to_json()).unwrap()).unwrap();
+        assert_eq!(resumed.to_save(), game.to_save());
+        assert_eq!(resumed.mulligan(1), Err(MoveError::MulliganTaken));
+        // The seed of the reshuffle counts the mulligans taken before the save
+        game.mulligan(2).unwrap();
+        resumed.mulligan(2).unwrap();
+        assert_eq!(resumed.players()[2].tray(), game.players()[2].tray());
+
+        game.apply_move(Move::Draw).unwrap();
+        let mut resumed = GameState::from_save(game.to_save()).unwrap();
+        assert!(resumed.to_save().started);
+        assert_eq!(resumed.mulligan(0), Err(MoveError::GameStarted));
+    }
+
+    #[test]
+    fn mulligan_without_sets_test() {
+        let mut game = game_with_trays(
+            &[Card::new(4, Color::Red), Card::new(5, Color::Red), Card::new(6, Color::Red)],
+            &[Card::new(1, Color::Red), Card::new(7, Color::Blue), Card::new(11, Color::Black)]
+        );
+        game.rules.mulligan = Mulligan::OncePerPlayer { only_without_sets: true };
+
+        assert_eq!(game.mulligan(0), Err(MoveError::TrayHasSets));
+        assert_eq!(game.mulligan(1), Ok(()));
+        assert_eq!(all_tiles(&game), CardCounts::from_cards(&Deck::new().cards));
+    }
+
+    #[test]
+    fn draw_discard_test() {
+        let red = |number| Card::new(number, Color::Red);
+        let mut game = game_with_trays(&[red(7), Card::new(2, Color::Blue)], &[red(5), red(6), Card::new(9, Color::Black)]);
+        assert_eq!(game.apply_move(Move::Discard(red(7))), Err(MoveError::NoDiscardPile));
+        game.rules.variant = VariantRules::DrawDiscard;
+
+        assert_eq!(game.apply_move(Move::PlayNewMeld(vec![red(7)])), Err(MoveError::MustDrawFirst));
+        assert_eq!(game.apply_move(Move::TakeDiscard), Err(MoveError::DiscardPileEmpty));
+        game.apply_move(Move::Draw).unwrap();
+        assert_eq!(game.apply_move(Move::EndTurn), Err(MoveError::MustDiscard));
+        assert_eq!(game.apply_move(Move::Discard(red(8))), Err(MoveError::NotInTray(red(8))));
+        game.apply_move(Move::Discard(red(7))).unwrap();
+        assert_eq!(game.discards(), &[red(7)]);
+        assert_eq!(game.players()[0].tray().len(), 2);
+        assert_eq!(game.current_player_index(), 1);
+
+        // Bo takes the 7 and lays it down right away
+        game.apply_move(Move::TakeDiscard).unwrap();
+        assert!(game.discards().is_empty());
+        assert_eq!(game.view_for(0).players[1].tile_count, 4);
+        let meld = game.apply_move(Move::PlayNewMeld(vec![red(5), red(6), red(7)])).unwrap().meld.unwrap();
+        assert_eq!(game.table().meld(meld), Some(&[red(5), red(6), red(7)][..]));
+        assert_eq!(game.apply_move(Move::Draw), Err(MoveError::AlreadyDrew));
+        game.undo().unwrap();
+        game.undo().unwrap();
+        assert_eq!(game.discards(), &[red(7)]);
+        game.redo().unwrap();
+        game.redo().unwrap();
+
+        // Discarding the last tile goes out
+        let outcome = game.apply_move(Move::Discard(Card::new(9, Color::Black))).unwrap();
+        assert!(outcome.gone_out);
+        assert_eq!(game.round_result().map(|result| result.winner), Some(1));
+        assert_eq!(total_tiles(&game) + game.discards().len(), 106);
+    }
+
+    #[test]
+    fn save_round_trip_with_discards_test() {
+        let rules = RulesConfig { variant: VariantRules::DrawDiscard, ..RulesConfig::default() };
+        let mut game = GameState::new_game_with_rules(&["Ann", "Bo"], 6, 0, rules);
+        game.apply_move(Move::Draw).unwrap();
+        let discard = game.current_player().tray()[0];
+        game.apply_move(Move::Discard(discard)).unwrap();
+
+        let resumed = GameState::from_save(GameSave::from_json(&game.to_save().to_json()).unwrap()).unwrap();
+        assert_eq!(resumed.discards(), &[discard]);
+        assert_eq!(resumed.current_player_index(), 1);
+        assert_eq!(resumed.to_save(), game.to_save());
+    }
+
+    #[test]
+    fn draw_discard_empty_deck_test() {
+        let mut game = game_with_trays(&[Card::new(7, Color::Red), Card::new(2, Color::Blue)], &[Card::new(9, Color::Black)]);
+        game.rules.variant = VariantRules::DrawDiscard;
+        game.deck = Deck::from_cards(vec![Card::new(12, Color::Yellow)]);
+        game.apply_move(Move::Draw).unwrap();
+
+        // The round ends with the discard, the lowest tray winning
+        game.apply_move(Move::Discard(Card::new(7, Color::Red))).unwrap();
+        let result = game.round_result().unwrap();
+        assert_eq!(result.winner, 1);
+        assert_eq!(result.penalties, vec![(0, 9 - 14)]);
+    }
+
+    #[test]
+    fn skip_turn_test() {
+        let run = vec![Card::new(4, Color::Red), Card::new(5, Color::Red), Card::new(6, Color::Red)];
+        let mut game = game_with_tray(&[run.clone(), vec![Card::new(9, Color::Blue)]].concat());
+        game.apply_move(Move::PlayNewMeld(run)).unwrap();
+
+        // The meld goes back to the tray and a tile is drawn instead
+        game.skip_turn();
+        assert!(game.table().is_empty());
+        assert_eq!(game.players()[0].tray().len(), 5);
+        assert_eq!(game.current_player_index(), 1);
+        let moves: Vec<&Move> = game.events().iter().map(|event| &event.mv).collect();
+        assert_eq!(moves, vec![&Move::Draw, &Move::EndTurn]);
+
+        // With the deck empty the player passes
+        game.deck.cards.clear();
+        game.skip_turn();
+        assert_eq!(game.current_player_index(), 0);
+        assert_eq!(game.players()[1].tray().len(), 0);
+    }
+
+    #[test]
+    fn new_move_clears_redo_test() {
+        let mut game = GameState::new_game(&["Ann", "Bo"], 9);
+        game.apply_move(Move::Draw).unwrap();
+        game.apply_move(Move::EndTurn).unwrap();
+
+        game.undo().unwrap();
+        game.apply_move(Move::EndTurn).unwrap();
+        assert_eq!(game.redo(), Err(UndoError::NothingToRedo));
+
+        // A refused move isn't recorded
+        assert!(game.apply_move(Move::EndTurn).is_err());
+        game.undo().unwrap();
+        assert_eq!(game.current_player_index(), 0);
+    }
+
+    #[test]
+    fn history_depth_test() {
+        let mut game = GameState::new_game(&["Ann", "Bo"], 9);
+        game.set_history_depth(2);
+        for _ in 0..3 {
+            game.apply_move(Move::Draw).unwrap();
+            game.apply_move(Move::EndTurn).unwrap();
+        }
+
+        assert!(game.undo().is_ok() && game.undo().is_ok());
+        assert_eq!(game.undo(), Err(UndoError::NothingToUndo));
+        assert_eq!(game.deck_len(), 76);
+
+        game.set_history_depth(0);
+        game.apply_move(Move::Draw).unwrap();
+        assert_eq!(game.undo(), Err(UndoError::NothingToUndo));
+    }
+
+    #[test]
+    fn view_for_test() {
+        let mut game = GameState::new_game(&["Ann", "Bo"], 9);
+        let drawn = game.apply_move(Move::Draw).unwrap().drawn;
+        game.apply_move(Move::EndTurn).unwrap();
+
+        let ann = game.view_for(0);
+        let bo = game.view_for(1);
+
+        assert_eq!(ann.tray, game.players()[0].tray());
+        assert_eq!(bo.tray, game.players()[1].tray());
+        assert_eq!(bo.players[0], PlayerSummary { name: "Ann", tile_count: 15, has_melded: false });
+        assert_eq!(ann.events[0].drawn, drawn);
+        assert_eq!(bo.events[0].drawn, None);
+        assert_eq!(bo.events[0].mv, Move::Draw);
+        assert_eq!((bo.deck_len, bo.turn_number), (77, 1));
+    }
+
+    // Every string of the JSON, keys included
+    fn json_strings(value: &serde_json::Value, strings: &mut Vec<String>) {
+        match value {
+            serde_json::Value::String(string) => strings.push(string.clone()),
+            serde_json::Value::Array(values) => values.iter().for_each(|v| json_strings(v, strings)),
+            serde_json::Value::Object(map) => map.iter().for_each(|(key, v)| {
+                strings.push(key.clone());
+                json_strings(v, strings);
+            }),
+            _ => {}
+        }
+    }
+
+    // The tiles of the trays whose codes show up nowhere public, that no view may hold
+    fn hidden_codes(game: &GameState, trays: &[PlayerId]) -> Vec<String> {
+        let public: Vec<String> = game.table.melds().flat_map(|(_, cards)| cards.iter().map(Card::code)).collect();
+        trays.iter()
+            .flat_map(|player| game.players[*player].tray())
+            .map(Card::code)
+            .filter(|code| !public.contains(code))
+            .collect()
+    }
+
+    #[test]
+    fn serialized_views_test() {
+        let ann = [Card::new(1, Color::Red), Card::new(2, Color::Red), Card::new(3, Color::Red), Card::new(5, Color::Blue)];
+        let bo = [Card::new(13, Color::Black), Card::new(12, Color::Yellow)];
+        let mut game = game_with_trays(&ann, &bo);
+        game.apply_move(Move::PlayNewMeld(ann[..3].to_vec())).unwrap();
+        game.apply_move(Move::EndTurn).unwrap();
+        let bo_drawn = game.apply_move(Move::Draw).unwrap().drawn.unwrap();
+        game.apply_move(Move::EndTurn).unwrap();
+        let ann_drawn = game.apply_move(Move::Draw).unwrap().drawn.unwrap();
+
+        let spectator = serde_json::to_value(game.spectator_view()).unwrap();
+        let mut strings = vec![];
+        json_strings(&spectator, &mut strings);
+        for code in hidden_codes(&game, &[0, 1]) {
+            assert!(!strings.contains(&code), "{} in {}", code, spectator);
+        }
+        assert!(strings.contains(&"R2".to_string()));
+        assert!(spectator["events"].as_array().unwrap().iter().all(|event| event["drawn"].is_null()));
+        assert!(spectator.get("tray").is_none());
+        assert_eq!(spectator["players"][1]["tile_count"], 3);
+
+        let view = serde_json::to_value(game.view_for(0)).unwrap();
+        let mut strings = vec![];
+        json_strings(&view, &mut strings);
+        let own: Vec<String> = game.players[0].tray().iter().map(Card::code).collect();
+        for code in hidden_codes(&game, &[1]).into_iter().filter(|code| !own.contains(code)) {
+            assert!(!strings.contains(&code), "{} in {}", code, view);
+        }
+        assert!(strings.contains(&"B5".to_string()));
+        assert_eq!(view["events"][2]["drawn"], serde_json::Value::Null);
+        assert_eq!(view["events"][4]["drawn"], ann_drawn.code());
+        // The private log still knows
+        assert_eq!(game.events()[2].drawn, Some(bo_drawn));
+    }
+
+}
@@ -0,0 +1,165 @@
+use std::collections::BTreeSet;
+use super::{best_partition, can_go_out, find_opening_melds, set_points, validate_set, Card, Move, PlayerView, Tray};
+use super::opening::OPENING_POINTS;
+use super::strategy::TurnSoFar;
+
+/// A move suggested by suggest_moves, with what it is worth
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoredMove {
+    pub mv: Move,
+    /// Face value of the tiles laid down, wildcards worth the number they stand for
+    pub points: u32,
+    /// How many tiles leave the tray, 0 when a wildcard is taken back
+    pub tiles: usize,
+    /// Whether the rest of the tray can then be laid down in new sets, or nothing is left
+    pub goes_out: bool
+}
+
+/// The moves the player of the view can play now, best first: the ones that let them go
+/// out, then the ones worth the most points, then the ones playing the most tiles. New melds
+/// come from the best partition of the tray, before opening only when they add up to 30
+/// points with the melds played this turn. Once opened, the tiles that fit at the end of a
+/// table meld and the wildcards the tray can take back are suggested as well. Drawing, or
+/// ending the turn, always comes last.
+pub fn suggest_moves(view: &PlayerView) -> Vec<ScoredMove> {
+    let turn = TurnSoFar::of(view);
+    if turn.drew {
+        return vec![ScoredMove { mv: Move::EndTurn, points: 0, tiles: 0, goes_out: false }];
+    }
+
+    let mut moves: Vec<ScoredMove> = new_melds(view, &turn).into_iter()
+        .map(|set| {
+            let points = set_points(&set.iter().collect::<Vec<&Card>>());
+            let rest = without(view.tray, &set);
+            ScoredMove { tiles: set.len(), points, goes_out: can_go_out(&rest).is_some(), mv: Move::PlayNewMeld(set) }
+        })
+        .collect();
+
+    if view.has_melded {
+        let tiles: BTreeSet<Card> = view.tray.iter().copied().collect();
+        for card in &tiles {
+            let rest = without(view.tray, &[*card]);
+            for (meld, cards) in view.table.melds() {
+                let mut extended = cards.to_vec();
+                extended.push(*card);
+                if validate_set(&extended).is_ok() {
+                    let before = set_points(&cards.iter().collect::<Vec<&Card>>());
+                    let points = set_points(&extended.iter().collect::<Vec<&Card>>()).saturating_sub(before);
+                    moves.push(ScoredMove { mv: Move::ExtendMeld { meld, card: *card }, points, tiles: 1, goes_out: can_go_out(&rest).is_some() });
+                }
+                if cards.contains(&Card::Wildcard) && view.table.clone().replace_wildcard(meld, *card).is_ok() {
+                    let mut rest = rest.clone();
+                    rest.push(Card::Wildcard);
+                    let points = card.number().map(|n| *n as u32).unwrap_or(0);
+                    moves.push(ScoredMove { mv: Move::RetrieveWildcard { meld, card: *card }, points, tiles: 0, goes_out: can_go_out(&rest).is_some() });
+                }
+            }
+        }
+    }
+
+    // sort_by_key is stable: ties keep the order they were found in
+    moves.sort_by_key(|scored| std::cmp::Reverse((scored.goes_out, scored.points, scored.tiles)));
+    moves.push(ScoredMove { mv: turn.end_or_draw(view), points: 0, tiles: 0, goes_out: false });
+    moves
+}
+
+// The sets worth laying down. Before opening, those of the best partition if they are enough,
+// otherwise those of the opening worth the most points.
+fn new_melds(view: &PlayerView, turn: &TurnSoFar) -> Vec<Vec<Card>> {
+    let partition = best_partition(view.tray).sets;
+    if view.has_melded {
+        return partition;
+    }
+    let played: u32 = turn.played.iter()
+        .map(|set| set_points(&set.iter().collect::<Vec<&Card>>()))
+        .sum();
+    let points: u32 = partition.iter()
+        .map(|set| set_points(&set.iter().collect::<Vec<&Card>>()))
+        .sum();
+    if played >= OPENING_POINTS || played + points >= OPENING_POINTS {
+        partition
+    } else if played == 0 {
+        find_opening_melds(view.tray).into_iter().next().unwrap_or_default()
+    } else {
+        vec![]
+    }
+}
+
+// The tray once one copy of every card is taken out
+fn without(tray: &[Card], cards: &[Card]) -> Vec<Card> {
+    let mut rest = Tray::from(tray.to_vec());
+    for card in cards {
+        rest.remove_one(card);
+    }
+    rest.into()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::{Color, Table};
+
+    fn view<'a>(tray: &'a [Card], table: &'a Table, has_melded: bool) -> PlayerView<'a> {
+        PlayerView { player: 0, tray, has_melded, table, deck_len: 50, discards: &[], players: vec![], events: vec![], turn_number: 0 }
+    }
+
+    #[test]
+    fn extension_ranks_first_test() {
+        let tray = vec![
+            Card::new(4, Color::Red),
+            Card::new(5, Color::Red),
+            Card::new(6, Color::Red),
+            Card::new(9, Color::Blue)
+        ];
+        let table = Table::from_melds(vec![vec![Card::new(6, Color::Blue), Card::new(7, Color::Blue), Card::new(8, Color::Blue)]]);
+        let meld = table.melds().next().unwrap().0;
+
+        let moves = suggest_moves(&view(&tray, &table, true));
+
+        // Playing the run is worth more, but only the extension lets the run go out next
+        assert_eq!(moves, vec![
+            ScoredMove { mv: Move::ExtendMeld { meld, card: Card::new(9, Color::Blue) }, points: 9, tiles: 1, goes_out: true },
+            ScoredMove { mv: Move::PlayNewMeld(tray[..3].to_vec()), points: 15, tiles: 3, goes_out: false },
+            ScoredMove { mv: Move::Draw, points: 0, tiles: 0, goes_out: false }
+        ]);
+    }
+
+    #[test]
+    fn wildcard_retrieval_hint_test() {
+        let tray = vec![Card::new(5, Color::Red), Card::new(1, Color::Black)];
+        let table = Table::from_melds(vec![vec![Card::new(4, Color::Red), Card::Wildcard, Card::new(6, Color::Red)]]);
+        let meld = table.melds().next().unwrap().0;
+
+        let moves = suggest_moves(&view(&tray, &table, true));
+
+        assert!(moves.contains(&ScoredMove { mv: Move::RetrieveWildcard { meld, card: Card::new(5, Color::Red) }, points: 5, tiles: 0, goes_out: false }));
+        assert!(moves.iter().all(|scored| scored.mv != Move::RetrieveWildcard { meld, card: Card::new(1, Color::Black) }));
+    }
+
+    #[test]
+    fn opening_hints_test() {
+        let table = Table::from_melds(vec![vec![Card::new(6, Color::Blue), Card::new(7, Color::Blue), Card::new(8, Color::Blue)]]);
+        let low = vec![
+            Card::new(1, Color::Blue),
+            Card::new(2, Color::Blue),
+            Card::new(3, Color::Blue),
+            Card::new(9, Color::Blue)
+        ];
+        let mut high = low.clone();
+        high.extend([Card::new(10, Color::Red), Card::new(11, Color::Red), Card::new(12, Color::Red)]);
+
+        // 6 points are not enough, and table melds can't be touched yet
+        assert_eq!(suggest_moves(&view(&low, &table, false)), vec![ScoredMove { mv: Move::Draw, points: 0, tiles: 0, goes_out: false }]);
+
+        let moves = suggest_moves(&view(&high, &table, false));
+        let melds: Vec<&Move> = moves.iter().map(|scored| &scored.mv).collect();
+        assert_eq!(melds, vec![
+            &Move::PlayNewMeld(high[4..].to_vec()),
+            &Move::PlayNewMeld(low[..3].to_vec()),
+            &Move::Draw
+        ]);
+        assert_eq!((moves[0].points, moves[1].points), (33, 6));
+    }
+
+}
@@ -0,0 +1,194 @@
+use super::{resolve_wildcards, resolve_wildcards_with, Card, Color, MeldId, Table};
+use crate::prelude::*;
+
+// The look of the tiles, one rule per color, then how a hovered set shows its tiles
+const STYLE: &str = concat!(
+    ".rummikub { font-family: sans-serif; }\n",
+    ".rummikub .meld, .rummikub .tray, .rummikub .set { display: flex; flex-wrap: wrap; gap: 4px; margin: 4px 0; }\n",
+    ".rummikub .tile { display: inline-block; min-width: 2em; padding: 6px 4px; border-radius: 4px; text-align: center; font-weight: bold; color: #fff; }\n",
+    ".rummikub .red { background: #d32f2f; }\n",
+    ".rummikub .blue { background: #1976d2; }\n",
+    ".rummikub .black { background: #212121; }\n",
+    ".rummikub .yellow { background: #f9a825; color: #212121; }\n",
+    ".rummikub .green { background: #388e3c; }\n",
+    ".rummikub .orange { background: #ef6c00; }\n",
+    ".rummikub .wildcard { background: #fff; color: #8e24aa; border: 2px dashed #8e24aa; }\n",
+    ".rummikub .joker { background: #fff; color: #212121; border: 2px dashed #212121; }\n",
+    ".rummikub .set { cursor: default; }\n",
+    ".rummikub .set:hover { outline: 2px solid #8e24aa; }\n"
+);
+
+/// The position as a self-contained HTML snippet, styled without JavaScript: the melds of the
+/// table one per row, then the tiles of the hand. Tiles are spans colored by their color,
+/// wildcards marked as such, with what they stand for in a meld as their title.
+pub fn render_html(table: &Table, hand: &[Card]) -> String {
+    render_html_with_sets(table, hand, &[])
+}
+
+/// Same as render_html, followed by the sets, one per row. Hovering a set outlines the tiles
+/// of the table and of the hand it is made of, which carry the indices of their sets in a
+/// data-sets attribute. The tiles of a set are matched to copies in the position, those of
+/// the table first and those no earlier set was matched to before them, and a tile the
+/// position doesn't hold is only shown in the set.
+pub fn render_html_with_sets(table: &Table, hand: &[Card], sets: &[Vec<Card>]) -> String {
+    let melds: Vec<(MeldId, &[Card])> = table.melds().collect();
+    let position: Vec<&Card> = melds.iter().flat_map(|(_, cards)| cards.iter()).chain(hand).collect();
+    let mut in_sets: Vec<Vec<usize>> = vec![vec![]; position.len()];
+    for (set_idx, set) in sets.iter().enumerate() {
+        let mut used = vec![false; position.len()];
+        for card in set {
+            let free = |idx: &usize| !used[*idx] && position[*idx] == card;
+            // A copy no earlier set took goes first, so sets sharing no tile show none
+            let unclaimed = (0..position.len()).find(|idx| free(idx) && in_sets[*idx].is_empty());
+            if let Some(idx) = unclaimed.or_else(|| (0..position.len()).find(free)) {
+                used[idx] = true;
+                in_sets[idx].push(set_idx);
+            }
+        }
+    }
+
+    let mut html = String::from("<div class=\"rummikub\">\n<style>\n");
+    html += STYLE;
+    for set_idx in 0..sets.len() {
+        html += &format!(
+            ".rummikub:has(.set[data-set=\"{0}\"]:hover) .tile[data-sets~=\"{0}\"] {{ outline: 3px solid #8e24aa; }}\n",
+            set_idx
+        );
+    }
+    html += "</style>\n<section class=\"table\">\n<h3>Table</h3>\n";
+    if melds.is_empty() {
+        html += "<p class=\"empty\">(empty table)</p>\n";
+    }
+    let mut tile_idx = 0;
+    for (id, cards) in &melds {
+        let values = resolve_wildcards_with(&cards.iter().collect::<Vec<&Card>>(), table.rules());
+        html += &format!("<div class=\"meld\" data-meld=\"{}\">", escape_html(&id.to_string()));
+        for (idx, card) in cards.iter().enumerate() {
+            let value = values.as_ref().map(|values| values[idx]);
+            html += &tile_html(card, value, &in_sets[tile_idx]);
+            tile_idx += 1;
+        }
+        html += "</div>\n";
+    }
+    html += "</section>\n<section class=\"hand\">\n<h3>Hand</h3>\n<div class=\"tray\">";
+    for card in hand {
+        html += &tile_html(card, None, &in_sets[tile_idx]);
+        tile_idx += 1;
+    }
+    html += "</div>\n</section>\n";
+
+    if !sets.is_empty() {
+        html += "<section class=\"sets\">\n<h3>Sets</h3>\n";
+        for (set_idx, set) in sets.iter().enumerate() {
+            let values = resolve_wildcards(&set.iter().collect::<Vec<&Card>>());
+            html += &format!("<div class=\"set\" data-set=\"{}\">", set_idx);
+            for (idx, card) in set.iter().enumerate() {
+                html += &tile_html(card, values.as_ref().map(|values| values[idx]), &[]);
+            }
+            html += "</div>\n";
+        }
+        html += "</section>\n";
+    }
+    html += "</div>\n";
+    html
+}
+
+// A tile as a span, value being what a wildcard stands for
+fn tile_html(card: &Card, value: Option<(i8, Color)>, sets: &[usize]) -> String {
+    let class = match card {
+        Card::Numbered { color, .. } => color_class(*color),
+        Card::Wildcard => "wildcard",
+        Card::DoubleJoker | Card::MirrorJoker => "joker"
+    };
+    let mut attributes = format!("class=\"tile {}\"", class);
+    if let (Card::Wildcard, Some((number, color))) = (card, value) {
+        attributes += &format!(" title=\"{}\"", escape_html(&Card::new(number, color).code()));
+    }
+    if !sets.is_empty() {
+        let indices: Vec<String> = sets.iter().map(|idx| idx.to_string()).collect();
+        attributes += &format!(" data-sets=\"{}\"", indices.join(" "));
+    }
+    format!("<span {}>{}</span>", attributes, escape_html(&card.code()))
+}
+
+fn color_class(color: Color) -> &'static str {
+    match color {
+        Color::Red => "red",
+        Color::Blue => "blue",
+        Color::Black => "black",
+        Color::Yellow => "yellow",
+        Color::Green => "green",
+        Color::Orange => "orange"
+    }
+}
+
+// The text is all generated, but goes through this all the same
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped += "&amp;",
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            '"' => escaped += "&quot;",
+            '\'' => escaped += "&#39;",
+            _ => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn position() -> (Table, Vec<Card>) {
+        let table = Table::from_melds(vec![
+            vec![Card::new(4, Color::Red), Card::Wildcard, Card::new(6, Color::Red)],
+            vec![Card::new(9, Color::Yellow), Card::new(9, Color::Blue), Card::new(9, Color::Black)]
+        ]);
+        (table, vec![Card::new(7, Color::Red), Card::new(13, Color::Black)])
+    }
+
+    #[test]
+    fn render_html_test() {
+        let (table, hand) = position();
+        let html = render_html(&table, &hand);
+
+        assert!(html.starts_with("<div class=\"rummikub\">\n<style>\n"));
+        assert!(html.ends_with("</div>\n"));
+        assert!(html.contains(".rummikub .red { background: #d32f2f; }"));
+        assert!(html.contains(".rummikub .yellow { background: #f9a825; color: #212121; }"));
+        assert!(html.contains(concat!(
+            "<div class=\"meld\" data-meld=\"#0\"><span class=\"tile red\">R4</span>",
+            "<span class=\"tile wildcard\" title=\"R5\">W</span><span class=\"tile red\">R6</span></div>\n"
+        )));
+        assert!(html.contains("<span class=\"tile yellow\">Y9</span>"));
+        // The table comes before the hand
+        let hand_at = html.find("<section class=\"hand\">").unwrap();
+        assert!(html.find("<section class=\"table\">").unwrap() < hand_at);
+        assert!(html[hand_at..].contains("<div class=\"tray\"><span class=\"tile red\">R7</span><span class=\"tile black\">K13</span></div>"));
+        assert!(!html.contains("class=\"sets\""));
+        assert!(render_html(&Table::new(), &[]).contains("(empty table)"));
+    }
+
+    #[test]
+    fn render_html_with_sets_test() {
+        let (table, hand) = position();
+        let sets = vec![
+            vec![Card::new(7, Color::Red), Card::new(8, Color::Red), Card::Wildcard],
+            vec![Card::new(4, Color::Red), Card::new(7, Color::Red)]
+        ];
+        let html = render_html_with_sets(&table, &hand, &sets);
+
+        assert!(html.contains(".rummikub:has(.set[data-set=\"1\"]:hover) .tile[data-sets~=\"1\"] { outline: 3px solid #8e24aa; }"));
+        assert!(html.contains("<span class=\"tile red\" data-sets=\"1\">R4</span>"));
+        assert!(html.contains("<span class=\"tile wildcard\" title=\"R5\" data-sets=\"0\">W</span>"));
+        assert!(html.contains("<span class=\"tile red\" data-sets=\"0 1\">R7</span>"));
+        assert!(html.contains("<span class=\"tile black\">K13</span>"));
+        assert!(html.contains("<div class=\"set\" data-set=\"0\"><span class=\"tile red\">R7</span><span class=\"tile red\">R8</span><span class=\"tile wildcard\" title=\"R9\">W</span></div>"));
+        assert_eq!(escape_html("<W & \"R5\">"), "&lt;W &amp; &quot;R5&quot;&gt;");
+    }
+
+}
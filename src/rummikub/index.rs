@@ -0,0 +1,190 @@
+use alloc::collections::BTreeMap;
+use super::{Card, Color, SetSearchOptions};
+use crate::prelude::*;
+
+/// A tray kept grouped by color and number, so that valid sets can be searched again after
+/// every draw or play without grouping the whole tray from scratch
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrayIndex {
+    // Every copy of a numbered tile, in the order they were added
+    tiles: BTreeMap<(Color, i8), Vec<Card>>,
+    wildcards: Vec<Card>,
+    // The jokers of the Twist rules, kept but left out of the set searches
+    jokers: Vec<Card>,
+    len: usize
+}
+
+impl TrayIndex {
+    pub fn new(cards: &[Card]) -> TrayIndex {
+        let mut index = TrayIndex::default();
+        for card in cards {
+            index.add_card(*card);
+        }
+        index
+    }
+
+    pub fn add_card(&mut self, card: Card) {
+        match card {
+            Card::Numbered { number, color } => self.tiles.entry((color, number)).or_default().push(card),
+            Card::Wildcard => self.wildcards.push(card),
+            Card::DoubleJoker | Card::MirrorJoker => self.jokers.push(card)
+        }
+        self.len += 1;
+    }
+
+    /// Removes one copy of the card, returning false when the tray doesn't hold it
+    pub fn remove_card(&mut self, card: &Card) -> bool {
+        let removed = match card {
+            Card::Numbered { number, color } => match self.tiles.get_mut(&(*color, *number)) {
+                Some(copies) => {
+                    copies.pop();
+                    if copies.is_empty() {
+                        self.tiles.remove(&(*color, *number));
+                    }
+                    true
+                },
+                None => false
+            },
+            Card::Wildcard => self.wildcards.pop().is_some(),
+            Card::DoubleJoker | Card::MirrorJoker => match self.jokers.iter().position(|joker| joker == card) {
+                Some(idx) => {
+                    self.jokers.remove(idx);
+                    true
+                },
+                None => false
+            }
+        };
+        if removed {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn wildcard_count(&self) -> usize {
+        self.wildcards.len()
+    }
+
+    pub fn count(&self, card: &Card) -> usize {
+        match card {
+            Card::Numbered { number, color } => self.tiles.get(&(*color, *number)).map_or(0, |c| c.len()),
+            Card::Wildcard => self.wildcards.len(),
+            Card::DoubleJoker | Card::MirrorJoker => self.jokers.iter().filter(|joker| *joker == card).count()
+        }
+    }
+
+    /// The tiles of the tray, sorted
+    pub fn cards(&self) -> Vec<Card> {
+        let mut cards: Vec<Card> = self.tiles.values().flatten().chain(&self.wildcards).chain(&self.jokers).copied().collect();
+        cards.sort();
+        cards
+    }
+
+    fn tile(&self, number: i8, color: Color, copy: usize) -> Option<&Card> {
+        self.tiles.get(&(color, number)).and_then(|c| c.get(copy))
+    }
+}
+
+/// Same as valid_sets, on the tiles held by the index
+pub fn valid_sets_from_index(index: &TrayIndex) -> Vec<Vec<&Card>> {
+    let wildcards: Vec<&Card> = index.wildcards.iter().collect();
+    super::valid_sets_in(
+        &|number, color, copy| index.tile(number, color, copy),
+        &wildcards,
+        &SetSearchOptions::default()
+    )
+}
+
+/// Same as has_valid_set, on the tiles held by the index
+pub fn has_valid_set_from_index(index: &TrayIndex) -> bool {
+    let wildcards: Vec<&Card> = index.wildcards.iter().collect();
+    super::has_valid_set_in(
+        &|number, color, copy| index.tile(number, color, copy),
+        &wildcards,
+        &SetSearchOptions::default()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rand::prelude::*;
+    use super::*;
+    use super::super::{valid_sets, Deck};
+
+    fn owned(sets: Vec<Vec<&Card>>) -> Vec<Vec<Card>> {
+        sets.into_iter().map(|s| s.into_iter().copied().collect()).collect()
+    }
+
+    #[test]
+    fn tray_index_add_remove_test() {
+        let mut index = TrayIndex::new(&[
+            Card::new(4, Color::Red),
+            Card::new(4, Color::Red),
+            Card::Wildcard
+        ]);
+
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.count(&Card::new(4, Color::Red)), 2);
+        assert!(index.remove_card(&Card::new(4, Color::Red)));
+        assert!(!index.remove_card(&Card::new(5, Color::Red)));
+        assert!(index.remove_card(&Card::Wildcard));
+        assert!(!index.remove_card(&Card::Wildcard));
+        index.add_card(Card::new(1, Color::Blue));
+
+        assert_eq!(index.cards(), vec![Card::new(1, Color::Blue), Card::new(4, Color::Red)]);
+        assert_eq!(index.wildcard_count(), 0);
+        assert_eq!(index.len(), 2);
+    }
+
+    #[test]
+    fn valid_sets_from_index_test() {
+        let test_tray = vec![
+            Card::new(7, Color::Red),
+            Card::new(7, Color::Blue),
+            Card::new(7, Color::Black),
+            Card::new(8, Color::Red),
+            Card::new(9, Color::Red),
+            Card::Wildcard
+        ];
+
+        let index = TrayIndex::new(&test_tray);
+
+        assert_eq!(owned(valid_sets_from_index(&index)), owned(valid_sets(&test_tray)));
+    }
+
+    #[test]
+    fn valid_sets_from_index_interleaved_test() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut deck = Deck::new_seeded(7);
+        let mut tray = deck.pick_tray(14);
+        let mut index = TrayIndex::new(&tray);
+
+        for _ in 0..200 {
+            // Draw more often than play, so that the tray keeps growing
+            if tray.is_empty() || rng.gen_bool(0.6) {
+                match deck.pick_card() {
+                    Some(card) => {
+                        tray.push(card);
+                        index.add_card(card);
+                    },
+                    None => break
+                }
+            } else {
+                let card = tray.swap_remove(rng.gen_range(0..tray.len()));
+                assert!(index.remove_card(&card));
+            }
+
+            assert_eq!(index.len(), tray.len());
+            assert_eq!(owned(valid_sets_from_index(&index)), owned(valid_sets(&tray)), "{:?}", tray);
+        }
+    }
+
+}
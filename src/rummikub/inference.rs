@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+use super::{Card, Deck, GameEvent, Move, PlayerId, PlayerView, Table};
+
+/// What a player can tell about the tiles they can't see. Every tile out of their tray and
+/// off the table is either in the deck or in the tray of another player, and is taken to be
+/// anywhere among those with the same chance. Kept up to date with observe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inference {
+    me: PlayerId,
+    // Copies of every tile not seen yet
+    unseen: BTreeMap<Card, usize>,
+    // Tiles in the tray of every player, 0 for the own one
+    tile_counts: Vec<usize>,
+    // The tiles known to be in the tray of every player, like a wildcard taken from the table
+    known: Vec<Vec<Card>>,
+    // The discard pile of VariantRules::DrawDiscard, the last discard last
+    discards: Vec<Card>,
+    deck_len: usize
+}
+
+impl Inference {
+    /// What player me knows from their tray, the table, how many tiles every player has and
+    /// how many are left in the deck
+    pub fn new(me: PlayerId, tray: &[Card], table: &Table, tile_counts: &[usize], deck_len: usize) -> Inference {
+        let mut unseen: BTreeMap<Card, usize> = BTreeMap::new();
+        for card in Deck::new().cards {
+            *unseen.entry(card).or_default() += 1;
+        }
+        let mut tile_counts = tile_counts.to_vec();
+        tile_counts[me] = 0;
+        let mut inference = Inference {
+            me,
+            unseen,
+            known: vec![vec![]; tile_counts.len()],
+            tile_counts,
+            discards: vec![],
+            deck_len
+        };
+        for card in tray.iter().chain(table.melds().flat_map(|(_, cards)| cards)) {
+            inference.see(card);
+        }
+        inference
+    }
+
+    /// What the player of the view knows
+    pub fn from_view(view: &PlayerView) -> Inference {
+        let tile_counts: Vec<usize> = view.players.iter().map(|player| player.tile_count).collect();
+        let mut inference = Inference::new(view.player, view.tray, view.table, &tile_counts, view.deck_len);
+        for card in view.discards {
+            inference.see(card);
+        }
+        inference.discards = view.discards.to_vec();
+        inference
+    }
+
+    /// Takes in a move played after the inference was made. Draws by the others only tell
+    /// that their tray grew, tiles laid down or discarded are seen.
+    pub fn observe(&mut self, event: &GameEvent) {
+        let player = event.player;
+        match &event.mv {
+            Move::TakeDiscard => {
+                let card = self.discards.pop();
+                if let (Some(card), false) = (card, player == self.me) {
+                    self.tile_counts[player] += 1;
+                    self.known[player].push(card);
+                }
+            },
+            Move::Discard(card) => {
+                self.discards.push(*card);
+                if player != self.me {
+                    self.laid_down(player, card);
+                }
+            },
+            Move::Draw => {
+                self.deck_len = self.deck_len.saturating_sub(1);
+                if player == self.me {
+                    if let Some(card) = &event.drawn {
+                        self.see(card);
+                    }
+                } else {
+                    self.tile_counts[player] += 1;
+                }
+            },
+            _ if player == self.me => {},
+            Move::PlayNewMeld(cards) => {
+                for card in cards {
+                    self.laid_down(player, card);
+                }
+            },
+            Move::ExtendMeld { card, .. } => self.laid_down(player, card),
+            Move::RetrieveWildcard { card, .. } => {
+                self.laid_down(player, card);
+                self.tile_counts[player] += 1;
+                self.known[player].push(Card::Wildcard);
+            },
+            Move::EndTurn | Move::Mulligan(_) => {}
+        }
+    }
+
+    /// The chance that a copy of card the player me can't see is in the tray of player. It is 0
+    /// for player me, and when both copies have been seen.
+    pub fn probability_opponent_holds(&self, player: PlayerId, card: &Card) -> f64 {
+        if player == self.me || self.unseen(card) == 0 {
+            return 0.0;
+        }
+        self.share(self.hidden_tiles(player))
+    }
+
+    /// How many copies of card are expected to be left in the deck
+    pub fn expected_tiles_remaining(&self, card: &Card) -> f64 {
+        self.unseen(card) as f64 * self.share(self.deck_len)
+    }
+
+    /// Copies of card the player me hasn't seen
+    pub fn unseen(&self, card: &Card) -> usize {
+        self.unseen.get(card).copied().unwrap_or(0)
+    }
+
+    /// All the tiles the player me hasn't seen
+    pub fn unseen_tiles(&self) -> usize {
+        self.unseen.values().sum()
+    }
+
+    // How much of the unseen tiles a place holding that many of them has
+    fn share(&self, tiles: usize) -> f64 {
+        let total: usize = self.deck_len + (0..self.tile_counts.len()).map(|p| self.hidden_tiles(p)).sum::<usize>();
+        if total == 0 { 0.0 } else { tiles as f64 / total as f64 }
+    }
+
+    // The tiles in the tray of another player that can be any of the unseen ones
+    fn hidden_tiles(&self, player: PlayerId) -> usize {
+        if player == self.me {
+            0
+        } else {
+            self.tile_counts[player].saturating_sub(self.known[player].len())
+        }
+    }
+
+    fn see(&mut self, card: &Card) {
+        if let Some(count) = self.unseen.get_mut(card) {
+            *count = count.saturating_sub(1);
+        }
+    }
+
+    // A tile that another player moved from their tray to the table
+    fn laid_down(&mut self, player: PlayerId, card: &Card) {
+        self.tile_counts[player] = self.tile_counts[player].saturating_sub(1);
+        match self.known[player].iter().position(|known| known == card) {
+            Some(idx) => {
+                self.known[player].remove(idx);
+            },
+            None => self.see(card)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::{Color, GameState};
+
+    // The chances of all the places an unseen tile can be in add up to one
+    fn total_probability(inference: &Inference, players: usize) -> f64 {
+        Deck::new().cards.iter()
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .map(|card| {
+                let held: f64 = (0..players).map(|p| inference.probability_opponent_holds(p, card)).sum();
+                inference.unseen(card) as f64 * held + inference.expected_tiles_remaining(card)
+            })
+            .sum()
+    }
+
+    #[test]
+    fn seen_tile_test() {
+        let tray = vec![Card::new(5, Color::Blue), Card::new(9, Color::Red)];
+        let table = Table::from_melds(vec![vec![
+            Card::new(4, Color::Blue),
+            Card::new(5, Color::Blue),
+            Card::new(6, Color::Blue)
+        ]]);
+
+        let inference = Inference::new(0, &tray, &table, &[2, 14, 14], 73);
+
+        for player in 0..3 {
+            assert_eq!(inference.probability_opponent_holds(player, &Card::new(5, Color::Blue)), 0.0);
+        }
+        assert_eq!(inference.expected_tiles_remaining(&Card::new(5, Color::Blue)), 0.0);
+        assert_eq!(inference.probability_opponent_holds(1, &Card::new(9, Color::Red)), 14.0 / 101.0);
+        assert_eq!(inference.probability_opponent_holds(0, &Card::new(9, Color::Red)), 0.0);
+        assert_eq!(inference.expected_tiles_remaining(&Card::new(7, Color::Black)), 2.0 * (73.0 / 101.0));
+        assert_eq!(inference.unseen_tiles(), 101);
+        assert!((total_probability(&inference, 3) - 101.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn observe_events_test() {
+        let mut game = GameState::new_game(&["Ann", "Bo"], 9);
+        let mut inference = Inference::from_view(&game.view_for(1));
+        assert_eq!(inference.unseen_tiles(), 92);
+
+        // Ann draws, and Bo only sees the tray of Ann growing
+        game.apply_move(Move::Draw).unwrap();
+        game.apply_move(Move::EndTurn).unwrap();
+        for event in &game.view_for(1).events {
+            inference.observe(event);
+        }
+        assert_eq!(inference, Inference::from_view(&game.view_for(1)));
+        assert_eq!(inference.probability_opponent_holds(0, &Card::Wildcard), 15.0 / 92.0);
+
+        // Bo's own draw is seen
+        let drawn = game.apply_move(Move::Draw).unwrap().drawn.unwrap();
+        inference.observe(game.view_for(1).events.last().unwrap());
+        assert_eq!(inference.unseen_tiles(), 91);
+        assert_eq!(inference, Inference::from_view(&game.view_for(1)));
+        assert!(inference.unseen(&drawn) < 2);
+        assert!((total_probability(&inference, 2) - 91.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn observe_laid_down_tiles_test() {
+        let table = Table::from_melds(vec![vec![Card::new(4, Color::Red), Card::Wildcard, Card::new(6, Color::Red)]]);
+        let meld = table.melds().next().unwrap().0;
+        let mut inference = Inference::new(0, &[], &table, &[0, 5], 98);
+
+        let event = |mv| GameEvent { player: 1, turn: 0, mv, drawn: None, timed_out: false };
+        inference.observe(&event(Move::RetrieveWildcard { meld, card: Card::new(5, Color::Red) }));
+        assert_eq!(inference.unseen(&Card::new(5, Color::Red)), 1);
+        // The wildcard is known to be in the tray, only 4 of the 5 tiles can be anything
+        assert_eq!(inference.probability_opponent_holds(1, &Card::new(5, Color::Red)), 4.0 / 102.0);
+
+        inference.observe(&event(Move::PlayNewMeld(vec![Card::new(8, Color::Blue), Card::Wildcard, Card::new(10, Color::Blue)])));
+        assert_eq!(inference.unseen(&Card::Wildcard), 1);
+        assert_eq!(inference.unseen(&Card::new(8, Color::Blue)), 1);
+        assert_eq!(inference.probability_opponent_holds(1, &Card::new(8, Color::Blue)), 2.0 / 100.0);
+        assert!((total_probability(&inference, 2) - 100.0).abs() < 1e-9);
+    }
+
+}
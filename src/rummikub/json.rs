@@ -0,0 +1,220 @@
+use serde::Serialize;
+use super::{Card, Color, Partition, resolve_wildcards};
+use super::validation::validate_refs;
+use crate::prelude::*;
+
+/// Bumped whenever the layout of the JSON output changes
+pub const JSON_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct JsonReport {
+    version: u32,
+    tray: Vec<JsonCard>,
+    sets: Vec<JsonSet>
+}
+
+#[derive(Serialize)]
+struct JsonPartition {
+    version: u32,
+    sets: Vec<JsonSet>,
+    leftover: Vec<JsonCard>
+}
+
+#[derive(Serialize)]
+struct JsonValidation {
+    version: u32,
+    valid: bool,
+    set: Option<JsonSet>,
+    error: Option<String>
+}
+
+#[derive(Serialize)]
+struct JsonSet {
+    kind: &'static str,
+    cards: Vec<JsonCard>,
+    points: u32
+}
+
+// Wildcards in the tray have no number nor color, while in sets they get the ones they stand for
+#[derive(Serialize)]
+struct JsonCard {
+    code: String,
+    wildcard: bool,
+    number: Option<i8>,
+    color: Option<&'static str>
+}
+
+impl JsonCard {
+    fn new(card: &Card, value: Option<(i8, Color)>) -> JsonCard {
+        JsonCard {
+            code: card.code(),
+            wildcard: card.is_wildcard(),
+            number: value.map(|(number, _)| number),
+            color: value.map(|(_, color)| color_name(color))
+        }
+    }
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::Red => "red",
+        Color::Blue => "blue",
+        Color::Black => "black",
+        Color::Yellow => "yellow",
+        Color::Green => "green",
+        Color::Orange => "orange"
+    }
+}
+
+fn card_value(card: &Card) -> Option<(i8, Color)> {
+    match card {
+        Card::Numbered { number, color } => Some((*number, *color)),
+        _ => None
+    }
+}
+
+/// Describes the tray and its sets as JSON: a "version" field (JSON_VERSION), the "tray"
+/// and the "sets", each with its "kind" (run or group), its "cards" and its "points".
+/// Cards are objects with their "code", whether they are a "wildcard", their "number" and
+/// their "color"; wildcards in sets take the values they stand for. Invalid sets are left out.
+pub fn sets_to_json(tray: &[Card], sets: &[Vec<&Card>], pretty: bool) -> String {
+    let report = JsonReport {
+        version: JSON_VERSION,
+        tray: tray.iter().map(|card| JsonCard::new(card, card_value(card))).collect(),
+        sets: sets.iter().filter_map(|set| json_set(set)).collect()
+    };
+    to_string(&report, pretty)
+}
+
+/// Describes a partition as JSON: the "version", the "sets" laid out like in sets_to_json
+/// and the "leftover" cards.
+pub fn partition_to_json(partition: &Partition, pretty: bool) -> String {
+    let report = JsonPartition {
+        version: JSON_VERSION,
+        sets: partition.sets.iter()
+            .filter_map(|set| json_set(&set.iter().collect::<Vec<&Card>>()))
+            .collect(),
+        leftover: partition.leftover.iter().map(|card| JsonCard::new(card, card_value(card))).collect()
+    };
+    to_string(&report, pretty)
+}
+
+/// Describes whether the cards make a valid set as JSON: the "version", "valid", and either
+/// the "set" laid out like in sets_to_json or the "error" saying why it isn't one, the
+/// other being null.
+pub fn validation_to_json(cards: &[Card], pretty: bool) -> String {
+    let refs: Vec<&Card> = cards.iter().collect();
+    let (set, error) = match validate_refs(&refs) {
+        Ok(_) => (json_set(&refs), None),
+        Err(error) => (None, Some(error.to_string()))
+    };
+    let report = JsonValidation { version: JSON_VERSION, valid: set.is_some(), set, error };
+    to_string(&report, pretty)
+}
+
+fn json_set(set: &[&Card]) -> Option<JsonSet> {
+    let kind = validate_refs(set).ok()?.name();
+    let values = resolve_wildcards(set)?;
+    Some(JsonSet {
+        kind,
+        cards: set.iter().zip(&values).map(|(card, value)| JsonCard::new(card, Some(*value))).collect(),
+        points: values.iter().map(|(number, _)| *number as u32).sum()
+    })
+}
+
+fn to_string(report: &impl Serialize, pretty: bool) -> String {
+    let json = if pretty {
+        serde_json::to_string_pretty(report)
+    } else {
+        serde_json::to_string(report)
+    };
+    // Plain structs of strings and numbers always serialize
+    json.unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use serde_json::{json, Value};
+    use super::*;
+
+    #[test]
+    fn sets_to_json_test() {
+        let tray = vec![
+            Card::new(4, Color::Red),
+            Card::new(5, Color::Red),
+            Card::Wildcard
+        ];
+        let set: Vec<&Card> = tray.iter().collect();
+
+        let output: Value = serde_json::from_str(&sets_to_json(&tray, &[set], false)).unwrap();
+
+        assert_eq!(output, json!({
+            "version": 1,
+            "tray": [
+                { "code": "R4", "wildcard": false, "number": 4, "color": "red" },
+                { "code": "R5", "wildcard": false, "number": 5, "color": "red" },
+                { "code": "W", "wildcard": true, "number": null, "color": null }
+            ],
+            "sets": [{
+                "kind": "run",
+                "cards": [
+                    { "code": "R4", "wildcard": false, "number": 4, "color": "red" },
+                    { "code": "R5", "wildcard": false, "number": 5, "color": "red" },
+                    { "code": "W", "wildcard": true, "number": 6, "color": "red" }
+                ],
+                "points": 15
+            }]
+        }));
+    }
+
+    #[test]
+    fn sets_to_json_pretty_test() {
+        let tray = vec![Card::new(9, Color::Blue)];
+
+        let compact = sets_to_json(&tray, &[], false);
+        let pretty = sets_to_json(&tray, &[], true);
+
+        assert!(!compact.contains('\n'));
+        assert!(pretty.contains('\n'));
+        assert_eq!(
+            serde_json::from_str::<Value>(&compact).unwrap(),
+            serde_json::from_str::<Value>(&pretty).unwrap()
+        );
+    }
+
+    #[test]
+    fn partition_to_json_test() {
+        let partition = Partition {
+            sets: vec![vec![Card::new(8, Color::Red), Card::new(8, Color::Blue), Card::new(8, Color::Black)]],
+            leftover: vec![Card::Wildcard]
+        };
+
+        let output: Value = serde_json::from_str(&partition_to_json(&partition, false)).unwrap();
+
+        assert_eq!(output["version"], 1);
+        assert_eq!(output["sets"][0]["kind"], "group");
+        assert_eq!(output["sets"][0]["points"], 24);
+        assert_eq!(output["leftover"], json!([{ "code": "W", "wildcard": true, "number": null, "color": null }]));
+    }
+
+    #[test]
+    fn validation_to_json_test() {
+        let set = vec![Card::new(7, Color::Red), Card::Wildcard, Card::new(7, Color::Blue)];
+
+        let output: Value = serde_json::from_str(&validation_to_json(&set, false)).unwrap();
+        assert_eq!(output["valid"], true);
+        assert_eq!(output["set"]["kind"], "group");
+        assert_eq!(output["set"]["points"], 21);
+        assert_eq!(output["error"], Value::Null);
+
+        let output: Value = serde_json::from_str(&validation_to_json(&set[..2], false)).unwrap();
+        assert_eq!(output, json!({
+            "version": JSON_VERSION,
+            "valid": false,
+            "set": null,
+            "error": "a set needs at least 3 tiles, this one has 2"
+        }));
+    }
+
+}
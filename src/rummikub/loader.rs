@@ -0,0 +1,74 @@
+use std::io::BufRead;
+use thiserror::Error;
+use super::{Card, ParseCardError};
+
+/// Error returned by load_tray, with the line (starting from 1) of the offending token
+#[derive(Debug, Error)]
+pub enum TrayLoadError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("line {line}: {error}")]
+    InvalidTile { line: usize, #[source] error: ParseCardError }
+}
+
+/// Reads a tray written as tile codes (like B2 or W), one per line or separated by spaces.
+/// Everything after a '#' is a comment, and blank lines are skipped.
+pub fn load_tray(reader: impl BufRead) -> Result<Vec<Card>, TrayLoadError> {
+    let mut tray = vec![];
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        let content = line.split('#').next().unwrap_or("");
+        for token in content.split_whitespace() {
+            let card = token.parse()
+                .map_err(|error| TrayLoadError::InvalidTile { line: idx + 1, error })?;
+            tray.push(card);
+        }
+    }
+    Ok(tray)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::io::Cursor;
+    use super::*;
+    use super::super::Color;
+
+    #[test]
+    fn load_tray_test() {
+        let text = "# Hand from Sunday's game\nB2\nB3 B4\tR7\n\n   \nW # drawn last\n";
+
+        let tray = load_tray(Cursor::new(text)).unwrap();
+
+        assert_eq!(tray, vec![
+            Card::new(2, Color::Blue),
+            Card::new(3, Color::Blue),
+            Card::new(4, Color::Blue),
+            Card::new(7, Color::Red),
+            Card::Wildcard
+        ]);
+    }
+
+    #[test]
+    fn load_tray_empty_test() {
+        assert_eq!(load_tray(Cursor::new("# nothing yet\n")).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn load_tray_invalid_line_test() {
+        let text = "B2 B3\n# comment\nR7 X12 W\n";
+
+        match load_tray(Cursor::new(text)) {
+            Err(TrayLoadError::InvalidTile { line, error }) => {
+                assert_eq!(line, 3);
+                assert_eq!(error.token, "X12");
+            },
+            other => panic!("unexpected result: {:?}", other)
+        }
+        assert_eq!(
+            load_tray(Cursor::new(text)).unwrap_err().to_string(),
+            format!("line 3: {}", ParseCardError { token: "X12".to_string(), min: 1, max: 13 })
+        );
+    }
+
+}
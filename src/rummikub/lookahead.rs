@@ -0,0 +1,314 @@
+use std::collections::HashSet;
+use rand::prelude::*;
+use super::{can_go_out, find_almost_sets, has_valid_set_from_index, Card, CardCounts, Color, Deck, DeckError, TrayIndex};
+
+/// What p_complete_within waits for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawGoal {
+    /// The tray holds at least one valid set
+    AnySet,
+    /// The whole tray can be laid down, see can_go_out
+    GoOut
+}
+
+/// The fewest tiles, at most n, the tray could draw to go out, or None when no n of the tiles
+/// left would do. An empty list means the tray can go out already. The tiles left are those
+/// of draw_outcomes, a full deck minus the tray and the seen tiles. This is the best case,
+/// the tiles being picked rather than drawn, see p_out_within for the chance of it.
+pub fn best_case_outs(tray: &[Card], seen: &[Card], n: usize) -> Result<Option<Vec<Card>>, DeckError> {
+    let left = CardCounts::from_cards(&Deck::standard_without(&[tray, seen].concat())?.cards);
+
+    // Hands that need the same tiles drawn are searched once, whatever the order of the draws
+    let mut visited = HashSet::new();
+    let mut level = vec![(tray.to_vec(), left, vec![])];
+    for drawn_count in 0..=n {
+        let mut next = vec![];
+        for (hand, left, drawn) in level {
+            if can_go_out(&hand).is_some() {
+                return Ok(Some(drawn));
+            }
+            if drawn_count == n {
+                continue;
+            }
+            for card in worth_drawing(&hand, &left, drawn_count + 1 == n) {
+                let mut hand = hand.clone();
+                hand.push(card);
+                if !visited.insert(CardCounts::from_cards(&hand)) {
+                    continue;
+                }
+                let mut left = left.clone();
+                // The tile comes from left
+                left.remove(&card).unwrap();
+                let mut drawn = drawn.clone();
+                drawn.push(card);
+                next.push((hand, left, drawn));
+            }
+        }
+        level = next;
+    }
+    Ok(None)
+}
+
+/// The chance of going out within n draws, over samples random orders of the tiles left
+/// shuffled from the seed. The tray keeps every tile it draws and goes out as soon as it
+/// can. See best_case_outs for the tiles left; no samples give 0.
+pub fn p_out_within(tray: &[Card], seen: &[Card], n: usize, samples: usize, seed: u64) -> Result<f64, DeckError> {
+    let mut deck = Deck::standard_without(&[tray, seen].concat())?;
+    match best_case_outs(tray, seen, n)? {
+        Some(drawn) if drawn.is_empty() => return Ok(1.0),
+        None => return Ok(0.0),
+        Some(_) => {}
+    }
+    if samples == 0 {
+        return Ok(0.0);
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut outs = 0;
+    for _ in 0..samples {
+        deck.shuffle_with(&mut rng);
+        let mut hand = tray.to_vec();
+        for card in deck.cards.iter().take(n) {
+            hand.push(*card);
+            if can_go_out(&hand).is_some() {
+                outs += 1;
+                break;
+            }
+        }
+    }
+    Ok(outs as f64 / samples as f64)
+}
+
+/// The chance that the tray reaches the goal once k tiles are drawn, over samples random
+/// draws from the seed. The tiles left are those of best_case_outs; k larger than them draws
+/// them all. A tray already reaching the goal gives 1 and no samples give 0.
+pub fn p_complete_within(tray: &[Card], seen: &[Card], k: usize, samples: usize, seed: u64, goal: DrawGoal) -> Result<f64, DeckError> {
+    let mut deck = Deck::standard_without(&[tray, seen].concat())?;
+    let mut index = TrayIndex::new(tray);
+    let reached = |index: &TrayIndex| match goal {
+        DrawGoal::AnySet => has_valid_set_from_index(index),
+        DrawGoal::GoOut => can_go_out(index.cards()).is_some()
+    };
+    if reached(&index) {
+        return Ok(1.0);
+    }
+    if samples == 0 {
+        return Ok(0.0);
+    }
+
+    let k = k.min(deck.len());
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut hits = 0;
+    for _ in 0..samples {
+        let (drawn, _) = deck.cards.partial_shuffle(&mut rng, k);
+        for card in drawn.iter() {
+            index.add_card(*card);
+        }
+        // Tiles are only added, so the goal reached after the last draw is enough
+        hits += reached(&index) as usize;
+        for card in drawn.iter() {
+            index.remove_card(card);
+        }
+    }
+    Ok(hits as f64 / samples as f64)
+}
+
+/// A lower bound on the tiles the tray must draw before it can go out, without searching the
+/// draws like best_case_outs: 0 only when can_go_out finds the sets already. Besides the 3
+/// tiles of a set, every tile needs the ones missing from the closest set it could be in,
+/// wildcards of the tray filling in, and tiles no other tile of the tray can share a set with
+/// need two drawn tiles each. None when a tile can't be in any set, like a 14 or a joker of
+/// the Twist rules.
+pub fn min_draws_to_out(tray: &[Card]) -> Option<usize> {
+    let wildcards = tray.iter().filter(|card| card.is_wildcard()).count();
+    let held = |number: i8, color: Color| tray.contains(&Card::new(number, color));
+    // An empty tray is out already
+    let mut bound = if tray.is_empty() { 0 } else { 3usize.saturating_sub(tray.len()) };
+    let mut isolated = 0;
+    for card in tray {
+        let Card::Numbered { number, color } = *card else {
+            if card.is_twist_joker() {
+                return None;
+            }
+            continue;
+        };
+        if !(1..=13).contains(&number) || !Color::ALL.contains(&color) {
+            return None;
+        }
+        let group_colors = Color::ALL.iter().filter(|other| **other != color && held(number, **other)).count();
+        let group_missing = 2 - group_colors.min(2);
+        // A longer run always holds a run of 3 around the tile missing no more tiles
+        let run_missing = (number - 2..=number).filter(|start| *start >= 1 && start + 2 <= 13)
+            .map(|start| (start..start + 3).filter(|n| *n != number && !held(*n, color)).count())
+            .min()
+            .unwrap_or(2);
+        bound = bound.max(group_missing.min(run_missing).saturating_sub(wildcards));
+
+        let partnered = tray.iter().any(|other| match other {
+            Card::Numbered { number: other_number, color: other_color } => {
+                (*other_number == number) != (*other_color == color)
+            },
+            _ => true
+        });
+        if !partnered {
+            isolated += 1;
+        }
+    }
+    // The sets of two such tiles can't be the same one, nor share drawn tiles
+    bound = bound.max(2 * isolated);
+    if bound == 0 && can_go_out(tray).is_none() {
+        bound = 1;
+    }
+    Some(bound)
+}
+
+// The tiles of left worth drawing next. Drawn tiles that go out together with a tile of the
+// hand can be drawn nearest to it first, so a draw sits next to a tile of the hand: of its
+// number, or of its color and at most two apart. The last draw has to complete a set of the
+// hand on its own, being one of the tiles find_almost_sets misses or a copy of a tile held.
+// A wildcard in the hand stands in anywhere, every tile being worth drawing then.
+fn worth_drawing(hand: &[Card], left: &CardCounts, last: bool) -> Vec<Card> {
+    let wildcard_held = hand.iter().any(Card::is_wildcard);
+    let almost_sets = if last { find_almost_sets(hand) } else { vec![] };
+    left.counts()
+        .map(|(card, _)| card)
+        .filter(|card| {
+            let Card::Numbered { number, color } = card else {
+                return true;
+            };
+            if wildcard_held || hand.contains(card) {
+                return true;
+            }
+            if last {
+                almost_sets.iter().any(|almost| almost.missing.contains(card))
+            } else {
+                hand.iter().any(|held| match held {
+                    Card::Numbered { number: held_number, color: held_color } => {
+                        held_number == number || (held_color == color && (held_number - number).abs() <= 2)
+                    },
+                    _ => false
+                })
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::testing::random_tray;
+
+    #[test]
+    fn missing_one_tile_test() {
+        let test_tray = vec![
+            Card::new(1, Color::Red),
+            Card::new(2, Color::Red),
+            Card::new(3, Color::Red),
+            Card::new(9, Color::Red),
+            Card::new(11, Color::Red)
+        ];
+        // With both wildcards seen, only the two 10 Red are left to go out with
+        let seen = [Card::Wildcard, Card::Wildcard];
+
+        assert_eq!(best_case_outs(&test_tray, &seen, 0).unwrap(), None);
+        assert_eq!(best_case_outs(&test_tray, &seen, 1).unwrap(), Some(vec![Card::new(10, Color::Red)]));
+        assert_eq!(best_case_outs(&test_tray, &seen, 3).unwrap(), Some(vec![Card::new(10, Color::Red)]));
+
+        // 99 tiles are left, 2 of them going out
+        let p = p_out_within(&test_tray, &seen, 1, 20000, 7).unwrap();
+        assert!((p - 2.0 / 99.0).abs() < 0.005, "{}", p);
+        // A first draw that doesn't go out is kept, only 4, 8 and 12 Red still fitting once the
+        // 10 Red comes second
+        let p = p_out_within(&test_tray, &seen, 2, 20000, 7).unwrap();
+        assert!((p - (2.0 / 99.0 + 6.0 / 99.0 * 2.0 / 98.0)).abs() < 0.005, "{}", p);
+        assert_eq!(p_out_within(&test_tray, &seen, 0, 100, 7).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn two_draws_away_test() {
+        // 12 Red needs two tiles, none of which completes a pair of the tray
+        let test_tray = vec![
+            Card::new(5, Color::Red),
+            Card::new(6, Color::Red),
+            Card::new(7, Color::Red),
+            Card::new(12, Color::Red)
+        ];
+
+        assert_eq!(best_case_outs(&test_tray, &[], 1).unwrap(), None);
+        let drawn = best_case_outs(&test_tray, &[], 2).unwrap().unwrap();
+        assert_eq!(drawn.len(), 2);
+        assert!(can_go_out([test_tray.as_slice(), &drawn].concat()).is_some());
+        assert_eq!(best_case_outs(&test_tray[..3], &[], 2).unwrap(), Some(vec![]));
+        assert_eq!(p_out_within(&test_tray[..3], &[], 2, 10, 1).unwrap(), 1.0);
+        assert!(best_case_outs(&test_tray, &[Card::new(12, Color::Red); 2], 2).is_err());
+    }
+
+    #[test]
+    fn p_complete_within_test() {
+        let test_tray = vec![
+            Card::new(4, Color::Blue),
+            Card::new(6, Color::Blue),
+            Card::new(11, Color::Black)
+        ];
+        // Only the two 5 Blue turn the tray into a set, the wildcards being seen
+        let seen = [Card::Wildcard, Card::Wildcard];
+
+        // 101 tiles are left
+        let p = p_complete_within(&test_tray, &seen, 1, 10000, 5, DrawGoal::AnySet).unwrap();
+        assert!((p - 2.0 / 101.0).abs() < 0.005, "{}", p);
+        assert_eq!(p, 0.0207);
+        // More draws give other sets too, like 6, 7 and 8 Blue
+        let p = p_complete_within(&test_tray, &seen, 3, 10000, 5, DrawGoal::AnySet).unwrap();
+        assert_eq!(p, 0.0966);
+        assert_eq!(p_complete_within(&test_tray, &seen, 3, 0, 5, DrawGoal::AnySet).unwrap(), 0.0);
+        assert_eq!(p_complete_within(&test_tray[..2], &[], 0, 100, 5, DrawGoal::AnySet).unwrap(), 0.0);
+
+        // A set held already, but not a hand laid down whole
+        let with_set = [test_tray.as_slice(), &[Card::new(5, Color::Blue)]].concat();
+        assert_eq!(p_complete_within(&with_set, &seen, 0, 100, 5, DrawGoal::AnySet).unwrap(), 1.0);
+        assert_eq!(p_complete_within(&with_set, &seen, 0, 100, 5, DrawGoal::GoOut).unwrap(), 0.0);
+
+        // Only one 5 Blue is left, drawn whatever k above it
+        let mut rest = CardCounts::from_cards(&Deck::new().cards);
+        for card in [test_tray.as_slice(), &[Card::new(5, Color::Blue)]].concat() {
+            rest.remove(&card).unwrap();
+        }
+        let seen = rest.to_vec();
+        assert_eq!(p_complete_within(&test_tray, &seen, 1, 100, 5, DrawGoal::AnySet).unwrap(), 1.0);
+        assert_eq!(p_complete_within(&test_tray, &seen, 5, 100, 5, DrawGoal::GoOut).unwrap(), 0.0);
+        let with_black = [seen.as_slice(), &[Card::new(11, Color::Black)]].concat();
+        assert_eq!(p_complete_within(&test_tray[..2], &with_black, 5, 100, 5, DrawGoal::GoOut).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn min_draws_to_out_test() {
+        let red = |numbers: &[i8]| numbers.iter().map(|n| Card::new(*n, Color::Red)).collect::<Vec<Card>>();
+
+        assert_eq!(min_draws_to_out(&red(&[5, 6, 7])), Some(0));
+        assert_eq!(min_draws_to_out(&[]), Some(0));
+        assert_eq!(min_draws_to_out(&red(&[1])), Some(2));
+        // Neither can share a set with the other
+        assert_eq!(min_draws_to_out(&[Card::new(1, Color::Red), Card::new(13, Color::Black)]), Some(4));
+        assert_eq!(min_draws_to_out(&[red(&[5, 6, 7]).as_slice(), &[Card::new(9, Color::Blue)]].concat()), Some(2));
+        assert_eq!(min_draws_to_out(&[red(&[5, 6, 7]).as_slice(), &[Card::new(9, Color::Blue), Card::Wildcard]].concat()), Some(1));
+        // R5 R7 is one R6 away, and K9 B9 one 9 away
+        assert_eq!(min_draws_to_out(&[red(&[5, 7]).as_slice(), &[Card::new(9, Color::Black), Card::new(9, Color::Blue)]].concat()), Some(1));
+        // Both R5 R6 R7 and R5 R6 have every tile in a set of the tray or close to one
+        assert_eq!(min_draws_to_out(&red(&[5, 6, 7, 5, 6])), Some(1));
+        assert_eq!(min_draws_to_out(&red(&[14, 15, 16])), None);
+    }
+
+    #[test]
+    fn min_draws_never_above_truth_test() {
+        for seed in 0..60 {
+            let tray = random_tray(seed, 6 + seed as usize % 5);
+            let bound = min_draws_to_out(&tray).unwrap();
+            assert_eq!(bound == 0, can_go_out(&tray).is_some(), "seed {}: {:?}", seed, tray);
+            if let Some(drawn) = best_case_outs(&tray, &[], 2).unwrap() {
+                assert!(bound <= drawn.len(), "seed {}: {} for {:?}", seed, bound, drawn);
+            }
+        }
+    }
+
+}
@@ -0,0 +1,269 @@
+use alloc::collections::BTreeSet;
+use core::cmp::Reverse;
+use super::{check_turn, resolve_wildcards_with, valid_sets, validate_set_with, Card, Color, MeldId, MeldKind, Table};
+use crate::prelude::*;
+
+// The most tiles a plan takes from the melds of the table to lay down a new meld
+const MAX_TAKEN: usize = 2;
+// The most tiles of the tray filling the gap between two runs joined into one
+const MAX_GAP: usize = 2;
+
+/// A step of a ManipulationPlan
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TableOp {
+    /// Takes a copy of the tile out of the meld
+    RemoveTile { meld: MeldId, card: Card },
+    /// Lays down a new meld, of tiles from the tray and tiles removed earlier in the plan
+    CreateMeld(Vec<Card>),
+    /// Adds a tile from the tray, or one removed earlier in the plan, to the meld
+    AppendTile { meld: MeldId, card: Card }
+}
+
+/// A rearrangement of the melds of the table playing tiles of the tray, see
+/// generate_manipulations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManipulationPlan {
+    /// The steps, in the order they are applied
+    pub ops: Vec<TableOp>,
+    /// The tiles of the tray the plan plays, sorted
+    pub played: Vec<Card>
+}
+
+impl ManipulationPlan {
+    /// The table once every step is applied, or None when one of them can't be: a tile
+    /// missing from its meld, or a new meld that isn't valid. A meld the plan empties leaves
+    /// the table. The melds left may still be invalid, check_turn tells.
+    pub fn apply(&self, table: &Table) -> Option<Table> {
+        let mut table = table.clone();
+        for op in &self.ops {
+            match op {
+                TableOp::RemoveTile { meld, card } => {
+                    let idx = table.meld(*meld)?.iter().position(|c| c == card)?;
+                    table.remove_card(*meld, idx)?;
+                    if table.meld(*meld).is_some_and(|cards| cards.is_empty()) {
+                        table.remove_meld(*meld);
+                    }
+                },
+                TableOp::CreateMeld(cards) => {
+                    table.add_meld(cards.clone()).ok()?;
+                },
+                TableOp::AppendTile { meld, card } => {
+                    if !table.append_to_meld(*meld, *card) {
+                        return None;
+                    }
+                }
+            }
+        }
+        Some(table)
+    }
+}
+
+/// Finds the turns a player who has melded can play by rearranging the table, the ones
+/// playing the most tiles first. Three kinds of plays are searched, each on its own:
+/// - splitting a run at a number the tray holds, B4 B5 B6 B7 B8 and B6 giving B4 B5 B6 and
+///   B6 B7 B8;
+/// - taking up to 2 tiles the melds can spare, like the end of a run of 4 or a tile of a
+///   group of 4, to lay them down in a new meld with tiles of the tray;
+/// - joining two runs of a color with the up to 2 tiles of the tray between them.
+///
+/// Every plan is applied and passes check_turn before it is returned.
+pub fn generate_manipulations(tray: &[Card], table: &Table) -> Vec<ManipulationPlan> {
+    let mut plans = split_runs(tray, table);
+    plans.extend(take_for_new_melds(tray, table));
+    plans.extend(join_runs(tray, table));
+
+    let mut seen = BTreeSet::new();
+    plans.retain(|plan| seen.insert(plan.ops.clone()) && passes_check_turn(plan, tray, table));
+    // sort_by_key is stable: ties keep the order they were found in
+    plans.sort_by_key(|plan| Reverse(plan.played.len()));
+    plans
+}
+
+fn passes_check_turn(plan: &ManipulationPlan, tray: &[Card], table: &Table) -> bool {
+    let Some(after) = plan.apply(table) else {
+        return false;
+    };
+    let mut hand = tray.to_vec();
+    for card in &plan.played {
+        let Some(idx) = hand.iter().position(|c| c == card) else {
+            return false;
+        };
+        hand.remove(idx);
+    }
+    check_turn(table, &after, tray, &hand, true).is_ok()
+}
+
+// What the tiles of a run stand for, None for the other melds
+fn run_values(cards: &[Card], table: &Table) -> Option<Vec<(i8, Color)>> {
+    if validate_set_with(cards, table.rules()) != Ok(MeldKind::Run) {
+        return None;
+    }
+    resolve_wildcards_with(&cards.iter().collect::<Vec<&Card>>(), table.rules())
+}
+
+// The tiles above the cut leave the run for a new meld, led by the copy from the tray
+fn split_runs(tray: &[Card], table: &Table) -> Vec<ManipulationPlan> {
+    let mut plans = vec![];
+    for (meld, cards) in table.melds() {
+        let Some(values) = run_values(cards, table) else {
+            continue;
+        };
+        for (number, color) in &values {
+            let copy = Card::new(*number, *color);
+            let above: Vec<Card> = cards.iter().zip(&values)
+                .filter(|(_, (n, _))| n > number)
+                .map(|(card, _)| *card)
+                .collect();
+            if above.is_empty() || !tray.contains(&copy) {
+                continue;
+            }
+            let mut ops: Vec<TableOp> = above.iter().map(|card| TableOp::RemoveTile { meld, card: *card }).collect();
+            ops.push(TableOp::CreateMeld([copy].into_iter().chain(above).collect()));
+            plans.push(ManipulationPlan { ops, played: vec![copy] });
+        }
+    }
+    plans
+}
+
+// The tiles a meld stays valid without, one per value
+fn spare_tiles(table: &Table) -> Vec<(MeldId, Card)> {
+    let mut spare = vec![];
+    for (meld, cards) in table.melds() {
+        let mut tried = BTreeSet::new();
+        for (idx, card) in cards.iter().enumerate() {
+            let mut rest = cards.to_vec();
+            rest.remove(idx);
+            if tried.insert(*card) && validate_set_with(&rest, table.rules()).is_ok() {
+                spare.push((meld, *card));
+            }
+        }
+    }
+    spare
+}
+
+// The sets of the tray and the spare tiles holding both. Two spare tiles of a meld may be
+// more than it can give, then check_turn drops the plan.
+fn take_for_new_melds(tray: &[Card], table: &Table) -> Vec<ManipulationPlan> {
+    let spare = spare_tiles(table);
+    let pool: Vec<Card> = tray.iter().copied().chain(spare.iter().map(|(_, card)| *card)).collect();
+    let mut plans = vec![];
+    for set in valid_sets(&pool) {
+        let (mut played, mut taken) = (vec![], vec![]);
+        for card in &set {
+            match pool.iter().position(|c| core::ptr::eq(c, *card)) {
+                Some(idx) if idx >= tray.len() => taken.push(spare[idx - tray.len()]),
+                _ => played.push(**card)
+            }
+        }
+        if played.is_empty() || taken.is_empty() || taken.len() > MAX_TAKEN {
+            continue;
+        }
+        played.sort();
+        let mut ops: Vec<TableOp> = taken.iter().map(|(meld, card)| TableOp::RemoveTile { meld: *meld, card: *card }).collect();
+        ops.push(TableOp::CreateMeld(set.into_iter().copied().collect()));
+        plans.push(ManipulationPlan { ops, played });
+    }
+    plans
+}
+
+// The tiles of the tray go at the end of the lower run, then the tiles of the higher one
+fn join_runs(tray: &[Card], table: &Table) -> Vec<ManipulationPlan> {
+    let runs: Vec<_> = table.melds()
+        .filter_map(|(meld, cards)| run_values(cards, table).map(|values| (meld, cards, values)))
+        .collect();
+    let mut plans = vec![];
+    for (low, _, low_values) in &runs {
+        for (high, high_cards, high_values) in &runs {
+            let (Some((top, color)), Some((bottom, other))) = (low_values.iter().max(), high_values.iter().min()) else {
+                continue;
+            };
+            let gap: Vec<Card> = (top + 1..*bottom).map(|number| Card::new(number, *color)).collect();
+            if color != other || gap.is_empty() || gap.len() > MAX_GAP || !gap.iter().all(|card| tray.contains(card)) {
+                continue;
+            }
+            let mut ops: Vec<TableOp> = gap.iter().map(|card| TableOp::AppendTile { meld: *low, card: *card }).collect();
+            for card in high_cards.iter() {
+                ops.push(TableOp::RemoveTile { meld: *high, card: *card });
+                ops.push(TableOp::AppendTile { meld: *low, card: *card });
+            }
+            plans.push(ManipulationPlan { ops, played: gap });
+        }
+    }
+    plans
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn blue(numbers: &[i8]) -> Vec<Card> {
+        numbers.iter().map(|n| Card::new(*n, Color::Blue)).collect()
+    }
+
+    fn all_pass_check_turn(plans: &[ManipulationPlan], tray: &[Card], table: &Table) {
+        for plan in plans {
+            let after = plan.apply(table).unwrap();
+            let mut hand = tray.to_vec();
+            for card in &plan.played {
+                hand.remove(hand.iter().position(|c| c == card).unwrap());
+            }
+            assert_eq!(check_turn(table, &after, tray, &hand, true), Ok(()), "{:?}", plan);
+        }
+    }
+
+    #[test]
+    fn split_run_and_insert_test() {
+        let table = Table::from_melds(vec![blue(&[4, 5, 6, 7, 8])]);
+        let tray = [Card::new(6, Color::Blue), Card::new(9, Color::Red)];
+        let plans = generate_manipulations(&tray, &table);
+
+        let meld = MeldId::from_number(0);
+        let split = ManipulationPlan {
+            ops: vec![
+                TableOp::RemoveTile { meld, card: Card::new(7, Color::Blue) },
+                TableOp::RemoveTile { meld, card: Card::new(8, Color::Blue) },
+                TableOp::CreateMeld(blue(&[6, 7, 8]))
+            ],
+            played: blue(&[6])
+        };
+        assert_eq!(plans, vec![split]);
+        let after = plans[0].apply(&table).unwrap();
+        assert_eq!(after.melds().map(|(_, cards)| cards.to_vec()).collect::<Vec<Vec<Card>>>(), vec![blue(&[4, 5, 6]), blue(&[6, 7, 8])]);
+        all_pass_check_turn(&plans, &tray, &table);
+    }
+
+    #[test]
+    fn steal_from_run_test() {
+        // R8 leaves R5 R6 R7 R8 for a group with K8 and Y8
+        let run = vec![Card::new(5, Color::Red), Card::new(6, Color::Red), Card::new(7, Color::Red), Card::new(8, Color::Red)];
+        let table = Table::from_melds(vec![run, blue(&[1, 2, 3])]);
+        let tray = [Card::new(8, Color::Black), Card::new(8, Color::Yellow), Card::new(13, Color::Blue)];
+        let plans = generate_manipulations(&tray, &table);
+
+        let group = plans.iter()
+            .find(|plan| plan.ops.first() == Some(&TableOp::RemoveTile { meld: MeldId::from_number(0), card: Card::new(8, Color::Red) }))
+            .unwrap();
+        assert_eq!(group.played, vec![Card::new(8, Color::Black), Card::new(8, Color::Yellow)]);
+        assert_eq!(group.ops.len(), 2);
+        // R5 can go too, but not with tiles to fill a group
+        assert!(plans.iter().all(|plan| plan.played.len() == 2));
+        all_pass_check_turn(&plans, &tray, &table);
+    }
+
+    #[test]
+    fn join_runs_test() {
+        let table = Table::from_melds(vec![blue(&[1, 2, 3]), blue(&[6, 7, 8])]);
+        let tray = blue(&[4, 5]);
+        let plans = generate_manipulations(&tray, &table);
+
+        let joined = plans.iter().find(|plan| plan.played == blue(&[4, 5])).unwrap();
+        let after = joined.apply(&table).unwrap();
+        assert_eq!(after.len(), 1);
+        assert_eq!(after.meld(MeldId::from_number(0)).unwrap().len(), 8);
+        all_pass_check_turn(&plans, &tray, &table);
+
+        assert!(generate_manipulations(&[Card::new(9, Color::Red)], &table).is_empty());
+    }
+
+}
@@ -0,0 +1,274 @@
+use super::{GameEvent, GameState, PlayerId, RoundResult, RulesConfig, ScoringVariant, StatsBook, Teams};
+
+/// When a match is over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchEnd {
+    /// After this many rounds
+    FixedRounds(usize),
+    /// Once a player, or a team when playing in teams, scores this many points in total
+    FirstTo(i32)
+}
+
+/// Where a match stands after a round
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchStatus {
+    pub over: bool,
+    /// The player with the best total, see Match::leader
+    pub leader: PlayerId
+}
+
+/// Several rounds played by the same players, adding up their scores
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    players: Vec<String>,
+    scores: Vec<i32>,
+    rounds_played: usize,
+    target: MatchEnd,
+    rules: RulesConfig,
+    teams: Option<Teams>,
+    team_scores: Vec<i32>,
+    stats: StatsBook
+}
+
+impl Match {
+    pub fn new(names: &[&str], target: MatchEnd) -> Match {
+        Match::with_rules(names, target, RulesConfig::default())
+    }
+
+    /// Like new, every round being played with house rules. With ScoringVariant::RaceTo, the
+    /// match is also over once a player reaches its points, whatever the target.
+    pub fn with_rules(names: &[&str], target: MatchEnd, rules: RulesConfig) -> Match {
+        Match {
+            players: names.iter().map(|name| name.to_string()).collect(),
+            scores: vec![0; names.len()],
+            rounds_played: 0,
+            target,
+            rules,
+            teams: None,
+            team_scores: vec![],
+            stats: StatsBook::new()
+        }
+    }
+
+    /// Has the players score in teams from now on, every round being dealt with them. The
+    /// match is then over once a team reaches the points of the target or of
+    /// ScoringVariant::RaceTo. Panics unless the teams are made for the players of the match.
+    pub fn set_teams(&mut self, teams: Teams) {
+        assert_eq!(teams.player_count(), self.players.len(), "the teams are for another number of players");
+        self.team_scores = vec![0; teams.names().len()];
+        self.teams = Some(teams);
+    }
+
+    pub fn teams(&self) -> Option<&Teams> {
+        self.teams.as_ref()
+    }
+
+    /// The total of every team by TeamId, empty without teams
+    pub fn team_scores(&self) -> &[i32] {
+        &self.team_scores
+    }
+
+    pub fn players(&self) -> &[String] {
+        &self.players
+    }
+
+    /// The total of every player, in the order of players
+    pub fn scores(&self) -> &[i32] {
+        &self.scores
+    }
+
+    pub fn rounds_played(&self) -> usize {
+        self.rounds_played
+    }
+
+    pub fn target(&self) -> MatchEnd {
+        self.target
+    }
+
+    pub fn rules(&self) -> &RulesConfig {
+        &self.rules
+    }
+
+    /// Deals the next round. The first turn goes to the next player every round, starting
+    /// with the first one.
+    pub fn start_round(&self, seed: u64) -> GameState {
+        let names: Vec<&str> = self.players.iter().map(|name| name.as_str()).collect();
+        let mut game = GameState::new_game_with_rules(&names, seed, self.rounds_played, self.rules);
+        if let Some(teams) = &self.teams {
+            game.set_teams(teams.clone());
+        }
+        game
+    }
+
+    /// Adds the scores of a round, see GameState::round_result. Only the results reach the
+    /// stats, finish_game also has them go through the moves played.
+    pub fn finish_round(&mut self, result: &RoundResult) -> MatchStatus {
+        self.finish_round_with(result, &[])
+    }
+
+    /// Like finish_round, for a game dealt by start_round, or None while it is still going
+    pub fn finish_game(&mut self, game: &GameState) -> Option<MatchStatus> {
+        let result = game.round_result()?;
+        Some(self.finish_round_with(result, game.events()))
+    }
+
+    /// The stats of the players over the rounds finished so far
+    pub fn stats(&self) -> &StatsBook {
+        &self.stats
+    }
+
+    fn finish_round_with(&mut self, result: &RoundResult, events: &[GameEvent]) -> MatchStatus {
+        self.stats.record(&self.players, result, events);
+        self.scores[result.winner] += result.winner_gain;
+        for (player, penalty) in &result.penalties {
+            self.scores[*player] += penalty;
+        }
+        for (total, score) in self.team_scores.iter_mut().zip(&result.team_scores) {
+            *total += score;
+        }
+        self.rounds_played += 1;
+        self.status()
+    }
+
+    pub fn status(&self) -> MatchStatus {
+        let over = match self.target {
+            MatchEnd::FixedRounds(rounds) => self.rounds_played >= rounds,
+            MatchEnd::FirstTo(points) => self.totals().iter().any(|score| *score >= points)
+        };
+        let eliminated = match self.rules.scoring {
+            ScoringVariant::RaceTo(points) => self.totals().iter().any(|score| *score >= points),
+            _ => false
+        };
+        MatchStatus { over: over || eliminated, leader: self.leader() }
+    }
+
+    /// The player with the highest total, or the lowest with ScoringVariant::RaceTo, the
+    /// first one in case of a tie. Panics on a match without players.
+    pub fn leader(&self) -> PlayerId {
+        // max_by_key would keep the last one of a tie
+        let best = match self.rules.scoring {
+            ScoringVariant::RaceTo(_) => *self.scores.iter().min().unwrap(),
+            _ => *self.scores.iter().max().unwrap()
+        };
+        self.scores.iter().position(|score| *score == best).unwrap()
+    }
+
+    /// The names and totals of the players, or of the teams when playing in teams, the best
+    /// first as leader ranks them, ties keeping their order
+    pub fn standings(&self) -> Vec<(String, i32)> {
+        let names = self.teams.as_ref().map(|teams| teams.names()).unwrap_or(&self.players);
+        let mut standings: Vec<(String, i32)> = names.iter().cloned().zip(self.totals().iter().copied()).collect();
+        match self.rules.scoring {
+            ScoringVariant::RaceTo(_) => standings.sort_by_key(|(_, score)| *score),
+            _ => standings.sort_by_key(|(_, score)| core::cmp::Reverse(*score))
+        }
+        standings
+    }
+
+    // The scores the match is played for: those of the teams, if any
+    fn totals(&self) -> &[i32] {
+        match self.teams {
+            Some(_) => &self.team_scores,
+            None => &self.scores
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::RoundEnd;
+
+    fn result(winner: PlayerId, penalties: &[(PlayerId, i32)]) -> RoundResult {
+        let winner_gain = -penalties.iter().map(|(_, penalty)| penalty).sum::<i32>();
+        RoundResult { winner, end: RoundEnd::WentOut, penalties: penalties.to_vec(), winner_gain, scoring: ScoringVariant::Standard, team_scores: vec![] }
+    }
+
+    // Ann wins 30, then Cy 25 and Ann again 48
+    fn rounds() -> [RoundResult; 3] {
+        [
+            result(0, &[(1, -10), (2, -20)]),
+            result(2, &[(0, -5), (1, -20)]),
+            result(0, &[(1, -40), (2, -8)])
+        ]
+    }
+
+    #[test]
+    fn fixed_rounds_match_test() {
+        let mut game = Match::new(&["Ann", "Bo", "Cy"], MatchEnd::FixedRounds(3));
+        let [first, second, third] = rounds();
+
+        assert_eq!(game.finish_round(&first), MatchStatus { over: false, leader: 0 });
+        assert_eq!(game.finish_round(&second), MatchStatus { over: false, leader: 0 });
+        assert_eq!(game.scores(), &[25, -30, 5]);
+        assert_eq!(game.finish_round(&third), MatchStatus { over: true, leader: 0 });
+        assert_eq!(game.scores(), &[73, -70, -3]);
+        assert_eq!(game.scores().iter().sum::<i32>(), 0);
+        assert_eq!(game.rounds_played(), 3);
+    }
+
+    #[test]
+    fn first_to_match_test() {
+        let mut game = Match::new(&["Ann", "Bo", "Cy"], MatchEnd::FirstTo(50));
+        let [first, second, third] = rounds();
+
+        // A tie goes to the first player
+        assert_eq!(game.status(), MatchStatus { over: false, leader: 0 });
+        assert_eq!(game.finish_round(&result(1, &[(0, -10)])), MatchStatus { over: false, leader: 1 });
+        assert_eq!(game.finish_round(&first), MatchStatus { over: false, leader: 0 });
+        assert!(!game.finish_round(&second).over);
+        assert_eq!(game.finish_round(&third), MatchStatus { over: true, leader: 0 });
+        assert_eq!(game.scores()[0], 63);
+    }
+
+    #[test]
+    fn race_to_match_test() {
+        let rules = RulesConfig { scoring: ScoringVariant::RaceTo(100), ..RulesConfig::default() };
+        let mut game = Match::with_rules(&["Ann", "Bo", "Cy"], MatchEnd::FixedRounds(10), rules);
+        let result = |winner, penalties: &[(PlayerId, i32)]| RoundResult { winner, end: RoundEnd::WentOut, penalties: penalties.to_vec(), winner_gain: 0, scoring: rules.scoring, team_scores: vec![] };
+
+        assert_eq!(game.finish_round(&result(0, &[(1, 40), (2, 30)])), MatchStatus { over: false, leader: 0 });
+        assert_eq!(game.finish_round(&result(1, &[(0, 20), (2, 50)])), MatchStatus { over: false, leader: 0 });
+        assert_eq!(game.scores(), &[20, 40, 80]);
+        // Cy reaches 100, and the lowest total wins
+        assert_eq!(game.finish_round(&result(0, &[(1, 30), (2, 25)])), MatchStatus { over: true, leader: 0 });
+        assert_eq!(game.scores(), &[20, 70, 105]);
+        assert_eq!(game.rounds_played(), 3);
+        assert_eq!(game.start_round(3).rules().scoring, rules.scoring);
+    }
+
+    #[test]
+    fn team_standings_test() {
+        let mut game = Match::new(&["Ann", "Bo", "Cy", "Di"], MatchEnd::FirstTo(60));
+        game.set_teams(Teams::partners());
+        let team_result = |winner, penalties: &[(PlayerId, i32)], team_scores: Vec<i32>| RoundResult { team_scores, ..result(winner, penalties) };
+
+        // Ann goes out, Cy losing nothing as a teammate
+        assert!(!game.finish_round(&team_result(0, &[(1, -20), (2, 0), (3, -15)], vec![35, -35])).over);
+        assert_eq!(game.standings(), vec![("A".to_string(), 35), ("B".to_string(), -35)]);
+        assert!(!game.finish_round(&team_result(3, &[(0, -5), (1, 0), (2, -25)], vec![-30, 30])).over);
+        assert_eq!(game.team_scores(), &[5, -5]);
+        // Ann alone has 30, but team A gets to 65
+        assert!(game.finish_round(&team_result(2, &[(1, -40), (3, -20)], vec![60, -60])).over);
+        assert_eq!(game.standings()[0], ("A".to_string(), 65));
+        assert_eq!(game.start_round(3).teams(), Some(&Teams::partners()));
+    }
+
+    #[test]
+    fn deal_rotation_test() {
+        let mut game = Match::new(&["Ann", "Bo", "Cy"], MatchEnd::FixedRounds(4));
+        let mut first_players = vec![];
+        for (round, result) in rounds().iter().enumerate() {
+            let state = game.start_round(round as u64);
+            assert_eq!(state.players().len(), 3);
+            first_players.push(state.current_player().name().to_string());
+            game.finish_round(result);
+        }
+
+        assert_eq!(first_players, ["Ann", "Bo", "Cy"]);
+        assert_eq!(game.stats().get("Ann").map(|ann| (ann.games, ann.wins, ann.penalty)), Some((3, 2, 5)));
+        assert_eq!(game.start_round(3).current_player_index(), 0);
+    }
+
+}
@@ -0,0 +1,502 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use thiserror::Error;
+use super::{replay_moves, Card, Color, GameEvent, GameState, MeldId, MeldParseError, Move, Mulligan, ParseCardError, PlayerId, ReplayError, RulesConfig, ScoringVariant, VariantRules};
+use super::shorthand::read_meld;
+
+/// Why a line of a game in move notation can't be read
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SyntaxError {
+    #[error("'{0}' is not a move, expected D, P, E, J, N, T, X or M")]
+    UnknownMove(String),
+    #[error("the move needs a tile, a meld or a player after it")]
+    MissingArgument,
+    #[error("'{0}' is one word too many")]
+    UnexpectedArgument(String),
+    #[error("{0}")]
+    InvalidTile(#[from] ParseCardError),
+    #[error("{0}")]
+    InvalidMeld(#[from] MeldParseError),
+    #[error("'{0}' is not a meld number")]
+    InvalidMeldId(String),
+    /// Players count from 1, like in the First header
+    #[error("'{0}' is not a player number")]
+    InvalidPlayer(String),
+    /// Extensions read like 3+R7 and wildcard retrievals like 2>Y11
+    #[error("expected {separator} between the meld number and the tile")]
+    MissingSeparator { separator: char },
+    #[error("a header reads like [Seed \"4\"]")]
+    InvalidHeader,
+    #[error("{0} is not a header")]
+    UnknownHeader(String),
+    #[error("'{value}' is not a valid {key}")]
+    InvalidValue { key: String, value: String },
+    #[error("the headers come before the moves")]
+    LateHeader
+}
+
+/// Error returned by parse_notation, GameRecord::from_text and the notation files
+#[derive(Debug, Error)]
+pub enum NotationError {
+    /// Lines and columns count from 1
+    #[error("line {line}, column {column}: {error}")]
+    Syntax { line: usize, column: usize, #[source] error: SyntaxError },
+    #[error("the game has no {0} header")]
+    MissingHeader(&'static str),
+    #[error("the game has no seed, so its draws can't be written down")]
+    NoSeed,
+    #[error("{0}")]
+    Replay(#[from] ReplayError),
+    #[error("{0}")]
+    Io(#[from] io::Error)
+}
+
+/// A game as its notation keeps it, enough to replay it with replay_moves: once the players,
+/// the seed and the rules give the deal, the moves give the rest.
+///
+/// The text starts with headers like PGN ones, one per line: a `[Player "Ann"]` for every
+/// player in order, then `[Seed "4"]`, `[First "2"]` counting players from 1 and `[Rules
+/// "standard"]`. One move per line follows, written by write_notation. Blank lines and the
+/// lines starting with `;` are left out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameRecord {
+    pub players: Vec<String>,
+    pub seed: u64,
+    pub first_player: PlayerId,
+    pub rules: RulesConfig,
+    pub moves: Vec<Move>
+}
+
+impl GameRecord {
+    /// The record of a game started with GameState::new_game_with_rules, None when the game
+    /// has no seed. The moves of resumed saves are not kept, such games replay differently.
+    pub fn from_game(game: &GameState) -> Option<GameRecord> {
+        let first_move = game.events().iter().find(|event| !matches!(event.mv, Move::Mulligan(_)));
+        Some(GameRecord {
+            players: game.players().iter().map(|player| player.name().to_string()).collect(),
+            seed: game.seed()?,
+            first_player: first_move.map_or(game.current_player_index(), |event| event.player),
+            rules: *game.rules(),
+            moves: game.events().iter().map(|event| event.mv.clone()).collect()
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for name in &self.players {
+            // Writing to a String never fails
+            writeln!(text, "[Player \"{}\"]", escape(name)).unwrap();
+        }
+        writeln!(text, "[Seed \"{}\"]", self.seed).unwrap();
+        writeln!(text, "[First \"{}\"]", self.first_player + 1).unwrap();
+        writeln!(text, "[Rules \"{}\"]", rules_notation(&self.rules)).unwrap();
+        text.push('\n');
+        for mv in &self.moves {
+            text.push_str(&move_notation(mv));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Reads a record written by to_text. Tiles are read with the numbers of the rules.
+    pub fn from_text(text: &str) -> Result<GameRecord, NotationError> {
+        let mut players = vec![];
+        let (mut seed, mut first_player, mut rules) = (None, None, None);
+        let mut moves = vec![];
+        for (line_idx, line) in lines(text) {
+            let syntax = |(column, error)| NotationError::Syntax { line: line_idx + 1, column, error };
+            if !line.trim_start().starts_with('[') {
+                moves.push(parse_move(line, &rules.unwrap_or_default()).map_err(syntax)?);
+                continue;
+            }
+            if !moves.is_empty() {
+                return Err(syntax((column_of(line, line.trim_start()), SyntaxError::LateHeader)));
+            }
+            let (key, value) = parse_header(line).map_err(syntax)?;
+            let invalid = || syntax((column_of(line, value), SyntaxError::InvalidValue { key: key.to_string(), value: value.to_string() }));
+            match key {
+                "Player" => players.push(unescape(value)),
+                "Seed" => seed = Some(value.parse().map_err(|_| invalid())?),
+                "First" => first_player = Some(value.parse::<usize>().ok().and_then(|first| first.checked_sub(1)).ok_or_else(invalid)?),
+                "Rules" => rules = Some(parse_rules(value).ok_or_else(invalid)?),
+                _ => return Err(syntax((column_of(line, key), SyntaxError::UnknownHeader(key.to_string()))))
+            }
+        }
+        if players.is_empty() {
+            return Err(NotationError::MissingHeader("Player"));
+        }
+        Ok(GameRecord {
+            players,
+            seed: seed.ok_or(NotationError::MissingHeader("Seed"))?,
+            first_player: first_player.unwrap_or(0),
+            rules: rules.unwrap_or_default(),
+            moves
+        })
+    }
+
+    /// Deals the game again and plays every move, see replay_moves
+    pub fn replay(&self) -> Result<GameState, ReplayError> {
+        let names: Vec<&str> = self.players.iter().map(String::as_str).collect();
+        replay_moves(&names, self.seed, self.first_player, self.rules, &self.moves)
+    }
+}
+
+/// The moves of the events, one per line: `D` draws, `P B2-B3-B4` lays down a new meld,
+/// `E 3+R7` adds R7 to meld #3, `J 2>Y11` puts Y11 in place of the wildcard of meld #2 and
+/// `N` ends the turn. With the DrawDiscard rules, `T` takes the top discard and `X R7` ends
+/// the turn discarding R7. `M 2` is the mulligan of the second player. Groups can also be
+/// read written like 7RBY, see parse_meld.
+pub fn write_notation(events: &[GameEvent]) -> String {
+    events.iter()
+        .map(|event| move_notation(&event.mv) + "\n")
+        .collect()
+}
+
+/// Reads the moves written by write_notation, with the standard rules. Blank lines and
+/// the lines starting with `;` are left out.
+pub fn parse_notation(text: &str) -> Result<Vec<Move>, NotationError> {
+    lines(text)
+        .map(|(line_idx, line)| {
+            parse_move(line, &RulesConfig::default())
+                .map_err(|(column, error)| NotationError::Syntax { line: line_idx + 1, column, error })
+        })
+        .collect()
+}
+
+/// Writes the GameRecord of the game to a text file, usually a .rmk one
+pub fn save_notation(path: impl AsRef<Path>, game: &GameState) -> Result<(), NotationError> {
+    let record = GameRecord::from_game(game).ok_or(NotationError::NoSeed)?;
+    fs::write(path, record.to_text())?;
+    Ok(())
+}
+
+/// Reads a file written by save_notation and replays the game it holds
+pub fn load_notation(path: impl AsRef<Path>) -> Result<GameState, NotationError> {
+    let record = GameRecord::from_text(&fs::read_to_string(path)?)?;
+    Ok(record.replay()?)
+}
+
+fn move_notation(mv: &Move) -> String {
+    match mv {
+        Move::Draw => "D".to_string(),
+        Move::PlayNewMeld(cards) => format!("P {}", cards.iter().map(Card::code).collect::<Vec<String>>().join("-")),
+        Move::ExtendMeld { meld, card } => format!("E {}+{}", meld.number(), card.code()),
+        Move::RetrieveWildcard { meld, card } => format!("J {}>{}", meld.number(), card.code()),
+        Move::EndTurn => "N".to_string(),
+        Move::TakeDiscard => "T".to_string(),
+        Move::Discard(card) => format!("X {}", card.code()),
+        Move::Mulligan(player) => format!("M {}", player + 1)
+    }
+}
+
+// The lines holding something, with their index
+fn lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with(';'))
+}
+
+// The column of part, a slice of line
+fn column_of(line: &str, part: &str) -> usize {
+    part.as_ptr() as usize - line.as_ptr() as usize + 1
+}
+
+// Errors come with their column
+fn parse_move(line: &str, rules: &RulesConfig) -> Result<Move, (usize, SyntaxError)> {
+    let mut words = line.split_whitespace();
+    // lines only gives lines holding a word
+    let letter = words.next().unwrap();
+    let argument = words.next();
+    if let Some(extra) = words.next() {
+        return Err((column_of(line, extra), SyntaxError::UnexpectedArgument(extra.to_string())));
+    }
+    let needs_argument = matches!(letter, "P" | "E" | "J" | "X" | "M");
+    match argument {
+        None if needs_argument => return Err((column_of(line, letter) + letter.len(), SyntaxError::MissingArgument)),
+        Some(argument) if !needs_argument && matches!(letter, "D" | "N" | "T") => {
+            return Err((column_of(line, argument), SyntaxError::UnexpectedArgument(argument.to_string())));
+        },
+        _ => {}
+    }
+    let tile = |code: &str| Card::parse_with(code, rules).map_err(|error| (column_of(line, code), error.into()));
+    let on_meld = |separator: char| {
+        // A needed argument is there
+        let argument = argument.unwrap();
+        let (meld, code) = argument.split_once(separator)
+            .ok_or_else(|| (column_of(line, argument), SyntaxError::MissingSeparator { separator }))?;
+        let meld = meld.parse().map_err(|_| (column_of(line, meld), SyntaxError::InvalidMeldId(meld.to_string())))?;
+        Ok((MeldId::from_number(meld), tile(code)?))
+    };
+
+    match letter {
+        "D" => Ok(Move::Draw),
+        "N" => Ok(Move::EndTurn),
+        "T" => Ok(Move::TakeDiscard),
+        "P" => {
+            let meld = argument.unwrap();
+            read_meld(meld, rules)
+                .map(Move::PlayNewMeld)
+                .map_err(|error| (column_of(line, meld) + error.position().unwrap_or(1) - 1, error.into()))
+        },
+        "E" => on_meld('+').map(|(meld, card)| Move::ExtendMeld { meld, card }),
+        "J" => on_meld('>').map(|(meld, card)| Move::RetrieveWildcard { meld, card }),
+        "X" => Ok(Move::Discard(tile(argument.unwrap())?)),
+        "M" => {
+            let player = argument.unwrap();
+            player.parse::<usize>().ok()
+                .and_then(|player| player.checked_sub(1))
+                .map(Move::Mulligan)
+                .ok_or_else(|| (column_of(line, player), SyntaxError::InvalidPlayer(player.to_string())))
+        },
+        _ => Err((column_of(line, letter), SyntaxError::UnknownMove(letter.to_string())))
+    }
+}
+
+// The key and the escaped value of a [Key "value"] line
+fn parse_header(line: &str) -> Result<(&str, &str), (usize, SyntaxError)> {
+    let invalid = || (column_of(line, line.trim_start()), SyntaxError::InvalidHeader);
+    let inner = line.trim().strip_prefix('[').and_then(|l| l.strip_suffix(']')).ok_or_else(invalid)?;
+    let (key, quoted) = inner.split_once(' ').ok_or_else(invalid)?;
+    let value = quoted.strip_prefix('"').and_then(|q| q.strip_suffix('"')).ok_or_else(invalid)?;
+    Ok((key, value))
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            _ => unescaped.push(c)
+        }
+    }
+    unescaped
+}
+
+// "standard", or the rules that change like "max_group_size=6 colors=RBKYGO"
+pub(super) fn rules_notation(rules: &RulesConfig) -> String {
+    let standard = RulesConfig::default();
+    let mut changes = vec![];
+    if rules.min_run_len != standard.min_run_len {
+        changes.push(format!("min_run_len={}", rules.min_run_len));
+    }
+    if rules.max_group_size != standard.max_group_size {
+        changes.push(format!("max_group_size={}", rules.max_group_size));
+    }
+    if rules.wrap_runs != standard.wrap_runs {
+        changes.push(format!("wrap_runs={}", rules.wrap_runs));
+    }
+    if rules.opening_points != standard.opening_points {
+        changes.push(format!("opening_points={}", rules.opening_points));
+    }
+    if rules.wildcards != standard.wildcards {
+        changes.push(format!("wildcards={}", rules.wildcards));
+    }
+    if rules.colors != standard.colors {
+        changes.push(format!("colors={}", rules.colors.iter().map(|color| Card::new(1, *color).code().remove(0)).collect::<String>()));
+    }
+    if rules.min_number != standard.min_number {
+        changes.push(format!("min_number={}", rules.min_number));
+    }
+    if rules.max_number != standard.max_number {
+        changes.push(format!("max_number={}", rules.max_number));
+    }
+    match rules.mulligan {
+        Mulligan::Off => {},
+        Mulligan::OncePerPlayer { only_without_sets: false } => changes.push("mulligan=once".to_string()),
+        Mulligan::OncePerPlayer { only_without_sets: true } => changes.push("mulligan=once_without_sets".to_string())
+    }
+    if rules.variant == VariantRules::DrawDiscard {
+        changes.push("variant=draw_discard".to_string());
+    }
+    if rules.twist_jokers {
+        changes.push("twist_jokers=true".to_string());
+    }
+    match rules.scoring {
+        ScoringVariant::Standard => {},
+        ScoringVariant::WinnerDouble => changes.push("scoring=winner_double".to_string()),
+        ScoringVariant::RaceTo(points) => changes.push(format!("scoring=race_to:{}", points))
+    }
+    if changes.is_empty() {
+        "standard".to_string()
+    } else {
+        changes.join(" ")
+    }
+}
+
+pub(super) fn parse_rules(value: &str) -> Option<RulesConfig> {
+    let mut rules = RulesConfig::default();
+    if value == "standard" {
+        return Some(rules);
+    }
+    for change in value.split_whitespace() {
+        let (key, value) = change.split_once('=')?;
+        match key {
+            "min_run_len" => rules.min_run_len = value.parse().ok()?,
+            "max_group_size" => rules.max_group_size = value.parse().ok()?,
+            "wrap_runs" => rules.wrap_runs = value.parse().ok()?,
+            "opening_points" => rules.opening_points = value.parse().ok()?,
+            "wildcards" => rules.wildcards = value.parse().ok()?,
+            // Only the color sets of the crate can be named
+            "colors" => rules.colors = [&Color::ALL[..], &Color::SIX[..]].into_iter()
+                .find(|colors| colors.iter().map(|color| Card::new(1, *color).code().remove(0)).collect::<String>() == value)?,
+            "min_number" => rules.min_number = value.parse().ok()?,
+            "max_number" => rules.max_number = value.parse().ok()?,
+            "mulligan" => rules.mulligan = match value {
+                "off" => Mulligan::Off,
+                "once" => Mulligan::OncePerPlayer { only_without_sets: false },
+                "once_without_sets" => Mulligan::OncePerPlayer { only_without_sets: true },
+                _ => return None
+            },
+            "variant" => rules.variant = match value {
+                "standard" => VariantRules::Standard,
+                "draw_discard" => VariantRules::DrawDiscard,
+                _ => return None
+            },
+            "twist_jokers" => rules.twist_jokers = value.parse().ok()?,
+            "scoring" => rules.scoring = match value.split_once(':') {
+                Some(("race_to", points)) => ScoringVariant::RaceTo(points.parse().ok()?),
+                None if value == "standard" => ScoringVariant::Standard,
+                None if value == "winner_double" => ScoringVariant::WinnerDouble,
+                _ => return None
+            },
+            _ => return None
+        }
+    }
+    Some(rules)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::{play_round, RandomStrategy, Strategy};
+
+    // Three random players some moves in, melds and extensions included
+    fn scripted_game(rules: RulesConfig) -> GameState {
+        let mut game = GameState::new_game_with_rules(&["Ann", "Bo \"the bot\"", "Cy"], 12, 2, rules);
+        let mut strategies: Vec<Box<dyn Strategy>> = (0..3)
+            .map(|seed| Box::new(RandomStrategy::new(seed)) as Box<dyn Strategy>)
+            .collect();
+        play_round(&mut game, &mut strategies, 300).unwrap();
+        game
+    }
+
+    #[test]
+    fn notation_round_trip_test() {
+        let game = scripted_game(RulesConfig::default());
+        let text = write_notation(game.events());
+        assert!(text.lines().any(|line| line.starts_with("P ")), "{}", text);
+
+        let moves = parse_notation(&text).unwrap();
+        assert_eq!(moves, game.events().iter().map(|event| event.mv.clone()).collect::<Vec<Move>>());
+
+        let record = GameRecord::from_game(&game).unwrap();
+        let text = record.to_text();
+        assert!(text.starts_with("[Player \"Ann\"]\n[Player \"Bo \\\"the bot\\\"\"]\n[Player \"Cy\"]\n[Seed \"12\"]\n[First \"3\"]\n[Rules \"standard\"]\n\n"));
+        assert_eq!(GameRecord::from_text(&text).unwrap(), record);
+        assert_eq!(record.replay().unwrap(), game);
+
+        let rules = RulesConfig {
+            wrap_runs: true,
+            mulligan: Mulligan::OncePerPlayer { only_without_sets: true },
+            scoring: ScoringVariant::RaceTo(100),
+            ..RulesConfig::six_colors()
+        };
+        let record = GameRecord::from_game(&scripted_game(rules)).unwrap();
+        assert!(record.to_text().contains("[Rules \"max_group_size=6 wrap_runs=true colors=RBKYGO mulligan=once_without_sets scoring=race_to:100\"]"));
+        assert_eq!(GameRecord::from_text(&record.to_text()).unwrap(), record);
+    }
+
+    #[test]
+    fn every_move_test() {
+        let text = "; Ann opens\nD\nP B2-B3-W\n\nE 3+R7\nJ 12>Y11\nN\nT\nX K13\nP 8WYR\nM 2\n";
+
+        assert_eq!(parse_notation(text).unwrap(), vec![
+            Move::Draw,
+            Move::PlayNewMeld(vec![Card::new(2, Color::Blue), Card::new(3, Color::Blue), Card::Wildcard]),
+            Move::ExtendMeld { meld: MeldId::from_number(3), card: Card::new(7, Color::Red) },
+            Move::RetrieveWildcard { meld: MeldId::from_number(12), card: Card::new(11, Color::Yellow) },
+            Move::EndTurn,
+            Move::TakeDiscard,
+            Move::Discard(Card::new(13, Color::Black)),
+            // In the order written
+            Move::PlayNewMeld(vec![Card::Wildcard, Card::new(8, Color::Yellow), Card::new(8, Color::Red)]),
+            Move::Mulligan(1)
+        ]);
+    }
+
+    #[test]
+    fn mulligan_record_test() {
+        let rules = RulesConfig { mulligan: Mulligan::OncePerPlayer { only_without_sets: false }, ..RulesConfig::default() };
+        let mut game = GameState::new_game_with_rules(&["Ann", "Bo"], 12, 1, rules);
+        game.mulligan(0).unwrap();
+        game.apply_move(Move::Draw).unwrap();
+        game.apply_move(Move::EndTurn).unwrap();
+
+        let record = GameRecord::from_game(&game).unwrap();
+        assert_eq!(record.first_player, 1);
+        assert!(record.to_text().ends_with("\nM 1\nD\nN\n"), "{}", record.to_text());
+        assert_eq!(GameRecord::from_text(&record.to_text()).unwrap().replay().unwrap(), game);
+    }
+
+    fn syntax_error(text: &str) -> (usize, usize, SyntaxError) {
+        match parse_notation(text) {
+            Err(NotationError::Syntax { line, column, error }) => (line, column, error),
+            other => panic!("{:?}", other)
+        }
+    }
+
+    #[test]
+    fn malformed_lines_test() {
+        assert_eq!(syntax_error("D\nN\nQ"), (3, 1, SyntaxError::UnknownMove("Q".to_string())));
+        assert_eq!(syntax_error("P"), (1, 2, SyntaxError::MissingArgument));
+        assert_eq!(syntax_error("  D R4"), (1, 5, SyntaxError::UnexpectedArgument("R4".to_string())));
+        assert_eq!(syntax_error("P B2 B3"), (1, 6, SyntaxError::UnexpectedArgument("B3".to_string())));
+        assert!(matches!(syntax_error("P B2-B33-B4"), (1, 6, SyntaxError::InvalidMeld(MeldParseError::InvalidTile { .. }))));
+        assert!(matches!(syntax_error("P 7RXB"), (1, 5, SyntaxError::InvalidMeld(MeldParseError::InvalidColor { .. }))));
+        assert_eq!(syntax_error("E 3R7"), (1, 3, SyntaxError::MissingSeparator { separator: '+' }));
+        assert_eq!(syntax_error("J x>R7"), (1, 3, SyntaxError::InvalidMeldId("x".to_string())));
+        assert!(matches!(syntax_error("E 3+R77"), (1, 5, SyntaxError::InvalidTile(_))));
+        assert_eq!(syntax_error("M 0"), (1, 3, SyntaxError::InvalidPlayer("0".to_string())));
+    }
+
+    #[test]
+    fn malformed_record_test() {
+        let record = |text: &str| match GameRecord::from_text(text) {
+            Err(NotationError::Syntax { line, column, error }) => (line, column, error),
+            other => panic!("{:?}", other)
+        };
+
+        assert_eq!(record("[Player \"Ann\"]\n[Seed \"x\"]"), (2, 8, SyntaxError::InvalidValue { key: "Seed".to_string(), value: "x".to_string() }));
+        assert_eq!(record("[Player Ann]"), (1, 1, SyntaxError::InvalidHeader));
+        assert_eq!(record("[Date \"today\"]"), (1, 2, SyntaxError::UnknownHeader("Date".to_string())));
+        assert_eq!(record("[Player \"Ann\"]\nD\n[Seed \"4\"]"), (3, 1, SyntaxError::LateHeader));
+        assert!(matches!(record("[Rules \"wrap_runs=maybe\"]"), (1, 9, SyntaxError::InvalidValue { .. })));
+        assert!(matches!(GameRecord::from_text("[Player \"Ann\"]\nD"), Err(NotationError::MissingHeader("Seed"))));
+        assert!(matches!(GameRecord::from_text("[Seed \"4\"]"), Err(NotationError::MissingHeader("Player"))));
+    }
+
+    #[test]
+    fn notation_file_test() {
+        let game = scripted_game(RulesConfig::default());
+        let path = std::env::temp_dir().join(format!("rummikub-notation-{}.rmk", std::process::id()));
+
+        save_notation(&path, &game).unwrap();
+        let loaded = load_notation(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), game);
+
+        // A draw taken back gives the next player a move they can't play
+        let mut record = GameRecord::from_game(&game).unwrap();
+        record.moves.remove(0);
+        assert!(matches!(record.replay(), Err(ReplayError::IllegalMove { index: 0, .. })));
+    }
+
+}
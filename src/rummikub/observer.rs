@@ -0,0 +1,73 @@
+use core::fmt;
+use super::{Card, MeldId, PlayerId, RoundResult};
+
+/// What everyone at the table sees of a draw
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicDraw {
+    /// The tiles left in the deck after the draw
+    pub deck_len: usize,
+    /// The tile taken with Move::TakeDiscard, a tile drawn from the deck staying hidden
+    pub taken_discard: Option<Card>
+}
+
+/// Told about the moves of a game as they are played, see GameState::subscribe. The methods
+/// get the game only through their arguments, and do nothing unless overridden.
+pub trait GameObserver: Send + Sync {
+    /// The player drew from the deck or took the top of the discard pile
+    fn on_draw(&self, _player: PlayerId, _draw: &PublicDraw) {}
+
+    /// The player laid down a new meld, holding cards
+    fn on_meld_played(&self, _player: PlayerId, _meld: MeldId, _cards: &[Card]) {}
+
+    /// The player added card to the meld, which now holds cards
+    fn on_meld_extended(&self, _player: PlayerId, _meld: MeldId, _card: Card, _cards: &[Card]) {}
+
+    /// The player swapped card for the wildcard of the meld
+    fn on_joker_retrieved(&self, _player: PlayerId, _meld: MeldId, _card: Card) {}
+
+    /// The player ended their turn, passing it or discarding
+    fn on_turn_end(&self, _player: PlayerId) {}
+
+    /// The round is over, after the move of the winner or the last turn once the deck ran out
+    fn on_round_end(&self, _result: &RoundResult) {}
+}
+
+// The observers of a game. A copy of the game starts without them, so that the searches
+// trying moves on copies don't report those moves, and games compare the same whoever
+// watches them.
+#[derive(Default)]
+pub(super) struct Observers(Vec<Box<dyn GameObserver>>);
+
+impl Observers {
+    pub(super) fn push(&mut self, observer: Box<dyn GameObserver>) {
+        self.0.push(observer);
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(super) fn iter(&self) -> impl Iterator<Item = &dyn GameObserver> {
+        self.0.iter().map(|observer| observer.as_ref())
+    }
+}
+
+impl Clone for Observers {
+    fn clone(&self) -> Self {
+        Observers::default()
+    }
+}
+
+impl PartialEq for Observers {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Observers {}
+
+impl fmt::Debug for Observers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} observers", self.0.len())
+    }
+}
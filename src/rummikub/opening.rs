@@ -0,0 +1,182 @@
+use super::Card;
+use super::partition::{create_candidates, Candidate, TileCounts};
+use crate::prelude::*;
+
+// Points needed by the first meld of a player
+pub(super) const OPENING_POINTS: u32 = 30;
+
+/// Lists every combination of disjoint sets worth at least 30 points, wildcards counting as the
+/// number they stand for. The best combinations come first; an empty result means the player
+/// has no opening meld and has to draw.
+pub fn find_opening_melds(cards: &[Card]) -> Vec<Vec<Vec<Card>>> {
+    let mut tiles = TileCounts::from_cards(cards);
+    let candidates = create_candidates(&tiles.counts, tiles.wildcards);
+
+    let mut combinations: Vec<(u32, Vec<usize>)> = vec![];
+    let mut chosen = vec![];
+    collect_combinations(&candidates, 0, &mut tiles, &mut chosen, 0, &mut combinations);
+
+    combinations.sort_by(|(points, sets), (other_points, other_sets)| {
+        other_points.cmp(points).then(sets.len().cmp(&other_sets.len()))
+    });
+    combinations.into_iter()
+        .map(|(_, sets)| sets.iter().map(|idx| candidates[*idx].cards.clone()).collect())
+        .collect()
+}
+
+/// Whether the tray has an opening meld, like !find_opening_melds(cards).is_empty() but
+/// stopping at the first combination found
+pub fn has_opening_meld(cards: &[Card]) -> bool {
+    let mut tiles = TileCounts::from_cards(cards);
+    let candidates = create_candidates(&tiles.counts, tiles.wildcards);
+    reaches_opening(&candidates, 0, &mut tiles, 0)
+}
+
+fn reaches_opening(candidates: &[Candidate], from: usize, tiles: &mut TileCounts, points: u32) -> bool {
+    if points >= OPENING_POINTS {
+        return true;
+    }
+    for idx in from..candidates.len() {
+        let candidate = &candidates[idx];
+        let available = candidate.wildcards <= tiles.wildcards &&
+            candidate.numbered.iter().all(|n| tiles.counts[*n] > 0);
+        if !available {
+            continue;
+        }
+        tiles.remove_set(candidate);
+        let found = reaches_opening(candidates, idx, tiles, points + candidate.points);
+        tiles.restore_set(candidate);
+        if found {
+            return true;
+        }
+    }
+    false
+}
+
+fn collect_combinations(
+    candidates: &[Candidate],
+    from: usize,
+    tiles: &mut TileCounts,
+    chosen: &mut Vec<usize>,
+    points: u32,
+    combinations: &mut Vec<(u32, Vec<usize>)>
+) {
+    if points >= OPENING_POINTS {
+        combinations.push((points, chosen.clone()));
+    }
+
+    // Candidates are tried in order, and can be picked again when the tray holds enough copies
+    for idx in from..candidates.len() {
+        let candidate = &candidates[idx];
+        let available = candidate.wildcards <= tiles.wildcards &&
+            candidate.numbered.iter().all(|n| tiles.counts[*n] > 0);
+        if !available {
+            continue;
+        }
+        tiles.remove_set(candidate);
+        chosen.push(idx);
+        collect_combinations(candidates, idx, tiles, chosen, points + candidate.points, combinations);
+        chosen.pop();
+        tiles.restore_set(candidate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::Color;
+
+    #[test]
+    fn opening_single_run_test() {
+        let test_tray = vec![
+            Card::new(10, Color::Red),
+            Card::new(11, Color::Red),
+            Card::new(12, Color::Red),
+            Card::new(2, Color::Blue),
+            Card::new(5, Color::Yellow)
+        ];
+
+        let openings = find_opening_melds(&test_tray);
+
+        assert_eq!(openings, vec![vec![vec![
+            Card::new(10, Color::Red),
+            Card::new(11, Color::Red),
+            Card::new(12, Color::Red)
+        ]]]);
+    }
+
+    #[test]
+    fn opening_two_groups_test() {
+        // 15 + 18 points: neither group is enough on its own
+        let test_tray = vec![
+            Card::new(5, Color::Red),
+            Card::new(5, Color::Blue),
+            Card::new(5, Color::Black),
+            Card::new(6, Color::Red),
+            Card::new(6, Color::Blue),
+            Card::new(6, Color::Yellow),
+            Card::new(13, Color::Black)
+        ];
+
+        let openings = find_opening_melds(&test_tray);
+
+        assert_eq!(openings.len(), 1);
+        assert_eq!(openings[0].len(), 2);
+    }
+
+    #[test]
+    fn opening_with_wildcard_value_test() {
+        // The wildcard is worth 12 here
+        let test_tray = vec![
+            Card::new(10, Color::Blue),
+            Card::new(11, Color::Blue),
+            Card::Wildcard
+        ];
+
+        let openings = find_opening_melds(&test_tray);
+
+        assert_eq!(openings, vec![vec![vec![
+            Card::new(10, Color::Blue),
+            Card::new(11, Color::Blue),
+            Card::Wildcard
+        ]]]);
+    }
+
+    #[test]
+    fn opening_best_first_test() {
+        let test_tray = vec![
+            Card::new(9, Color::Red),
+            Card::new(10, Color::Red),
+            Card::new(11, Color::Red),
+            Card::new(12, Color::Red)
+        ];
+
+        let openings = find_opening_melds(&test_tray);
+
+        // 9-12, 10-12 and 9-11 are all good enough
+        assert_eq!(openings.len(), 3);
+        assert_eq!(openings[0][0].len(), 4);
+    }
+
+    #[test]
+    fn opening_not_enough_points_test() {
+        // The best play is 5-6-7-8 Blue and the group of 1s: 26 + 3 = 29 points
+        let test_tray = vec![
+            Card::new(5, Color::Blue),
+            Card::new(6, Color::Blue),
+            Card::new(7, Color::Blue),
+            Card::new(8, Color::Blue),
+            Card::new(1, Color::Red),
+            Card::new(1, Color::Yellow),
+            Card::new(1, Color::Black),
+            Card::new(13, Color::Black),
+            Card::new(11, Color::Red)
+        ];
+
+        assert!(find_opening_melds(&test_tray).is_empty());
+        assert!(!has_opening_meld(&test_tray));
+        assert!(has_opening_meld(&test_tray[..4].iter().chain(&[Card::Wildcard]).copied().collect::<Vec<Card>>()));
+    }
+
+}
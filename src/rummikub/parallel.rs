@@ -0,0 +1,47 @@
+// Helpers to spread the searches over the rayon thread pool. Without the parallel feature
+// they just run everything on the current thread.
+#[cfg(feature = "parallel")]
+use std::cell::Cell;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use crate::prelude::*;
+
+#[cfg(feature = "parallel")]
+thread_local! {
+    // Set by sequential, so the tests can compare with the results on a single thread
+    static SEQUENTIAL: Cell<bool> = const { Cell::new(false) };
+}
+
+#[cfg(feature = "parallel")]
+pub(super) fn is_enabled() -> bool {
+    !SEQUENTIAL.with(|s| s.get())
+}
+
+#[cfg(not(feature = "parallel"))]
+pub(super) fn is_enabled() -> bool {
+    false
+}
+
+// Runs f with parallelism disabled on the current thread
+#[cfg(all(test, feature = "parallel"))]
+pub(super) fn sequential<R>(f: impl FnOnce() -> R) -> R {
+    let previous = SEQUENTIAL.with(|s| s.replace(true));
+    let result = f();
+    SEQUENTIAL.with(|s| s.set(previous));
+    result
+}
+
+#[cfg(all(test, not(feature = "parallel")))]
+pub(super) fn sequential<R>(f: impl FnOnce() -> R) -> R {
+    f()
+}
+
+// Maps the items with par_iter, keeping the results in order. Searches nested in f share
+// the same pool instead of starting threads of their own.
+pub(super) fn map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync + Send) -> Vec<R> {
+    #[cfg(feature = "parallel")]
+    if is_enabled() && items.len() >= 2 {
+        return items.par_iter().map(f).collect();
+    }
+    items.iter().map(f).collect()
+}
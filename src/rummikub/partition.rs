@@ -0,0 +1,805 @@
+use alloc::collections::BTreeMap;
+use core::borrow::Borrow;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+use super::{Card, Color};
+use super::bitset::{self, PackedTray};
+use super::wildcards::resolve_wildcards;
+use crate::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Partition {
+    pub sets: Vec<Vec<Card>>,
+    pub leftover: Vec<Card>
+}
+
+impl Partition {
+    pub fn played_count(&self) -> usize {
+        self.sets.iter().map(|s| s.len()).sum()
+    }
+}
+
+/// What best_partition_with_budget found
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetedPartition {
+    /// The best partition found, only proven to be the best one when complete is true
+    pub partition: Partition,
+    /// Steps of the search, each one trying to add a set or to keep a tile in the hand
+    pub nodes_explored: u64,
+    /// Whether the search went through before the budget ran out
+    pub complete: bool
+}
+
+// A set the solver can pick: the numbered tiles it consumes and how many wildcards it needs
+pub(super) struct Candidate {
+    pub(super) cards: Vec<Card>,
+    pub(super) numbered: Vec<usize>,
+    pub(super) wildcards: usize,
+    // Face value of the set, with wildcards worth the number they stand for
+    pub(super) points: u32
+}
+
+impl Candidate {
+    fn new(mut numbered: Vec<usize>, wildcards: usize) -> Candidate {
+        numbered.sort();
+        let mut cards: Vec<Card> = numbered.iter().map(|idx| tile_card(*idx)).collect();
+        cards.sort();
+        cards.extend(core::iter::repeat_n(Card::Wildcard, wildcards));
+        let refs: Vec<&Card> = cards.iter().collect();
+        let points = resolve_wildcards(&refs)
+            .map(|values| values.iter().map(|(number, _)| *number as u32).sum())
+            .unwrap_or(0);
+        Candidate { cards, numbered, wildcards, points }
+    }
+}
+
+// The tray as counts of every numbered tile, plus the wildcards
+pub(super) struct TileCounts {
+    pub(super) counts: Vec<usize>,
+    pub(super) wildcards: usize,
+    // Tiles out of range and jokers of the Twist rules can't belong to any set
+    pub(super) unusable: Vec<Card>
+}
+
+impl TileCounts {
+    pub(super) fn from_cards(cards: &[Card]) -> TileCounts {
+        let mut tiles = TileCounts {
+            counts: vec![0; 13 * Color::ALL.len()],
+            wildcards: 0,
+            unusable: vec![]
+        };
+        for card in cards {
+            match card {
+                Card::Numbered { number, color } => match tile_index(*number, *color) {
+                    Some(idx) => tiles.counts[idx] += 1,
+                    None => tiles.unusable.push(*card)
+                },
+                Card::Wildcard => tiles.wildcards += 1,
+                Card::DoubleJoker | Card::MirrorJoker => tiles.unusable.push(*card)
+            }
+        }
+        tiles
+    }
+
+    pub(super) fn numbered_count(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    pub(super) fn remove_set(&mut self, candidate: &Candidate) {
+        for idx in &candidate.numbered {
+            self.counts[*idx] -= 1;
+        }
+        self.wildcards -= candidate.wildcards;
+    }
+
+    // Removes the numbered tiles of the cards, and a wildcard for every wildcard
+    pub(super) fn remove_cards(&mut self, cards: &[Card]) {
+        for card in cards {
+            match card {
+                Card::Numbered { number, color } => if let Some(idx) = tile_index(*number, *color) {
+                    self.counts[idx] -= 1;
+                },
+                Card::Wildcard => self.wildcards -= 1,
+                Card::DoubleJoker | Card::MirrorJoker => {}
+            }
+        }
+    }
+
+    pub(super) fn restore_set(&mut self, candidate: &Candidate) {
+        for idx in &candidate.numbered {
+            self.counts[*idx] += 1;
+        }
+        self.wildcards += candidate.wildcards;
+    }
+
+    // The remaining tiles, sorted
+    pub(super) fn into_cards(self) -> Vec<Card> {
+        let mut cards = self.unusable;
+        for (idx, count) in self.counts.iter().enumerate() {
+            cards.extend(core::iter::repeat_n(tile_card(idx), *count));
+        }
+        cards.extend(core::iter::repeat_n(Card::Wildcard, self.wildcards));
+        cards.sort();
+        cards
+    }
+}
+
+// Position of a numbered card in the counts table
+pub(super) fn tile_index(number: i8, color: Color) -> Option<usize> {
+    if !(1..=13).contains(&number) {
+        return None;
+    }
+    let color_idx = Color::ALL.iter().position(|c| *c == color)?;
+    Some((number as usize - 1) * Color::ALL.len() + color_idx)
+}
+
+pub(super) fn tile_card(idx: usize) -> Card {
+    let number = (idx / Color::ALL.len()) as i8 + 1;
+    Card::new(number, Color::ALL[idx % Color::ALL.len()])
+}
+
+// Branch and bound on tile counts
+struct CountsSolver<'a> {
+    candidates: &'a [Candidate],
+    // Candidates indexed by the lowest tile they use
+    by_first: Vec<Vec<usize>>,
+    counts: Vec<usize>,
+    wildcards: usize,
+    chosen: Vec<usize>,
+    played: usize,
+    best: Vec<usize>,
+    best_played: usize,
+    // When search has to give up, if ever
+    #[cfg(feature = "std")]
+    deadline: Option<Instant>,
+    nodes: u64,
+    timed_out: bool
+}
+
+impl<'a> CountsSolver<'a> {
+    fn new(tiles: &TileCounts, candidates: &'a [Candidate]) -> CountsSolver<'a> {
+        let mut by_first: Vec<Vec<usize>> = (0..tiles.counts.len()).map(|_| vec![]).collect();
+        for (idx, candidate) in candidates.iter().enumerate() {
+            by_first[candidate.numbered[0]].push(idx);
+        }
+        CountsSolver {
+            candidates,
+            by_first,
+            counts: tiles.counts.clone(),
+            wildcards: tiles.wildcards,
+            chosen: vec![],
+            played: 0,
+            best: vec![],
+            best_played: 0,
+            #[cfg(feature = "std")]
+            deadline: None,
+            nodes: 0,
+            timed_out: false
+        }
+    }
+
+    fn is_available(&self, candidate: &Candidate) -> bool {
+        candidate.wildcards <= self.wildcards &&
+            candidate.numbered.iter().all(|n| self.counts[*n] > 0)
+    }
+
+    fn take(&mut self, candidate_idx: usize) {
+        let candidate = &self.candidates[candidate_idx];
+        for n in &candidate.numbered {
+            self.counts[*n] -= 1;
+        }
+        self.wildcards -= candidate.wildcards;
+        self.played += candidate.cards.len();
+        self.chosen.push(candidate_idx);
+    }
+
+    fn give_back(&mut self) {
+        let candidate = &self.candidates[self.chosen.pop().unwrap()];
+        for n in &candidate.numbered {
+            self.counts[*n] += 1;
+        }
+        self.wildcards += candidate.wildcards;
+        self.played -= candidate.cards.len();
+    }
+
+    fn search(&mut self, from: usize, remaining: usize) {
+        if self.played > self.best_played {
+            self.best_played = self.played;
+            self.best = self.chosen.clone();
+        }
+        self.nodes += 1;
+        // Reading the clock on every node would slow the search down
+        #[cfg(feature = "std")]
+        if self.nodes.is_multiple_of(256) && self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            self.timed_out = true;
+        }
+        if self.timed_out {
+            return;
+        }
+        // Even playing every remaining tile can't beat the best partition found so far
+        if self.played + remaining + self.wildcards <= self.best_played {
+            return;
+        }
+        let idx = match (from..self.counts.len()).find(|idx| self.counts[*idx] > 0) {
+            Some(idx) => idx,
+            None => return
+        };
+
+        for pos in 0..self.by_first[idx].len() {
+            let candidate_idx = self.by_first[idx][pos];
+            let candidate = &self.candidates[candidate_idx];
+            if !self.is_available(candidate) {
+                continue;
+            }
+            let used = candidate.numbered.len();
+            self.take(candidate_idx);
+            self.search(idx, remaining - used);
+            self.give_back();
+        }
+
+        // Keep one copy of this tile in the hand
+        self.counts[idx] -= 1;
+        self.search(idx, remaining - 1);
+        self.counts[idx] += 1;
+    }
+
+    // Like search, but only the tiles counted in keepable may stay out of the sets, and the
+    // best partition is only taken once every tile is placed
+    fn search_turn(&mut self, from: usize, remaining: usize, keepable: &mut [usize], keepable_wildcards: usize) {
+        self.nodes += 1;
+        if self.played + remaining + self.wildcards <= self.best_played {
+            return;
+        }
+        let idx = match (from..self.counts.len()).find(|idx| self.counts[*idx] > 0) {
+            Some(idx) => idx,
+            None => {
+                // The wildcards left out must be ones that can stay
+                if self.wildcards <= keepable_wildcards {
+                    self.best_played = self.played;
+                    self.best = self.chosen.clone();
+                }
+                return;
+            }
+        };
+
+        for pos in 0..self.by_first[idx].len() {
+            let candidate_idx = self.by_first[idx][pos];
+            let candidate = &self.candidates[candidate_idx];
+            if !self.is_available(candidate) {
+                continue;
+            }
+            let used = candidate.numbered.len();
+            self.take(candidate_idx);
+            self.search_turn(idx, remaining - used, keepable, keepable_wildcards);
+            self.give_back();
+        }
+
+        if keepable[idx] > 0 {
+            keepable[idx] -= 1;
+            self.counts[idx] -= 1;
+            self.search_turn(idx, remaining - 1, keepable, keepable_wildcards);
+            self.counts[idx] += 1;
+            keepable[idx] += 1;
+        }
+    }
+
+    // Looks only for partitions using every tile, stopping at the first one
+    fn search_complete(&mut self, from: usize) -> bool {
+        let idx = match (from..self.counts.len()).find(|idx| self.counts[*idx] > 0) {
+            Some(idx) => idx,
+            None => {
+                // Every wildcard must end up in a set too
+                if self.wildcards == 0 {
+                    self.best = self.chosen.clone();
+                }
+                return self.wildcards == 0;
+            }
+        };
+
+        // The lowest tile left must belong to one of the sets starting with it
+        for pos in 0..self.by_first[idx].len() {
+            let candidate_idx = self.by_first[idx][pos];
+            if !self.is_available(&self.candidates[candidate_idx]) {
+                continue;
+            }
+            self.take(candidate_idx);
+            let found = self.search_complete(idx);
+            self.give_back();
+            if found {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+pub(super) fn create_candidates(counts: &[usize], wildcards: usize) -> Vec<Candidate> {
+    let mut candidates = vec![];
+    let held = |number: i8, color: Color| tile_index(number, color).filter(|idx| counts[*idx] > 0);
+
+    // Runs: every window of a color, where each slot is a held tile or a wildcard.
+    // Held tiles may be replaced by wildcards too, to free them for other sets.
+    for color in Color::ALL {
+        for start in 1..=11 {
+            for end in start+2..=13 {
+                let slots: Vec<Option<usize>> = (start..=end).map(|n| held(n, color)).collect();
+                let missing = slots.iter().filter(|s| s.is_none()).count();
+                if missing > wildcards {
+                    continue;
+                }
+                let held_slots: Vec<usize> = (0..slots.len()).filter(|i| slots[*i].is_some()).collect();
+                for replaced in wildcard_choices(&held_slots, wildcards - missing) {
+                    let numbered: Vec<usize> = held_slots.iter()
+                        .filter(|i| !replaced.contains(i))
+                        .map(|i| slots[*i].unwrap())
+                        .collect();
+                    if numbered.is_empty() {
+                        continue;
+                    }
+                    let run_wildcards = slots.len() - numbered.len();
+                    candidates.push(Candidate::new(numbered, run_wildcards));
+                }
+            }
+        }
+    }
+
+    // Groups: any subset of the held colors of a number, completed with wildcards
+    for number in 1..=13 {
+        let colors: Vec<usize> = Color::ALL.iter().filter_map(|c| held(number, *c)).collect();
+        for mask in 1..(1 << colors.len()) {
+            let numbered: Vec<usize> = (0..colors.len())
+                .filter(|i| mask & (1 << i) != 0)
+                .map(|i| colors[i])
+                .collect();
+            for group_wildcards in 0..=wildcards {
+                let len = numbered.len() + group_wildcards;
+                if (3..=4).contains(&len) {
+                    candidates.push(Candidate::new(numbered.clone(), group_wildcards));
+                }
+            }
+        }
+    }
+
+    // The same tiles can be arranged in more than one way: keep only one of them
+    let mut unique: BTreeMap<(Vec<usize>, usize), Candidate> = BTreeMap::new();
+    for candidate in candidates {
+        unique.entry((candidate.numbered.clone(), candidate.wildcards)).or_insert(candidate);
+    }
+    unique.into_values().collect()
+}
+
+// All the ways of picking at most max_count slots to be filled by wildcards
+fn wildcard_choices(slots: &[usize], max_count: usize) -> Vec<Vec<usize>> {
+    let mut choices = vec![vec![]];
+    for slot in slots {
+        let mut extended: Vec<Vec<usize>> = choices.iter()
+            .filter(|c| c.len() < max_count)
+            .map(|c| {
+                let mut choice = c.clone();
+                choice.push(*slot);
+                choice
+            })
+            .collect();
+        choices.append(&mut extended);
+    }
+    choices
+}
+
+/// Picks the sets which play the most tiles, using every physical tile (wildcards included)
+/// at most once. Sets are sorted like valid_sets ones, and so is the leftover. The tiles can
+/// come from a slice, a Tray or any iterator of cards or references to them.
+pub fn best_partition(cards: impl IntoIterator<Item = impl Borrow<Card>>) -> Partition {
+    best_partition_of(&owned_cards(cards))
+}
+
+fn best_partition_of(cards: &[Card]) -> Partition {
+    let mut tiles = TileCounts::from_cards(cards);
+    let sets = match PackedTray::from_cards(cards) {
+        Some(tray) => bitset::best_sets(&tray),
+        None => best_sets_by_counts(&tiles)
+    };
+    for set in &sets {
+        tiles.remove_cards(set);
+    }
+    Partition { sets, leftover: tiles.into_cards() }
+}
+
+/// Like best_partition, but gives up once the budget is spent and returns the best partition
+/// found so far. The search tries the sets that use the lowest tiles first, so it has a
+/// partition early on and improves it from there. A 14-tile tray is usually solved well
+/// within 10ms.
+#[cfg(feature = "std")]
+pub fn best_partition_with_budget(cards: impl IntoIterator<Item = impl Borrow<Card>>, budget: Duration) -> BudgetedPartition {
+    let deadline = Instant::now() + budget;
+    let mut tiles = TileCounts::from_cards(&owned_cards(cards));
+    let candidates = create_candidates(&tiles.counts, tiles.wildcards);
+    let mut solver = CountsSolver::new(&tiles, &candidates);
+    solver.deadline = Some(deadline);
+    solver.search(0, tiles.numbered_count());
+
+    let mut sets: Vec<Vec<Card>> = solver.best.iter().map(|idx| candidates[*idx].cards.clone()).collect();
+    sets.sort();
+    for set in &sets {
+        tiles.remove_cards(set);
+    }
+    BudgetedPartition {
+        partition: Partition { sets, leftover: tiles.into_cards() },
+        nodes_explored: solver.nodes,
+        complete: !solver.timed_out
+    }
+}
+
+/// Returns a partition of the whole tray into valid sets, if the player can go out
+pub fn can_go_out(cards: impl IntoIterator<Item = impl Borrow<Card>>) -> Option<Vec<Vec<Card>>> {
+    let cards = owned_cards(cards);
+    let tiles = TileCounts::from_cards(&cards);
+    if !tiles.unusable.is_empty() {
+        return None;
+    }
+    match PackedTray::from_cards(&cards) {
+        Some(tray) => bitset::complete_sets(&tray),
+        None => complete_sets_by_counts(&tiles)
+    }
+}
+
+// The solvers count the tiles, so they take a copy of them whatever the finders were given
+fn owned_cards(cards: impl IntoIterator<Item = impl Borrow<Card>>) -> Vec<Card> {
+    cards.into_iter().map(|card| *card.borrow()).collect()
+}
+
+// The search on tile counts, for trays too big for the bitset solver
+fn best_sets_by_counts(tiles: &TileCounts) -> Vec<Vec<Card>> {
+    let candidates = create_candidates(&tiles.counts, tiles.wildcards);
+    let mut solver = CountsSolver::new(tiles, &candidates);
+    solver.search(0, tiles.numbered_count());
+    solver.best.iter().map(|idx| candidates[*idx].cards.clone()).collect()
+}
+
+// The sets holding every tile of placed and the most of kept, if they hold more tiles than
+// placed alone. The tiles of kept that are left out are the ones not in any set.
+pub(super) fn best_sets_placing(placed: &[Card], kept: &[Card]) -> Option<Vec<Vec<Card>>> {
+    let all = [placed, kept].concat();
+    let tiles = TileCounts::from_cards(&all);
+    if !TileCounts::from_cards(placed).unusable.is_empty() {
+        return None;
+    }
+    let mut keepable = TileCounts::from_cards(kept);
+    let candidates = create_candidates(&tiles.counts, tiles.wildcards);
+    let mut solver = CountsSolver::new(&tiles, &candidates);
+    // Playing only the placed tiles is not enough
+    solver.best_played = placed.len();
+    solver.search_turn(0, tiles.numbered_count(), &mut keepable.counts, keepable.wildcards);
+    if solver.best_played == placed.len() {
+        return None;
+    }
+    let mut sets: Vec<Vec<Card>> = solver.best.iter().map(|idx| candidates[*idx].cards.clone()).collect();
+    sets.sort();
+    Some(sets)
+}
+
+fn complete_sets_by_counts(tiles: &TileCounts) -> Option<Vec<Vec<Card>>> {
+    let candidates = create_candidates(&tiles.counts, tiles.wildcards);
+    let mut solver = CountsSolver::new(tiles, &candidates);
+    if !solver.search_complete(0) {
+        return None;
+    }
+    Some(solver.best.iter().map(|idx| candidates[*idx].cards.clone()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[cfg(feature = "std")]
+    use std::time::{Duration, Instant};
+    use super::*;
+    use super::super::{validate_set, Deck};
+    use super::super::tests::count_allocations;
+
+    fn assert_valid_partition(cards: &[Card], partition: &Partition) {
+        for set in &partition.sets {
+            assert!(validate_set(set).is_ok(), "{:?}", set);
+        }
+        let mut used: Vec<Card> = partition.sets.iter()
+            .flatten()
+            .chain(partition.leftover.iter())
+            .copied()
+            .collect();
+        used.sort();
+        let mut tray = cards.to_vec();
+        tray.sort();
+        assert_eq!(used, tray);
+    }
+
+    #[test]
+    fn best_partition_beats_greedy_test() {
+        // Greedily playing the longest run leaves 5 Red and 5 Yellow in the hand
+        let test_tray = vec![
+            Card::new(2, Color::Blue),
+            Card::new(3, Color::Blue),
+            Card::new(4, Color::Blue),
+            Card::new(5, Color::Blue),
+            Card::new(6, Color::Blue),
+            Card::new(7, Color::Blue),
+            Card::new(8, Color::Blue),
+            Card::new(5, Color::Red),
+            Card::new(5, Color::Yellow)
+        ];
+
+        let partition = best_partition(&test_tray);
+
+        assert_valid_partition(&test_tray, &partition);
+        assert_eq!(partition.played_count(), 9);
+        assert!(partition.leftover.is_empty());
+        assert!(partition.sets.contains(&vec![
+            Card::new(5, Color::Red),
+            Card::new(5, Color::Blue),
+            Card::new(5, Color::Yellow)
+        ]));
+    }
+
+    #[test]
+    fn best_partition_wildcard_replaces_held_tile_test() {
+        // The wildcard takes the place of 5 Blue in the run, so that 5 Blue completes the group
+        let test_tray = vec![
+            Card::new(4, Color::Blue),
+            Card::new(5, Color::Blue),
+            Card::new(6, Color::Blue),
+            Card::new(5, Color::Red),
+            Card::new(5, Color::Black),
+            Card::new(9, Color::Yellow),
+            Card::new(10, Color::Yellow),
+            Card::Wildcard
+        ];
+
+        let partition = best_partition(&test_tray);
+
+        assert_valid_partition(&test_tray, &partition);
+        assert_eq!(partition.played_count(), 6);
+        assert_eq!(partition.leftover, vec![Card::new(9, Color::Yellow), Card::new(10, Color::Yellow)]);
+    }
+
+    #[test]
+    fn best_partition_wildcard_used_once_test() {
+        let test_tray = vec![
+            Card::new(1, Color::Red),
+            Card::new(2, Color::Red),
+            Card::new(8, Color::Black),
+            Card::new(8, Color::Yellow),
+            Card::Wildcard
+        ];
+
+        let partition = best_partition(&test_tray);
+
+        assert_valid_partition(&test_tray, &partition);
+        assert_eq!(partition.sets.len(), 1);
+        assert_eq!(partition.played_count(), 3);
+        assert_eq!(partition.leftover.len(), 2);
+    }
+
+    #[test]
+    fn best_partition_duplicated_tiles_test() {
+        let test_tray = vec![
+            Card::new(7, Color::Red),
+            Card::new(7, Color::Red),
+            Card::new(7, Color::Blue),
+            Card::new(7, Color::Blue),
+            Card::new(7, Color::Yellow),
+            Card::new(7, Color::Yellow),
+            Card::new(11, Color::Black)
+        ];
+
+        let partition = best_partition(&test_tray);
+
+        assert_valid_partition(&test_tray, &partition);
+        assert_eq!(partition.sets.len(), 2);
+        assert_eq!(partition.leftover, vec![Card::new(11, Color::Black)]);
+    }
+
+    #[test]
+    fn best_partition_no_sets_test() {
+        let test_tray = vec![
+            Card::new(1, Color::Red),
+            Card::new(5, Color::Blue),
+            Card::new(9, Color::Yellow)
+        ];
+
+        let partition = best_partition(&test_tray);
+
+        assert!(partition.sets.is_empty());
+        assert_eq!(partition.leftover, test_tray);
+    }
+
+    // Two runs of 1-6 with two wildcards: lots of candidates sharing the same tiles
+    fn speed_test_tray() -> Vec<Card> {
+        (1..=6).map(|n| Card::new(n, Color::Red))
+            .chain((1..=6).map(|n| Card::new(n, Color::Blue)))
+            .chain([Card::Wildcard, Card::Wildcard])
+            .collect()
+    }
+
+    #[test]
+    fn best_partition_speed_test() {
+        let test_tray = speed_test_tray();
+
+        let partition = best_partition(&test_tray);
+
+        // Counted instead of timed, this doesn't depend on the machine: about 30, the search
+        // only allocating when it finds a better partition
+        let allocations = count_allocations(|| best_partition(&test_tray));
+        assert!(allocations < 100, "{} allocations", allocations);
+        assert_valid_partition(&test_tray, &partition);
+        assert_eq!(partition.played_count(), 14);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn budgeted_partition_complete_test() {
+        let test_tray = vec![
+            Card::new(5, Color::Red),
+            Card::new(5, Color::Blue),
+            Card::new(5, Color::Yellow),
+            Card::new(9, Color::Black),
+            Card::Wildcard
+        ];
+
+        let budgeted = best_partition_with_budget(&test_tray, Duration::from_secs(1));
+
+        assert!(budgeted.complete);
+        assert!(budgeted.nodes_explored > 0);
+        assert_valid_partition(&test_tray, &budgeted.partition);
+        assert_eq!(budgeted.partition.played_count(), best_partition(&test_tray).played_count());
+        assert_eq!(budgeted.partition.leftover, vec![Card::new(9, Color::Black)]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn budgeted_partition_timeout_test() {
+        // 28 tiles that can't all be played and two wildcards: tens of thousands of nodes
+        let mut test_tray: Vec<Card> = Deck::new_seeded(5).cards.into_iter()
+            .filter(|c| !c.is_wildcard())
+            .take(28)
+            .collect();
+        test_tray.extend([Card::Wildcard, Card::Wildcard]);
+
+        let start = Instant::now();
+        let budgeted = best_partition_with_budget(&test_tray, Duration::from_millis(5));
+
+        // Slack for debug builds and busy machines
+        assert!(start.elapsed() < Duration::from_millis(100), "{:?}", start.elapsed());
+        assert_valid_partition(&test_tray, &budgeted.partition);
+        assert!(!budgeted.partition.sets.is_empty());
+    }
+
+    #[test]
+    fn bitset_solver_allocations_test() {
+        let test_tray = speed_test_tray();
+        let tiles = TileCounts::from_cards(&test_tray);
+        let tray = PackedTray::from_cards(&test_tray).unwrap();
+
+        // The allocations are what made the counts solver slow, and unlike its time their
+        // number doesn't depend on the machine: about 4300 against 31
+        let counts = count_allocations(|| best_sets_by_counts(&tiles));
+        let bitset = count_allocations(|| bitset::best_sets(&tray));
+
+        assert!(bitset * 10 < counts, "{} vs {} allocations", bitset, counts);
+    }
+
+    #[test]
+    fn bitset_solver_matches_counts_solver_test() {
+        for seed in 0..200 {
+            let test_tray = Deck::new_seeded(seed).pick_tray(14);
+            let tiles = TileCounts::from_cards(&test_tray);
+            let tray = PackedTray::from_cards(&test_tray).unwrap();
+
+            let sets = bitset::best_sets(&tray);
+            for set in &sets {
+                assert!(validate_set(set).is_ok(), "{:?}", set);
+            }
+            let played = |sets: &[Vec<Card>]| sets.iter().map(|s| s.len()).sum::<usize>();
+            assert_eq!(played(&sets), played(&best_sets_by_counts(&tiles)), "{:?}", test_tray);
+            assert_eq!(
+                bitset::complete_sets(&tray).is_some(),
+                complete_sets_by_counts(&tiles).is_some(),
+                "{:?}", test_tray
+            );
+        }
+    }
+
+    #[test]
+    fn best_partition_too_many_tiles_test() {
+        // Both decks minus the wildcards don't fit the bitset: the counts solver takes over
+        let test_tray: Vec<Card> = Deck::new().cards.into_iter().filter(|c| !c.is_wildcard()).collect();
+        assert!(PackedTray::from_cards(&test_tray).is_none());
+
+        let partition = best_partition(&test_tray);
+
+        assert_valid_partition(&test_tray, &partition);
+        assert_eq!(partition.played_count(), 104);
+    }
+
+    #[test]
+    fn can_go_out_two_groups_test() {
+        let test_tray = vec![
+            Card::new(4, Color::Red),
+            Card::new(4, Color::Blue),
+            Card::new(4, Color::Yellow),
+            Card::new(9, Color::Black),
+            Card::new(9, Color::Blue),
+            Card::new(9, Color::Red)
+        ];
+
+        let sets = can_go_out(&test_tray).unwrap();
+
+        assert_eq!(sets.len(), 2);
+        let partition = Partition { sets, leftover: vec![] };
+        assert_valid_partition(&test_tray, &partition);
+    }
+
+    #[test]
+    fn can_go_out_specific_wildcard_test() {
+        // The wildcard can only be the missing 6 Blue: as 9 Yellow or 3 Red something is left out
+        let test_tray = vec![
+            Card::new(5, Color::Blue),
+            Card::new(7, Color::Blue),
+            Card::new(9, Color::Red),
+            Card::new(9, Color::Black),
+            Card::new(9, Color::Blue),
+            Card::new(1, Color::Red),
+            Card::new(2, Color::Red),
+            Card::new(3, Color::Red),
+            Card::Wildcard
+        ];
+
+        let sets = can_go_out(&test_tray).unwrap();
+
+        assert!(sets.contains(&vec![Card::new(5, Color::Blue), Card::new(7, Color::Blue), Card::Wildcard]));
+        let partition = Partition { sets, leftover: vec![] };
+        assert_valid_partition(&test_tray, &partition);
+    }
+
+    #[test]
+    fn can_go_out_unused_wildcard_test() {
+        // Every tile of a full partition must be played, wildcards included
+        let test_tray = vec![
+            Card::new(3, Color::Red),
+            Card::new(3, Color::Blue),
+            Card::new(3, Color::Yellow),
+            Card::new(3, Color::Black),
+            Card::Wildcard
+        ];
+        assert_eq!(can_go_out(&test_tray), None);
+
+        let test_tray = vec![
+            Card::new(3, Color::Red),
+            Card::new(3, Color::Blue),
+            Card::new(3, Color::Yellow),
+            Card::Wildcard
+        ];
+        assert!(can_go_out(&test_tray).is_some());
+    }
+
+    #[test]
+    fn can_go_out_almost_test() {
+        let test_tray = vec![
+            Card::new(1, Color::Yellow),
+            Card::new(2, Color::Yellow),
+            Card::new(3, Color::Yellow),
+            Card::new(4, Color::Yellow),
+            Card::new(8, Color::Red),
+            Card::new(8, Color::Blue),
+            Card::new(8, Color::Black),
+            Card::new(11, Color::Blue),
+            Card::new(12, Color::Blue),
+            Card::new(13, Color::Blue),
+            Card::Wildcard,
+            Card::new(6, Color::Red),
+            Card::new(2, Color::Black),
+            Card::new(10, Color::Red)
+        ];
+
+        assert_eq!(best_partition(&test_tray).played_count(), 11);
+        assert_eq!(can_go_out(&test_tray), None);
+    }
+
+}
@@ -0,0 +1,57 @@
+use super::{Card, PlayerId};
+
+/// What a wildcard left in a hand costs at the end of a round, whatever it could stand for
+pub const WILDCARD_PENALTY: u32 = 30;
+
+/// What the tiles left in a hand cost at the end of a round: their face value, wildcards
+/// costing WILDCARD_PENALTY. Unlike score_meld, a wildcard is never worth the tile it could
+/// stand for.
+pub fn hand_penalty(cards: &[Card]) -> u32 {
+    cards.iter()
+        .map(|card| card.number().map(|n| *n as u32).unwrap_or(WILDCARD_PENALTY))
+        .sum()
+}
+
+/// The hand_penalty of every player, in player order
+pub fn hand_penalties<T: AsRef<[Card]>>(hands: &[T]) -> Vec<(PlayerId, u32)> {
+    hands.iter()
+        .map(|hand| hand_penalty(hand.as_ref()))
+        .enumerate()
+        .collect()
+}
+
+/// What the winner of a round conventionally gains: the penalties of all the other players
+pub fn winner_bonus<T: AsRef<[Card]>>(hands: &[T], winner: PlayerId) -> u32 {
+    hand_penalties(hands).into_iter()
+        .filter(|(player, _)| *player != winner)
+        .map(|(_, penalty)| penalty)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::Color;
+
+    #[test]
+    fn hand_penalty_test() {
+        assert_eq!(hand_penalty(&[]), 0);
+        assert_eq!(hand_penalty(&[Card::new(13, Color::Red), Card::new(13, Color::Blue), Card::Wildcard]), 56);
+    }
+
+    #[test]
+    fn winner_bonus_test() {
+        let hands = vec![
+            vec![],
+            vec![Card::new(5, Color::Red), Card::Wildcard],
+            vec![Card::new(1, Color::Black), Card::new(2, Color::Black), Card::new(12, Color::Yellow)]
+        ];
+
+        assert_eq!(hand_penalties(&hands), vec![(0, 0), (1, 35), (2, 15)]);
+        assert_eq!(winner_bonus(&hands, 0), 50);
+        // The winner's own hand doesn't count
+        assert_eq!(winner_bonus(&hands, 2), 35);
+    }
+
+}
@@ -0,0 +1,121 @@
+use thiserror::Error;
+use super::{validate_set, Card, Deck, DeckError, MeldParseError, RulesConfig, SetError, Table};
+use super::shorthand::read_meld;
+use crate::prelude::*;
+
+/// Why load_position can't read a position
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PositionError {
+    /// Lines count from 1, the column is the position of the error
+    #[error("line {line}: {error}")]
+    Syntax { line: usize, #[source] error: MeldParseError },
+    #[error("line {line} comes before the table: and hand: blocks")]
+    OutsideBlock { line: usize },
+    #[error("line {line}: the meld is not valid: {error}")]
+    InvalidMeld { line: usize, #[source] error: SetError },
+    /// The table and the hand together hold a third copy of the tile, or a third wildcard
+    #[error("{} is there more times than the deck holds it", .0.code())]
+    TooManyCopies(Card)
+}
+
+/// Reads a position shared as text, a table and the hand of the player to move:
+///
+/// ```text
+/// table:
+/// B4-B5-B6-B7-B8
+/// 7RBK
+/// hand:
+/// B6 R9 W
+/// ```
+///
+/// Every line of the `table:` block is a meld, read like parse_meld reads them, and the lines
+/// of the `hand:` block hold tiles. Blank lines and the lines starting with `#` are left out.
+/// The melds must be valid, and the tiles must all come from one standard deck.
+pub fn load_position(text: &str) -> Result<(Table, Vec<Card>), PositionError> {
+    let rules = RulesConfig::default();
+    let mut melds = vec![];
+    let mut hand = vec![];
+    let mut in_hand = None;
+    for (line_idx, line) in text.lines().enumerate() {
+        let line_number = line_idx + 1;
+        match line.trim() {
+            "" => continue,
+            comment if comment.starts_with('#') => continue,
+            "table:" => in_hand = Some(false),
+            "hand:" => in_hand = Some(true),
+            _ => {
+                let cards = read_meld(line, &rules).map_err(|error| PositionError::Syntax { line: line_number, error })?;
+                match in_hand {
+                    Some(true) => hand.extend(cards),
+                    Some(false) => {
+                        validate_set(&cards).map_err(|error| PositionError::InvalidMeld { line: line_number, error })?;
+                        melds.push(cards);
+                    },
+                    None => return Err(PositionError::OutsideBlock { line: line_number })
+                }
+            }
+        }
+    }
+
+    let all: Vec<Card> = melds.iter().flatten().chain(&hand).copied().collect();
+    Deck::standard_without(&all).map_err(|DeckError::TooManyCopies(card)| PositionError::TooManyCopies(card))?;
+    Ok((Table::from_melds(melds), hand))
+}
+
+/// Writes the position for load_position: the melds as tile codes joined by dashes, in their
+/// order, then the tiles of the hand
+pub fn save_position(table: &Table, hand: &[Card]) -> String {
+    let codes = |cards: &[Card], separator: &str| cards.iter().map(Card::code).collect::<Vec<String>>().join(separator);
+    let mut text = "table:\n".to_string();
+    for (_, cards) in table.melds() {
+        text.push_str(&codes(cards, "-"));
+        text.push('\n');
+    }
+    text.push_str("hand:\n");
+    if !hand.is_empty() {
+        text.push_str(&codes(hand, " "));
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::Color;
+
+    fn red(numbers: &[i8]) -> Vec<Card> {
+        numbers.iter().map(|n| Card::new(*n, Color::Red)).collect()
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let text = "# Find the out\ntable:\nR3-R4-R5\n7RBK\n\nhand:\nB6 R6\nW\n";
+        let (table, hand) = load_position(text).unwrap();
+        let melds: Vec<&[Card]> = table.melds().map(|(_, cards)| cards).collect();
+        assert_eq!(melds, vec![red(&[3, 4, 5]).as_slice(), &[Card::new(7, Color::Red), Card::new(7, Color::Blue), Card::new(7, Color::Black)]]);
+        assert_eq!(hand, vec![Card::new(6, Color::Blue), Card::new(6, Color::Red), Card::Wildcard]);
+
+        let saved = save_position(&table, &hand);
+        assert_eq!(saved, "table:\nR3-R4-R5\nR7-B7-K7\nhand:\nB6 R6 W\n");
+        assert_eq!(load_position(&saved), Ok((table, hand)));
+        assert_eq!(save_position(&Table::new(), &[]), "table:\nhand:\n");
+        assert_eq!(load_position("table:\nhand:\n"), Ok((Table::new(), vec![])));
+    }
+
+    #[test]
+    fn invalid_position_test() {
+        // Two R4s on the table and one in the hand
+        let error = load_position("table:\nR3-R4-R5\nR4-R5-R6\nhand:\nR4\n").unwrap_err();
+        assert_eq!(error, PositionError::TooManyCopies(Card::new(4, Color::Red)));
+        assert!(error.to_string().contains("R4"), "{}", error);
+        assert_eq!(load_position("table:\nW W\nhand:\nW\n"), Err(PositionError::InvalidMeld { line: 2, error: SetError::TooShort { len: 2 } }));
+        assert_eq!(load_position("table:\nR1-R2-R3\nhand:\nR1 W W W\n"), Err(PositionError::TooManyCopies(Card::Wildcard)));
+
+        assert_eq!(load_position("R1\ntable:\n"), Err(PositionError::OutsideBlock { line: 1 }));
+        assert!(matches!(load_position("table:\nR1-R2-X3\n"), Err(PositionError::Syntax { line: 2, error: MeldParseError::InvalidTile { position: 7, .. } })));
+        assert!(matches!(load_position("hand:\nR1 B2\ntable:\nR4-R6-R5\n"), Ok((_, hand)) if hand.len() == 2));
+    }
+
+}
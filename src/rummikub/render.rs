@@ -0,0 +1,128 @@
+use super::{canonicalize_set, resolve_wildcards_with, validate_set_with, Card, Color, DisplayTheme, RulesConfig, Table};
+use crate::prelude::*;
+
+// Width of a tile on the board in characters, as long as the longest wildcard like W(R13)
+const CELL_WIDTH: usize = 6;
+
+// The text of a tile and its color, none for wildcards
+type Cell = (String, Option<Color>);
+
+/// Lays the melds of the table out one per row: the meld id, the tiles, then the kind of the
+/// meld or why it is not valid. The tiles come in the order of what they stand for, a
+/// wildcard being followed by the tile it is read as, see resolve_wildcards on the
+/// canonicalize_set order, like W(R5). Every tile takes the same width, so the columns line up.
+pub fn render_table(table: &Table) -> String {
+    render_table_with(table, &DisplayTheme::plain())
+}
+
+/// Same as render_table, with the tiles colored for a terminal like the full screen UI does
+pub fn render_table_colored(table: &Table) -> String {
+    render_table_with(table, &DisplayTheme::ansi())
+}
+
+/// Same as render_table, with the tiles written out in the theme
+pub fn render_table_with(table: &Table, theme: &DisplayTheme) -> String {
+    if table.is_empty() {
+        return "(empty table)\n".to_string();
+    }
+    let rows: Vec<(String, Vec<Cell>, String)> = table.melds()
+        .map(|(id, cards)| {
+            let status = match validate_set_with(cards, table.rules()) {
+                Ok(kind) => kind.name().to_string(),
+                Err(error) => format!("invalid: {}", error)
+            };
+            (id.to_string(), cells(cards, table.rules(), theme), status)
+        })
+        .collect();
+    let widest = rows.iter().map(|(_, cells, _)| cells.len()).max().unwrap_or(0);
+
+    let mut output = String::new();
+    for (id, cells, status) in rows {
+        let mut line = format!("{:>4} ", id);
+        for (text, color) in &cells {
+            let padding = " ".repeat(CELL_WIDTH.saturating_sub(text.chars().count()));
+            line += &format!(" {}{}", theme.paint(text, *color), padding);
+        }
+        line += &" ".repeat((widest - cells.len()) * (CELL_WIDTH + 1));
+        output += &format!("{}  {}\n", line, status);
+    }
+    output
+}
+
+// The text and color of every tile of a meld, in the order of what they stand for
+fn cells(cards: &[Card], rules: &RulesConfig, theme: &DisplayTheme) -> Vec<Cell> {
+    let canonical = canonicalize_set(&cards.iter().collect::<Vec<&Card>>());
+    let refs: Vec<&Card> = canonical.iter().collect();
+    match resolve_wildcards_with(&refs, rules) {
+        Some(values) => {
+            let mut tiles: Vec<((i8, Color), &Card)> = values.into_iter().zip(canonical.iter()).collect();
+            tiles.sort_by_key(|(value, _)| *value);
+            tiles.into_iter()
+                .map(|((number, color), card)| match card {
+                    Card::Wildcard => (format!("{}({})", theme.wildcard, theme.tile(&Card::new(number, color))), None),
+                    _ => (theme.tile(card), Some(color))
+                })
+                .collect()
+        },
+        None => canonical.iter().map(|card| (theme.tile(card), card.color().copied())).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn board() -> Table {
+        Table::from_melds(vec![
+            vec![Card::new(6, Color::Red), Card::new(4, Color::Red), Card::Wildcard],
+            vec![Card::new(10, Color::Blue), Card::new(11, Color::Blue), Card::new(12, Color::Blue), Card::new(13, Color::Blue)],
+            vec![Card::new(9, Color::Yellow), Card::new(9, Color::Red), Card::new(9, Color::Black)]
+        ])
+    }
+
+    #[test]
+    fn render_table_test() {
+        assert_eq!(render_table(&Table::new()), "(empty table)\n");
+        assert_eq!(render_table(&board()), concat!(
+            "  #0  R4     W(R5)  R6             run\n",
+            "  #1  B10    B11    B12    B13     run\n",
+            "  #2  R9     K9     Y9             group\n"
+        ));
+
+        let mut table = board();
+        let id = table.melds().next().unwrap().0;
+        table.remove_card(id, 0);
+        assert!(render_table(&table).starts_with("  #0  R4     W                     invalid: a set needs at least 3 tiles, this one has 2\n"));
+    }
+
+    #[test]
+    fn render_table_colored_test() {
+        let colored = render_table_colored(&board());
+        assert!(colored.contains("\x1b[91mR4\x1b[0m"));
+        assert!(colored.contains("\x1b[95mW(R5)\x1b[0m"));
+        assert!(colored.contains("\x1b[90mK9\x1b[0m"));
+
+        // Without the escape codes the columns are those of render_table
+        let mut plain = String::new();
+        let mut chars = colored.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                chars.by_ref().find(|c| *c == 'm');
+            } else {
+                plain.push(c);
+            }
+        }
+        assert_eq!(plain, render_table(&board()));
+    }
+
+    #[test]
+    fn render_table_colorblind_test() {
+        assert_eq!(render_table_with(&board(), &DisplayTheme::colorblind()), concat!(
+            "  #0  ●4     ★(●5)  ●6             run\n",
+            "  #1  ■10    ■11    ■12    ■13     run\n",
+            "  #2  ●9     ▲9     ◆9             group\n"
+        ));
+    }
+
+}
@@ -0,0 +1,174 @@
+use thiserror::Error;
+use super::{Card, GameEvent, GameState, Move, MoveError, PlayerId, RulesConfig};
+
+/// Why replay stopped, index being the position of the faulty event in the log
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ReplayError {
+    // The event was played by another player than the one whose turn it was
+    #[error("event {index}: played by player {} on the turn of player {}", found + 1, expected + 1)]
+    WrongPlayer { index: usize, expected: PlayerId, found: PlayerId },
+    #[error("event {index}: {error}")]
+    IllegalMove { index: usize, #[source] error: MoveError },
+    // The replayed draw doesn't give the recorded tile, so the seed or the log is wrong
+    #[error("event {index}: drew {} instead of {}", code(found), code(expected))]
+    WrongDraw { index: usize, expected: Option<Card>, found: Option<Card> }
+}
+
+// The code of a drawn tile, for the messages
+fn code(card: &Option<Card>) -> String {
+    card.map(|c| c.code()).unwrap_or_else(|| "nothing".to_string())
+}
+
+/// Plays again the events of a game started with GameState::new_game_from with these
+/// players and seed, the first event after the mulligans telling who played first. Every
+/// event is checked: it must be legal, by the current player or for a mulligan by the one
+/// taking it, and draw the recorded tile.
+pub fn replay(names: &[&str], seed: u64, events: &[GameEvent]) -> Result<GameState, ReplayError> {
+    let first_player = events.iter()
+        .find(|event| !matches!(event.mv, Move::Mulligan(_)))
+        .map(|event| event.player)
+        .unwrap_or(0);
+    let mut game = GameState::new_game_from(names, seed, first_player);
+
+    for (index, event) in events.iter().enumerate() {
+        let expected = match event.mv {
+            Move::Mulligan(player) => player,
+            _ => game.current_player_index()
+        };
+        if event.player != expected {
+            return Err(ReplayError::WrongPlayer { index, expected, found: event.player });
+        }
+        let outcome = game.apply_move(event.mv.clone())
+            .map_err(|error| ReplayError::IllegalMove { index, error })?;
+        if outcome.drawn != event.drawn {
+            return Err(ReplayError::WrongDraw { index, expected: event.drawn, found: outcome.drawn });
+        }
+    }
+    Ok(game)
+}
+
+/// Plays again the moves of a game started with GameState::new_game_with_rules, when only
+/// the moves were kept and not the tiles they drew. Every move must be legal; with the same
+/// seed the draws give the same tiles anyway.
+pub fn replay_moves(names: &[&str], seed: u64, first_player: PlayerId, rules: RulesConfig, moves: &[Move]) -> Result<GameState, ReplayError> {
+    let mut game = GameState::new_game_with_rules(names, seed, first_player, rules);
+    for (index, mv) in moves.iter().enumerate() {
+        game.apply_move(mv.clone()).map_err(|error| ReplayError::IllegalMove { index, error })?;
+    }
+    Ok(game)
+}
+
+/// Checks a game sent as its moves, like one played over the network: the game is dealt
+/// by GameState::new_game_with_rules with the first player first, and every move must be
+/// legal. The error tells the index of the first move that isn't. Two machines verifying the
+/// same moves end with the same GameState::state_hash, so comparing hashes tells whether they
+/// went out of sync.
+pub fn verify_replay(names: &[&str], seed: u64, rules: RulesConfig, moves: &[Move]) -> Result<GameState, ReplayError> {
+    replay_moves(names, seed, 0, rules, moves)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::{play_round, GreedyStrategy, Mulligan, Strategy};
+
+    // Bo starts, and everyone draws for two rounds
+    fn scripted_game() -> GameState {
+        let mut game = GameState::new_game_from(&["Ann", "Bo", "Cy"], 4, 1);
+        for _ in 0..6 {
+            game.apply_move(Move::Draw).unwrap();
+            game.apply_move(Move::EndTurn).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn replay_round_trip_test() {
+        let game = scripted_game();
+
+        let replayed = replay(&["Ann", "Bo", "Cy"], 4, game.events()).unwrap();
+
+        assert_eq!(game.events().len(), 12);
+        assert_eq!(game.events()[0].player, 1);
+        assert_eq!(game.events()[11].turn, 5);
+        assert_eq!(replayed, game);
+    }
+
+    #[test]
+    fn corrupted_replay_test() {
+        let game = scripted_game();
+        let names = ["Ann", "Bo", "Cy"];
+
+        let mut skipped_turn = game.events().to_vec();
+        skipped_turn.remove(3);
+        let mut no_draw = game.events().to_vec();
+        no_draw[2].mv = Move::EndTurn;
+        let mut other_draw = game.events().to_vec();
+        other_draw[6].drawn = None;
+
+        assert_eq!(replay(&names, 4, &skipped_turn), Err(ReplayError::WrongPlayer { index: 3, expected: 2, found: 0 }));
+        assert_eq!(replay(&names, 4, &no_draw), Err(ReplayError::IllegalMove { index: 2, error: MoveError::MustDrawOrPlay }));
+        assert!(matches!(replay(&names, 4, &other_draw), Err(ReplayError::WrongDraw { index: 6, expected: None, .. })));
+        assert!(matches!(replay(&names, 5, game.events()), Err(ReplayError::WrongDraw { index: 0, .. })));
+    }
+
+    #[test]
+    fn replay_moves_test() {
+        let game = scripted_game();
+        let moves: Vec<Move> = game.events().iter().map(|event| event.mv.clone()).collect();
+
+        assert_eq!(replay_moves(&["Ann", "Bo", "Cy"], 4, 1, RulesConfig::default(), &moves), Ok(game));
+        let error = replay_moves(&["Ann", "Bo", "Cy"], 4, 1, RulesConfig::default(), &moves[1..]);
+        assert_eq!(error, Err(ReplayError::IllegalMove { index: 0, error: MoveError::MustDrawOrPlay }));
+    }
+
+    #[test]
+    fn replay_mulligan_test() {
+        let names = ["Ann", "Bo", "Cy"];
+        let rules = RulesConfig { mulligan: Mulligan::OncePerPlayer { only_without_sets: false }, ..RulesConfig::default() };
+        let mut game = GameState::new_game_with_rules(&names, 4, 0, rules);
+        game.mulligan(2).unwrap();
+        game.apply_move(Move::Draw).unwrap();
+        game.apply_move(Move::EndTurn).unwrap();
+        let moves: Vec<Move> = game.events().iter().map(|event| event.mv.clone()).collect();
+
+        let verified = verify_replay(&names, 4, rules, &moves).unwrap();
+        assert_eq!(verified.state_hash(), game.state_hash());
+        assert_eq!(verified.players()[2].tray(), game.players()[2].tray());
+        assert_ne!(verify_replay(&names, 4, rules, &moves[1..]).unwrap().state_hash(), game.state_hash());
+
+        // The mulligan of Cy said to be taken by Ann
+        let mut forged = game.events().to_vec();
+        forged[0].player = 0;
+        assert_eq!(replay(&names, 4, &forged), Err(ReplayError::WrongPlayer { index: 0, expected: 2, found: 0 }));
+    }
+
+    #[test]
+    fn verify_bot_game_test() {
+        let names = ["Ann", "Bo", "Cy"];
+        let mut game = GameState::new_game(&names, 9);
+        let mut strategies: Vec<Box<dyn Strategy>> = (0..3)
+            .map(|_| Box::new(GreedyStrategy) as Box<dyn Strategy>)
+            .collect();
+        play_round(&mut game, &mut strategies, 300).unwrap();
+        let moves: Vec<Move> = game.events().iter().map(|event| event.mv.clone()).collect();
+        assert!(moves.iter().any(|mv| matches!(mv, Move::PlayNewMeld(_))));
+
+        let first = verify_replay(&names, 9, RulesConfig::default(), &moves).unwrap();
+        let second = verify_replay(&names, 9, RulesConfig::default(), &moves).unwrap();
+        assert_eq!(first.state_hash(), game.state_hash());
+        assert_eq!(second.state_hash(), game.state_hash());
+        assert_ne!(GameState::new_game(&names, 9).state_hash(), game.state_hash());
+
+        // A draw turned into the end of the turn
+        let index = moves.iter().rposition(|mv| *mv == Move::Draw).unwrap();
+        let mut flipped = moves.clone();
+        flipped[index] = Move::EndTurn;
+        assert_eq!(
+            verify_replay(&names, 9, RulesConfig::default(), &flipped),
+            Err(ReplayError::IllegalMove { index, error: MoveError::MustDrawOrPlay })
+        );
+    }
+
+}
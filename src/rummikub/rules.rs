@@ -0,0 +1,111 @@
+use core::ops::RangeInclusive;
+use super::Color;
+
+/// The rules a game is played with, so that house rules can change them. The default is
+/// the standard game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RulesConfig {
+    /// Shortest run allowed, runs below 3 tiles are never valid
+    pub min_run_len: usize,
+    /// Biggest group allowed, groups can't be bigger than the number of colors anyway
+    pub max_group_size: usize,
+    /// Whether a run can go on from 13 to 1, like 12 13 1
+    pub wrap_runs: bool,
+    /// Points the first melds of a player must be worth
+    pub opening_points: u32,
+    /// Wildcards in the deck
+    pub wildcards: usize,
+    /// Colors of the numbered tiles, the deck holding two copies of every tile of each
+    pub colors: &'static [Color],
+    /// Lowest number of the tiles
+    pub min_number: i8,
+    /// Highest number of the tiles, runs wrap from it back to min_number
+    pub max_number: i8,
+    /// Whether players can throw back the tray they were dealt, see GameState::mulligan
+    pub mulligan: Mulligan,
+    /// How a turn goes
+    pub variant: VariantRules,
+    /// Whether the deck holds the jokers of Rummikub Twist, Card::DoubleJoker and
+    /// Card::MirrorJoker. Without them, sets holding such a joker are never valid.
+    pub twist_jokers: bool,
+    /// How the end of a round is scored, see RoundResult
+    pub scoring: ScoringVariant
+}
+
+/// How the trays left at the end of a round are scored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScoringVariant {
+    /// The other players lose the value of their tray, and the winner gains it all
+    #[default]
+    Standard,
+    /// Like Standard, the winner gaining twice what the others lose
+    WinnerDouble,
+    /// The other players score the value of their tray as points against them, the winner
+    /// scoring nothing. A match ends once a player reaches the points, the lowest total
+    /// winning.
+    RaceTo(i32)
+}
+
+/// How a turn goes, the sets and the scoring being the same in every variant
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VariantRules {
+    /// Players either draw a tile or play, then end their turn
+    #[default]
+    Standard,
+    /// Players start their turn drawing a tile, from the deck or the top of the discard
+    /// pile, can then play, and end it discarding a tile. Once the deck is empty the round
+    /// ends with the next discard, the lowest tray winning like when everyone passes.
+    DrawDiscard
+}
+
+/// When a player can throw back the tray they were dealt and get a new one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mulligan {
+    #[default]
+    Off,
+    /// Once per player, before the first turn. With only_without_sets, only when the tray
+    /// makes no valid set.
+    OncePerPlayer { only_without_sets: bool }
+}
+
+impl Default for RulesConfig {
+    fn default() -> Self {
+        RulesConfig {
+            min_run_len: 3,
+            max_group_size: 4,
+            wrap_runs: false,
+            opening_points: 30,
+            wildcards: 2,
+            colors: &Color::ALL,
+            min_number: 1,
+            max_number: 13,
+            mulligan: Mulligan::Off,
+            variant: VariantRules::Standard,
+            twist_jokers: false,
+            scoring: ScoringVariant::Standard
+        }
+    }
+}
+
+impl RulesConfig {
+    /// The large-format variant, played with the six colors of Color::SIX and groups of up to
+    /// six tiles
+    pub fn six_colors() -> RulesConfig {
+        RulesConfig { max_group_size: 6, colors: &Color::SIX, ..RulesConfig::default() }
+    }
+
+    /// The numbers of the tiles, from min_number to max_number
+    pub fn numbers(&self) -> RangeInclusive<i8> {
+        self.min_number..=self.max_number
+    }
+
+    /// How many numbers each color has, the longest run there can be
+    pub fn number_count(&self) -> usize {
+        (self.max_number - self.min_number + 1).max(0) as usize
+    }
+
+    /// The biggest group that can be laid down: one tile of each color, at most max_group_size
+    pub fn group_limit(&self) -> usize {
+        self.max_group_size.min(self.colors.len())
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+use super::{Card, Deck, RulesConfig, SetError, validate_set_with, WireError};
+use super::notation::{parse_rules, rules_notation};
+
+/// Bumped whenever the layout of the save files changes
+pub const SAVE_VERSION: u32 = 1;
+
+/// One player of a saved game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedPlayer {
+    pub name: String,
+    pub tray: Vec<Card>,
+    /// Whether the player made their opening, false when missing from the file
+    #[serde(default)]
+    pub has_melded: bool,
+    /// Whether the player took their mulligan, false when missing from the file
+    #[serde(default)]
+    pub took_mulligan: bool
+}
+
+/// Everything needed to resume a game. Together, the deck, the discard pile, the trays and
+/// the table hold the tiles of the game.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameSave {
+    /// The seed the deck was shuffled with, if any
+    pub seed: Option<u64>,
+    /// The tiles left to draw, the last one being drawn first
+    pub deck: Vec<Card>,
+    pub players: Vec<SavedPlayer>,
+    pub table: Vec<Vec<Card>>,
+    /// The discard pile of VariantRules::DrawDiscard, the last discard last, empty when
+    /// missing from the file
+    #[serde(default)]
+    pub discards: Vec<Card>,
+    /// Index in players of the one whose turn it is
+    pub current_player: usize,
+    /// Whether a move other than a mulligan was played, which ends the mulligans. False when
+    /// missing from the file.
+    #[serde(default)]
+    pub started: bool,
+    /// The rules of the game, scoring included, written like the Rules of a game record.
+    /// Saves without them are of standard games.
+    #[serde(default, serialize_with = "serialize_rules", deserialize_with = "deserialize_rules")]
+    pub rules: RulesConfig
+}
+
+// The file adds the version to the game
+#[derive(Serialize)]
+struct SaveFile<'a> {
+    version: u32,
+    #[serde(flatten)]
+    game: &'a GameSave
+}
+
+#[derive(Deserialize)]
+struct LoadedFile {
+    version: u32,
+    #[serde(flatten)]
+    game: GameSave
+}
+
+/// Error returned by load_game and save_game
+#[derive(Debug, Error)]
+pub enum SaveError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("not a valid save: {0}")]
+    Json(#[source] serde_json::Error),
+    #[error("not a valid binary save: {0}")]
+    Wire(#[from] WireError),
+    #[error("save version {version} is not supported, expected {}", SAVE_VERSION)]
+    UnsupportedVersion { version: u32 },
+    // A tile shows up more or less often than in a full deck
+    #[error("the game has {found} {} tiles, a full deck has {expected}", card.code())]
+    WrongTileCount { card: Card, expected: usize, found: usize },
+    #[error("meld {} on the table is not valid: {error}", meld + 1)]
+    InvalidMeld { meld: usize, #[source] error: SetError },
+    #[error("it is the turn of player {} but the game has {players} players", current_player + 1)]
+    NoSuchPlayer { current_player: usize, players: usize }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(error: serde_json::Error) -> Self {
+        SaveError::Json(error)
+    }
+}
+
+// Tiles are saved as their codes, like "R7" or "W"
+impl Serialize for Card {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for Card {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        code.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+fn serialize_rules<S: Serializer>(rules: &RulesConfig, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&rules_notation(rules))
+}
+
+fn deserialize_rules<'de, D: Deserializer<'de>>(deserializer: D) -> Result<RulesConfig, D::Error> {
+    let notation = String::deserialize(deserializer)?;
+    parse_rules(&notation).ok_or_else(|| serde::de::Error::custom(format!("'{}' are not rules", notation)))
+}
+
+impl GameSave {
+    /// The deck to keep drawing from
+    pub fn deck(&self) -> Deck {
+        Deck { cards: self.deck.clone() }
+    }
+
+    /// Checks that the game holds exactly the tiles of a full deck of the rules, that every
+    /// meld on the table is valid under them and that current_player is one of the players
+    pub fn validate(&self) -> Result<(), SaveError> {
+        let mut expected: BTreeMap<Card, usize> = BTreeMap::new();
+        for card in Deck::with_rules(&self.rules).cards {
+            *expected.entry(card).or_default() += 1;
+        }
+        let mut found: BTreeMap<Card, usize> = BTreeMap::new();
+        let trays = self.players.iter().flat_map(|p| &p.tray);
+        for card in self.deck.iter().chain(&self.discards).chain(trays).chain(self.table.iter().flatten()) {
+            *found.entry(*card).or_default() += 1;
+        }
+        for (card, expected) in &expected {
+            let found = found.get(card).copied().unwrap_or(0);
+            if found != *expected {
+                return Err(SaveError::WrongTileCount { card: *card, expected: *expected, found });
+            }
+        }
+
+        for (meld, cards) in self.table.iter().enumerate() {
+            validate_set_with(cards, &self.rules).map_err(|error| SaveError::InvalidMeld { meld, error })?;
+        }
+        if self.current_player >= self.players.len() {
+            return Err(SaveError::NoSuchPlayer { current_player: self.current_player, players: self.players.len() });
+        }
+        Ok(())
+    }
+
+    /// The save as JSON, with a "version" field (SAVE_VERSION) and tiles written as codes
+    pub fn to_json(&self) -> String {
+        let file = SaveFile { version: SAVE_VERSION, game: self };
+        // Strings, numbers and lists always serialize
+        serde_json::to_string_pretty(&file).unwrap()
+    }
+
+    /// Reads a save written by to_json, refusing it unless validate passes
+    pub fn from_json(json: &str) -> Result<GameSave, SaveError> {
+        let file: LoadedFile = serde_json::from_str(json)?;
+        if file.version != SAVE_VERSION {
+            return Err(SaveError::UnsupportedVersion { version: file.version });
+        }
+        file.game.validate()?;
+        Ok(file.game)
+    }
+}
+
+/// Writes a game to a file, see GameSave::to_json
+pub fn save_game(path: impl AsRef<Path>, game: &GameSave) -> Result<(), SaveError> {
+    fs::write(path, game.to_json())?;
+    Ok(())
+}
+
+/// Reads a game written by save_game, see GameSave::from_json
+pub fn load_game(path: impl AsRef<Path>) -> Result<GameSave, SaveError> {
+    GameSave::from_json(&fs::read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rand::prelude::*;
+    use super::*;
+    use super::super::Color;
+
+    fn test_game() -> GameSave {
+        // A run on the table, the other tiles shuffled and dealt
+        let table = vec![Card::new(1, Color::Red), Card::new(2, Color::Red), Card::new(3, Color::Red)];
+        let mut cards = Deck::new().cards;
+        for card in &table {
+            let idx = cards.iter().position(|c| c == card).unwrap();
+            cards.remove(idx);
+        }
+        let mut deck = Deck { cards };
+        deck.shuffle_with(&mut StdRng::seed_from_u64(5));
+        let players = ["Ann", "Bo"].iter()
+            .map(|name| SavedPlayer { name: name.to_string(), tray: deck.pick_tray(14), has_melded: true, took_mulligan: false })
+            .collect();
+
+        GameSave { seed: Some(5), deck: deck.cards, players, table: vec![table], discards: vec![], current_player: 1, started: true, rules: RulesConfig::default() }
+    }
+
+    #[test]
+    fn save_round_trip_test() {
+        let game = test_game();
+        assert_eq!(game.validate().map_err(|e| e.to_string()), Ok(()));
+
+        let path = std::env::temp_dir().join(format!("rummikub-save-{}.json", std::process::id()));
+        save_game(&path, &game).unwrap();
+        let loaded = load_game(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded, game);
+        assert_eq!(loaded.deck().len(), game.deck.len());
+        assert_eq!(loaded.deck().pick_card(), game.deck.last().copied());
+    }
+
+    #[test]
+    fn save_format_test() {
+        let json: serde_json::Value = serde_json::from_str(&test_game().to_json()).unwrap();
+
+        assert_eq!(json["version"], 1);
+        assert_eq!(json["seed"], 5);
+        assert_eq!(json["current_player"], 1);
+        assert_eq!(json["players"][0]["name"], "Ann");
+        assert!(json["deck"][0].is_string());
+        assert_eq!(json["rules"], "standard");
+    }
+
+    #[test]
+    fn save_without_rules_test() {
+        // Saves from before the rules were kept are of standard games
+        let mut json: serde_json::Value = serde_json::from_str(&test_game().to_json()).unwrap();
+        json.as_object_mut().unwrap().remove("rules");
+        let json = json.to_string();
+        assert_eq!(GameSave::from_json(&json).unwrap(), test_game());
+
+        let json = test_game().to_json().replacen("\"standard\"", "\"opening_points=many\"", 1);
+        assert!(matches!(GameSave::from_json(&json), Err(SaveError::Json(_))));
+    }
+
+    #[test]
+    fn duplicated_tile_test() {
+        let mut game = test_game();
+        let card = game.deck[0];
+        game.players[0].tray[0] = card;
+        let json = game.to_json();
+
+        match GameSave::from_json(&json) {
+            Err(SaveError::WrongTileCount { expected: 2, found, .. }) => assert!(found == 3 || found == 1),
+            other => panic!("{:?}", other)
+        }
+    }
+
+    #[test]
+    fn corrupted_save_test() {
+        let game = test_game();
+
+        let missing = game.to_json().replacen("\"W\"", "\"R13\"", 1);
+        let unknown_tile = game.to_json().replacen("\"W\"", "\"Q4\"", 1);
+        let version = game.to_json().replacen("\"version\": 1", "\"version\": 7", 1);
+        let player = game.to_json().replacen("\"current_player\": 1", "\"current_player\": 2", 1);
+
+        assert!(matches!(GameSave::from_json(&missing), Err(SaveError::WrongTileCount { .. })));
+        assert!(GameSave::from_json(&unknown_tile).unwrap_err().to_string().contains("'Q4'"));
+        assert!(matches!(GameSave::from_json(&version), Err(SaveError::UnsupportedVersion { version: 7 })));
+        assert!(matches!(GameSave::from_json(&player), Err(SaveError::NoSuchPlayer { .. })));
+        assert!(matches!(GameSave::from_json("{"), Err(SaveError::Json(_))));
+    }
+
+    #[test]
+    fn invalid_meld_test() {
+        let mut game = test_game();
+        let last = game.table[0].pop().unwrap();
+        game.deck.push(last);
+
+        assert!(matches!(game.validate(), Err(SaveError::InvalidMeld { meld: 0, .. })));
+    }
+
+}
@@ -0,0 +1,156 @@
+use thiserror::Error;
+use super::{canonicalize_set, Card, ParseCardError, RulesConfig};
+use crate::prelude::*;
+
+/// Why parse_meld can't read a meld, see position for where
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MeldParseError {
+    #[error("the meld has no tiles")]
+    Empty,
+    #[error("{error}")]
+    InvalidTile { position: usize, #[source] error: ParseCardError },
+    /// A letter of a group like 7RBY is not a color nor W
+    #[error("'{letter}' is not a color of the group, expected R, B, K, Y, G, O or W")]
+    InvalidColor { position: usize, letter: char },
+    #[error("'{token}' is not a group like 7RBY")]
+    InvalidGroup { position: usize, token: String },
+    /// A group like 7RBY written next to other tiles, like R3-7B
+    #[error("'{token}' mixes a group like 7RBY with other tiles")]
+    MixedShorthand { position: usize, token: String }
+}
+
+impl MeldParseError {
+    /// The column of the bad part of the meld, from 1
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            MeldParseError::Empty => None,
+            MeldParseError::InvalidTile { position, .. }
+            | MeldParseError::InvalidColor { position, .. }
+            | MeldParseError::InvalidGroup { position, .. }
+            | MeldParseError::MixedShorthand { position, .. } => Some(*position)
+        }
+    }
+}
+
+/// Reads a meld typed quickly: tiles like "R3-R4-R5" or "R3 R4 R5", or a group as its number
+/// followed by its colors like "7RBY", W standing for a wildcard in both. The tiles come
+/// sorted like canonicalize_set does. Whether they make a valid set is not checked.
+pub fn parse_meld(s: &str) -> Result<Vec<Card>, MeldParseError> {
+    parse_meld_with(s, &RulesConfig::default())
+}
+
+/// Like parse_meld, with the numbers of the rules instead of 1 to 13
+pub fn parse_meld_with(s: &str, rules: &RulesConfig) -> Result<Vec<Card>, MeldParseError> {
+    let cards = read_meld(s, rules)?;
+    Ok(canonicalize_set(&cards.iter().collect::<Vec<&Card>>()))
+}
+
+// The tiles of the meld in the order they are written
+pub(super) fn read_meld(s: &str, rules: &RulesConfig) -> Result<Vec<Card>, MeldParseError> {
+    let tokens = tokens(s);
+    let Some(first) = tokens.first() else {
+        return Err(MeldParseError::Empty);
+    };
+
+    if let Some(group) = tokens.iter().find(|(_, token)| is_group(token)) {
+        // A group goes alone; next to tiles, the first part that doesn't fit is pointed at
+        let mixed = if group == first { tokens.get(1) } else { Some(group) };
+        return match mixed {
+            Some((position, token)) => Err(MeldParseError::MixedShorthand { position: *position, token: token.to_string() }),
+            None => read_group(first.1, first.0, rules)
+        };
+    }
+    tokens.iter()
+        .map(|(position, token)| {
+            Card::parse_with(token, rules).map_err(|error| MeldParseError::InvalidTile { position: *position, error })
+        })
+        .collect()
+}
+
+// The words of the meld split on whitespace, dashes and commas, with their columns
+fn tokens(s: &str) -> Vec<(usize, &str)> {
+    let mut tokens = vec![];
+    let mut start = None;
+    for (idx, c) in s.char_indices().chain([(s.len(), ' ')]) {
+        let separator = c.is_whitespace() || c == '-' || c == ',';
+        match start {
+            Some(begin) if separator => {
+                tokens.push((begin + 1, &s[begin..idx]));
+                start = None;
+            },
+            None if !separator => start = Some(idx),
+            _ => {}
+        }
+    }
+    tokens
+}
+
+// Groups start with their number, once the wildcards written before it are left out
+fn is_group(token: &str) -> bool {
+    token.trim_start_matches(['W', 'w']).starts_with(|c: char| c.is_ascii_digit())
+}
+
+fn read_group(token: &str, position: usize, rules: &RulesConfig) -> Result<Vec<Card>, MeldParseError> {
+    let leading = token.len() - token.trim_start_matches(['W', 'w']).len();
+    let after_number = token[leading..].find(|c: char| !c.is_ascii_digit()).map_or(token.len(), |idx| idx + leading);
+    let number = &token[leading..after_number];
+    let letters = &token[after_number..];
+    if letters.is_empty() {
+        return Err(MeldParseError::InvalidGroup { position, token: token.to_string() });
+    }
+
+    let mut cards = vec![Card::Wildcard; leading];
+    for (idx, letter) in letters.char_indices() {
+        let letter = letter.to_ascii_uppercase();
+        match letter {
+            'W' => cards.push(Card::Wildcard),
+            'R' | 'B' | 'K' | 'Y' | 'G' | 'O' => {
+                let card = Card::parse_with(&format!("{}{}", letter, number), rules)
+                    .map_err(|error| MeldParseError::InvalidTile { position: position + leading, error })?;
+                cards.push(card);
+            },
+            _ => return Err(MeldParseError::InvalidColor { position: position + after_number + idx, letter })
+        }
+    }
+    Ok(cards)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::Color;
+
+    #[test]
+    fn run_shorthand_test() {
+        let run = vec![Card::new(3, Color::Red), Card::new(4, Color::Red), Card::new(5, Color::Red)];
+
+        assert_eq!(parse_meld("R3-R4-R5"), Ok(run.clone()));
+        assert_eq!(parse_meld("r5 R3, r4"), Ok(run.clone()));
+        assert_eq!(parse_meld(" R12 - W - R10 "), Ok(vec![Card::new(10, Color::Red), Card::new(12, Color::Red), Card::Wildcard]));
+        assert_eq!(parse_meld(" - "), Err(MeldParseError::Empty));
+    }
+
+    #[test]
+    fn group_shorthand_test() {
+        assert_eq!(parse_meld("7RBY"), Ok(vec![Card::new(7, Color::Red), Card::new(7, Color::Blue), Card::new(7, Color::Yellow)]));
+        assert_eq!(parse_meld("12kwr"), Ok(vec![Card::new(12, Color::Red), Card::new(12, Color::Black), Card::Wildcard]));
+        assert_eq!(parse_meld("W13BY"), Ok(vec![Card::new(13, Color::Blue), Card::new(13, Color::Yellow), Card::Wildcard]));
+        assert_eq!(parse_meld_with("15GO", &RulesConfig { max_number: 15, ..RulesConfig::six_colors() }).unwrap().len(), 2);
+        // Unsorted with read_meld
+        assert_eq!(read_meld("8YWR", &RulesConfig::default()).unwrap()[1], Card::Wildcard);
+    }
+
+    #[test]
+    fn error_positions_test() {
+        assert_eq!(parse_meld("R3-7B"), Err(MeldParseError::MixedShorthand { position: 4, token: "7B".to_string() }));
+        assert_eq!(parse_meld("7RB R7"), Err(MeldParseError::MixedShorthand { position: 5, token: "R7".to_string() }));
+        assert_eq!(parse_meld("7RXB"), Err(MeldParseError::InvalidColor { position: 3, letter: 'X' }));
+        assert_eq!(parse_meld("W7"), Err(MeldParseError::InvalidGroup { position: 1, token: "W7".to_string() }));
+        assert!(matches!(parse_meld("R3-R4-X5"), Err(MeldParseError::InvalidTile { position: 7, .. })));
+        assert!(matches!(parse_meld("W14RB"), Err(MeldParseError::InvalidTile { position: 2, .. })));
+        assert_eq!(parse_meld("R3-R44").unwrap_err().position(), Some(4));
+        assert_eq!(parse_meld("").unwrap_err().position(), None);
+    }
+
+}
@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+use rand::prelude::*;
+use serde::Serialize;
+use super::{best_partition, count_valid_sets, dead_tiles, has_opening_meld, parallel, Card, Deck};
+
+/// What simulate measures on every dealt tray
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stat {
+    /// 1 when the tray has a 30 point opening, 0 otherwise
+    Opening30,
+    /// How many sets valid_sets finds
+    SetsPerHand,
+    /// How many wildcards the tray holds
+    WildcardHands
+}
+
+impl Stat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stat::Opening30 => "opening30",
+            Stat::SetsPerHand => "sets-per-hand",
+            Stat::WildcardHands => "wildcard-hands"
+        }
+    }
+
+    fn measure(&self, tray: &[Card]) -> u64 {
+        match self {
+            Stat::Opening30 => has_opening_meld(tray) as u64,
+            Stat::SetsPerHand => count_valid_sets(tray) as u64,
+            Stat::WildcardHands => tray.iter().filter(|c| c.is_wildcard()).count() as u64
+        }
+    }
+
+    // Set counts spread widely, so they are grouped by ten
+    fn bucket_width(&self) -> u64 {
+        match self {
+            Stat::SetsPerHand => 10,
+            Stat::Opening30 | Stat::WildcardHands => 1
+        }
+    }
+}
+
+/// How many trays gave a value between low and high, both included
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Bucket {
+    pub low: u64,
+    pub high: u64,
+    pub count: usize
+}
+
+/// The statistics of the trays dealt by simulate, serializable to JSON
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SimulationReport {
+    pub stat: String,
+    pub hands: usize,
+    pub tray_size: usize,
+    pub seed: u64,
+    pub mean: f64,
+    pub std_dev: f64,
+    /// 95% confidence interval of the mean
+    pub confidence_low: f64,
+    pub confidence_high: f64,
+    /// Only the buckets holding at least one tray, lowest first
+    pub buckets: Vec<Bucket>
+}
+
+/// Deals hands trays of tray_size tiles, each from a fresh deck, and measures the stat on
+/// every one of them. The decks are shuffled by a generator seeded with seed, so the same
+/// arguments give the same report. Panics if tray_size is larger than the deck.
+pub fn simulate(stat: Stat, hands: usize, tray_size: usize, seed: u64) -> SimulationReport {
+    assert!(tray_size <= Deck::new().len(), "a tray can't hold more than the {} tiles of the deck", Deck::new().len());
+    let mut rng = StdRng::seed_from_u64(seed);
+    let values: Vec<u64> = (0..hands)
+        .map(|_| {
+            let mut deck = Deck::new();
+            deck.shuffle_with(&mut rng);
+            stat.measure(&deck.pick_tray(tray_size))
+        })
+        .collect();
+
+    let n = values.len() as f64;
+    let mean = if hands == 0 { 0.0 } else { values.iter().sum::<u64>() as f64 / n };
+    // The sample standard deviation, 0 below two trays
+    let std_dev = if hands < 2 {
+        0.0
+    } else {
+        (values.iter().map(|v| (*v as f64 - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+    };
+    let margin = if hands == 0 { 0.0 } else { 1.96 * std_dev / n.sqrt() };
+
+    let width = stat.bucket_width();
+    let mut counts: BTreeMap<u64, usize> = BTreeMap::new();
+    for value in &values {
+        *counts.entry(value / width).or_default() += 1;
+    }
+    let buckets = counts.into_iter()
+        .map(|(bucket, count)| Bucket { low: bucket * width, high: bucket * width + width - 1, count })
+        .collect();
+
+    SimulationReport {
+        stat: stat.name().to_string(),
+        hands,
+        tray_size,
+        seed,
+        mean,
+        std_dev,
+        confidence_low: mean - margin,
+        confidence_high: mean + margin,
+        buckets
+    }
+}
+
+/// What analyze_trays found over its trays, serializable to JSON
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrayStats {
+    pub samples: usize,
+    pub tray_size: usize,
+    pub seed: u64,
+    /// How many trays had every number of valid sets, one bucket per number, lowest first
+    pub set_counts: Vec<Bucket>,
+    pub mean_sets: f64,
+    /// The lower of the two middle trays for an even number of them
+    pub median_sets: u64,
+    /// The share of trays with at least one valid set
+    pub p_any_set: f64,
+    /// How many sets best_partition lays down on average
+    pub mean_partition_sets: f64,
+    pub mean_dead_tiles: f64
+}
+
+/// Deals samples trays like simulate does, then finds the valid sets, the best partition
+/// and the dead tiles of all of them at once, over threads with the parallel feature. The
+/// same arguments give the same statistics. Panics if tray_size is larger than the deck.
+pub fn analyze_trays(samples: usize, tray_size: usize, seed: u64) -> TrayStats {
+    assert!(tray_size <= Deck::new().len(), "a tray can't hold more than the {} tiles of the deck", Deck::new().len());
+    let mut rng = StdRng::seed_from_u64(seed);
+    let trays: Vec<Vec<Card>> = (0..samples)
+        .map(|_| {
+            let mut deck = Deck::new();
+            deck.shuffle_with(&mut rng);
+            deck.pick_tray(tray_size)
+        })
+        .collect();
+
+    let mut set_counts: Vec<u64> = parallel::map(&trays, |tray| count_valid_sets(tray) as u64);
+    let others = parallel::map(&trays, |tray| (best_partition(tray).sets.len(), dead_tiles(tray).len()));
+    let mean = |total: usize| if samples == 0 { 0.0 } else { total as f64 / samples as f64 };
+
+    set_counts.sort_unstable();
+    let mut counts: BTreeMap<u64, usize> = BTreeMap::new();
+    for count in &set_counts {
+        *counts.entry(*count).or_default() += 1;
+    }
+
+    TrayStats {
+        samples,
+        tray_size,
+        seed,
+        set_counts: counts.into_iter().map(|(sets, count)| Bucket { low: sets, high: sets, count }).collect(),
+        mean_sets: mean(set_counts.iter().sum::<u64>() as usize),
+        median_sets: if samples == 0 { 0 } else { set_counts[(samples - 1) / 2] },
+        p_any_set: mean(set_counts.iter().filter(|count| **count > 0).count()),
+        mean_partition_sets: mean(others.iter().map(|(sets, _)| sets).sum()),
+        mean_dead_tiles: mean(others.iter().map(|(_, dead)| dead).sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::valid_sets;
+
+    #[test]
+    fn wildcard_hands_test() {
+        let report = simulate(Stat::WildcardHands, 200, 14, 3);
+
+        assert_eq!(report.buckets, vec![
+            Bucket { low: 0, high: 0, count: 152 },
+            Bucket { low: 1, high: 1, count: 46 },
+            Bucket { low: 2, high: 2, count: 2 }
+        ]);
+        assert_eq!(report.mean, 0.25);
+        assert!(report.confidence_low < 0.25 && report.confidence_high > 0.25);
+    }
+
+    #[test]
+    fn opening30_test() {
+        let report = simulate(Stat::Opening30, 100, 14, 3);
+
+        assert_eq!(report.buckets.iter().map(|b| b.count).sum::<usize>(), 100);
+        assert_eq!(report.mean, 0.42);
+        assert_eq!(report, simulate(Stat::Opening30, 100, 14, 3));
+    }
+
+    #[test]
+    fn sets_per_hand_test() {
+        let report = simulate(Stat::SetsPerHand, 20, 14, 3);
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut deck = Deck::new();
+        deck.shuffle_with(&mut rng);
+        let first = valid_sets(&deck.pick_tray(14)).len() as u64;
+        assert!(report.buckets.iter().any(|b| b.low <= first && first <= b.high));
+        assert!(report.buckets.iter().all(|b| b.high == b.low + 9));
+        assert_eq!(report.mean, 3.5);
+    }
+
+    #[test]
+    fn analyze_trays_test() {
+        let stats = analyze_trays(200, 14, 3);
+
+        assert_eq!(stats.set_counts.len(), 21);
+        assert_eq!(stats.set_counts[..3], [
+            Bucket { low: 0, high: 0, count: 43 },
+            Bucket { low: 1, high: 1, count: 56 },
+            Bucket { low: 2, high: 2, count: 16 }
+        ]);
+        assert_eq!(stats.set_counts.last(), Some(&Bucket { low: 38, high: 38, count: 1 }));
+        assert_eq!(stats.mean_sets, 3.99);
+        assert_eq!(stats.median_sets, 2);
+        assert_eq!(stats.p_any_set, 0.785);
+        assert_eq!(stats.mean_partition_sets, 1.15);
+        assert_eq!(stats.mean_dead_tiles, 8.84);
+        // The same trays as simulate
+        assert_eq!(simulate(Stat::SetsPerHand, 200, 14, 3).mean, stats.mean_sets);
+        assert_eq!(analyze_trays(0, 14, 3).median_sets, 0);
+    }
+
+}
@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use super::{GameEvent, Move, PlayerId, RoundResult, ScoringVariant};
+
+/// What a player did over the rounds recorded with update, the averages being worked out
+/// from the totals
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerStats {
+    /// Rounds played to the end
+    pub games: usize,
+    pub wins: usize,
+    /// Points lost over all the rounds, 0 counting for the rounds won
+    pub penalty: i64,
+    /// Rounds in which the player laid down a first meld
+    pub opened: usize,
+    /// Turns of the player up to the one of the first meld, added up over the rounds opened
+    pub turns_to_open: usize,
+    /// Wildcards and twist jokers laid down in a meld, counted again when retrieved and
+    /// played anew
+    pub jokers_played: usize,
+    /// The most tiles laid down in a single new meld
+    pub biggest_meld: usize
+}
+
+impl PlayerStats {
+    /// Adds a round the player took part in, given its result and the events of the game
+    pub fn update(&mut self, player: PlayerId, result: &RoundResult, events: &[GameEvent]) {
+        self.games += 1;
+        if result.winner == player {
+            self.wins += 1;
+        }
+        if let Some((_, penalty)) = result.penalties.iter().find(|(id, _)| *id == player) {
+            // Penalties are negative unless racing to a number of points
+            self.penalty += match result.scoring {
+                ScoringVariant::RaceTo(_) => *penalty as i64,
+                _ => -*penalty as i64
+            };
+        }
+
+        let mut turns = vec![];
+        let mut opening = None;
+        // Mulligans come before the first turn
+        for event in events.iter().filter(|event| event.player == player && !matches!(event.mv, Move::Mulligan(_))) {
+            if turns.last() != Some(&event.turn) {
+                turns.push(event.turn);
+            }
+            let played = match &event.mv {
+                Move::PlayNewMeld(cards) => {
+                    opening = opening.or(Some(turns.len()));
+                    self.biggest_meld = self.biggest_meld.max(cards.len());
+                    cards.as_slice()
+                },
+                Move::ExtendMeld { card, .. } => core::slice::from_ref(card),
+                _ => &[]
+            };
+            self.jokers_played += played.iter().filter(|card| card.is_wildcard() || card.is_twist_joker()).count();
+        }
+        if let Some(turns) = opening {
+            self.opened += 1;
+            self.turns_to_open += turns;
+        }
+    }
+
+    /// Adds up the totals of both, keeping the biggest meld of the two
+    pub fn merge(&mut self, other: &PlayerStats) {
+        self.games += other.games;
+        self.wins += other.wins;
+        self.penalty += other.penalty;
+        self.opened += other.opened;
+        self.turns_to_open += other.turns_to_open;
+        self.jokers_played += other.jokers_played;
+        self.biggest_meld = self.biggest_meld.max(other.biggest_meld);
+    }
+
+    /// Points lost per round, 0 before the first one
+    pub fn average_penalty(&self) -> f64 {
+        if self.games == 0 { 0.0 } else { self.penalty as f64 / self.games as f64 }
+    }
+
+    /// Turns taken to lay down a first meld, over the rounds it happened in
+    pub fn average_turns_to_open(&self) -> f64 {
+        if self.opened == 0 { 0.0 } else { self.turns_to_open as f64 / self.opened as f64 }
+    }
+}
+
+/// The PlayerStats of everyone who played, by name, so they add up across games with other
+/// players. It serializes to a JSON object of the names, to be kept by the caller between
+/// runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StatsBook {
+    players: BTreeMap<String, PlayerStats>
+}
+
+impl StatsBook {
+    pub fn new() -> StatsBook {
+        StatsBook::default()
+    }
+
+    /// Updates the stats of every player of a round, names being in player order
+    pub fn record(&mut self, names: &[impl AsRef<str>], result: &RoundResult, events: &[GameEvent]) {
+        for (player, name) in names.iter().enumerate() {
+            self.players.entry(name.as_ref().to_string()).or_default().update(player, result, events);
+        }
+    }
+
+    /// Adds the stats of the other book, players only in it joining this one
+    pub fn merge(&mut self, other: &StatsBook) {
+        for (name, stats) in &other.players {
+            self.players.entry(name.clone()).or_default().merge(stats);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PlayerStats> {
+        self.players.get(name)
+    }
+
+    /// The players by name, in alphabetical order
+    pub fn players(&self) -> impl Iterator<Item = (&str, &PlayerStats)> {
+        self.players.iter().map(|(name, stats)| (name.as_str(), stats))
+    }
+
+    pub fn len(&self) -> usize {
+        self.players.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.players.is_empty()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<StatsBook, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::{Card, Color, MeldId, RoundEnd};
+
+    fn event(player: PlayerId, turn: usize, mv: Move) -> GameEvent {
+        GameEvent { player, turn, mv, drawn: None, timed_out: false }
+    }
+
+    fn run(numbers: &[i8], color: Color) -> Vec<Card> {
+        numbers.iter().map(|n| Card::new(*n, color)).collect()
+    }
+
+    // Bo opens first and goes out in the first round, Ann opens at once and goes out in the
+    // second
+    fn two_rounds() -> StatsBook {
+        let first = [
+            event(0, 0, Move::Draw),
+            event(1, 1, Move::PlayNewMeld(run(&[10, 11, 12], Color::Red))),
+            event(1, 1, Move::EndTurn),
+            event(0, 2, Move::PlayNewMeld(vec![Card::Wildcard, Card::new(10, Color::Black), Card::new(10, Color::Yellow), Card::new(10, Color::Blue)])),
+            event(0, 2, Move::EndTurn),
+            event(1, 3, Move::PlayNewMeld(run(&[1, 2, 3, 4], Color::Blue)))
+        ];
+        let second = [
+            event(0, 0, Move::PlayNewMeld(run(&[9, 10, 11, 12, 13], Color::Yellow))),
+            event(0, 0, Move::EndTurn),
+            event(1, 1, Move::Draw),
+            event(0, 2, Move::ExtendMeld { meld: MeldId::from_number(0), card: Card::Wildcard })
+        ];
+        let result = |winner, penalty| RoundResult {
+            winner,
+            end: RoundEnd::WentOut,
+            penalties: vec![(1 - winner, penalty)],
+            winner_gain: -penalty,
+            scoring: ScoringVariant::Standard,
+            team_scores: vec![]
+        };
+
+        let mut book = StatsBook::new();
+        book.record(&["Ann", "Bo"], &result(1, -25), &first);
+        book.record(&["Ann", "Bo"], &result(0, -40), &second);
+        book
+    }
+
+    #[test]
+    fn two_rounds_test() {
+        let book = two_rounds();
+
+        let ann = book.get("Ann").unwrap();
+        assert_eq!(ann, &PlayerStats { games: 2, wins: 1, penalty: 25, opened: 2, turns_to_open: 3, jokers_played: 2, biggest_meld: 5 });
+        assert_eq!(ann.average_penalty(), 12.5);
+        assert_eq!(ann.average_turns_to_open(), 1.5);
+        let bo = book.get("Bo").unwrap();
+        assert_eq!(bo, &PlayerStats { games: 2, wins: 1, penalty: 40, opened: 1, turns_to_open: 1, jokers_played: 0, biggest_meld: 4 });
+        assert_eq!(bo.average_turns_to_open(), 1.0);
+        assert_eq!(book.get("Cy"), None);
+    }
+
+    #[test]
+    fn merge_books_test() {
+        let mut book = two_rounds();
+        let mut other = two_rounds();
+        let third = RoundResult { winner: 0, end: RoundEnd::WentOut, penalties: vec![(1, -7)], winner_gain: 7, scoring: ScoringVariant::Standard, team_scores: vec![] };
+        other.record(&["Cy", "Ann"], &third, &[]);
+
+        book.merge(&other);
+        assert_eq!(book.len(), 3);
+        assert_eq!(book.get("Ann"), Some(&PlayerStats { games: 5, wins: 2, penalty: 57, opened: 4, turns_to_open: 6, jokers_played: 4, biggest_meld: 5 }));
+        assert_eq!(book.get("Bo").map(|bo| (bo.games, bo.wins, bo.penalty)), Some((4, 2, 80)));
+        assert_eq!(book.get("Cy").map(|cy| (cy.games, cy.wins, cy.opened)), Some((1, 1, 0)));
+        assert_eq!(book.players().map(|(name, _)| name).collect::<Vec<&str>>(), ["Ann", "Bo", "Cy"]);
+
+        let json = book.to_json();
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&json).unwrap()["Cy"]["wins"], 1);
+        assert_eq!(StatsBook::from_json(&json).unwrap(), book);
+    }
+
+}
@@ -0,0 +1,265 @@
+use rand::prelude::*;
+use super::{best_partition, set_points, valid_sets, validate_set, Card, GameState, Move, MoveError, PlayerView, RoundResult};
+use super::opening::OPENING_POINTS;
+
+/// How a computer player picks its moves. It only sees the game through a PlayerView, so it
+/// can't know the tiles of the others.
+pub trait Strategy {
+    /// The next move of the player of the view, whose turn it is. A turn takes several calls,
+    /// the last one returning Move::EndTurn. The round must not be over, and the move must be
+    /// legal: play_round stops on the first refused one.
+    fn choose_move(&mut self, view: &PlayerView) -> Move;
+}
+
+/// Lays down as many tiles as it can. It plays the sets of its best partition, then adds its
+/// loose tiles to the melds on the table, and draws when it can't play anything. Before
+/// opening it only plays when its best partition is worth 30 points.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GreedyStrategy;
+
+impl Strategy for GreedyStrategy {
+    fn choose_move(&mut self, view: &PlayerView) -> Move {
+        let turn = TurnSoFar::of(view);
+        if turn.drew {
+            return Move::EndTurn;
+        }
+        if !view.has_melded {
+            return opening_move(view, &turn);
+        }
+
+        let partition = best_partition(view.tray);
+        if let Some(set) = partition.sets.first() {
+            return Move::PlayNewMeld(set.clone());
+        }
+        extension(view, &partition.leftover).unwrap_or_else(|| turn.end_or_draw(view))
+    }
+}
+
+/// Gets rid of its highest tiles first, as they cost the most if another player goes out:
+/// it plays the set worth the most points, then extends melds with its highest tiles.
+/// It opens like GreedyStrategy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HighTileDumper;
+
+impl Strategy for HighTileDumper {
+    fn choose_move(&mut self, view: &PlayerView) -> Move {
+        let turn = TurnSoFar::of(view);
+        if turn.drew {
+            return Move::EndTurn;
+        }
+        if !view.has_melded {
+            return opening_move(view, &turn);
+        }
+
+        // max_by_key keeps the last of a tie, the order of valid_sets is kept by reversing
+        let best = valid_sets(view.tray).into_iter().rev().max_by_key(|set| set_points(set));
+        if let Some(set) = best {
+            return Move::PlayNewMeld(set.into_iter().copied().collect());
+        }
+        let mut tiles = view.tray.to_vec();
+        tiles.sort_by_key(|card| std::cmp::Reverse(card.number().copied().unwrap_or(0)));
+        extension(view, &tiles).unwrap_or_else(|| turn.end_or_draw(view))
+    }
+}
+
+/// Picks any legal move, the same ones for the same seed. Before opening it only plays a
+/// single set worth 30 points.
+#[derive(Debug, Clone)]
+pub struct RandomStrategy {
+    rng: StdRng
+}
+
+impl RandomStrategy {
+    pub fn new(seed: u64) -> RandomStrategy {
+        RandomStrategy { rng: StdRng::seed_from_u64(seed) }
+    }
+}
+
+impl Strategy for RandomStrategy {
+    fn choose_move(&mut self, view: &PlayerView) -> Move {
+        let turn = TurnSoFar::of(view);
+        if turn.drew {
+            return Move::EndTurn;
+        }
+
+        let mut moves: Vec<Move> = valid_sets(view.tray).into_iter()
+            .filter(|set| view.has_melded || set_points(set) >= OPENING_POINTS)
+            .map(|set| Move::PlayNewMeld(set.into_iter().copied().collect()))
+            .collect();
+        if view.has_melded {
+            for card in view.tray {
+                for (meld, cards) in view.table.melds() {
+                    let mut extended = cards.to_vec();
+                    extended.push(*card);
+                    if validate_set(&extended).is_ok() {
+                        moves.push(Move::ExtendMeld { meld, card: *card });
+                    }
+                }
+            }
+        }
+        // Once the opening set is down, the turn is over
+        if view.has_melded || turn.played.is_empty() {
+            moves.push(turn.end_or_draw(view));
+        } else {
+            moves = vec![Move::EndTurn];
+        }
+        moves.swap_remove(self.rng.gen_range(0..moves.len()))
+    }
+}
+
+// What the player did so far this turn, read from the events
+pub(super) struct TurnSoFar<'a> {
+    pub(super) drew: bool,
+    // The melds laid down
+    pub(super) played: Vec<&'a Vec<Card>>,
+    // Whether any move was played
+    pub(super) moved: bool
+}
+
+impl<'a> TurnSoFar<'a> {
+    pub(super) fn of(view: &'a PlayerView) -> TurnSoFar<'a> {
+        let events: Vec<_> = view.events.iter()
+            .rev()
+            .take_while(|event| event.turn == view.turn_number)
+            .collect();
+        TurnSoFar {
+            drew: events.iter().any(|event| event.mv == Move::Draw),
+            played: events.iter()
+                .rev()
+                .filter_map(|event| match &event.mv {
+                    Move::PlayNewMeld(cards) => Some(cards),
+                    _ => None
+                })
+                .collect(),
+            moved: !events.is_empty()
+        }
+    }
+
+    // Players who played end their turn, the others draw, or pass once the deck is empty
+    pub(super) fn end_or_draw(&self, view: &PlayerView) -> Move {
+        if self.moved || view.deck_len == 0 {
+            Move::EndTurn
+        } else {
+            Move::Draw
+        }
+    }
+}
+
+// The opening is the best partition of the tray the turn started with, played a set at a time,
+// if it is worth 30 points
+fn opening_move(view: &PlayerView, turn: &TurnSoFar) -> Move {
+    let mut tray = view.tray.to_vec();
+    tray.extend(turn.played.iter().copied().flatten());
+    let partition = best_partition(&tray);
+    let points: u32 = partition.sets.iter()
+        .map(|set| set_points(&set.iter().collect::<Vec<&Card>>()))
+        .sum();
+    if points < OPENING_POINTS {
+        return turn.end_or_draw(view);
+    }
+    match partition.sets.get(turn.played.len()) {
+        Some(set) => Move::PlayNewMeld(set.clone()),
+        None => Move::EndTurn
+    }
+}
+
+// The first of the tiles that fits at the end of a meld of the table
+fn extension(view: &PlayerView, tiles: &[Card]) -> Option<Move> {
+    for card in tiles {
+        for (meld, cards) in view.table.melds() {
+            let mut extended = cards.to_vec();
+            extended.push(*card);
+            if validate_set(&extended).is_ok() {
+                return Some(Move::ExtendMeld { meld, card: *card });
+            }
+        }
+    }
+    None
+}
+
+/// Lets every player play with their strategy, in the order of the players, until the round
+/// is over or max_moves moves were played, returning None in that case. Stops on the first
+/// move refused by apply_move. Panics unless there is a strategy for every player.
+pub fn play_round(
+    game: &mut GameState,
+    strategies: &mut [Box<dyn Strategy>],
+    max_moves: usize
+) -> Result<Option<RoundResult>, MoveError> {
+    assert_eq!(strategies.len(), game.players().len(), "one strategy per player");
+    for _ in 0..max_moves {
+        if let Some(result) = game.round_result() {
+            return Ok(Some(result.clone()));
+        }
+        let player = game.current_player_index();
+        let mv = strategies[player].choose_move(&game.view_for(player));
+        game.apply_move(mv)?;
+    }
+    Ok(game.round_result().cloned())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // Plays rounds with the strategy in every seat until 1000 turns were played
+    fn check_legal_moves(strategy: impl Fn(u64) -> Box<dyn Strategy>) {
+        let mut turns = 0;
+        for seed in 0.. {
+            let mut game = GameState::new_game(&["Ann", "Bo", "Cy", "Di"], seed);
+            let mut strategies: Vec<Box<dyn Strategy>> = (0..4).map(|seat| strategy(seed * 4 + seat)).collect();
+
+            let result = play_round(&mut game, &mut strategies, 5_000);
+            assert!(matches!(result, Ok(Some(_))), "seed {}: {:?}", seed, result);
+            assert!(game.table().is_valid());
+            turns += game.turn_number();
+            if turns >= 1000 {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn greedy_strategy_legal_moves_test() {
+        check_legal_moves(|_| Box::new(GreedyStrategy));
+    }
+
+    #[test]
+    fn high_tile_dumper_legal_moves_test() {
+        check_legal_moves(|_| Box::new(HighTileDumper));
+    }
+
+    #[test]
+    fn random_strategy_legal_moves_test() {
+        check_legal_moves(|seed| Box::new(RandomStrategy::new(seed)));
+    }
+
+    #[test]
+    fn two_greedy_players_finish_round_test() {
+        let mut game = GameState::new_game(&["Ann", "Bo"], 12);
+        let mut strategies: Vec<Box<dyn Strategy>> = vec![Box::new(GreedyStrategy), Box::new(GreedyStrategy)];
+
+        let result = play_round(&mut game, &mut strategies, 5_000).unwrap().unwrap();
+
+        assert_eq!(result.penalties.len(), 1);
+        assert!(result.winner_gain >= 0);
+        assert_eq!(result.winner_gain, -result.penalties[0].1);
+        assert!(game.players()[result.winner].has_melded());
+    }
+
+    #[test]
+    fn mixed_strategies_test() {
+        let mut game = GameState::new_game(&["Ann", "Bo", "Cy"], 3);
+        let mut strategies: Vec<Box<dyn Strategy>> = vec![
+            Box::new(GreedyStrategy),
+            Box::new(HighTileDumper),
+            Box::new(RandomStrategy::new(3))
+        ];
+
+        // Too few moves to finish
+        assert_eq!(play_round(&mut game, &mut strategies, 5), Ok(None));
+        assert_eq!(game.events().len(), 5);
+        assert!(play_round(&mut game, &mut strategies, 5_000).unwrap().is_some());
+    }
+
+}
@@ -0,0 +1,346 @@
+use core::fmt;
+use alloc::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use super::{resolve_wildcards_with, Card, MeldKind, RulesConfig, SetError, TableDiff, validate_set_with};
+use crate::prelude::*;
+
+/// Names a meld on the table. Ids are never reused, so they keep pointing to the same meld
+/// while others are added and removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct MeldId(u32);
+
+impl MeldId {
+    /// The number Display shows after the #
+    pub fn number(&self) -> u32 {
+        self.0
+    }
+
+    /// The id of the given number, to read back ids written with number
+    pub fn from_number(number: u32) -> MeldId {
+        MeldId(number)
+    }
+}
+
+impl fmt::Display for MeldId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Why a wildcard can't be taken back from the table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum JokerError {
+    #[error("there is no meld {0} on the table")]
+    NoSuchMeld(MeldId),
+    #[error("meld {0} has no wildcard")]
+    NoWildcard(MeldId),
+    // The replacement is not a tile the wildcard stands for in the meld
+    #[error("the wildcard of meld {meld} doesn't stand for {}", replacement.code())]
+    WrongTile { meld: MeldId, replacement: Card }
+}
+
+/// The melds in play, shared by every player. During a turn melds can be broken and rebuilt,
+/// so they are only checked as a whole by is_valid.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Table {
+    melds: BTreeMap<MeldId, Vec<Card>>,
+    next_id: u32,
+    // What the melds are checked against
+    rules: RulesConfig
+}
+
+// Only the melds are public, as (id, tiles) pairs like the state sent over the network.
+// Tiles serialize with std.
+#[cfg(feature = "std")]
+impl Serialize for Table {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.melds())
+    }
+}
+
+impl Table {
+    pub fn new() -> Table {
+        Table::default()
+    }
+
+    /// An empty table checking melds against house rules
+    pub fn with_rules(rules: RulesConfig) -> Table {
+        Table { rules, ..Table::default() }
+    }
+
+    /// A table holding the given melds, valid or not, like one being rearranged during a turn
+    pub fn from_melds(melds: Vec<Vec<Card>>) -> Table {
+        let next_id = melds.len() as u32;
+        Table {
+            melds: melds.into_iter().enumerate().map(|(id, cards)| (MeldId(id as u32), cards)).collect(),
+            next_id,
+            rules: RulesConfig::default()
+        }
+    }
+
+    /// Lays down a new meld, if it is a valid run or group
+    pub fn add_meld(&mut self, cards: Vec<Card>) -> Result<MeldId, SetError> {
+        validate_set_with(&cards, &self.rules)?;
+        let id = MeldId(self.next_id);
+        self.next_id += 1;
+        self.melds.insert(id, cards);
+        Ok(id)
+    }
+
+    /// Adds a tile at the end of a meld, returning false if there is no such meld. The meld
+    /// isn't checked: the order of its tiles doesn't matter and it may be fixed later in the turn.
+    pub fn append_to_meld(&mut self, id: MeldId, card: Card) -> bool {
+        match self.melds.get_mut(&id) {
+            Some(cards) => {
+                cards.push(card);
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Takes the tile at position idx out of a meld, if both exist. The meld stays on the
+    /// table, even when emptied.
+    pub fn remove_card(&mut self, id: MeldId, idx: usize) -> Option<Card> {
+        let cards = self.melds.get_mut(&id)?;
+        (idx < cards.len()).then(|| cards.remove(idx))
+    }
+
+    /// Puts replacement in place of a wildcard of the meld and hands the wildcard back, if the
+    /// replacement is a tile the wildcard stands for: in a run the tile resolve_wildcards
+    /// reads it as, in a group any color the group misses. The meld is left as it was on
+    /// error.
+    pub fn replace_wildcard(&mut self, id: MeldId, replacement: Card) -> Result<Card, JokerError> {
+        let rules = self.rules;
+        let cards = self.melds.get_mut(&id).ok_or(JokerError::NoSuchMeld(id))?;
+        let idx = cards.iter().position(|c| c.is_wildcard()).ok_or(JokerError::NoWildcard(id))?;
+        let wrong_tile = JokerError::WrongTile { meld: id, replacement };
+        if replacement.is_wildcard() {
+            return Err(wrong_tile);
+        }
+
+        let kind = validate_set_with(cards, &rules).ok();
+        let mut replaced = cards.clone();
+        replaced[idx] = replacement;
+        if kind.is_none() || validate_set_with(&replaced, &rules).ok() != kind {
+            return Err(wrong_tile);
+        }
+        // A run only takes the tile the wildcard is read as, while a group takes any color
+        // it misses, which the check above already makes sure of. Runs without a reading get
+        // the check above only.
+        if kind == Some(MeldKind::Run) {
+            let values = resolve_wildcards_with(&cards.iter().collect::<Vec<&Card>>(), &rules);
+            if values.is_some_and(|values| Card::new(values[idx].0, values[idx].1) != replacement) {
+                return Err(wrong_tile);
+            }
+        }
+        Ok(core::mem::replace(&mut cards[idx], replacement))
+    }
+
+    /// Takes a whole meld off the table
+    pub fn remove_meld(&mut self, id: MeldId) -> Option<Vec<Card>> {
+        self.melds.remove(&id)
+    }
+
+    /// The rules the melds are checked against
+    pub fn rules(&self) -> &RulesConfig {
+        &self.rules
+    }
+
+    pub fn meld(&self, id: MeldId) -> Option<&[Card]> {
+        self.melds.get(&id).map(|cards| cards.as_slice())
+    }
+
+    /// The melds in the order they were laid down
+    pub fn melds(&self) -> impl Iterator<Item = (MeldId, &[Card])> {
+        self.melds.iter().map(|(id, cards)| (*id, cards.as_slice()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.melds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.melds.is_empty()
+    }
+
+    /// Whether every meld on the table is a valid run or group
+    pub fn is_valid(&self) -> bool {
+        self.invalid_melds().is_empty()
+    }
+
+    /// The melds added, removed and changed from this table to after, and the tiles that
+    /// went from one meld to another. Melds are matched by id, so after should come from
+    /// this table, like the table at the end of a turn.
+    pub fn diff(&self, after: &Table) -> TableDiff {
+        super::diff::diff_tables(self, after)
+    }
+
+    /// The melds that are no longer valid, and why
+    pub fn invalid_melds(&self) -> Vec<(MeldId, SetError)> {
+        self.melds.iter()
+            .filter_map(|(id, cards)| validate_set_with(cards, &self.rules).err().map(|error| (*id, error)))
+            .collect()
+    }
+}
+
+/// One meld per line: its id, its tiles and its kind, or why it is not valid
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.melds.is_empty() {
+            return writeln!(f, "(empty table)");
+        }
+        for (id, cards) in &self.melds {
+            let codes: Vec<String> = cards.iter().map(|c| c.code()).collect();
+            let status = match validate_set_with(cards, &self.rules) {
+                Ok(kind) => kind.name().to_string(),
+                Err(error) => format!("invalid: {}", error)
+            };
+            writeln!(f, "{:>4}  {:<40} {}", id.to_string(), codes.join(" "), status)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::Color;
+
+    fn run() -> Vec<Card> {
+        vec![Card::new(4, Color::Blue), Card::new(5, Color::Blue), Card::new(6, Color::Blue)]
+    }
+
+    #[test]
+    fn add_meld_test() {
+        let mut table = Table::new();
+
+        let first = table.add_meld(run()).unwrap();
+        let error = table.add_meld(vec![Card::new(4, Color::Blue), Card::new(6, Color::Red)]);
+        let second = table.add_meld(vec![Card::new(9, Color::Red), Card::new(9, Color::Black), Card::Wildcard]).unwrap();
+
+        assert_eq!(error, Err(SetError::TooShort { len: 2 }));
+        assert_ne!(first, second);
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.meld(first), Some(run().as_slice()));
+        assert!(table.is_valid());
+    }
+
+    #[test]
+    fn meld_ids_are_stable_test() {
+        let mut table = Table::new();
+        let first = table.add_meld(run()).unwrap();
+        let second = table.add_meld(run()).unwrap();
+
+        table.remove_meld(first);
+        let third = table.add_meld(run()).unwrap();
+
+        assert_eq!(table.meld(first), None);
+        assert!(table.meld(second).is_some());
+        assert!(third != first && third != second);
+        assert_eq!(table.melds().map(|(id, _)| id).collect::<Vec<MeldId>>(), vec![second, third]);
+    }
+
+    #[test]
+    fn append_to_meld_test() {
+        let mut table = Table::new();
+        let id = table.add_meld(run()).unwrap();
+
+        assert!(table.append_to_meld(id, Card::new(3, Color::Blue)));
+        assert!(table.is_valid());
+        assert_eq!(table.meld(id).unwrap().len(), 4);
+
+        assert!(table.append_to_meld(id, Card::new(3, Color::Red)));
+        assert!(!table.is_valid());
+        assert!(!table.append_to_meld(MeldId(99), Card::Wildcard));
+    }
+
+    #[test]
+    fn remove_card_test() {
+        let mut table = Table::new();
+        let id = table.add_meld(run()).unwrap();
+        table.append_to_meld(id, Card::new(7, Color::Blue));
+
+        // Taking B5 out of B4 B5 B6 B7 leaves a gap
+        assert_eq!(table.remove_card(id, 1), Some(Card::new(5, Color::Blue)));
+        assert_eq!(table.remove_card(id, 5), None);
+
+        assert!(!table.is_valid());
+        assert_eq!(table.invalid_melds(), vec![(id, SetError::GapInRun { missing: 5 })]);
+    }
+
+    #[test]
+    fn replace_wildcard_in_run_test() {
+        let mut table = Table::new();
+        let id = table.add_meld(vec![Card::new(4, Color::Red), Card::Wildcard, Card::new(6, Color::Red)]).unwrap();
+
+        assert_eq!(table.replace_wildcard(id, Card::new(5, Color::Red)), Ok(Card::Wildcard));
+        assert_eq!(table.meld(id), Some([Card::new(4, Color::Red), Card::new(5, Color::Red), Card::new(6, Color::Red)].as_slice()));
+        assert_eq!(table.replace_wildcard(id, Card::new(5, Color::Red)), Err(JokerError::NoWildcard(id)));
+    }
+
+    #[test]
+    fn replace_wildcard_in_group_test() {
+        let mut table = Table::new();
+        let id = table.add_meld(vec![Card::new(7, Color::Red), Card::new(7, Color::Blue), Card::Wildcard]).unwrap();
+
+        assert_eq!(table.replace_wildcard(id, Card::new(7, Color::Yellow)), Ok(Card::Wildcard));
+        assert!(table.is_valid());
+    }
+
+    #[test]
+    fn replace_wildcard_with_wrong_tile_test() {
+        let mut table = Table::new();
+        let run = vec![Card::new(4, Color::Red), Card::Wildcard, Card::new(6, Color::Red)];
+        let id = table.add_meld(run.clone()).unwrap();
+
+        for replacement in [Card::new(7, Color::Red), Card::new(5, Color::Blue), Card::Wildcard] {
+            assert_eq!(table.replace_wildcard(id, replacement), Err(JokerError::WrongTile { meld: id, replacement }));
+        }
+        // A wildcard at an end of a run is read as one number: W R4 R5 R6 still makes a run
+        // with R7 in place of the W, but the W stands for R3
+        let first = table.add_meld(vec![Card::Wildcard, Card::new(4, Color::Red), Card::new(5, Color::Red), Card::new(6, Color::Red)]).unwrap();
+        assert_eq!(table.replace_wildcard(first, Card::new(7, Color::Red)), Err(JokerError::WrongTile { meld: first, replacement: Card::new(7, Color::Red) }));
+        let last = table.add_meld(vec![Card::new(3, Color::Red), Card::new(4, Color::Red), Card::Wildcard]).unwrap();
+        assert_eq!(table.replace_wildcard(last, Card::new(2, Color::Red)), Err(JokerError::WrongTile { meld: last, replacement: Card::new(2, Color::Red) }));
+        assert_eq!(table.replace_wildcard(first, Card::new(3, Color::Red)), Ok(Card::Wildcard));
+        assert_eq!(table.replace_wildcard(last, Card::new(5, Color::Red)), Ok(Card::Wildcard));
+        // A group can't hold a color twice
+        let group = table.add_meld(vec![Card::new(4, Color::Red), Card::new(4, Color::Blue), Card::Wildcard]).unwrap();
+        assert!(table.replace_wildcard(group, Card::new(4, Color::Blue)).is_err());
+        assert_eq!(table.meld(id), Some(run.as_slice()));
+        // but takes any color it misses
+        assert_eq!(table.clone().replace_wildcard(group, Card::new(4, Color::Black)), Ok(Card::Wildcard));
+        assert_eq!(table.replace_wildcard(group, Card::new(4, Color::Yellow)), Ok(Card::Wildcard));
+    }
+
+    #[test]
+    fn from_melds_test() {
+        let mut table = Table::from_melds(vec![run(), vec![Card::Wildcard]]);
+
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.invalid_melds().len(), 1);
+
+        let id = table.add_meld(run()).unwrap();
+        assert_eq!(table.melds().last().map(|(last, _)| last), Some(id));
+    }
+
+    #[test]
+    fn display_test() {
+        let mut table = Table::new();
+        assert_eq!(table.to_string(), "(empty table)\n");
+
+        let id = table.add_meld(run()).unwrap();
+        table.add_meld(vec![Card::new(1, Color::Red), Card::new(1, Color::Yellow), Card::new(1, Color::Blue)]).unwrap();
+        table.remove_card(id, 0);
+
+        let output = table.to_string();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("  #0  B5 B6") && lines[0].ends_with("invalid: a set needs at least 3 tiles, this one has 2"));
+        assert!(lines[1].starts_with("  #1  R1 Y1 B1") && lines[1].ends_with("group"));
+    }
+
+}
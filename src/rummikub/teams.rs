@@ -0,0 +1,47 @@
+use super::PlayerId;
+
+/// Index of a team in Teams::names
+pub type TeamId = usize;
+
+/// Which team every player plays for. Teammates score together, see RoundResult::team_scores,
+/// but take their turns and keep their trays on their own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Teams {
+    names: Vec<String>,
+    // The team of every player, in player order
+    players: Vec<TeamId>
+}
+
+impl Teams {
+    /// The teams of partnership play: 4 players, teammates sitting opposite, so players 0 and
+    /// 2 are team A against players 1 and 3, team B
+    pub fn partners() -> Teams {
+        Teams::new(&["A", "B"], &[0, 1, 0, 1])
+    }
+
+    /// Teams of the given names, players giving the team of every player in player order.
+    /// Panics when a player is given a team that has no name.
+    pub fn new(names: &[&str], players: &[TeamId]) -> Teams {
+        assert!(players.iter().all(|team| *team < names.len()), "every team needs a name");
+        Teams { names: names.iter().map(|name| name.to_string()).collect(), players: players.to_vec() }
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Panics when the teams have no such player
+    pub fn team_of(&self, player: PlayerId) -> TeamId {
+        self.players[player]
+    }
+
+    /// The players of the team, in player order
+    pub fn members(&self, team: TeamId) -> Vec<PlayerId> {
+        (0..self.players.len()).filter(|player| self.players[*player] == team).collect()
+    }
+
+    /// How many players the teams were made for
+    pub fn player_count(&self) -> usize {
+        self.players.len()
+    }
+}
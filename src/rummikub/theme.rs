@@ -0,0 +1,92 @@
+use super::{Card, Color};
+use crate::prelude::*;
+
+/// How tiles are written out: a symbol for every color, followed by the number, a symbol for
+/// the wildcard, and for terminals that take them the ANSI colors of the tiles. The default
+/// is the plain theme, the tile codes like R7 and W.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayTheme {
+    /// Written before the number, for the colors of Color::SIX in order
+    pub symbols: [&'static str; 6],
+    pub wildcard: &'static str,
+    /// The ANSI SGR codes of the colors of Color::SIX in order, then of the wildcard. None
+    /// writes plain text.
+    pub styles: Option<[u8; 7]>
+}
+
+impl Default for DisplayTheme {
+    fn default() -> Self {
+        DisplayTheme::plain()
+    }
+}
+
+impl DisplayTheme {
+    /// The tile codes, in ASCII only
+    pub fn plain() -> DisplayTheme {
+        DisplayTheme { symbols: ["R", "B", "K", "Y", "G", "O"], wildcard: "W", styles: None }
+    }
+
+    /// The tile codes in the colors of the full screen UI, wildcards standing out in magenta
+    pub fn ansi() -> DisplayTheme {
+        DisplayTheme { styles: Some([91, 94, 90, 93, 92, 33, 95]), ..DisplayTheme::plain() }
+    }
+
+    /// A shape for every color, like ●7 for a red 7 and ■7 for a blue one, without colors
+    pub fn colorblind() -> DisplayTheme {
+        DisplayTheme { symbols: ["●", "■", "▲", "◆", "▼", "✚"], wildcard: "★", styles: None }
+    }
+
+    pub fn symbol(&self, color: Color) -> &'static str {
+        self.symbols[color_index(color)]
+    }
+
+    /// The text of the tile, without its style
+    pub fn tile(&self, card: &Card) -> String {
+        match card {
+            Card::Numbered { number, color } => format!("{}{}", self.symbol(*color), number),
+            Card::Wildcard => self.wildcard.to_string(),
+            Card::DoubleJoker | Card::MirrorJoker => card.code()
+        }
+    }
+
+    /// Styles the text in the color, none meaning the wildcard, when the theme has styles
+    pub fn paint(&self, text: &str, color: Option<Color>) -> String {
+        let style = self.styles.map(|styles| styles[color.map(color_index).unwrap_or(Color::SIX.len())]);
+        match style {
+            Some(code) => format!("\x1b[{}m{}\x1b[0m", code, text),
+            None => text.to_string()
+        }
+    }
+
+    /// The tile, styled
+    pub fn card(&self, card: &Card) -> String {
+        self.paint(&self.tile(card), card.color().copied())
+    }
+}
+
+fn color_index(color: Color) -> usize {
+    // Every color is one of Color::SIX
+    Color::SIX.iter().position(|c| *c == color).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn built_in_themes_test() {
+        let set = [Card::new(7, Color::Red), Card::new(7, Color::Blue), Card::new(7, Color::Black), Card::new(7, Color::Yellow), Card::Wildcard];
+        let line = |theme: DisplayTheme| set.iter().map(|card| theme.card(card)).collect::<Vec<String>>().join(" ");
+
+        assert_eq!(line(DisplayTheme::default()), "R7 B7 K7 Y7 W");
+        assert_eq!(line(DisplayTheme::colorblind()), "●7 ■7 ▲7 ◆7 ★");
+        assert_eq!(
+            line(DisplayTheme::ansi()),
+            "\x1b[91mR7\x1b[0m \x1b[94mB7\x1b[0m \x1b[90mK7\x1b[0m \x1b[93mY7\x1b[0m \x1b[95mW\x1b[0m"
+        );
+        assert_eq!(DisplayTheme::colorblind().tile(&Card::new(12, Color::Orange)), "✚12");
+        assert!(set.iter().all(|card| DisplayTheme::plain().tile(card) == card.code()));
+    }
+
+}
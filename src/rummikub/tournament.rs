@@ -0,0 +1,220 @@
+use serde::Serialize;
+use super::{play_round, GameState, GreedyStrategy, HighTileDumper, MoveError, RandomStrategy, StatsBook, Strategy};
+
+// Moves after which a game is given up as a draw
+const MAX_MOVES: usize = 10_000;
+
+// Rating of every strategy before the first game, and how much a game can change it
+const START_RATING: f64 = 1000.0;
+const RATING_K: f64 = 16.0;
+
+/// The strategies that can be entered in a tournament
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrategyKind {
+    Greedy,
+    Random,
+    HighTile
+}
+
+impl StrategyKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StrategyKind::Greedy => "greedy",
+            StrategyKind::Random => "random",
+            StrategyKind::HighTile => "hightile"
+        }
+    }
+
+    /// A new player of this kind, the seed only mattering to random ones
+    pub fn new_strategy(&self, seed: u64) -> Box<dyn Strategy> {
+        match self {
+            StrategyKind::Greedy => Box::new(GreedyStrategy),
+            StrategyKind::Random => Box::new(RandomStrategy::new(seed)),
+            StrategyKind::HighTile => Box::new(HighTileDumper)
+        }
+    }
+}
+
+/// The results of one strategy over all its games
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StrategyStats {
+    pub name: String,
+    pub games: usize,
+    pub wins: usize,
+    /// Games where it had the first turn
+    pub started: usize,
+    pub win_rate: f64,
+    /// Points lost per game, 0 counting for the games won
+    pub average_penalty: f64,
+    /// Turns played per game, by both players
+    pub average_turns: f64,
+    /// Elo rating, starting from 1000, a draw counting half a win
+    pub rating: f64
+}
+
+/// The results of the games between two strategies
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PairingStats {
+    pub first: String,
+    pub second: String,
+    pub games: usize,
+    pub first_wins: usize,
+    pub second_wins: usize,
+    /// Games given up after too many moves
+    pub unfinished: usize
+}
+
+/// What run_tournament found, serializable to JSON
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TournamentReport {
+    pub seed: u64,
+    /// Games played by every pairing
+    pub games_per_pairing: usize,
+    pub strategies: Vec<StrategyStats>,
+    pub pairings: Vec<PairingStats>,
+    /// The stats of every strategy by name over the games played to the end
+    pub stats: StatsBook
+}
+
+// Running totals of a strategy
+#[derive(Default)]
+struct Totals {
+    games: usize,
+    wins: usize,
+    started: usize,
+    penalty: i64,
+    turns: usize
+}
+
+/// Plays that many two-player games between every pair of strategies, a strategy can be
+/// entered twice. The strategies of a pairing take turns having the first turn, and game i
+/// of every pairing is dealt with seed + i. Games longer than 10000 moves are given up as
+/// draws. Fails on the first move refused by the game.
+pub fn run_tournament(strategies: &[StrategyKind], games: usize, seed: u64) -> Result<TournamentReport, MoveError> {
+    let mut totals: Vec<Totals> = strategies.iter().map(|_| Totals::default()).collect();
+    let mut ratings = vec![START_RATING; strategies.len()];
+    let mut pairings = vec![];
+    let mut stats = StatsBook::new();
+
+    for first in 0..strategies.len() {
+        for second in first + 1..strategies.len() {
+            let mut pairing = PairingStats {
+                first: strategies[first].name().to_string(),
+                second: strategies[second].name().to_string(),
+                games,
+                first_wins: 0,
+                second_wins: 0,
+                unfinished: 0
+            };
+            for game_index in 0..games {
+                let game_seed = seed.wrapping_add(game_index as u64);
+                let seats = if game_index % 2 == 0 { [first, second] } else { [second, first] };
+                let mut game = GameState::new_game(&[strategies[seats[0]].name(), strategies[seats[1]].name()], game_seed);
+                let mut players: Vec<Box<dyn Strategy>> = seats.iter()
+                    .enumerate()
+                    .map(|(seat, idx)| strategies[*idx].new_strategy(game_seed.wrapping_mul(2).wrapping_add(seat as u64)))
+                    .collect();
+
+                let result = play_round(&mut game, &mut players, MAX_MOVES)?;
+                if let Some(result) = &result {
+                    stats.record(&[strategies[seats[0]].name(), strategies[seats[1]].name()], result, game.events());
+                }
+                totals[seats[0]].started += 1;
+                for (seat, idx) in seats.iter().enumerate() {
+                    let total = &mut totals[*idx];
+                    total.games += 1;
+                    total.turns += game.turn_number();
+                    if let Some(result) = &result {
+                        let penalty = result.penalties.iter().find(|(player, _)| *player == seat).map(|(_, p)| *p).unwrap_or(0);
+                        total.penalty -= penalty as i64;
+                    }
+                }
+
+                // The score of the first strategy of the pairing
+                let score = match result.map(|r| seats[r.winner]) {
+                    Some(winner) if winner == first => {
+                        pairing.first_wins += 1;
+                        totals[first].wins += 1;
+                        1.0
+                    },
+                    Some(_) => {
+                        pairing.second_wins += 1;
+                        totals[second].wins += 1;
+                        0.0
+                    },
+                    None => {
+                        pairing.unfinished += 1;
+                        0.5
+                    }
+                };
+                let expected = 1.0 / (1.0 + 10f64.powf((ratings[second] - ratings[first]) / 400.0));
+                ratings[first] += RATING_K * (score - expected);
+                ratings[second] -= RATING_K * (score - expected);
+            }
+            pairings.push(pairing);
+        }
+    }
+
+    let per_game = |value: f64, games: usize| if games == 0 { 0.0 } else { value / games as f64 };
+    let strategies = strategies.iter()
+        .zip(&totals)
+        .zip(&ratings)
+        .map(|((kind, total), rating)| StrategyStats {
+            name: kind.name().to_string(),
+            games: total.games,
+            wins: total.wins,
+            started: total.started,
+            win_rate: per_game(total.wins as f64, total.games),
+            average_penalty: per_game(total.penalty as f64, total.games),
+            average_turns: per_game(total.turns as f64, total.games),
+            rating: *rating
+        })
+        .collect();
+    Ok(TournamentReport { seed, games_per_pairing: games, strategies, pairings, stats })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn mini_tournament_test() {
+        let kinds = [StrategyKind::Greedy, StrategyKind::Random, StrategyKind::HighTile];
+
+        let report = run_tournament(&kinds, 4, 7).unwrap();
+
+        assert_eq!(report, run_tournament(&kinds, 4, 7).unwrap());
+        assert_eq!(report.pairings.len(), 3);
+        for pairing in &report.pairings {
+            assert_eq!(pairing.first_wins + pairing.second_wins + pairing.unfinished, 4);
+        }
+        for stats in &report.strategies {
+            assert_eq!(stats.games, 8);
+            assert!((0.0..=1.0).contains(&stats.win_rate));
+            assert!(stats.average_turns > 0.0);
+        }
+        let wins: usize = report.strategies.iter().map(|s| s.wins).sum();
+        let unfinished: usize = report.pairings.iter().map(|p| p.unfinished).sum();
+        assert_eq!(wins + unfinished, 12);
+        // Points only move from one strategy to the other
+        let ratings: f64 = report.strategies.iter().map(|s| s.rating).sum();
+        assert!((ratings - 3000.0).abs() < 1e-6);
+
+        let json: serde_json::Value = serde_json::from_str(&serde_json::to_string(&report).unwrap()).unwrap();
+        assert_eq!(json["pairings"][0]["first"], "greedy");
+        assert_eq!(json["strategies"][2]["name"], "hightile");
+        for stats in &report.strategies {
+            assert_eq!(report.stats.get(&stats.name).map(|s| s.wins), Some(stats.wins));
+        }
+    }
+
+    #[test]
+    fn seating_alternates_test() {
+        let report = run_tournament(&[StrategyKind::Greedy, StrategyKind::Greedy], 5, 1).unwrap();
+
+        assert_eq!(report.strategies[0].started, 3);
+        assert_eq!(report.strategies[1].started, 2);
+    }
+
+}
@@ -0,0 +1,160 @@
+use core::fmt;
+use super::{fill_window, get_wildcards, group_copies, group_layers, run_layers, run_windows, tile_copy, Card, Color, SetSearchOptions};
+use crate::prelude::*;
+
+/// A step of the set search, as recorded by trace_sets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceEvent {
+    /// The copy-th copies of the numbers of a color were searched for runs
+    RunLayer { color: Color, copy: usize, tiles: usize },
+    /// The copy-th copies of a color were left out: even with every wildcard they are too
+    /// few for a run
+    RunLayerSkipped { color: Color, copy: usize, tiles: usize, wildcards: usize, needed: usize },
+    /// The len numbers from start make a run
+    RunFound { color: Color, copy: usize, start: i8, len: usize },
+    /// The len numbers from start miss more tiles than there are wildcards
+    WindowRejected { color: Color, copy: usize, start: i8, len: usize, missing: usize, wildcards: usize },
+    /// The copy-th copies of a number in every color were searched for groups
+    GroupLayer { number: i8, copy: usize, tiles: usize },
+    /// The copy-th copies of a number were left out: even with every wildcard they are too
+    /// few for a group
+    GroupLayerSkipped { number: i8, copy: usize, tiles: usize, wildcards: usize, needed: usize },
+    /// The tiles and wildcards of a group layer are more than a group can hold, so the
+    /// combinations of more than max of them were left out
+    GroupsPruned { number: i8, copy: usize, candidates: usize, max: usize }
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceEvent::RunLayer { color, copy, tiles } => write!(f, "runs of {} (copy {}): {} tiles", color, copy, tiles),
+            TraceEvent::RunLayerSkipped { color, copy, tiles, wildcards, needed } => {
+                write!(f, "runs of {} (copy {}) skipped: {} tiles and {} wildcards, {} needed", color, copy, tiles, wildcards, needed)
+            },
+            TraceEvent::RunFound { color, copy, start, len } => write!(f, "run of {} from {} (copy {}), {} tiles: found", color, start, copy, len),
+            TraceEvent::WindowRejected { color, copy, start, len, missing, wildcards } => {
+                write!(f, "run of {} from {} (copy {}), {} tiles: rejected, {} missing and {} wildcards", color, start, copy, len, missing, wildcards)
+            },
+            TraceEvent::GroupLayer { number, copy, tiles } => write!(f, "groups of {} (copy {}): {} tiles", number, copy, tiles),
+            TraceEvent::GroupLayerSkipped { number, copy, tiles, wildcards, needed } => {
+                write!(f, "groups of {} (copy {}) skipped: {} tiles and {} wildcards, {} needed", number, copy, tiles, wildcards, needed)
+            },
+            TraceEvent::GroupsPruned { number, copy, candidates, max } => {
+                write!(f, "groups of {} (copy {}): {} candidates, groups of more than {} pruned", number, copy, candidates, max)
+            }
+        }
+    }
+}
+
+/// What trace_sets recorded, in the order the search went
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchTrace {
+    pub events: Vec<TraceEvent>
+}
+
+/// One event per line
+impl fmt::Display for SearchTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for event in &self.events {
+            writeln!(f, "{}", event)?;
+        }
+        Ok(())
+    }
+}
+
+/// Goes through the search of valid_sets_with step by step, recording which layers of tiles
+/// it looks at, which run windows it keeps or rejects and why, and which groups it prunes.
+/// The whole search is recorded, whatever max_results says.
+pub fn trace_sets(cards: &[Card], options: &SetSearchOptions) -> SearchTrace {
+    let copies = group_copies(cards);
+    let tile = |number, color, copy| tile_copy(&copies, number, color, copy);
+    let wildcards = get_wildcards(cards);
+    let mut events = vec![];
+
+    if options.include_groups {
+        for (number, copy, layer) in group_layers(&tile, options) {
+            let needed = options.min_len();
+            if layer.len() + wildcards.len() < needed {
+                events.push(TraceEvent::GroupLayerSkipped { number, copy, tiles: layer.len(), wildcards: wildcards.len(), needed });
+                continue;
+            }
+            events.push(TraceEvent::GroupLayer { number, copy, tiles: layer.len() });
+            let candidates = layer.len() + wildcards.len();
+            let max = options.rules.group_limit();
+            if candidates > max {
+                events.push(TraceEvent::GroupsPruned { number, copy, candidates, max });
+            }
+        }
+    }
+
+    if options.include_runs {
+        for (color, copy, layer) in run_layers(&tile, options) {
+            let tiles = layer.iter().flatten().count();
+            let needed = options.min_run_len();
+            if tiles + wildcards.len() < needed {
+                events.push(TraceEvent::RunLayerSkipped { color, copy, tiles, wildcards: wildcards.len(), needed });
+                continue;
+            }
+            events.push(TraceEvent::RunLayer { color, copy, tiles });
+            for (start_idx, len) in run_windows(layer.len(), options) {
+                let start = options.rules.min_number + start_idx as i8;
+                events.push(match fill_window(&layer, start_idx, len, &wildcards) {
+                    Ok(_) => TraceEvent::RunFound { color, copy, start, len },
+                    Err(missing) => TraceEvent::WindowRejected { color, copy, start, len, missing, wildcards: wildcards.len() }
+                });
+            }
+        }
+    }
+    SearchTrace { events }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::find_runs;
+
+    #[test]
+    fn near_miss_run_test() {
+        // 4 6 8 Red needs two wildcards for 5 and 7
+        let tray = vec![
+            Card::new(4, Color::Red),
+            Card::new(6, Color::Red),
+            Card::new(8, Color::Red),
+            Card::Wildcard,
+            Card::new(2, Color::Blue)
+        ];
+
+        let trace = trace_sets(&tray, &SetSearchOptions::default());
+
+        assert!(trace.events.contains(&TraceEvent::WindowRejected { color: Color::Red, copy: 0, start: 4, len: 5, missing: 2, wildcards: 1 }));
+        assert!(trace.events.contains(&TraceEvent::RunFound { color: Color::Red, copy: 0, start: 4, len: 3 }));
+        assert!(trace.events.contains(&TraceEvent::RunLayerSkipped { color: Color::Blue, copy: 0, tiles: 1, wildcards: 1, needed: 3 }));
+        let found = trace.events.iter().filter(|event| matches!(event, TraceEvent::RunFound { .. })).count();
+        assert_eq!(found, find_runs(&tray).len());
+        assert_eq!(
+            TraceEvent::WindowRejected { color: Color::Red, copy: 0, start: 4, len: 5, missing: 2, wildcards: 1 }.to_string(),
+            "run of Red from 4 (copy 0), 5 tiles: rejected, 2 missing and 1 wildcards"
+        );
+    }
+
+    #[test]
+    fn group_trace_test() {
+        let tray = vec![
+            Card::new(9, Color::Red),
+            Card::new(9, Color::Blue),
+            Card::new(9, Color::Black),
+            Card::new(9, Color::Red),
+            Card::Wildcard,
+            Card::Wildcard
+        ];
+        let options = SetSearchOptions { include_runs: false, min_len: 4, ..SetSearchOptions::default() };
+
+        assert_eq!(trace_sets(&tray, &options).events, vec![
+            TraceEvent::GroupLayer { number: 9, copy: 0, tiles: 3 },
+            TraceEvent::GroupsPruned { number: 9, copy: 0, candidates: 5, max: 4 },
+            TraceEvent::GroupLayerSkipped { number: 9, copy: 1, tiles: 1, wildcards: 2, needed: 4 }
+        ]);
+    }
+
+}
@@ -0,0 +1,220 @@
+use core::slice::Iter;
+use super::{valid_sets, Card};
+use crate::prelude::*;
+
+/// How sorted_view orders the tiles of a tray
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortMode {
+    /// By number, then by color: groups come together
+    ByNumberThenColor,
+    /// By color, then by number: runs come together
+    ByColorThenNumber
+}
+
+/// The tiles held by a player. Tiles come in two copies, so removing one of them only takes
+/// out a single copy.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tray {
+    cards: Vec<Card>
+}
+
+impl Tray {
+    pub fn new() -> Tray {
+        Tray::default()
+    }
+
+    pub fn push(&mut self, card: Card) {
+        self.cards.push(card);
+    }
+
+    /// Takes out one copy of the card, the first one, if the tray has any
+    pub fn remove_one(&mut self, card: &Card) -> Option<Card> {
+        let idx = self.cards.iter().position(|c| c == card)?;
+        Some(self.cards.remove(idx))
+    }
+
+    /// Whether the tray holds all these cards, a card given twice needing two copies
+    pub fn contains_all(&self, cards: &[Card]) -> bool {
+        let mut left = self.clone();
+        cards.iter().all(|card| left.remove_one(card).is_some())
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    pub fn iter(&self) -> Iter<'_, Card> {
+        self.cards.iter()
+    }
+
+    /// The tiles in that order, the tray itself keeping its own
+    pub fn sorted_view(&self, mode: SortMode) -> Vec<Card> {
+        let mut cards = self.cards.clone();
+        match mode {
+            SortMode::ByNumberThenColor => cards.sort(),
+            // Wildcards have no color, the last key keeps them at the end
+            SortMode::ByColorThenNumber => cards.sort_by_key(|c| (c.is_wildcard(), c.color().copied(), c.number().copied()))
+        }
+        cards
+    }
+
+    /// The tiles of sorted_view cut where the color changes when sorting by color, or where the
+    /// number changes when sorting by number. Wildcards come last, in a group of their own.
+    pub fn grouped_view(&self, mode: SortMode) -> Vec<Vec<Card>> {
+        let mut groups: Vec<Vec<Card>> = vec![];
+        for card in self.sorted_view(mode) {
+            let key = |c: &Card| match mode {
+                SortMode::ByNumberThenColor => (c.number().copied(), None),
+                SortMode::ByColorThenNumber => (None, c.color().copied())
+            };
+            match groups.last_mut() {
+                Some(group) if key(&group[0]) == key(&card) => group.push(card),
+                _ => groups.push(vec![card])
+            }
+        }
+        groups
+    }
+
+    /// The tile codes of grouped_view on one line, groups being split by a bar, like
+    /// "R2 R5 | B3 B3 | W"
+    pub fn display_grouped(&self, mode: SortMode) -> String {
+        self.grouped_view(mode).iter()
+            .map(|group| group.iter().map(|c| c.code()).collect::<Vec<String>>().join(" "))
+            .collect::<Vec<String>>()
+            .join(" | ")
+    }
+
+    /// Same as valid_sets on the tiles of the tray
+    pub fn valid_sets(&self) -> Vec<Vec<&Card>> {
+        valid_sets(self)
+    }
+}
+
+impl From<Vec<Card>> for Tray {
+    fn from(cards: Vec<Card>) -> Tray {
+        Tray { cards }
+    }
+}
+
+impl From<Tray> for Vec<Card> {
+    fn from(tray: Tray) -> Vec<Card> {
+        tray.cards
+    }
+}
+
+impl AsRef<[Card]> for Tray {
+    fn as_ref(&self) -> &[Card] {
+        &self.cards
+    }
+}
+
+impl<'a> IntoIterator for &'a Tray {
+    type Item = &'a Card;
+    type IntoIter = Iter<'a, Card>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cards.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::Color;
+
+    #[test]
+    fn remove_one_test() {
+        let mut tray = Tray::from(vec![
+            Card::new(5, Color::Blue),
+            Card::new(2, Color::Red),
+            Card::new(5, Color::Blue)
+        ]);
+
+        assert_eq!(tray.remove_one(&Card::new(5, Color::Blue)), Some(Card::new(5, Color::Blue)));
+        assert_eq!(tray.as_ref(), &[Card::new(2, Color::Red), Card::new(5, Color::Blue)]);
+        assert_eq!(tray.remove_one(&Card::Wildcard), None);
+        assert_eq!(tray.len(), 2);
+    }
+
+    #[test]
+    fn contains_all_test() {
+        let tray = Tray::from(vec![
+            Card::new(5, Color::Blue),
+            Card::new(6, Color::Blue),
+            Card::Wildcard
+        ]);
+
+        assert!(tray.contains_all(&[Card::Wildcard, Card::new(5, Color::Blue)]));
+        assert!(tray.contains_all(&[]));
+        assert!(!tray.contains_all(&[Card::new(5, Color::Blue), Card::new(5, Color::Blue)]));
+    }
+
+    #[test]
+    fn tray_sets_and_sorting_test() {
+        let tray = Tray::from(vec![
+            Card::Wildcard,
+            Card::new(7, Color::Blue),
+            Card::new(6, Color::Blue),
+            Card::new(7, Color::Red)
+        ]);
+
+        assert_eq!(tray.valid_sets(), valid_sets(&tray));
+        assert_eq!(tray.sorted_view(SortMode::ByNumberThenColor), vec![
+            Card::new(6, Color::Blue),
+            Card::new(7, Color::Red),
+            Card::new(7, Color::Blue),
+            Card::Wildcard
+        ]);
+        assert_eq!(tray.sorted_view(SortMode::ByColorThenNumber), vec![
+            Card::new(7, Color::Red),
+            Card::new(6, Color::Blue),
+            Card::new(7, Color::Blue),
+            Card::Wildcard
+        ]);
+        assert_eq!(tray.iter().next(), Some(&Card::Wildcard));
+    }
+
+    #[test]
+    fn grouped_view_test() {
+        let tray = Tray::from(vec![
+            Card::new(5, Color::Yellow),
+            Card::Wildcard,
+            Card::new(2, Color::Red),
+            Card::new(5, Color::Red),
+            Card::new(3, Color::Blue),
+            Card::Wildcard,
+            Card::new(3, Color::Blue),
+            Card::new(12, Color::Black)
+        ]);
+
+        assert_eq!(tray.sorted_view(SortMode::ByNumberThenColor), vec![
+            Card::new(2, Color::Red),
+            Card::new(3, Color::Blue),
+            Card::new(3, Color::Blue),
+            Card::new(5, Color::Red),
+            Card::new(5, Color::Yellow),
+            Card::new(12, Color::Black),
+            Card::Wildcard,
+            Card::Wildcard
+        ]);
+        assert_eq!(tray.sorted_view(SortMode::ByColorThenNumber), vec![
+            Card::new(2, Color::Red),
+            Card::new(5, Color::Red),
+            Card::new(3, Color::Blue),
+            Card::new(3, Color::Blue),
+            Card::new(12, Color::Black),
+            Card::new(5, Color::Yellow),
+            Card::Wildcard,
+            Card::Wildcard
+        ]);
+        assert_eq!(tray.display_grouped(SortMode::ByNumberThenColor), "R2 | B3 B3 | R5 Y5 | K12 | W W");
+        assert_eq!(tray.display_grouped(SortMode::ByColorThenNumber), "R2 R5 | B3 B3 | K12 | Y5 | W W");
+        assert_eq!(Tray::new().grouped_view(SortMode::ByColorThenNumber), Vec::<Vec<Card>>::new());
+    }
+
+}
@@ -0,0 +1,328 @@
+use alloc::collections::BTreeMap;
+use thiserror::Error;
+use super::{set_points_with, Card, MeldId, SetError, Table};
+use super::partition::best_sets_placing;
+use crate::prelude::*;
+
+/// Why a turn that rearranged the table is not legal
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TurnError {
+    // The hand after the turn has a tile it didn't have before
+    #[error("{} can't go from the table to the hand", .0.code())]
+    TileTakenIntoHand(Card),
+    // A wildcard taken back from the table has to be played again in the same turn
+    #[error("a wildcard taken from the table must be played again this turn")]
+    WildcardKeptInHand,
+    // A tile of the table is gone, without being one of the played ones
+    #[error("{} disappeared from the table", .0.code())]
+    TileMissingFromTable(Card),
+    // The table has a tile that was neither on it nor played from the hand
+    #[error("{} is on the table but wasn't there nor in the hand", .0.code())]
+    UnknownTileOnTable(Card),
+    #[error("no tile was played from the hand")]
+    NothingPlayed,
+    #[error("meld {meld} is not valid: {error}")]
+    InvalidMeld { meld: MeldId, #[source] error: SetError },
+    // Before the opening the melds on the table have to stay as they are
+    #[error("meld {0} was changed, but the table can only be used after opening")]
+    TableChangedBeforeOpening(MeldId),
+    #[error("the opening is worth {points} points, it needs {needed}")]
+    OpeningTooLow { points: u32, needed: u32 }
+}
+
+fn counts<'a>(cards: impl IntoIterator<Item = &'a Card>) -> BTreeMap<Card, usize> {
+    let mut counts = BTreeMap::new();
+    for card in cards {
+        *counts.entry(*card).or_default() += 1;
+    }
+    counts
+}
+
+fn table_counts(table: &Table) -> BTreeMap<Card, usize> {
+    counts(table.melds().flat_map(|(_, cards)| cards))
+}
+
+/// Checks a turn that may have broken and rebuilt the melds on the table: the tiles that left
+/// the hand all went to the table, no tile went from the table to the hand or disappeared, at
+/// least one tile was played and every meld on the table is valid at the end. A wildcard can
+/// be swapped for the tile it stands for, as long as it goes to another meld in the same turn.
+/// Copies of a tile and wildcards are counted, not told apart.
+///
+/// A player who hasn't melded yet is opening: the melds already on the table must be left
+/// untouched, and the new ones must be worth the opening points of the rules of the table after
+/// the turn, 30 unless it was made with Table::with_rules.
+pub fn check_turn(
+    before: &Table,
+    after: &Table,
+    hand_before: &[Card],
+    hand_after: &[Card],
+    has_melded: bool
+) -> Result<(), TurnError> {
+    let mut played = counts(hand_before);
+    for (card, count) in counts(hand_after) {
+        let held = played.entry(card).or_default();
+        if count > *held && card.is_wildcard() {
+            return Err(TurnError::WildcardKeptInHand);
+        }
+        if count > *held {
+            return Err(TurnError::TileTakenIntoHand(card));
+        }
+        *held -= count;
+    }
+
+    // The table before plus the played tiles must be the table after
+    let mut expected = table_counts(before);
+    for (card, count) in &played {
+        *expected.entry(*card).or_default() += count;
+    }
+    let found = table_counts(after);
+    for (card, count) in &expected {
+        if found.get(card).copied().unwrap_or(0) < *count {
+            return Err(TurnError::TileMissingFromTable(*card));
+        }
+    }
+    for (card, count) in &found {
+        if expected.get(card).copied().unwrap_or(0) < *count {
+            return Err(TurnError::UnknownTileOnTable(*card));
+        }
+    }
+
+    if played.values().all(|count| *count == 0) {
+        return Err(TurnError::NothingPlayed);
+    }
+    if !has_melded {
+        if let Some((meld, _)) = before.melds().find(|(id, cards)| after.meld(*id) != Some(*cards)) {
+            return Err(TurnError::TableChangedBeforeOpening(meld));
+        }
+    }
+    if let Some((meld, error)) = after.invalid_melds().into_iter().next() {
+        return Err(TurnError::InvalidMeld { meld, error });
+    }
+    if !has_melded {
+        let points = after.melds()
+            .filter(|(id, _)| before.meld(*id).is_none())
+            .map(|(_, cards)| set_points_with(&cards.iter().collect::<Vec<&Card>>(), after.rules()))
+            .sum();
+        let needed = after.rules().opening_points;
+        if points < needed {
+            return Err(TurnError::OpeningTooLow { points, needed });
+        }
+    }
+    Ok(())
+}
+
+/// The turn solve_turn found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TurnPlan {
+    /// Every meld of the table after the turn, numbered anew
+    pub table: Table,
+    /// The tiles played from the hand, sorted
+    pub played: Vec<Card>,
+    /// The tiles left in the hand, sorted
+    pub hand: Vec<Card>
+}
+
+/// Finds the turn playing the most tiles of the hand, breaking and rebuilding the melds of the
+/// table as check_turn allows a player who has melded, with the standard tiles. None when no
+/// tile of the hand can be played. Every arrangement of the tiles is searched, so a crowded
+/// table can take a while.
+pub fn solve_turn(table: &Table, hand: &[Card]) -> Option<TurnPlan> {
+    let placed: Vec<Card> = table.melds().flat_map(|(_, cards)| cards).copied().collect();
+    let sets = best_sets_placing(&placed, hand)?;
+
+    // What the sets hold beyond the tiles of the table came from the hand
+    let mut on_table = counts(&placed);
+    let mut played = vec![];
+    for card in sets.iter().flatten() {
+        match on_table.get_mut(card) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => played.push(*card)
+        }
+    }
+    played.sort();
+    let mut kept = hand.to_vec();
+    for card in &played {
+        // The sets only hold tiles of the table and the hand
+        let idx = kept.iter().position(|c| c == card).unwrap();
+        kept.remove(idx);
+    }
+    kept.sort();
+    Some(TurnPlan { table: Table::from_melds(sets), played, hand: kept })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::{Color, RulesConfig};
+
+    fn blue(numbers: &[i8]) -> Vec<Card> {
+        numbers.iter().map(|n| Card::new(*n, Color::Blue)).collect()
+    }
+
+    fn table(melds: &[Vec<Card>]) -> Table {
+        Table::from_melds(melds.to_vec())
+    }
+
+    #[test]
+    fn split_run_and_insert_test() {
+        // B4 B5 B6 B7 B8 becomes B4 B5 B6 and B6 B7 B8 with the B6 from the hand
+        let before = table(&[blue(&[4, 5, 6, 7, 8])]);
+        let after = table(&[blue(&[4, 5, 6]), blue(&[6, 7, 8])]);
+        let hand_before = [Card::new(6, Color::Blue), Card::new(9, Color::Red)];
+        let hand_after = [Card::new(9, Color::Red)];
+
+        assert_eq!(check_turn(&before, &after, &hand_before, &hand_after, true), Ok(()));
+    }
+
+    #[test]
+    fn split_into_group_test() {
+        // R5 R6 R7 R8 gives its R8 to a new group with two tiles from the hand
+        let before = table(&[vec![Card::new(5, Color::Red), Card::new(6, Color::Red), Card::new(7, Color::Red), Card::new(8, Color::Red)]]);
+        let after = table(&[
+            vec![Card::new(5, Color::Red), Card::new(6, Color::Red), Card::new(7, Color::Red)],
+            vec![Card::new(8, Color::Red), Card::new(8, Color::Black), Card::Wildcard]
+        ]);
+        let hand_before = [Card::Wildcard, Card::new(8, Color::Black)];
+
+        assert_eq!(check_turn(&before, &after, &hand_before, &[], true), Ok(()));
+    }
+
+    #[test]
+    fn steal_into_hand_test() {
+        let before = table(&[blue(&[4, 5, 6, 7, 8])]);
+        let after = table(&[blue(&[4, 5, 6, 7])]);
+        let hand_before = [Card::new(9, Color::Red)];
+        let hand_after = [Card::new(9, Color::Red), Card::new(8, Color::Blue)];
+
+        assert_eq!(
+            check_turn(&before, &after, &hand_before, &hand_after, true),
+            Err(TurnError::TileTakenIntoHand(Card::new(8, Color::Blue)))
+        );
+    }
+
+    #[test]
+    fn tile_accounting_test() {
+        let before = table(&[blue(&[4, 5, 6, 7])]);
+        let hand = [Card::new(9, Color::Red), Card::new(9, Color::Black), Card::new(9, Color::Yellow)];
+
+        // B7 is thrown away while the 9s are played
+        let dropped = table(&[blue(&[4, 5, 6]), hand.to_vec()]);
+        // B8 comes from nowhere
+        let invented = table(&[blue(&[4, 5, 6, 7, 8]), hand.to_vec()]);
+        let untouched = table(&[blue(&[4, 5, 6, 7])]);
+
+        assert_eq!(check_turn(&before, &dropped, &hand, &[], true), Err(TurnError::TileMissingFromTable(Card::new(7, Color::Blue))));
+        assert_eq!(check_turn(&before, &invented, &hand, &[], true), Err(TurnError::UnknownTileOnTable(Card::new(8, Color::Blue))));
+        assert_eq!(check_turn(&before, &untouched, &hand, &hand, true), Err(TurnError::NothingPlayed));
+    }
+
+    #[test]
+    fn invalid_meld_after_turn_test() {
+        let before = table(&[blue(&[4, 5, 6, 7])]);
+        // B7 moves to a meld where it doesn't fit
+        let after = table(&[blue(&[4, 5, 6]), vec![Card::new(7, Color::Blue), Card::new(9, Color::Red), Card::new(9, Color::Black)]]);
+        let hand_before = [Card::new(9, Color::Red), Card::new(9, Color::Black)];
+
+        assert!(matches!(
+            check_turn(&before, &after, &hand_before, &[], true),
+            Err(TurnError::InvalidMeld { error: SetError::MixedNumbersInGroup | SetError::MixedColorsInRun, .. })
+        ));
+    }
+
+    #[test]
+    fn wildcard_retrieval_test() {
+        let before = table(&[blue(&[4, 5]).into_iter().chain([Card::Wildcard]).collect()]);
+        let hand_before = [Card::new(6, Color::Blue), Card::new(9, Color::Red), Card::new(9, Color::Black)];
+
+        // B6 takes the place of the wildcard, which joins the two 9s
+        let replayed = table(&[blue(&[4, 5, 6]), vec![Card::new(9, Color::Red), Card::new(9, Color::Black), Card::Wildcard]]);
+        let kept = table(&[blue(&[4, 5, 6])]);
+        let kept_hand = [Card::new(9, Color::Red), Card::new(9, Color::Black), Card::Wildcard];
+
+        assert_eq!(check_turn(&before, &replayed, &hand_before, &[], true), Ok(()));
+        assert_eq!(check_turn(&before, &kept, &hand_before, &kept_hand, true), Err(TurnError::WildcardKeptInHand));
+    }
+
+    #[test]
+    fn opening_turn_test() {
+        let before = table(&[blue(&[4, 5, 6, 7])]);
+        let hand = [Card::new(10, Color::Red), Card::new(10, Color::Black), Card::Wildcard, Card::new(8, Color::Blue)];
+        let low_hand = [Card::new(8, Color::Red), Card::new(8, Color::Black), Card::Wildcard];
+
+        // R10 K10 W is worth exactly 30, R8 K8 W only 24
+        let opening = table(&[blue(&[4, 5, 6, 7]), hand[..3].to_vec()]);
+        let extended = table(&[blue(&[4, 5, 6, 7, 8]), hand[..3].to_vec()]);
+        let low = table(&[blue(&[4, 5, 6, 7]), low_hand.to_vec()]);
+        let (first, _) = before.melds().next().unwrap();
+
+        assert_eq!(check_turn(&before, &opening, &hand, &hand[3..], false), Ok(()));
+        assert_eq!(check_turn(&before, &extended, &hand, &[], false), Err(TurnError::TableChangedBeforeOpening(first)));
+        assert_eq!(check_turn(&before, &extended, &hand, &[], true), Ok(()));
+        assert_eq!(check_turn(&before, &low, &low_hand, &[], false), Err(TurnError::OpeningTooLow { points: 24, needed: 30 }));
+    }
+
+    #[test]
+    fn opening_points_from_rules_test() {
+        let rules = RulesConfig { opening_points: 40, ..RulesConfig::default() };
+        let hand = [Card::new(5, Color::Red), Card::new(6, Color::Red), Card::new(7, Color::Red), Card::new(8, Color::Red), Card::new(9, Color::Red)];
+        // R5 to R9 is worth 35, enough for the standard rules only
+        let mut after = Table::with_rules(rules);
+        after.add_meld(hand.to_vec()).unwrap();
+
+        let refused = check_turn(&Table::with_rules(rules), &after, &hand, &[], false);
+        assert_eq!(refused, Err(TurnError::OpeningTooLow { points: 35, needed: 40 }));
+        assert_eq!(refused.unwrap_err().to_string(), "the opening is worth 35 points, it needs 40");
+        assert_eq!(check_turn(&Table::new(), &table(&[hand.to_vec()]), &hand, &[], false), Ok(()));
+    }
+
+    #[test]
+    fn opening_above_13_test() {
+        let rules = RulesConfig { max_number: 16, ..RulesConfig::default() };
+        let hand = [Card::new(14, Color::Red), Card::new(15, Color::Red), Card::new(16, Color::Red)];
+        let mut after = Table::with_rules(rules);
+        after.add_meld(hand.to_vec()).unwrap();
+
+        assert_eq!(check_turn(&Table::with_rules(rules), &after, &hand, &[], false), Ok(()));
+    }
+
+    #[test]
+    fn opening_with_wrapping_run_test() {
+        let rules = RulesConfig { wrap_runs: true, ..RulesConfig::default() };
+        // R11 R12 R13 R1 is worth 37
+        let hand = [Card::new(11, Color::Red), Card::new(12, Color::Red), Card::new(13, Color::Red), Card::new(1, Color::Red)];
+        let mut after = Table::with_rules(rules);
+        after.add_meld(hand.to_vec()).unwrap();
+
+        assert_eq!(check_turn(&Table::with_rules(rules), &after, &hand, &[], false), Ok(()));
+    }
+
+    #[test]
+    fn opening_with_five_tile_group_test() {
+        let rules = RulesConfig::six_colors();
+        // The wildcard is the Green 10, for 50 points
+        let hand = [Card::new(10, Color::Red), Card::new(10, Color::Blue), Card::new(10, Color::Black), Card::new(10, Color::Yellow), Card::Wildcard];
+        let mut after = Table::with_rules(rules);
+        after.add_meld(hand.to_vec()).unwrap();
+
+        assert_eq!(check_turn(&Table::with_rules(rules), &after, &hand, &[], false), Ok(()));
+    }
+
+    #[test]
+    fn solve_turn_test() {
+        // Splitting the run for the B6, and breaking R5 R6 R7 R8 for a group of 8s
+        let before = table(&[blue(&[4, 5, 6, 7, 8]), vec![Card::new(5, Color::Red), Card::new(6, Color::Red), Card::new(7, Color::Red), Card::new(8, Color::Red)]]);
+        let hand = [Card::new(9, Color::Orange), Card::new(8, Color::Black), Card::new(6, Color::Blue), Card::Wildcard];
+
+        let plan = solve_turn(&before, &hand).unwrap();
+        assert_eq!(plan.played, vec![Card::new(6, Color::Blue), Card::new(8, Color::Black), Card::Wildcard]);
+        assert_eq!(plan.hand, vec![Card::new(9, Color::Orange)]);
+        assert_eq!(plan.table.len(), 4);
+        assert_eq!(check_turn(&before, &plan.table, &hand, &plan.hand, true), Ok(()));
+
+        assert_eq!(solve_turn(&before, &[Card::new(1, Color::Yellow), Card::new(12, Color::Black)]), None);
+        let plan = solve_turn(&Table::new(), &blue(&[1, 2, 3])).unwrap();
+        assert_eq!(plan.table.melds().next().unwrap().1, blue(&[1, 2, 3]).as_slice());
+    }
+
+}
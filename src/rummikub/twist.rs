@@ -0,0 +1,253 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use super::{Card, Color, RulesConfig, SetSearchOptions};
+use super::validation::{validate_refs_with, MeldKind, SetError};
+use crate::prelude::*;
+
+// Mirror runs hold their half twice, and the half needs at least 2 numbers
+const MIN_MIRROR_HALF: usize = 2;
+
+// Checks a set holding a double or a mirror joker. The double joker stands for two tiles of a
+// group, and the mirror joker sits in the middle of a run read the same both ways, like
+// 4-5-M-5-4. Wildcards fill in for the missing tiles, the two copies of a number a mirror run
+// misses taking two of them.
+pub(super) fn validate_twist(cards: &[&Card], rules: &RulesConfig) -> Result<MeldKind, SetError> {
+    if !rules.twist_jokers {
+        return Err(SetError::TwistJokerNotAllowed);
+    }
+    let numbered: Vec<(i8, Color)> = cards.iter()
+        .filter_map(|c| match c {
+            Card::Numbered { number, color } => Some((*number, *color)),
+            _ => None
+        })
+        .collect();
+    let (first_number, first_color) = match numbered.first() {
+        Some(first) => *first,
+        None => return Err(SetError::AllWildcards)
+    };
+    if let Some((number, _)) = numbered.iter().find(|(n, _)| !rules.numbers().contains(n)) {
+        return Err(SetError::NumberOutOfRange { number: *number, min: rules.min_number, max: rules.max_number });
+    }
+    let wildcards = cards.iter().filter(|c| c.is_wildcard()).count();
+    let doubles = cards.iter().filter(|c| **c == &Card::DoubleJoker).count();
+    let mirrors = cards.iter().filter(|c| **c == &Card::MirrorJoker).count();
+
+    if mirrors == 0 {
+        let mut seen = BTreeSet::new();
+        if let Some((_, color)) = numbered.iter().find(|(_, color)| !seen.insert(*color)) {
+            return Err(SetError::DuplicateColorInGroup { color: *color });
+        }
+        if numbered.iter().any(|(n, _)| *n != first_number) {
+            return Err(SetError::MixedNumbersInGroup);
+        }
+        let len = numbered.len() + wildcards + 2 * doubles;
+        let max = rules.group_limit();
+        if len > max {
+            return Err(SetError::GroupTooLarge { len, max });
+        }
+        return Ok(MeldKind::Group);
+    }
+
+    if doubles > 0 {
+        return Err(SetError::DoubleJokerInRun);
+    }
+    if numbered.iter().any(|(_, c)| *c != first_color) {
+        return Err(SetError::MixedColorsInRun);
+    }
+    if mirrors > 1 {
+        return Err(SetError::NotMirrored);
+    }
+    let half = mirror_half(&numbered, wildcards, rules)?;
+    let len = (numbered.len() + wildcards) / 2;
+    if half.len() < len {
+        return Err(SetError::RunOutOfRange { len, min: rules.min_number, max: rules.max_number });
+    }
+    if half.len() < MIN_MIRROR_HALF {
+        return Err(SetError::RunTooShort { len: cards.len(), min: 2 * MIN_MIRROR_HALF + 1 });
+    }
+    Ok(MeldKind::Run)
+}
+
+// The numbers one side of the mirror reads, from the lowest. The wildcards left once every
+// number has its two copies extend the half in pairs, upwards first, like the wildcards of
+// resolve_wildcards.
+fn mirror_half(numbered: &[(i8, Color)], wildcards: usize, rules: &RulesConfig) -> Result<Vec<i8>, SetError> {
+    let mut copies: BTreeMap<i8, usize> = BTreeMap::new();
+    for (number, _) in numbered {
+        *copies.entry(*number).or_default() += 1;
+    }
+    if copies.values().any(|count| *count > 2) {
+        return Err(SetError::NotMirrored);
+    }
+    let (low, high) = (*copies.keys().next().unwrap(), *copies.keys().next_back().unwrap());
+    let mut left = wildcards;
+    for number in low..=high {
+        let missing = 2 - copies.get(&number).copied().unwrap_or(0);
+        if missing > left {
+            return Err(if copies.contains_key(&number) { SetError::NotMirrored } else { SetError::GapInRun { missing: number } });
+        }
+        left -= missing;
+    }
+    if left % 2 == 1 {
+        return Err(SetError::NotMirrored);
+    }
+
+    let above = high + 1..=rules.max_number;
+    let below = (rules.min_number..low).rev();
+    let mut half: Vec<i8> = (low..=high).chain(above.chain(below).take(left / 2)).collect();
+    half.sort();
+    Ok(half)
+}
+
+// The face value of a set holding a twist joker, checking first that it is valid under the
+// twist rules: a group is worth its number for every tile it stands for, the double joker
+// counting twice, and a mirror run twice the numbers of its half, the mirror joker itself
+// being worth nothing
+pub(super) fn twist_score(cards: &[&Card], rules: &RulesConfig) -> Result<u32, SetError> {
+    let rules = RulesConfig { twist_jokers: true, ..*rules };
+    let kind = validate_refs_with(cards, &rules)?;
+    let numbered: Vec<(i8, Color)> = cards.iter()
+        .filter_map(|c| match c {
+            Card::Numbered { number, color } => Some((*number, *color)),
+            _ => None
+        })
+        .collect();
+    let number = numbered[0].0 as u32;
+    Ok(match kind {
+        MeldKind::Group => {
+            let doubles = cards.iter().filter(|c| **c == &Card::DoubleJoker).count();
+            number * (cards.len() + doubles) as u32
+        },
+        MeldKind::Run => {
+            let wildcards = cards.iter().filter(|c| c.is_wildcard()).count();
+            // Valid, so the half can be read
+            let half = mirror_half(&numbered, wildcards, &rules)?;
+            2 * half.iter().map(|n| *n as u32).sum::<u32>()
+        }
+    })
+}
+
+// The groups made of the numbered tiles of a number in a color each and the double joker of
+// the tray, without wildcards
+pub(super) fn double_groups<'a>(cards: &[&'a Card], options: &SetSearchOptions) -> Vec<Vec<&'a Card>> {
+    let Some(double) = cards.iter().copied().find(|c| **c == Card::DoubleJoker) else {
+        return vec![];
+    };
+    let mut groups = vec![];
+    for number in options.rules.numbers() {
+        let held: Vec<&Card> = options.rules.colors.iter()
+            .filter_map(|color| cards.iter().copied().find(|c| **c == Card::new(number, *color)))
+            .collect();
+        // Every choice of colors, from the bits of mask
+        for mask in 1..1usize << held.len() {
+            let mut group: Vec<&Card> = held.iter().enumerate()
+                .filter(|(idx, _)| mask & 1 << idx != 0)
+                .map(|(_, card)| *card)
+                .collect();
+            group.push(double);
+            if group.len() >= options.min_len() && group.len() < options.rules.group_limit() {
+                groups.push(group);
+            }
+        }
+    }
+    groups
+}
+
+// The mirror runs of the tray: a block of consecutive numbers held twice in one color, laid
+// out upwards then downwards around the mirror joker of the tray, without wildcards
+pub(super) fn mirror_runs<'a>(cards: &[&'a Card], options: &SetSearchOptions) -> Vec<Vec<&'a Card>> {
+    let Some(mirror) = cards.iter().copied().find(|c| **c == Card::MirrorJoker) else {
+        return vec![];
+    };
+    let mut runs = vec![];
+    for &color in options.rules.colors {
+        let held: Vec<Option<(&Card, &Card)>> = options.rules.numbers()
+            .map(|number| {
+                let mut copies = cards.iter().copied().filter(move |c| **c == Card::new(number, color));
+                copies.next().zip(copies.next())
+            })
+            .collect();
+        for start in 0..held.len() {
+            for end in start + MIN_MIRROR_HALF..=held.len() {
+                let Some(block) = held[start..end].iter().copied().collect::<Option<Vec<(&Card, &Card)>>>() else {
+                    break;
+                };
+                let run: Vec<&Card> = block.iter().map(|(first, _)| *first)
+                    .chain([mirror])
+                    .chain(block.iter().rev().map(|(_, second)| *second))
+                    .collect();
+                if run.len() >= options.min_len() {
+                    runs.push(run);
+                }
+            }
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::{find_runs_with, find_same_numbers_with, validate_set, validate_set_with, Deck};
+
+    fn twist() -> RulesConfig {
+        RulesConfig { twist_jokers: true, ..RulesConfig::default() }
+    }
+
+    #[test]
+    fn double_joker_group_test() {
+        let group = vec![Card::new(7, Color::Red), Card::new(7, Color::Blue), Card::DoubleJoker];
+        assert_eq!(validate_set_with(&group, &twist()), Ok(MeldKind::Group));
+        assert_eq!(validate_set(&group), Err(SetError::TwistJokerNotAllowed));
+        assert_eq!(twist_score(&group.iter().collect::<Vec<&Card>>(), &RulesConfig::default()), Ok(28));
+
+        // Standing for two tiles, the double leaves room for at most two others
+        let too_large = vec![Card::new(7, Color::Red), Card::new(7, Color::Blue), Card::Wildcard, Card::DoubleJoker];
+        assert_eq!(validate_set_with(&too_large, &twist()), Err(SetError::GroupTooLarge { len: 5, max: 4 }));
+        let mixed = vec![Card::new(7, Color::Red), Card::new(8, Color::Blue), Card::DoubleJoker];
+        assert_eq!(validate_set_with(&mixed, &twist()), Err(SetError::MixedNumbersInGroup));
+    }
+
+    #[test]
+    fn mirror_joker_run_test() {
+        let run = vec![Card::new(4, Color::Red), Card::new(5, Color::Red), Card::MirrorJoker, Card::new(5, Color::Red), Card::new(4, Color::Red)];
+        assert_eq!(validate_set_with(&run, &twist()), Ok(MeldKind::Run));
+        assert_eq!(validate_set(&run), Err(SetError::TwistJokerNotAllowed));
+        assert_eq!(twist_score(&run.iter().collect::<Vec<&Card>>(), &RulesConfig::default()), Ok(18));
+
+        // A wildcard can stand for the second R5, two for a missing number
+        let wildcard = vec![Card::new(4, Color::Red), Card::new(5, Color::Red), Card::MirrorJoker, Card::Wildcard, Card::new(4, Color::Red)];
+        assert_eq!(validate_set_with(&wildcard, &twist()), Ok(MeldKind::Run));
+        let gap = vec![Card::new(4, Color::Red), Card::new(6, Color::Red), Card::MirrorJoker, Card::new(6, Color::Red), Card::new(4, Color::Red)];
+        assert_eq!(validate_set_with(&gap, &twist()), Err(SetError::GapInRun { missing: 5 }));
+        let lopsided = vec![Card::new(4, Color::Red), Card::new(5, Color::Red), Card::MirrorJoker, Card::new(5, Color::Red)];
+        assert_eq!(validate_set_with(&lopsided, &twist()), Err(SetError::NotMirrored));
+        let short = vec![Card::new(4, Color::Red), Card::MirrorJoker, Card::new(4, Color::Red)];
+        assert_eq!(validate_set_with(&short, &twist()), Err(SetError::RunTooShort { len: 3, min: 5 }));
+    }
+
+    #[test]
+    fn twist_finders_test() {
+        let tray = vec![
+            Card::new(4, Color::Red), Card::new(5, Color::Red), Card::new(5, Color::Red), Card::new(4, Color::Red),
+            Card::new(4, Color::Blue), Card::MirrorJoker, Card::DoubleJoker
+        ];
+        let options = SetSearchOptions { rules: twist(), ..SetSearchOptions::default() };
+        let runs = find_runs_with(&tray, &options);
+        assert!(runs.contains(&vec![&tray[0], &tray[1], &tray[5], &tray[2], &tray[3]]), "{:?}", runs);
+        let groups = find_same_numbers_with(&tray, &options);
+        assert!(groups.contains(&vec![&tray[0], &tray[4], &tray[6]]), "{:?}", groups);
+        for set in runs.iter().chain(&groups) {
+            assert_eq!(validate_refs_with(set, &twist()).map(|_| ()), Ok(()), "{:?}", set);
+        }
+
+        // Standard rules find no set with the jokers
+        let standard = find_runs_with(&tray, &SetSearchOptions::default()).into_iter()
+            .chain(find_same_numbers_with(&tray, &SetSearchOptions::default()))
+            .filter(|set| set.iter().any(|c| c.is_twist_joker()))
+            .count();
+        assert_eq!(standard, 0);
+        assert_eq!(Deck::with_rules(&twist()).snapshot().len(), 108);
+    }
+
+}
@@ -0,0 +1,394 @@
+use core::fmt;
+use alloc::collections::BTreeSet;
+use thiserror::Error;
+use super::{Card, Color, RulesConfig};
+use super::twist::validate_twist;
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash)]
+pub enum MeldKind {
+    Run,
+    Group
+}
+
+impl MeldKind {
+    /// Lowercase name of the kind, as written by the JSON and CSV outputs
+    pub fn name(&self) -> &'static str {
+        match self {
+            MeldKind::Run => "run",
+            MeldKind::Group => "group"
+        }
+    }
+}
+
+impl fmt::Display for MeldKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Error)]
+pub enum SetError {
+    #[error("a set needs at least 3 tiles, this one has {len}")]
+    TooShort { len: usize },
+    #[error("a set needs at least one numbered tile")]
+    AllWildcards,
+    #[error("{number} is not a valid tile number ({min} to {max})")]
+    NumberOutOfRange { number: i8, min: i8, max: i8 },
+    #[error("the run is missing a {missing}")]
+    GapInRun { missing: i8 },
+    #[error("all the tiles of a run must have the same color")]
+    MixedColorsInRun,
+    #[error("the run contains {number} more than once")]
+    DuplicateNumberInRun { number: i8 },
+    #[error("a run of {len} tiles does not fit between {min} and {max}")]
+    RunOutOfRange { len: usize, min: i8, max: i8 },
+    #[error("a run needs at least {min} tiles, this one has {len}")]
+    RunTooShort { len: usize, min: usize },
+    #[error("the group contains {color} more than once")]
+    DuplicateColorInGroup { color: Color },
+    #[error("all the tiles of a group must have the same number")]
+    MixedNumbersInGroup,
+    #[error("a group can have at most {max} tiles, this one has {len}")]
+    GroupTooLarge { len: usize, max: usize },
+    /// A double or mirror joker, without RulesConfig::twist_jokers
+    #[error("double and mirror jokers are only played with the twist rules")]
+    TwistJokerNotAllowed,
+    #[error("a double joker can only be played in a group")]
+    DoubleJokerInRun,
+    /// A mirror run holds every number twice around a single mirror joker
+    #[error("the run doesn't read the same both ways around its mirror joker")]
+    NotMirrored
+}
+
+/// Checks whether the cards form a valid run or group, regardless of the order they are given in.
+/// Sets with a single numbered tile and wildcards can be both: they are reported as runs.
+pub fn validate_set(cards: &[Card]) -> Result<MeldKind, SetError> {
+    validate_set_with(cards, &RulesConfig::default())
+}
+
+/// Same as validate_set, with house rules. A single numbered tile with wildcards too short
+/// for a run can still be a group.
+pub fn validate_set_with(cards: &[Card], rules: &RulesConfig) -> Result<MeldKind, SetError> {
+    let refs: Vec<&Card> = cards.iter().collect();
+    validate_refs_with(&refs, rules)
+}
+
+pub(crate) fn validate_refs(cards: &[&Card]) -> Result<MeldKind, SetError> {
+    validate_refs_with(cards, &RulesConfig::default())
+}
+
+pub(crate) fn validate_refs_with(cards: &[&Card], rules: &RulesConfig) -> Result<MeldKind, SetError> {
+    if cards.len() < 3 {
+        return Err(SetError::TooShort { len: cards.len() });
+    }
+    if cards.iter().any(|c| c.is_twist_joker()) {
+        return validate_twist(cards, rules);
+    }
+
+    let numbered: Vec<(i8, Color)> = cards.iter()
+        .filter_map(|c| match c {
+            Card::Numbered { number, color } => Some((*number, *color)),
+            _ => None
+        })
+        .collect();
+    let wildcards = cards.len() - numbered.len();
+
+    let (first_number, first_color) = match numbered.first() {
+        Some(first) => *first,
+        None => return Err(SetError::AllWildcards)
+    };
+    if let Some((number, _)) = numbered.iter().find(|(n, _)| !rules.numbers().contains(n)) {
+        return Err(SetError::NumberOutOfRange { number: *number, min: rules.min_number, max: rules.max_number });
+    }
+
+    let same_number = numbered.iter().all(|(n, _)| *n == first_number);
+    let same_color = numbered.iter().all(|(_, c)| *c == first_color);
+
+    let colors: Vec<Color> = numbered.iter().map(|(_, c)| *c).collect();
+    if same_color && (!same_number || numbered.len() == 1) {
+        let numbers: Vec<i8> = numbered.iter().map(|(n, _)| *n).collect();
+        validate_run(numbers, wildcards, rules).map(|_| MeldKind::Run).or_else(|error| {
+            if numbered.len() == 1 && validate_group(&colors, wildcards, rules).is_ok() {
+                Ok(MeldKind::Group)
+            } else {
+                Err(error)
+            }
+        })
+    } else if same_number {
+        validate_group(&colors, wildcards, rules).map(|_| MeldKind::Group)
+    } else {
+        // Repeated numbers hint that the player was going for a group
+        let distinct_numbers: BTreeSet<i8> = numbered.iter().map(|(n, _)| *n).collect();
+        if distinct_numbers.len() < numbered.len() {
+            Err(SetError::MixedNumbersInGroup)
+        } else {
+            Err(SetError::MixedColorsInRun)
+        }
+    }
+}
+
+fn validate_run(mut numbers: Vec<i8>, wildcards: usize, rules: &RulesConfig) -> Result<(), SetError> {
+    numbers.sort();
+    if let Some(pair) = numbers.windows(2).find(|pair| pair[0] == pair[1]) {
+        return Err(SetError::DuplicateNumberInRun { number: pair[0] });
+    }
+
+    // Wildcards fill the gaps between numbered tiles first
+    let mut available_wildcards = wildcards;
+    'gaps: for pair in numbers.windows(2) {
+        for missing in pair[0]+1..pair[1] {
+            if available_wildcards == 0 {
+                if rules.wrap_runs && fits_around(&numbers, wildcards, rules.number_count()) {
+                    break 'gaps;
+                }
+                return Err(SetError::GapInRun { missing });
+            }
+            available_wildcards -= 1;
+        }
+    }
+
+    let len = numbers.len() + wildcards;
+    if len > rules.number_count() {
+        return Err(SetError::RunOutOfRange { len, min: rules.min_number, max: rules.max_number });
+    }
+    if len < rules.min_run_len {
+        return Err(SetError::RunTooShort { len, min: rules.min_run_len });
+    }
+    Ok(())
+}
+
+// Whether the sorted numbers make a run going on from the highest number to the lowest. The
+// count numbers sit on a circle, and the run leaves out the widest gap between two of them.
+fn fits_around(numbers: &[i8], wildcards: usize, count: usize) -> bool {
+    let count = count as i8;
+    let (first, last) = (numbers[0], numbers[numbers.len() - 1]);
+    let widest_gap = numbers.windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .chain([first + count - last])
+        .max()
+        .unwrap_or(count);
+    // Out of all the numbers, the numbered tiles and the left out gap need no wildcard
+    let missing = count - numbers.len() as i8 - (widest_gap - 1);
+    missing as usize <= wildcards
+}
+
+fn validate_group(colors: &[Color], wildcards: usize, rules: &RulesConfig) -> Result<(), SetError> {
+    let mut seen = BTreeSet::new();
+    for color in colors {
+        if !seen.insert(color) {
+            return Err(SetError::DuplicateColorInGroup { color: *color });
+        }
+    }
+
+    let len = colors.len() + wildcards;
+    let max = rules.group_limit();
+    if len > max {
+        return Err(SetError::GroupTooLarge { len, max });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn validate_run_test() {
+        let run = vec![
+            Card::new(3, Color::Red),
+            Card::new(4, Color::Red),
+            Card::new(5, Color::Red)
+        ];
+        assert_eq!(validate_set(&run), Ok(MeldKind::Run));
+
+        let unordered_run_with_wildcard = vec![
+            Card::new(6, Color::Blue),
+            Card::Wildcard,
+            Card::new(4, Color::Blue),
+            Card::new(3, Color::Blue)
+        ];
+        assert_eq!(validate_set(&unordered_run_with_wildcard), Ok(MeldKind::Run));
+
+        let full_run: Vec<Card> = (1..=13).map(|n| Card::new(n, Color::Yellow)).collect();
+        assert_eq!(validate_set(&full_run), Ok(MeldKind::Run));
+    }
+
+    #[test]
+    fn validate_group_test() {
+        let group = vec![
+            Card::new(7, Color::Red),
+            Card::new(7, Color::Blue),
+            Card::new(7, Color::Black)
+        ];
+        assert_eq!(validate_set(&group), Ok(MeldKind::Group));
+
+        let group_with_wildcard = vec![
+            Card::new(7, Color::Red),
+            Card::Wildcard,
+            Card::new(7, Color::Blue),
+            Card::new(7, Color::Black)
+        ];
+        assert_eq!(validate_set(&group_with_wildcard), Ok(MeldKind::Group));
+    }
+
+    #[test]
+    fn validate_single_numbered_tile_test() {
+        let set = vec![
+            Card::Wildcard,
+            Card::new(13, Color::Black),
+            Card::Wildcard
+        ];
+        assert_eq!(validate_set(&set), Ok(MeldKind::Run));
+    }
+
+    #[test]
+    fn validate_set_errors_test() {
+        let cases = vec![
+            (
+                vec![Card::new(3, Color::Red), Card::new(4, Color::Red)],
+                SetError::TooShort { len: 2 }
+            ),
+            (
+                vec![Card::Wildcard, Card::Wildcard, Card::Wildcard],
+                SetError::AllWildcards
+            ),
+            (
+                vec![Card::new(12, Color::Red), Card::new(13, Color::Red), Card::new(14, Color::Red)],
+                SetError::NumberOutOfRange { number: 14, min: 1, max: 13 }
+            ),
+            (
+                vec![Card::new(3, Color::Red), Card::new(4, Color::Red), Card::new(6, Color::Red)],
+                SetError::GapInRun { missing: 5 }
+            ),
+            (
+                vec![Card::new(3, Color::Red), Card::Wildcard, Card::new(7, Color::Red)],
+                SetError::GapInRun { missing: 5 }
+            ),
+            (
+                vec![Card::new(3, Color::Red), Card::new(4, Color::Blue), Card::new(5, Color::Red)],
+                SetError::MixedColorsInRun
+            ),
+            (
+                vec![Card::new(3, Color::Red), Card::new(3, Color::Red), Card::new(4, Color::Red)],
+                SetError::DuplicateNumberInRun { number: 3 }
+            ),
+            (
+                (1..=13).map(|n| Card::new(n, Color::Red)).chain([Card::Wildcard]).collect(),
+                SetError::RunOutOfRange { len: 14, min: 1, max: 13 }
+            ),
+            (
+                vec![Card::new(8, Color::Red), Card::new(8, Color::Blue), Card::new(8, Color::Red)],
+                SetError::DuplicateColorInGroup { color: Color::Red }
+            ),
+            (
+                vec![Card::new(8, Color::Red), Card::new(8, Color::Blue), Card::new(9, Color::Black)],
+                SetError::MixedNumbersInGroup
+            ),
+            (
+                vec![
+                    Card::new(8, Color::Red),
+                    Card::new(8, Color::Blue),
+                    Card::new(8, Color::Black),
+                    Card::new(8, Color::Yellow),
+                    Card::Wildcard
+                ],
+                SetError::GroupTooLarge { len: 5, max: 4 }
+            )
+        ];
+
+        for (set, error) in cases {
+            assert_eq!(validate_set(&set), Err(error), "{:?}", set);
+        }
+    }
+
+    #[test]
+    fn set_error_display_test() {
+        let messages = [
+            (SetError::TooShort { len: 2 }, "a set needs at least 3 tiles, this one has 2"),
+            (SetError::AllWildcards, "a set needs at least one numbered tile"),
+            (SetError::NumberOutOfRange { number: 14, min: 1, max: 13 }, "14 is not a valid tile number (1 to 13)"),
+            (SetError::GapInRun { missing: 5 }, "the run is missing a 5"),
+            (SetError::MixedColorsInRun, "all the tiles of a run must have the same color"),
+            (SetError::DuplicateNumberInRun { number: 4 }, "the run contains 4 more than once"),
+            (SetError::RunOutOfRange { len: 14, min: 1, max: 13 }, "a run of 14 tiles does not fit between 1 and 13"),
+            (SetError::RunTooShort { len: 3, min: 4 }, "a run needs at least 4 tiles, this one has 3"),
+            (SetError::DuplicateColorInGroup { color: Color::Blue }, "the group contains Blue more than once"),
+            (SetError::MixedNumbersInGroup, "all the tiles of a group must have the same number"),
+            (SetError::GroupTooLarge { len: 5, max: 4 }, "a group can have at most 4 tiles, this one has 5")
+        ];
+
+        for (error, message) in messages {
+            assert_eq!(error.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn house_rules_test() {
+        let wrapping = RulesConfig { wrap_runs: true, ..RulesConfig::default() };
+        let long_runs = RulesConfig { min_run_len: 4, max_group_size: 3, ..RulesConfig::default() };
+        let around = vec![Card::new(12, Color::Red), Card::new(13, Color::Red), Card::new(1, Color::Red)];
+        let run = vec![Card::new(3, Color::Red), Card::new(4, Color::Red), Card::new(5, Color::Red)];
+        let group = vec![
+            Card::new(8, Color::Red),
+            Card::new(8, Color::Blue),
+            Card::new(8, Color::Black),
+            Card::new(8, Color::Yellow)
+        ];
+
+        assert_eq!(validate_set(&around), Err(SetError::GapInRun { missing: 2 }));
+        assert_eq!(validate_set_with(&around, &wrapping), Ok(MeldKind::Run));
+        assert_eq!(
+            validate_set_with(&[Card::new(13, Color::Red), Card::Wildcard, Card::new(2, Color::Red)], &wrapping),
+            Ok(MeldKind::Run)
+        );
+        assert_eq!(
+            validate_set_with(&[Card::new(12, Color::Red), Card::new(2, Color::Red), Card::new(6, Color::Red)], &wrapping),
+            Err(SetError::GapInRun { missing: 3 })
+        );
+
+        assert_eq!(validate_set_with(&run, &long_runs), Err(SetError::RunTooShort { len: 3, min: 4 }));
+        assert_eq!(validate_set_with(&group, &long_runs), Err(SetError::GroupTooLarge { len: 4, max: 3 }));
+        assert_eq!(validate_set_with(&group[..3], &long_runs), Ok(MeldKind::Group));
+        // Too short for a run, but fine as a group
+        assert_eq!(validate_set_with(&[Card::new(5, Color::Red), Card::Wildcard, Card::Wildcard], &long_runs), Ok(MeldKind::Group));
+    }
+
+    #[test]
+    fn number_range_test() {
+        let kids = RulesConfig { max_number: 10, ..RulesConfig::default() };
+        let extended = RulesConfig { max_number: 16, ..RulesConfig::default() };
+        let top: Vec<Card> = (14..=16).map(|n| Card::new(n, Color::Red)).collect();
+        let full_run: Vec<Card> = (1..=16).map(|n| Card::new(n, Color::Red)).collect();
+
+        assert_eq!(validate_set(&top), Err(SetError::NumberOutOfRange { number: 14, min: 1, max: 13 }));
+        assert_eq!(validate_set_with(&top, &extended), Ok(MeldKind::Run));
+        assert_eq!(validate_set_with(&full_run, &extended), Ok(MeldKind::Run));
+        assert_eq!(
+            validate_set_with(&[Card::new(9, Color::Red), Card::new(10, Color::Red), Card::new(11, Color::Red)], &kids),
+            Err(SetError::NumberOutOfRange { number: 11, min: 1, max: 10 })
+        );
+        assert_eq!(
+            validate_set_with(&[full_run[..10].to_vec(), vec![Card::Wildcard]].concat(), &kids),
+            Err(SetError::RunOutOfRange { len: 11, min: 1, max: 10 })
+        );
+        // Wrapping goes back to 1 after the highest number
+        let wrapping = RulesConfig { wrap_runs: true, ..kids };
+        assert_eq!(validate_set_with(&[Card::new(10, Color::Red), Card::new(1, Color::Red), Card::Wildcard], &wrapping), Ok(MeldKind::Run));
+    }
+
+    #[test]
+    fn six_color_group_test() {
+        let group: Vec<Card> = [Color::Red, Color::Blue, Color::Black, Color::Green, Color::Orange].iter()
+            .map(|c| Card::new(9, *c))
+            .collect();
+        let six_colors = RulesConfig::six_colors();
+
+        assert_eq!(validate_set(&group), Err(SetError::GroupTooLarge { len: 5, max: 4 }));
+        assert_eq!(validate_set_with(&group, &six_colors), Ok(MeldKind::Group));
+        assert_eq!(validate_set_with(&group, &RulesConfig { max_group_size: 4, ..six_colors }), Err(SetError::GroupTooLarge { len: 5, max: 4 }));
+        assert_eq!(validate_set_with(&[group.clone(), vec![Card::Wildcard, Card::Wildcard]].concat(), &six_colors), Err(SetError::GroupTooLarge { len: 7, max: 6 }));
+    }
+
+}
@@ -0,0 +1,104 @@
+use alloc::collections::BTreeMap;
+use thiserror::Error;
+use super::{validation, Card, RulesConfig, SetError};
+use crate::prelude::*;
+
+/// The first set verify_sets finds wrong, by its index in the list, and what is wrong with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum VerifyError {
+    #[error("set {set} has {len} tiles, a set needs at least 3")]
+    TooShort { set: usize, len: usize },
+    #[error("set {set} is not a valid run or group")]
+    Invalid { set: usize, #[source] error: SetError },
+    #[error("set {set} uses {} more times than the tray holds it", .card.code())]
+    NotInTray { set: usize, card: Card }
+}
+
+/// Checks what a solver returned for the tray: every set has at least 3 tiles, is a valid run
+/// or group, and uses no tile more times than the tray holds it
+pub fn verify_sets(tray: &[Card], sets: &[Vec<&Card>]) -> Result<(), VerifyError> {
+    verify_sets_with(tray, sets, &RulesConfig::default())
+}
+
+/// Same as verify_sets, with the sets following the rules
+pub fn verify_sets_with(tray: &[Card], sets: &[Vec<&Card>], rules: &RulesConfig) -> Result<(), VerifyError> {
+    let mut held: BTreeMap<Card, usize> = BTreeMap::new();
+    for card in tray {
+        *held.entry(*card).or_default() += 1;
+    }
+
+    for (idx, set) in sets.iter().enumerate() {
+        if set.len() < 3 {
+            return Err(VerifyError::TooShort { set: idx, len: set.len() });
+        }
+        validation::validate_refs_with(set, rules).map_err(|error| VerifyError::Invalid { set: idx, error })?;
+
+        let mut used: BTreeMap<Card, usize> = BTreeMap::new();
+        for card in set {
+            let count = used.entry(**card).or_default();
+            *count += 1;
+            if *count > held.get(card).copied().unwrap_or(0) {
+                return Err(VerifyError::NotInTray { set: idx, card: **card });
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::{valid_sets, valid_sets_maximal, valid_sets_with, Color, SetSearchOptions};
+    use crate::testing::{random_tray, TrayBuilder};
+
+    #[test]
+    fn verify_errors_test() {
+        let tray = vec![
+            Card::new(5, Color::Red),
+            Card::new(6, Color::Red),
+            Card::new(7, Color::Red),
+            Card::new(7, Color::Blue),
+            Card::Wildcard
+        ];
+        let run: Vec<&Card> = tray[..3].iter().collect();
+
+        assert_eq!(verify_sets(&tray, std::slice::from_ref(&run)), Ok(()));
+        assert_eq!(verify_sets(&tray, &[run.clone(), vec![&tray[0], &tray[4]]]), Err(VerifyError::TooShort { set: 1, len: 2 }));
+        assert_eq!(
+            verify_sets(&tray, &[vec![&tray[0], &tray[1], &tray[3]]]),
+            Err(VerifyError::Invalid { set: 0, error: SetError::MixedColorsInRun })
+        );
+        // The tray holds no 7 Black, and a single wildcard
+        let other = [Card::new(7, Color::Blue), Card::new(7, Color::Black)];
+        assert_eq!(
+            verify_sets(&tray, &[run, vec![&other[1], &tray[3], &tray[2]]]),
+            Err(VerifyError::NotInTray { set: 1, card: Card::new(7, Color::Black) })
+        );
+        assert_eq!(
+            verify_sets(&tray, &[vec![&tray[0], &tray[4], &Card::Wildcard]]),
+            Err(VerifyError::NotInTray { set: 0, card: Card::Wildcard })
+        );
+        assert_eq!(
+            verify_sets(&tray, &[vec![&tray[2], &tray[3], &other[0], &Card::Wildcard]]),
+            Err(VerifyError::Invalid { set: 0, error: SetError::DuplicateColorInGroup { color: Color::Blue } })
+        );
+    }
+
+    #[test]
+    fn random_trays_test() {
+        for seed in 0..300 {
+            let tray = random_tray(seed, 14 + (seed % 30) as usize);
+            assert_eq!(verify_sets(&tray, &valid_sets(&tray)), Ok(()), "seed {}", seed);
+            assert_eq!(verify_sets(&tray, &valid_sets_maximal(&tray)), Ok(()), "seed {}", seed);
+        }
+
+        let rules = RulesConfig { wrap_runs: true, ..RulesConfig::default() };
+        let options = SetSearchOptions { rules, ..SetSearchOptions::default() };
+        for seed in 0..100 {
+            let tray = TrayBuilder::new(20).wildcards(2).seed(seed).build().unwrap();
+            assert_eq!(verify_sets_with(&tray, &valid_sets_with(&tray, &options), &rules), Ok(()), "seed {}", seed);
+        }
+    }
+
+}
@@ -0,0 +1,431 @@
+use super::{Card, Color, DisplayTheme, RulesConfig};
+use super::twist::twist_score;
+use super::validation::{validate_refs_with, MeldKind, SetError};
+use crate::prelude::*;
+
+/// Returns the (number, color) every card of a valid set stands for, wildcards included.
+/// Runs are read in the order they are laid out. When the numbered tiles are not in their
+/// positions (like in the sorted sets returned by valid_sets) the wildcards fill the gaps
+/// first, then extend the run upwards and, once 13 is reached, downwards.
+/// In groups each wildcard takes the first color missing from the group, following
+/// the order of Color::ALL.
+pub fn resolve_wildcards(set: &[&Card]) -> Option<Vec<(i8, Color)>> {
+    resolve_wildcards_with(set, &RulesConfig::default())
+}
+
+/// Same as resolve_wildcards, for a set valid under house rules: runs go up to max_number
+/// and down to min_number, wrapping around with wrap_runs once the tiles can't be read
+/// otherwise, and the wildcards of groups take the colors of the rules.
+pub fn resolve_wildcards_with(set: &[&Card], rules: &RulesConfig) -> Option<Vec<(i8, Color)>> {
+    match validate_refs_with(set, rules).ok()? {
+        MeldKind::Run => resolve_run(set, rules).or_else(|| {
+            // A lonely numbered tile with wildcards can also be read as a group
+            if set.len() <= rules.group_limit() && set.iter().filter(|c| !c.is_wildcard()).count() == 1 {
+                resolve_group(set, rules)
+            } else {
+                resolve_unordered_run(set, rules)
+            }
+        }),
+        MeldKind::Group => resolve_group(set, rules)
+    }
+}
+
+/// The face value of a set, checking first that it is valid. Wildcards are worth the number
+/// they stand for in this set, read like resolve_wildcards does. Sets with a double or mirror
+/// joker are checked under the twist rules: the double joker is worth its number twice, a
+/// mirror run twice the numbers of one side of the mirror.
+pub fn score_meld(cards: &[Card]) -> Result<u32, SetError> {
+    score_meld_with(cards, &RulesConfig::default())
+}
+
+/// Same as score_meld, checking the set against house rules
+pub fn score_meld_with(cards: &[Card], rules: &RulesConfig) -> Result<u32, SetError> {
+    let refs: Vec<&Card> = cards.iter().collect();
+    if refs.iter().any(|c| c.is_twist_joker()) {
+        return twist_score(&refs, rules);
+    }
+    validate_refs_with(&refs, rules)?;
+    // Every valid set has a reading
+    let values = resolve_wildcards_with(&refs, rules).unwrap();
+    Ok(values.iter().map(|(number, _)| *number as u32).sum())
+}
+
+/// Writes the cards of a set like their Display does, but with every wildcard followed by
+/// what it stands for, see resolve_wildcards: "W(5 Blue)" in a run, and in a group
+/// "W(7 Yellow)" when only one color is missing, "W(7 ?)" otherwise. The wildcards of an
+/// invalid set are written as plain wildcards.
+pub fn display_meld(set: &[&Card]) -> String {
+    write_meld(set, |card| card.to_string(), |number, color| match color {
+        Some(color) => format!("W({} {})", number, color),
+        None => format!("W({} ?)", number)
+    })
+}
+
+/// Same as display_meld, with the tiles written out in the theme: "W(B5)" in a run, and in
+/// a group "W(Y7)" or "W(7?)", with the symbols of the theme
+pub fn display_meld_with(set: &[&Card], theme: &DisplayTheme) -> String {
+    write_meld(set, |card| theme.card(card), |number, color| {
+        let stands_for = match color {
+            Some(color) => theme.tile(&Card::new(number, color)),
+            None => format!("{}?", number)
+        };
+        theme.paint(&format!("{}({})", theme.wildcard, stands_for), None)
+    })
+}
+
+// Writes every tile of the set with tile, and the wildcards of a valid set with wildcard,
+// given what they stand for and its color when it is known
+fn write_meld(set: &[&Card], tile: impl Fn(&Card) -> String, wildcard: impl Fn(i8, Option<Color>) -> String) -> String {
+    let values = resolve_wildcards(set);
+    // Only in a group of four do the wildcards get the colors left, a run has a single color
+    let color_known = match &values {
+        Some(values) if values.windows(2).all(|pair| pair[0].0 == pair[1].0) => set.len() == Color::ALL.len(),
+        _ => true
+    };
+
+    set.iter()
+        .enumerate()
+        .map(|(idx, card)| match (card, &values) {
+            (Card::Wildcard, Some(values)) => wildcard(values[idx].0, color_known.then_some(values[idx].1)),
+            _ => tile(card)
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn resolve_run(set: &[&Card], rules: &RulesConfig) -> Option<Vec<(i8, Color)>> {
+    let (first_idx, first_number, color) = set.iter()
+        .enumerate()
+        .find_map(|(idx, c)| match c {
+            Card::Numbered { number, color } => Some((idx, *number, *color)),
+            _ => None
+        })?;
+    let start = first_number - first_idx as i8;
+
+    // Laid out from start, and with wrap_runs going on from max_number to min_number
+    let count = rules.number_count() as i8;
+    let read = |wrap: bool| -> Option<Vec<(i8, Color)>> {
+        set.iter()
+            .enumerate()
+            .map(|(idx, card)| {
+                let mut number = start + idx as i8;
+                if wrap {
+                    number = rules.min_number + (number - rules.min_number).rem_euclid(count);
+                }
+                if !rules.numbers().contains(&number) || card.number().is_some_and(|n| *n != number) {
+                    return None;
+                }
+                Some((number, color))
+            })
+            .collect()
+    };
+    read(false).or_else(|| rules.wrap_runs.then(|| read(true)).flatten())
+}
+
+fn resolve_unordered_run(set: &[&Card], rules: &RulesConfig) -> Option<Vec<(i8, Color)>> {
+    let color = *set.iter().find_map(|c| c.color())?;
+    let mut numbers: Vec<i8> = set.iter().filter_map(|c| c.number()).copied().collect();
+    numbers.sort();
+    let (low, high) = (*numbers.first()?, *numbers.last()?);
+    let wildcards = set.len() - numbers.len();
+
+    // The numbers are counted from the first one of the run. With wrap_runs, a run the
+    // wildcards can't fill from low to high wraps around, starting after its widest gap.
+    let count = rules.number_count() as i8;
+    let mut first = rules.min_number;
+    if rules.wrap_runs && (high - low + 1) as usize - numbers.len() > wildcards {
+        let (idx, _) = numbers.windows(2).enumerate().max_by_key(|(_, pair)| pair[1] - pair[0])?;
+        first = numbers[idx + 1];
+    }
+    let number_at = |offset: i8| rules.min_number + (first - rules.min_number + offset).rem_euclid(count);
+    let mut offsets: Vec<i8> = numbers.iter().map(|number| (number - first).rem_euclid(count)).collect();
+    offsets.sort();
+    let (low, high) = (offsets[0], offsets[offsets.len() - 1]);
+
+    let gaps = (low..=high).filter(|offset| !offsets.contains(offset));
+    let above = high+1..count;
+    let below = (0..low).rev();
+    let mut wildcard_numbers = gaps.chain(above).chain(below).take(wildcards).map(number_at);
+
+    set.iter()
+        .map(|card| match card {
+            Card::Numbered { number, color } => Some((*number, *color)),
+            _ => wildcard_numbers.next().map(|number| (number, color))
+        })
+        .collect()
+}
+
+fn resolve_group(set: &[&Card], rules: &RulesConfig) -> Option<Vec<(i8, Color)>> {
+    let number = *set.iter().find_map(|c| c.number())?;
+    let mut unused_colors = rules.colors.iter()
+        .filter(|color| !set.iter().any(|c| c.color() == Some(color)));
+
+    set.iter()
+        .map(|card| match card {
+            Card::Numbered { number, color } => Some((*number, *color)),
+            _ => unused_colors.next().map(|color| (number, *color))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn resolve(set: &[Card]) -> Option<Vec<(i8, Color)>> {
+        let refs: Vec<&Card> = set.iter().collect();
+        resolve_wildcards(&refs)
+    }
+
+    #[test]
+    fn resolve_wildcard_in_middle_of_run_test() {
+        let set = vec![
+            Card::new(3, Color::Blue),
+            Card::new(4, Color::Blue),
+            Card::Wildcard,
+            Card::new(6, Color::Blue)
+        ];
+        assert_eq!(
+            resolve(&set),
+            Some(vec![(3, Color::Blue), (4, Color::Blue), (5, Color::Blue), (6, Color::Blue)])
+        );
+    }
+
+    #[test]
+    fn resolve_wildcard_at_run_ends_test() {
+        let start = vec![
+            Card::Wildcard,
+            Card::new(2, Color::Red),
+            Card::new(3, Color::Red)
+        ];
+        assert_eq!(
+            resolve(&start),
+            Some(vec![(1, Color::Red), (2, Color::Red), (3, Color::Red)])
+        );
+
+        let end = vec![
+            Card::new(11, Color::Red),
+            Card::new(12, Color::Red),
+            Card::Wildcard
+        ];
+        assert_eq!(
+            resolve(&end),
+            Some(vec![(11, Color::Red), (12, Color::Red), (13, Color::Red)])
+        );
+    }
+
+    #[test]
+    fn resolve_two_wildcards_in_run_test() {
+        let set = vec![
+            Card::Wildcard,
+            Card::new(2, Color::Yellow),
+            Card::new(3, Color::Yellow),
+            Card::Wildcard,
+            Card::new(5, Color::Yellow)
+        ];
+        assert_eq!(
+            resolve(&set),
+            Some(vec![
+                (1, Color::Yellow),
+                (2, Color::Yellow),
+                (3, Color::Yellow),
+                (4, Color::Yellow),
+                (5, Color::Yellow)
+            ])
+        );
+    }
+
+    #[test]
+    fn resolve_wildcard_in_group_test() {
+        let set = vec![
+            Card::new(7, Color::Red),
+            Card::new(7, Color::Black),
+            Card::Wildcard
+        ];
+        assert_eq!(
+            resolve(&set),
+            Some(vec![(7, Color::Red), (7, Color::Black), (7, Color::Blue)])
+        );
+    }
+
+    #[test]
+    fn resolve_single_tile_as_group_test() {
+        // 13 can't be followed by two more numbers, so this must be a group
+        let set = vec![
+            Card::new(13, Color::Blue),
+            Card::Wildcard,
+            Card::Wildcard
+        ];
+        assert_eq!(
+            resolve(&set),
+            Some(vec![(13, Color::Blue), (13, Color::Red), (13, Color::Black)])
+        );
+    }
+
+    #[test]
+    fn resolve_sorted_run_test() {
+        let set = vec![
+            Card::new(3, Color::Blue),
+            Card::new(4, Color::Blue),
+            Card::new(6, Color::Blue),
+            Card::Wildcard
+        ];
+        assert_eq!(
+            resolve(&set),
+            Some(vec![(3, Color::Blue), (4, Color::Blue), (6, Color::Blue), (5, Color::Blue)])
+        );
+
+        let top = vec![
+            Card::new(12, Color::Black),
+            Card::new(13, Color::Black),
+            Card::new(10, Color::Black),
+            Card::Wildcard,
+            Card::Wildcard
+        ];
+        assert_eq!(
+            resolve(&top),
+            Some(vec![
+                (12, Color::Black),
+                (13, Color::Black),
+                (10, Color::Black),
+                (11, Color::Black),
+                (9, Color::Black)
+            ])
+        );
+    }
+
+    #[test]
+    fn resolve_invalid_set_test() {
+        let invalid = vec![
+            Card::new(3, Color::Blue),
+            Card::new(5, Color::Red),
+            Card::Wildcard
+        ];
+        assert_eq!(resolve(&invalid), None);
+
+        let gap = vec![
+            Card::new(3, Color::Blue),
+            Card::new(6, Color::Blue),
+            Card::Wildcard
+        ];
+        assert_eq!(resolve(&gap), None);
+    }
+
+    #[test]
+    fn score_meld_test() {
+        let run = vec![
+            Card::new(3, Color::Blue),
+            Card::Wildcard,
+            Card::new(5, Color::Blue),
+            Card::new(6, Color::Blue)
+        ];
+        let group = vec![
+            Card::new(9, Color::Red),
+            Card::new(9, Color::Black),
+            Card::new(9, Color::Yellow),
+            Card::Wildcard
+        ];
+        let end = vec![
+            Card::new(12, Color::Red),
+            Card::new(13, Color::Red),
+            Card::Wildcard
+        ];
+
+        assert_eq!(score_meld(&run), Ok(18));
+        assert_eq!(score_meld(&group), Ok(36));
+        // Nothing comes after 13, the wildcard is an 11
+        assert_eq!(score_meld(&end), Ok(36));
+        assert_eq!(score_meld(&[Card::Wildcard, Card::new(2, Color::Red), Card::new(3, Color::Red)]), Ok(6));
+        assert_eq!(
+            score_meld(&[Card::new(3, Color::Blue), Card::new(4, Color::Red), Card::new(5, Color::Blue)]),
+            Err(SetError::MixedColorsInRun)
+        );
+    }
+
+    #[test]
+    fn score_meld_with_rules_test() {
+        let extended = RulesConfig { max_number: 16, ..RulesConfig::default() };
+        let high = vec![Card::new(14, Color::Red), Card::new(15, Color::Red), Card::new(16, Color::Red)];
+        let end = vec![Card::new(15, Color::Blue), Card::new(16, Color::Blue), Card::Wildcard];
+
+        assert_eq!(score_meld_with(&high, &extended), Ok(45));
+        // Nothing comes after 16, the wildcard is a 14
+        assert_eq!(score_meld_with(&end, &extended), Ok(45));
+        assert_eq!(
+            resolve_wildcards_with(&[&Card::new(12, Color::Red), &Card::new(13, Color::Red), &Card::Wildcard], &extended),
+            Some(vec![(12, Color::Red), (13, Color::Red), (14, Color::Red)])
+        );
+        assert_eq!(score_meld(&high), Err(SetError::NumberOutOfRange { number: 14, min: 1, max: 13 }));
+    }
+
+    #[test]
+    fn resolve_wrapping_run_test() {
+        let wrap = RulesConfig { wrap_runs: true, ..RulesConfig::default() };
+        let resolve_with = |set: &[Card]| resolve_wildcards_with(&set.iter().collect::<Vec<&Card>>(), &wrap);
+
+        let laid_out = [Card::new(12, Color::Red), Card::new(13, Color::Red), Card::new(1, Color::Red)];
+        assert_eq!(resolve_with(&laid_out), Some(vec![(12, Color::Red), (13, Color::Red), (1, Color::Red)]));
+        assert_eq!(score_meld_with(&laid_out, &wrap), Ok(26));
+        let wildcard = [Card::new(12, Color::Red), Card::Wildcard, Card::new(1, Color::Red)];
+        assert_eq!(resolve_with(&wildcard).unwrap()[1], (13, Color::Red));
+        // Sorted like valid_sets returns them, the wildcard still fills the gap around 13
+        let sorted = [Card::new(1, Color::Blue), Card::new(2, Color::Blue), Card::new(12, Color::Blue), Card::Wildcard];
+        assert_eq!(resolve_with(&sorted).unwrap()[3], (13, Color::Blue));
+        // Laid out after 13, the wildcard is a 1 rather than the 11 of the standard rules
+        let end = [Card::new(12, Color::Red), Card::new(13, Color::Red), Card::Wildcard];
+        assert_eq!(resolve_with(&end).unwrap()[2], (1, Color::Red));
+        let middle = [Card::new(3, Color::Red), Card::Wildcard, Card::new(5, Color::Red)];
+        assert_eq!(resolve_with(&middle), resolve(&middle));
+        assert_eq!(resolve(&laid_out), None);
+    }
+
+    #[test]
+    fn resolve_six_color_group_test() {
+        let six_colors = RulesConfig::six_colors();
+        let group = [Card::new(10, Color::Red), Card::new(10, Color::Blue), Card::new(10, Color::Black), Card::new(10, Color::Yellow), Card::Wildcard];
+        let refs: Vec<&Card> = group.iter().collect();
+
+        assert_eq!(resolve_wildcards_with(&refs, &six_colors).unwrap()[4], (10, Color::Green));
+        assert_eq!(score_meld_with(&group, &six_colors), Ok(50));
+        assert_eq!(resolve_wildcards(&refs), None);
+    }
+
+    #[test]
+    fn display_meld_test() {
+        let display = |set: &[Card]| display_meld(&set.iter().collect::<Vec<&Card>>());
+
+        assert_eq!(
+            display(&[Card::Wildcard, Card::new(2, Color::Red), Card::new(3, Color::Red)]),
+            "W(1 Red) 2 Red 3 Red"
+        );
+        assert_eq!(
+            display(&[Card::new(3, Color::Blue), Card::new(4, Color::Blue), Card::Wildcard, Card::new(6, Color::Blue)]),
+            "3 Blue 4 Blue W(5 Blue) 6 Blue"
+        );
+        assert_eq!(
+            display(&[Card::new(7, Color::Red), Card::new(7, Color::Black), Card::Wildcard]),
+            "7 Red 7 Black W(7 ?)"
+        );
+        assert_eq!(
+            display(&[Card::new(7, Color::Red), Card::new(7, Color::Black), Card::new(7, Color::Blue), Card::Wildcard]),
+            "7 Red 7 Black 7 Blue W(7 Yellow)"
+        );
+        assert_eq!(display(&[Card::new(7, Color::Red), Card::Wildcard]), "7 Red Wildcard");
+    }
+
+    #[test]
+    fn display_meld_with_test() {
+        let run = [Card::new(4, Color::Blue), Card::Wildcard, Card::new(6, Color::Blue)];
+        let group = [Card::new(7, Color::Red), Card::new(7, Color::Black), Card::Wildcard];
+        let display = |set: &[Card], theme: DisplayTheme| display_meld_with(&set.iter().collect::<Vec<&Card>>(), &theme);
+
+        assert_eq!(display(&run, DisplayTheme::plain()), "B4 W(B5) B6");
+        assert_eq!(display(&group, DisplayTheme::plain()), "R7 K7 W(7?)");
+        assert_eq!(display(&run, DisplayTheme::colorblind()), "■4 ★(■5) ■6");
+        assert_eq!(display(&group, DisplayTheme::colorblind()), "●7 ▲7 ★(7?)");
+        assert_eq!(display(&run, DisplayTheme::ansi()), "\x1b[94mB4\x1b[0m \x1b[95mW(B5)\x1b[0m \x1b[94mB6\x1b[0m");
+        assert_eq!(display(&run[..2], DisplayTheme::colorblind()), "■4 ★");
+    }
+
+}
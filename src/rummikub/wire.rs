@@ -0,0 +1,343 @@
+use thiserror::Error;
+use super::{Card, Color};
+#[cfg(feature = "std")]
+use super::{GameSave, GameState, SaveError, SavedPlayer};
+#[cfg(feature = "std")]
+use super::notation::{parse_rules, rules_notation};
+use crate::prelude::*;
+
+/// The first bytes of every encoded game
+pub const WIRE_MAGIC: [u8; 4] = *b"RMKB";
+
+/// Bumped whenever the layout of the encoded games changes
+pub const WIRE_VERSION: u8 = 2;
+
+// The bytes of a wildcard and of the twist jokers, the other tiles having the index of their
+// color in Color::SIX in the high bits and their number in the low ones
+const WILDCARD: u8 = 0xFF;
+const DOUBLE_JOKER: u8 = 0xFE;
+const MIRROR_JOKER: u8 = 0xFD;
+const NUMBER_BITS: u8 = 4;
+const NUMBER_MASK: u8 = (1 << NUMBER_BITS) - 1;
+
+/// Error returned by the encode and decode functions of the binary format
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum WireError {
+    /// Numbers take 4 bits, from 1 to 15
+    #[error("{} doesn't fit in a byte", .0.code())]
+    UnencodableTile(Card),
+    #[error("{0:#04x} is not a tile")]
+    InvalidTile(u8),
+    #[error("{0:#04x} is neither true nor false")]
+    InvalidFlag(u8),
+    /// A list or a name is longer than its length byte can tell
+    #[error("{len} items don't fit behind a length byte")]
+    TooLong { len: usize },
+    #[error("the data ends too early")]
+    Truncated,
+    #[error("{0} bytes are left after the end")]
+    TrailingBytes(usize),
+    #[error("not a binary game: the header is wrong")]
+    BadMagic,
+    #[error("binary game version {0} is not supported, expected {WIRE_VERSION}")]
+    UnsupportedVersion(u8),
+    #[error("a player name is not valid UTF-8")]
+    InvalidName,
+    /// The rules are written like the Rules of a game record
+    #[error("the rules of the game can't be read")]
+    InvalidRules
+}
+
+/// The tile as a single byte
+pub fn encode_card(card: &Card) -> Result<u8, WireError> {
+    match card {
+        Card::Numbered { number, color } => {
+            let color_idx = Color::SIX.iter().position(|c| c == color).unwrap() as u8;
+            if !(1..=NUMBER_MASK as i8).contains(number) {
+                return Err(WireError::UnencodableTile(*card));
+            }
+            Ok(color_idx << NUMBER_BITS | *number as u8)
+        },
+        Card::Wildcard => Ok(WILDCARD),
+        Card::DoubleJoker => Ok(DOUBLE_JOKER),
+        Card::MirrorJoker => Ok(MIRROR_JOKER)
+    }
+}
+
+/// The tile of a byte written by encode_card
+pub fn decode_card(byte: u8) -> Result<Card, WireError> {
+    match byte {
+        WILDCARD => return Ok(Card::Wildcard),
+        DOUBLE_JOKER => return Ok(Card::DoubleJoker),
+        MIRROR_JOKER => return Ok(Card::MirrorJoker),
+        _ => {}
+    }
+    let number = (byte & NUMBER_MASK) as i8;
+    match Color::SIX.get((byte >> NUMBER_BITS) as usize) {
+        Some(color) if number > 0 => Ok(Card::new(number, *color)),
+        _ => Err(WireError::InvalidTile(byte))
+    }
+}
+
+/// The tiles of a meld, in their order, behind their count
+pub fn encode_meld(cards: &[Card]) -> Result<Vec<u8>, WireError> {
+    let mut bytes = Vec::with_capacity(cards.len() + 1);
+    write_tiles(&mut bytes, cards)?;
+    Ok(bytes)
+}
+
+/// The meld written by encode_meld, which must take the whole buffer
+pub fn decode_meld(bytes: &[u8]) -> Result<Vec<Card>, WireError> {
+    let mut reader = Reader { bytes };
+    let cards = reader.tiles()?;
+    reader.finish()?;
+    Ok(cards)
+}
+
+/// The game as GameState::to_save keeps it, in a few hundred bytes: WIRE_MAGIC, the
+/// WIRE_VERSION byte, the seed, the players with their names, trays, openings and mulligans,
+/// the player whose turn it is, whether the game started, the deck, the melds of the table,
+/// the discard pile and the rules. Names and rules longer than 255 bytes and tiles
+/// encode_card can't write are errors.
+#[cfg(feature = "std")]
+pub fn encode_game(game: &GameState) -> Result<Vec<u8>, WireError> {
+    let save = game.to_save();
+    let mut bytes = WIRE_MAGIC.to_vec();
+    bytes.push(WIRE_VERSION);
+    match save.seed {
+        Some(seed) => {
+            bytes.push(1);
+            bytes.extend(seed.to_be_bytes());
+        },
+        None => bytes.push(0)
+    }
+    write_len(&mut bytes, save.players.len())?;
+    for player in &save.players {
+        write_len(&mut bytes, player.name.len())?;
+        bytes.extend(player.name.as_bytes());
+        bytes.push(player.has_melded as u8);
+        bytes.push(player.took_mulligan as u8);
+        write_tiles(&mut bytes, &player.tray)?;
+    }
+    write_len(&mut bytes, save.current_player)?;
+    bytes.push(save.started as u8);
+    write_tiles(&mut bytes, &save.deck)?;
+    write_len(&mut bytes, save.table.len())?;
+    for meld in &save.table {
+        write_tiles(&mut bytes, meld)?;
+    }
+    write_tiles(&mut bytes, &save.discards)?;
+    let rules = rules_notation(&save.rules);
+    write_len(&mut bytes, rules.len())?;
+    bytes.extend(rules.as_bytes());
+    Ok(bytes)
+}
+
+/// Resumes a game written by encode_game, once GameSave::validate accepts it like for the
+/// JSON saves
+#[cfg(feature = "std")]
+pub fn decode_game(bytes: &[u8]) -> Result<GameState, SaveError> {
+    let mut reader = Reader { bytes };
+    if reader.take(WIRE_MAGIC.len()).map_err(|_| WireError::BadMagic)? != WIRE_MAGIC {
+        return Err(WireError::BadMagic.into());
+    }
+    let version = reader.byte()?;
+    if version != WIRE_VERSION {
+        return Err(WireError::UnsupportedVersion(version).into());
+    }
+    let seed = if reader.flag()? {
+        // take gives exactly 8 bytes
+        Some(u64::from_be_bytes(reader.take(8)?.try_into().unwrap()))
+    } else {
+        None
+    };
+    let mut players = vec![];
+    for _ in 0..reader.byte()? {
+        let len = reader.byte()? as usize;
+        let name = String::from_utf8(reader.take(len)?.to_vec()).map_err(|_| WireError::InvalidName)?;
+        let (has_melded, took_mulligan) = (reader.flag()?, reader.flag()?);
+        players.push(SavedPlayer { name, tray: reader.tiles()?, has_melded, took_mulligan });
+    }
+    let current_player = reader.byte()? as usize;
+    let started = reader.flag()?;
+    let deck = reader.tiles()?;
+    let mut table = vec![];
+    for _ in 0..reader.byte()? {
+        table.push(reader.tiles()?);
+    }
+    let discards = reader.tiles()?;
+    let len = reader.byte()? as usize;
+    let rules = core::str::from_utf8(reader.take(len)?).ok().and_then(parse_rules).ok_or(WireError::InvalidRules)?;
+    reader.finish()?;
+    GameState::from_save(GameSave { seed, deck, players, table, discards, current_player, started, rules })
+}
+
+fn write_len(bytes: &mut Vec<u8>, len: usize) -> Result<(), WireError> {
+    bytes.push(u8::try_from(len).map_err(|_| WireError::TooLong { len })?);
+    Ok(())
+}
+
+fn write_tiles(bytes: &mut Vec<u8>, cards: &[Card]) -> Result<(), WireError> {
+    write_len(bytes, cards.len())?;
+    for card in cards {
+        bytes.push(encode_card(card)?);
+    }
+    Ok(())
+}
+
+// Reads a buffer from the front, running out of bytes being an error rather than a panic
+struct Reader<'a> {
+    bytes: &'a [u8]
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], WireError> {
+        if self.bytes.len() < len {
+            return Err(WireError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, WireError> {
+        Ok(self.take(1)?[0])
+    }
+
+    #[cfg(feature = "std")]
+    fn flag(&mut self) -> Result<bool, WireError> {
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            byte => Err(WireError::InvalidFlag(byte))
+        }
+    }
+
+    fn tiles(&mut self) -> Result<Vec<Card>, WireError> {
+        let len = self.byte()? as usize;
+        self.take(len)?.iter().map(|byte| decode_card(*byte)).collect()
+    }
+
+    fn finish(self) -> Result<(), WireError> {
+        match self.bytes.len() {
+            0 => Ok(()),
+            left => Err(WireError::TrailingBytes(left))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::{Deck, RulesConfig};
+
+    #[test]
+    fn card_round_trip_test() {
+        for card in Deck::with_rules(&RulesConfig::six_colors()).snapshot() {
+            assert_eq!(decode_card(encode_card(&card).unwrap()), Ok(card));
+        }
+        assert_eq!(encode_card(&Card::new(7, Color::Blue)), Ok(0x17));
+        assert_eq!(encode_card(&Card::new(16, Color::Red)), Err(WireError::UnencodableTile(Card::new(16, Color::Red))));
+        for byte in [0x00, 0x10, 0x61, 0xF1] {
+            assert_eq!(decode_card(byte), Err(WireError::InvalidTile(byte)));
+        }
+
+        let meld = vec![Card::new(7, Color::Red), Card::Wildcard, Card::new(7, Color::Orange)];
+        let bytes = encode_meld(&meld).unwrap();
+        assert_eq!(bytes, vec![3, 0x07, 0xFF, 0x57]);
+        assert_eq!(decode_meld(&bytes), Ok(meld));
+        assert_eq!(decode_meld(&bytes[..3]), Err(WireError::Truncated));
+        assert_eq!(decode_meld(&[bytes.as_slice(), &[0]].concat()), Err(WireError::TrailingBytes(1)));
+        assert_eq!(encode_meld(&[Card::Wildcard; 256]), Err(WireError::TooLong { len: 256 }));
+    }
+
+    #[cfg(feature = "std")]
+    mod games {
+
+        use super::*;
+        use super::super::super::{play_round, Move, Mulligan, RandomStrategy, ScoringVariant, Strategy, VariantRules};
+
+        // A game some random moves in, with 2 to 4 players
+        fn random_game(seed: u64) -> GameState {
+            let names = ["Ann", "Bob", "Cy", "Dee"];
+            let players = 2 + seed as usize % 3;
+            let mut game = GameState::new_game(&names[..players], seed);
+            let mut strategies: Vec<Box<dyn Strategy>> = (0..players)
+                .map(|player| Box::new(RandomStrategy::new(seed + player as u64)) as Box<dyn Strategy>)
+                .collect();
+            play_round(&mut game, &mut strategies, seed as usize * 7 % 200).unwrap();
+            game
+        }
+
+        #[test]
+        fn game_round_trip_test() {
+            for seed in 0..40 {
+                let game = random_game(seed);
+                let bytes = encode_game(&game).unwrap();
+                assert!(bytes.len() < 200, "{} bytes", bytes.len());
+                assert!(bytes.len() * 4 < game.to_save().to_json().len());
+                assert_eq!(decode_game(&bytes).unwrap().to_save(), game.to_save(), "seed {}", seed);
+            }
+        }
+
+        #[test]
+        fn discards_round_trip_test() {
+            let rules = RulesConfig { variant: VariantRules::DrawDiscard, ..RulesConfig::default() };
+            let mut game = GameState::new_game_with_rules(&["Ann", "Bo"], 6, 0, rules);
+            game.apply_move(Move::Draw).unwrap();
+            let discard = game.current_player().tray()[0];
+            game.apply_move(Move::Discard(discard)).unwrap();
+
+            let decoded = decode_game(&encode_game(&game).unwrap()).unwrap();
+            assert_eq!(decoded.discards(), &[discard]);
+            assert_eq!(decoded.to_save(), game.to_save());
+        }
+
+        #[test]
+        fn rules_round_trip_test() {
+            let rules = RulesConfig {
+                wildcards: 4,
+                wrap_runs: true,
+                mulligan: Mulligan::OncePerPlayer { only_without_sets: false },
+                scoring: ScoringVariant::WinnerDouble,
+                ..RulesConfig::six_colors()
+            };
+            let mut game = GameState::new_game_with_rules(&["Ann", "Bo", "Cy"], 8, 2, rules);
+            game.mulligan(1).unwrap();
+
+            let bytes = encode_game(&game).unwrap();
+            let decoded = decode_game(&bytes).unwrap();
+            assert_eq!(decoded.rules(), &rules);
+            assert_eq!(decoded.to_save(), game.to_save());
+
+            // The last byte of the rules is no longer one of them
+            let mut broken = bytes.clone();
+            *broken.last_mut().unwrap() = b'?';
+            assert!(matches!(decode_game(&broken), Err(SaveError::Wire(WireError::InvalidRules))));
+        }
+
+        #[test]
+        fn truncated_game_test() {
+            let bytes = encode_game(&random_game(3)).unwrap();
+
+            for len in 0..bytes.len() {
+                let error = decode_game(&bytes[..len]).err().unwrap();
+                let expected = if len < WIRE_MAGIC.len() { WireError::BadMagic } else { WireError::Truncated };
+                assert!(matches!(error, SaveError::Wire(ref wire) if *wire == expected), "{} bytes: {}", len, error);
+            }
+
+            let mut newer = bytes.clone();
+            newer[4] = WIRE_VERSION + 1;
+            assert!(matches!(decode_game(&newer), Err(SaveError::Wire(WireError::UnsupportedVersion(3)))));
+            // The first tile of Ann, after the header, the seed, her name and flags, swapped for
+            // another one: a tile goes missing and another shows up once too often
+            let mut swapped = bytes.clone();
+            let first_tile = WIRE_MAGIC.len() + 1 + 9 + 1 + 4 + 2 + 1;
+            swapped[first_tile] = if swapped[first_tile] == 0x01 { 0x02 } else { 0x01 };
+            assert!(matches!(decode_game(&swapped), Err(SaveError::WrongTileCount { .. })));
+        }
+
+    }
+
+}
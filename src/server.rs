@@ -0,0 +1,123 @@
+//! Serves the solver over HTTP, for scripts and web pages. Every endpoint takes a JSON body
+//! and answers with the JSON of the json module:
+//!
+//! - `POST /solve` takes `{"tray": ["R7", "B7", "W"]}` and gives the sets_to_json report.
+//!   The `min_len` and `maximal` query parameters work like the options of the solve command.
+//! - `POST /partition` takes a tray the same way and gives the partition_to_json report.
+//! - `POST /validate-set` takes `{"set": ["R7", "B7", "W"]}` and gives validation_to_json.
+//!
+//! Bodies that aren't JSON or hold unknown tiles get a 400 with `{"error": "..."}`, bodies
+//! over MAX_BODY_LEN bytes and trays over MAX_TRAY_LEN tiles a 413.
+
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use axum::body::Bytes;
+use axum::extract::{DefaultBodyLimit, Query};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::Router;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use crate::{best_partition, partition_to_json, sets_to_json, valid_sets_with, validation_to_json, Card, SetSearchOptions};
+
+/// Requests with longer bodies are refused before being read
+pub const MAX_BODY_LEN: usize = 16 * 1024;
+
+/// Trays with more tiles are refused, their sets being too many to search in a request. The
+/// tray of a long game rarely holds more than 30 tiles.
+pub const MAX_TRAY_LEN: usize = 40;
+
+#[derive(Deserialize)]
+struct TrayRequest {
+    tray: Vec<Card>
+}
+
+#[derive(Deserialize)]
+struct SetRequest {
+    set: Vec<Card>
+}
+
+#[derive(Deserialize)]
+struct SolveParams {
+    min_len: Option<usize>,
+    #[serde(default)]
+    maximal: bool
+}
+
+/// The routes of the server, ready to be served or called in tests
+pub fn router() -> Router {
+    Router::new()
+        .route("/solve", post(solve))
+        .route("/partition", post(partition))
+        .route("/validate-set", post(validate_set))
+        .layer(DefaultBodyLimit::max(MAX_BODY_LEN))
+}
+
+/// Serves the router on every interface until the process is stopped
+pub fn serve(port: u16) -> io::Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, port))).await?;
+        axum::serve(listener, router()).await
+    })
+}
+
+async fn solve(Query(params): Query<SolveParams>, body: Bytes) -> Result<Response, ApiError> {
+    let tray = read_tray(&body)?;
+    let defaults = SetSearchOptions::default();
+    let options = SetSearchOptions {
+        min_len: params.min_len.unwrap_or(defaults.min_len),
+        maximal_only: params.maximal,
+        ..defaults
+    };
+    Ok(json_response(StatusCode::OK, sets_to_json(&tray, &valid_sets_with(&tray, &options), false)))
+}
+
+async fn partition(body: Bytes) -> Result<Response, ApiError> {
+    let tray = read_tray(&body)?;
+    Ok(json_response(StatusCode::OK, partition_to_json(&best_partition(&tray), false)))
+}
+
+async fn validate_set(body: Bytes) -> Result<Response, ApiError> {
+    let request = parse::<SetRequest>(&body)?;
+    check_len(request.set.len())?;
+    Ok(json_response(StatusCode::OK, validation_to_json(&request.set, false)))
+}
+
+// A refused request, answered with {"error": message}
+struct ApiError {
+    status: StatusCode,
+    message: String
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        json_response(self.status, serde_json::json!({ "error": self.message }).to_string())
+    }
+}
+
+fn read_tray(body: &[u8]) -> Result<Vec<Card>, ApiError> {
+    let request = parse::<TrayRequest>(body)?;
+    check_len(request.tray.len())?;
+    Ok(request.tray)
+}
+
+// Unknown tiles are data errors of the body, reported like syntax errors
+fn parse<T: DeserializeOwned>(body: &[u8]) -> Result<T, ApiError> {
+    serde_json::from_slice(body).map_err(|error| ApiError { status: StatusCode::BAD_REQUEST, message: error.to_string() })
+}
+
+fn check_len(len: usize) -> Result<(), ApiError> {
+    if len > MAX_TRAY_LEN {
+        return Err(ApiError {
+            status: StatusCode::PAYLOAD_TOO_LARGE,
+            message: format!("{} tiles is more than the {} a request can hold", len, MAX_TRAY_LEN)
+        });
+    }
+    Ok(())
+}
+
+fn json_response(status: StatusCode, json: String) -> Response {
+    (status, [(header::CONTENT_TYPE, "application/json")], json).into_response()
+}
@@ -0,0 +1,227 @@
+//! Random trays for tests and benchmarks. Every tray can be dealt from a standard deck: it
+//! never holds more than two copies of a tile, nor more than two wildcards.
+
+use rand::prelude::*;
+use thiserror::Error;
+use crate::{Card, Color, Deck, DeckError};
+use crate::prelude::*;
+
+/// A tray of size tiles drawn from a shuffled deck, the whole deck when size is bigger
+pub fn random_tray(seed: u64, size: usize) -> Vec<Card> {
+    let size = size.min(Deck::new().len());
+    TrayBuilder::new(size).seed(seed).build().unwrap_or_default()
+}
+
+/// Error returned by TrayBuilder::build when no standard deck can deal the tray
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TrayBuildError {
+    /// The tray would hold more copies of the tile than the deck does
+    #[error("the deck doesn't hold that many copies of {}", .0.code())]
+    TooManyCopies(Card),
+    /// The forced tiles and the wildcards don't fit in the tray
+    #[error("a tray of {size} tiles can't hold the {needed} tiles asked for")]
+    TooSmall { size: usize, needed: usize },
+    /// There aren't enough tiles of the allowed colors left to fill the tray
+    #[error("only {available} tiles can fill a tray of {size}")]
+    NotEnoughTiles { size: usize, available: usize },
+    /// More wildcards are forced into the tray than it must hold
+    #[error("{forced} wildcards are forced into a tray of {wildcards} wildcards")]
+    WildcardsForced { wildcards: usize, forced: usize }
+}
+
+/// Builds a random tray within some constraints, like
+/// `TrayBuilder::new(14).wildcards(2).include(&[Card::new(5, Color::Red)]).seed(3).build()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrayBuilder {
+    size: usize,
+    wildcards: Option<usize>,
+    forced: Vec<Card>,
+    colors: Vec<Color>,
+    seed: u64
+}
+
+impl TrayBuilder {
+    /// A tray of size tiles of the standard colors, where wildcards come by chance
+    pub fn new(size: usize) -> TrayBuilder {
+        TrayBuilder { size, wildcards: None, forced: vec![], colors: Color::ALL.to_vec(), seed: 0 }
+    }
+
+    /// Exactly this many wildcards, the forced ones included
+    pub fn wildcards(mut self, wildcards: usize) -> TrayBuilder {
+        self.wildcards = Some(wildcards);
+        self
+    }
+
+    /// Tiles the tray must hold, a tile given twice meaning both copies
+    pub fn include(mut self, cards: &[Card]) -> TrayBuilder {
+        self.forced.extend_from_slice(cards);
+        self
+    }
+
+    /// The colors the random tiles are drawn from. Forced tiles can be of any color.
+    pub fn colors(mut self, colors: &[Color]) -> TrayBuilder {
+        self.colors = colors.to_vec();
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> TrayBuilder {
+        self.seed = seed;
+        self
+    }
+
+    /// The tray, its tiles in random order
+    pub fn build(&self) -> Result<Vec<Card>, TrayBuildError> {
+        let forced_wildcards = self.forced.iter().filter(|c| c.is_wildcard()).count();
+        let wildcards = self.wildcards.unwrap_or(forced_wildcards);
+        if wildcards < forced_wildcards {
+            return Err(TrayBuildError::WildcardsForced { wildcards, forced: forced_wildcards });
+        }
+        let mut tray = self.forced.clone();
+        tray.extend(vec![Card::Wildcard; wildcards - forced_wildcards]);
+        if tray.len() > self.size {
+            return Err(TrayBuildError::TooSmall { size: self.size, needed: tray.len() });
+        }
+
+        let mut left = Deck::standard_without(&tray).map_err(|err| match err {
+            DeckError::TooManyCopies(card) => TrayBuildError::TooManyCopies(card)
+        })?.snapshot();
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        left.shuffle(&mut rng);
+
+        // Wildcards come by chance only when their count is left open
+        let drawable = left.into_iter().filter(|card| match card {
+            Card::Numbered { color, .. } => self.colors.contains(color),
+            Card::Wildcard => self.wildcards.is_none(),
+            _ => false
+        });
+        let missing = self.size - tray.len();
+        tray.extend(drawable.take(missing));
+        if tray.len() < self.size {
+            return Err(TrayBuildError::NotEnoughTiles { size: self.size, available: tray.len() });
+        }
+        tray.shuffle(&mut rng);
+        Ok(tray)
+    }
+}
+
+/// A tray the benchmarks measure, named after its scenario. The trays are seeded, so the
+/// numbers stay comparable from one run to the next.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fixture {
+    pub name: &'static str,
+    pub tray: Vec<Card>
+}
+
+// The seed of every fixture
+const FIXTURE_SEED: u64 = 7;
+
+/// Trays of 14 tiles for valid_sets, without wildcards, with one and with two
+pub fn valid_sets_fixtures() -> Vec<Fixture> {
+    [("no_wildcards", 0), ("one_wildcard", 1), ("two_wildcards", 2)].into_iter()
+        .map(|(name, wildcards)| Fixture { name, tray: fixture(TrayBuilder::new(14).wildcards(wildcards)) })
+        .collect()
+}
+
+/// A tray for find_runs: 20 tiles of one color, most numbers held twice
+pub fn long_run_fixture() -> Fixture {
+    Fixture { name: "long_single_color", tray: fixture(TrayBuilder::new(20).wildcards(0).colors(&[Color::Red])) }
+}
+
+/// A tray for find_same_numbers: both copies of the 4s to 7s of every color, and a wildcard
+pub fn duplicates_fixture() -> Fixture {
+    let pairs: Vec<Card> = (4..=7)
+        .flat_map(|number| Color::ALL.iter().flat_map(move |color| [Card::new(number, *color); 2]))
+        .collect();
+    Fixture { name: "duplicated_tiles", tray: fixture(TrayBuilder::new(33).wildcards(1).include(&pairs)) }
+}
+
+/// Trays of 14 and 20 tiles for best_partition
+pub fn partition_fixtures() -> Vec<Fixture> {
+    [("14_tiles", 14), ("20_tiles", 20)].into_iter()
+        .map(|(name, size)| Fixture { name, tray: fixture(TrayBuilder::new(size)) })
+        .collect()
+}
+
+// Every fixture fits in a standard deck
+fn fixture(builder: TrayBuilder) -> Vec<Card> {
+    builder.seed(FIXTURE_SEED).build().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::collections::BTreeMap;
+    use super::*;
+
+    fn count(tray: &[Card], card: &Card) -> usize {
+        tray.iter().filter(|c| *c == card).count()
+    }
+
+    #[test]
+    fn builder_constraints_test() {
+        let red = Card::new(5, Color::Red);
+        let tray = TrayBuilder::new(14).wildcards(2).include(&[red, red]).seed(3).build().unwrap();
+
+        assert_eq!(tray.len(), 14);
+        assert_eq!(count(&tray, &Card::Wildcard), 2);
+        assert_eq!(count(&tray, &red), 2);
+
+        let tray = TrayBuilder::new(20).wildcards(0).colors(&[Color::Blue]).include(&[red]).build().unwrap();
+        assert_eq!(count(&tray, &Card::Wildcard), 0);
+        assert!(tray.iter().all(|c| *c == red || c.color() == Some(&Color::Blue)));
+        assert_ne!(TrayBuilder::new(20).seed(1).build(), TrayBuilder::new(20).seed(2).build());
+        assert_eq!(random_tray(4, 14), random_tray(4, 14));
+        assert_eq!(random_tray(4, 200).len(), 106);
+    }
+
+    #[test]
+    fn impossible_trays_test() {
+        let red = Card::new(5, Color::Red);
+
+        assert_eq!(TrayBuilder::new(5).include(&[red, red, red]).build(), Err(TrayBuildError::TooManyCopies(red)));
+        assert_eq!(TrayBuilder::new(5).wildcards(3).build(), Err(TrayBuildError::TooManyCopies(Card::Wildcard)));
+        assert_eq!(TrayBuilder::new(2).wildcards(2).include(&[red]).build(), Err(TrayBuildError::TooSmall { size: 2, needed: 3 }));
+        assert_eq!(TrayBuilder::new(5).wildcards(1).include(&[Card::Wildcard, Card::Wildcard]).build(), Err(TrayBuildError::WildcardsForced { wildcards: 1, forced: 2 }));
+        // 26 blue tiles only
+        assert_eq!(TrayBuilder::new(30).wildcards(0).colors(&[Color::Blue]).build(), Err(TrayBuildError::NotEnoughTiles { size: 30, available: 26 }));
+    }
+
+    #[test]
+    fn no_third_copies_test() {
+        for seed in 0..1000 {
+            let tray = TrayBuilder::new(40)
+                .wildcards((seed % 3) as usize)
+                .include(&[Card::new(7, Color::Black), Card::new(7, Color::Black)])
+                .seed(seed)
+                .build()
+                .unwrap();
+            let mut copies: BTreeMap<Card, usize> = BTreeMap::new();
+            for card in &tray {
+                *copies.entry(*card).or_default() += 1;
+            }
+            assert!(copies.values().all(|count| *count <= 2), "seed {}", seed);
+            assert_eq!(count(&tray, &Card::Wildcard), (seed % 3) as usize);
+            assert_eq!(tray.len(), 40);
+        }
+    }
+
+    #[test]
+    fn fixtures_test() {
+        let sets = valid_sets_fixtures();
+        assert_eq!(sets.iter().map(|f| (f.name, f.tray.len(), count(&f.tray, &Card::Wildcard))).collect::<Vec<_>>(), vec![
+            ("no_wildcards", 14, 0),
+            ("one_wildcard", 14, 1),
+            ("two_wildcards", 14, 2)
+        ]);
+        let long_run = long_run_fixture();
+        assert!(long_run.tray.iter().all(|c| c.color() == Some(&Color::Red)) && long_run.tray.len() == 20);
+        assert!(!crate::find_runs(&long_run.tray).is_empty());
+        let duplicates = duplicates_fixture();
+        assert_eq!(count(&duplicates.tray, &Card::new(6, Color::Yellow)), 2);
+        assert!(!crate::find_same_numbers(&duplicates.tray).is_empty());
+        assert_eq!(partition_fixtures().iter().map(|f| f.tray.len()).collect::<Vec<usize>>(), vec![14, 20]);
+        // Seeded, so the same every time
+        assert_eq!(partition_fixtures(), partition_fixtures());
+    }
+
+}
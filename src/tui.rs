@@ -0,0 +1,264 @@
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+use crossterm::{cursor, event, execute, queue, style, terminal};
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use crossterm::style::Stylize;
+use rummikub::{render_table_colored, Card, Color, Table};
+use crate::repl::{Action, Outcome, Session};
+
+// Tiles shown on each row of the tray, the up and down arrows move by a row
+const ROW_LEN: usize = 13;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Left,
+    Right,
+    Up,
+    Down,
+    Select,
+    Commit,
+    Draw,
+    Quit
+}
+
+impl Key {
+    fn from_code(code: KeyCode) -> Option<Key> {
+        match code {
+            KeyCode::Left => Some(Key::Left),
+            KeyCode::Right => Some(Key::Right),
+            KeyCode::Up => Some(Key::Up),
+            KeyCode::Down => Some(Key::Down),
+            KeyCode::Char(' ') => Some(Key::Select),
+            KeyCode::Enter => Some(Key::Commit),
+            KeyCode::Char('d') => Some(Key::Draw),
+            KeyCode::Char('q') | KeyCode::Esc => Some(Key::Quit),
+            _ => None
+        }
+    }
+}
+
+// Everything the screen shows, updated by the keys without touching the terminal
+pub struct TuiState {
+    pub session: Session,
+    pub cursor: usize,
+    // Positions in the tray
+    pub selected: BTreeSet<usize>,
+    pub message: String,
+    pub finished: bool
+}
+
+impl TuiState {
+    pub fn new(session: Session) -> TuiState {
+        TuiState {
+            session,
+            cursor: 0,
+            selected: BTreeSet::new(),
+            message: String::new(),
+            finished: false
+        }
+    }
+
+    pub fn handle(&mut self, key: Key) {
+        let last = self.session.tray.len().saturating_sub(1);
+        match key {
+            Key::Left => self.cursor = self.cursor.saturating_sub(1),
+            Key::Right => self.cursor = (self.cursor + 1).min(last),
+            Key::Up => self.cursor = self.cursor.saturating_sub(ROW_LEN),
+            Key::Down => self.cursor = (self.cursor + ROW_LEN).min(last),
+            Key::Select => {
+                if self.cursor < self.session.tray.len() && !self.selected.remove(&self.cursor) {
+                    self.selected.insert(self.cursor);
+                }
+            },
+            Key::Commit => {
+                let set: Vec<Card> = self.selected.iter().map(|&idx| self.session.tray[idx]).collect();
+                let outcome = self.session.apply(Action::Play(set));
+                if !matches!(outcome, Outcome::Rejected(_)) {
+                    self.selected.clear();
+                    self.cursor = self.cursor.min(self.session.tray.len().saturating_sub(1));
+                }
+                self.show(outcome);
+            },
+            Key::Draw => {
+                let outcome = self.session.apply(Action::Draw);
+                self.show(outcome);
+            },
+            Key::Quit => self.show(Outcome::Quit)
+        }
+    }
+
+    fn show(&mut self, outcome: Outcome) {
+        self.finished = outcome.ends_game();
+        self.message = match outcome {
+            Outcome::Drew(card) => format!("You drew {}", card),
+            Outcome::Played(set) => format!("You played {}", codes(&set)),
+            Outcome::Rejected(message) => message,
+            Outcome::Won => "Your tray is empty: you won!".to_string(),
+            Outcome::DeckEmpty => "The deck is empty: game over".to_string(),
+            Outcome::Quit => "Bye".to_string(),
+            Outcome::Sets(_) | Outcome::Sorted => String::new()
+        };
+    }
+}
+
+// Plays on the alternate screen until the game ends, the terminal is restored even on errors
+pub fn run(session: Session) -> io::Result<()> {
+    let mut state = TuiState::new(session);
+    let mut stdout = io::stdout();
+
+    terminal::enable_raw_mode()?;
+    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+    let result = event_loop(&mut state, &mut stdout);
+    execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+
+    result?;
+    println!("{}", state.message);
+    Ok(())
+}
+
+fn event_loop(state: &mut TuiState, out: &mut impl Write) -> io::Result<()> {
+    while !state.finished {
+        render(state, out)?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press {
+                if let Some(key) = Key::from_code(key.code) {
+                    state.handle(key);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn render(state: &TuiState, out: &mut impl Write) -> io::Result<()> {
+    queue!(out, terminal::Clear(terminal::ClearType::All), cursor::MoveTo(0, 0))?;
+
+    queue!(out, style::Print("Table:\r\n"))?;
+    if !state.session.table.is_empty() {
+        for line in render_table_colored(&Table::from_melds(state.session.table.clone())).lines() {
+            queue!(out, style::Print(line), style::Print("\r\n"))?;
+        }
+    }
+
+    queue!(out, style::Print(format!("\r\nYour tray ({} tiles left in the deck):\r\n", state.session.deck_len())))?;
+    for (row, cards) in state.session.tray.chunks(ROW_LEN).enumerate() {
+        queue!(out, style::Print("  "))?;
+        for (col, card) in cards.iter().enumerate() {
+            let idx = row * ROW_LEN + col;
+            let mut cell = tile(card);
+            if state.selected.contains(&idx) {
+                cell = cell.reverse();
+            }
+            if idx == state.cursor {
+                cell = cell.underlined();
+            }
+            queue!(out, style::PrintStyledContent(cell), style::Print(" "))?;
+        }
+        queue!(out, style::Print("\r\n"))?;
+    }
+
+    queue!(
+        out,
+        style::Print(format!("\r\n{}\r\n", state.message)),
+        style::Print("←/→/↑/↓ move  space select  enter play  d draw  q quit\r\n")
+    )?;
+    out.flush()
+}
+
+fn tile(card: &Card) -> style::StyledContent<String> {
+    let cell = format!("[{:>3}]", card.code());
+    match card.color() {
+        Some(Color::Red) => cell.red(),
+        Some(Color::Blue) => cell.blue(),
+        Some(Color::Black) => cell.dark_grey(),
+        Some(Color::Yellow) => cell.yellow(),
+        Some(Color::Green) => cell.green(),
+        Some(Color::Orange) => cell.dark_yellow(),
+        None => cell.magenta()
+    }
+}
+
+fn codes(cards: &[Card]) -> String {
+    cards.iter().map(|c| c.code()).collect::<Vec<String>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rummikub::Deck;
+    use super::*;
+
+    fn state(tray: Vec<Card>) -> TuiState {
+        let mut session = Session::new(Deck::new_seeded(1), 0);
+        session.tray = tray;
+        TuiState::new(session)
+    }
+
+    #[test]
+    fn cursor_test() {
+        let mut state = state(Deck::new_seeded(2).pick_tray(20));
+
+        state.handle(Key::Left);
+        assert_eq!(state.cursor, 0);
+        state.handle(Key::Down);
+        assert_eq!(state.cursor, 13);
+        state.handle(Key::Down);
+        assert_eq!(state.cursor, 19);
+        state.handle(Key::Right);
+        assert_eq!(state.cursor, 19);
+        state.handle(Key::Up);
+        state.handle(Key::Left);
+        assert_eq!(state.cursor, 5);
+    }
+
+    #[test]
+    fn commit_selected_test() {
+        let mut state = state(vec![
+            Card::new(4, Color::Red),
+            Card::new(9, Color::Blue),
+            Card::new(5, Color::Red),
+            Card::Wildcard
+        ]);
+
+        for key in [Key::Select, Key::Right, Key::Select, Key::Select, Key::Commit] {
+            state.handle(key);
+        }
+        assert!(state.message.starts_with("not a valid set"));
+        assert_eq!(state.selected, BTreeSet::from([0]));
+
+        for key in [Key::Right, Key::Select, Key::Right, Key::Select, Key::Commit] {
+            state.handle(key);
+        }
+        assert_eq!(state.session.tray, vec![Card::new(9, Color::Blue)]);
+        assert_eq!(state.session.table, vec![vec![Card::new(4, Color::Red), Card::new(5, Color::Red), Card::Wildcard]]);
+        assert!(state.selected.is_empty());
+        assert_eq!(state.cursor, 0);
+        assert!(!state.finished);
+    }
+
+    #[test]
+    fn game_end_test() {
+        let mut state = state(vec![Card::new(1, Color::Red), Card::new(1, Color::Blue), Card::new(1, Color::Yellow)]);
+
+        for key in [Key::Select, Key::Right, Key::Select, Key::Right, Key::Select, Key::Commit] {
+            state.handle(key);
+        }
+
+        assert!(state.finished);
+        assert_eq!(state.message, "Your tray is empty: you won!");
+    }
+
+    #[test]
+    fn draw_and_quit_test() {
+        let mut state = state(vec![Card::new(1, Color::Red)]);
+
+        state.handle(Key::Draw);
+        assert_eq!(state.session.tray.len(), 2);
+        assert!(state.message.starts_with("You drew"));
+
+        state.handle(Key::Quit);
+        assert!(state.finished);
+    }
+
+}
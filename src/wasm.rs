@@ -0,0 +1,47 @@
+//! JavaScript bindings, built with the wasm feature. Trays are passed as strings of tile
+//! codes like "B2 B3 B4 R7 W" and errors are thrown as JavaScript `Error`s instead of
+//! panicking, which would abort the whole wasm instance.
+
+use js_sys::{Array, Error, JSON};
+use wasm_bindgen::prelude::*;
+use crate::{best_partition, partition_to_json, sets_to_json, valid_sets, Card, Deck};
+
+fn parse(tiles: &str) -> Result<Vec<Card>, JsValue> {
+    tiles.split_whitespace()
+        .map(|token| token.parse::<Card>().map_err(|e| Error::new(&e.to_string()).into()))
+        .collect()
+}
+
+fn codes(cards: &[Card]) -> JsValue {
+    cards.iter().map(|c| JsValue::from(c.code())).collect::<Array>().into()
+}
+
+/// Reads a tray, returning the codes of its tiles
+#[wasm_bindgen]
+pub fn parse_tray(tiles: &str) -> Result<JsValue, JsValue> {
+    parse(tiles).map(|tray| codes(&tray))
+}
+
+/// The valid sets of a tray, as the object described by sets_to_json
+#[wasm_bindgen]
+pub fn valid_sets_json(tray: &str) -> Result<JsValue, JsValue> {
+    let tray = parse(tray)?;
+    JSON::parse(&sets_to_json(&tray, &valid_sets(&tray), false))
+}
+
+/// The sets playing the most tiles of a tray, as the object described by partition_to_json
+#[wasm_bindgen]
+pub fn best_partition_json(tray: &str) -> Result<JsValue, JsValue> {
+    let tray = parse(tray)?;
+    JSON::parse(&partition_to_json(&best_partition(&tray), false))
+}
+
+/// Deals the codes of tray_size tiles from a deck shuffled with the given seed
+#[wasm_bindgen]
+pub fn deal(seed: u64, tray_size: usize) -> Result<JsValue, JsValue> {
+    let mut deck = Deck::new_seeded(seed);
+    if tray_size > deck.len() {
+        return Err(Error::new(&format!("can't deal {} tiles: the deck has only {}", tray_size, deck.len())).into());
+    }
+    Ok(codes(&deck.pick_tray(tray_size)))
+}
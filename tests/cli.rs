@@ -0,0 +1,364 @@
+// The binary runs as a process, which wasm32 can't do, and needs the standard library
+#![cfg(all(feature = "std", not(target_arch = "wasm32")))]
+
+use assert_cmd::Command;
+use rummikub::{
+    best_partition, dead_tiles, display_meld, score_meld, trace_sets, valid_sets_with, validate_set, Card, Color, Deck,
+    SetOrder, SetSearchOptions
+};
+
+fn rummikub() -> Command {
+    Command::cargo_bin("rummikub").unwrap()
+}
+
+// What solve prints for the tray: the sets, the best worth the most unless the options say
+// otherwise, then the summary
+fn sets_output(tray: &[Card], options: &SetSearchOptions) -> String {
+    let points = |set: &[&Card]| score_meld(&set.iter().map(|c| **c).collect::<Vec<Card>>()).unwrap();
+    let sets = valid_sets_with(tray, options);
+    let mut output = "Valid sets:\n".to_string();
+    for set in &sets {
+        let kind = validate_set(&set.iter().map(|c| **c).collect::<Vec<Card>>()).unwrap();
+        output += &format!(" -> {} ({} points, {})\n", display_meld(set), points(set), kind.name());
+    }
+    let best = best_partition(tray);
+    output += "Summary:\n";
+    output += &format!(" sets found: {}\n", sets.len());
+    output += &format!(" best meld: {} points\n", sets.iter().map(|set| points(set)).max().unwrap());
+    let partition_points: u32 = best.sets.iter().map(|set| points(&set.iter().collect::<Vec<&Card>>())).sum();
+    output += &format!(" best partition: {} tiles, {} points\n", best.played_count(), partition_points);
+    output += &format!(" dead tiles: {}\n", dead_tiles(tray).len());
+    output
+}
+
+// The order of solve in the text format
+fn by_points() -> SetSearchOptions {
+    SetSearchOptions { order: SetOrder::Points, ..SetSearchOptions::default() }
+}
+
+fn stdout(command: &mut Command) -> String {
+    let output = command.output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn deal_seeded_test() {
+    let output = stdout(rummikub().args(["deal", "--players", "2", "--tray-size", "14", "--seed", "42"]));
+
+    let mut deck = Deck::new_seeded(42);
+    let mut expected = String::new();
+    for player in 1..=2 {
+        let mut tray = deck.pick_tray(14);
+        tray.sort();
+        expected += &format!("Player {}:\n", player);
+        for card in tray {
+            expected += &format!(" - {}\n", card);
+        }
+    }
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn solve_theme_test() {
+    let output = stdout(rummikub().args(["solve", "R4 W R6", "--theme", "colorblind"]));
+    assert!(output.starts_with("Valid sets:\n -> ●4 ●6 ★(●5) (15 points, run)\n"), "{}", output);
+
+    let output = stdout(rummikub().args(["deal", "--tray-size", "2", "--seed", "1", "--theme", "ansi"]));
+    assert!(output.contains("\x1b["), "{}", output);
+    rummikub().args(["solve", "R4 W R6", "--theme", "plain", "--format", "json"]).assert().code(2);
+}
+
+#[test]
+fn deal_too_many_tiles_test() {
+    rummikub().args(["deal", "--players", "8"]).assert().code(2);
+}
+
+#[test]
+fn solve_test() {
+    let output = stdout(rummikub().args(["solve", "B2 B3 B4 R7 W"]));
+
+    let tray = vec![
+        Card::new(2, Color::Blue),
+        Card::new(3, Color::Blue),
+        Card::new(4, Color::Blue),
+        Card::new(7, Color::Red),
+        Card::Wildcard
+    ];
+    assert_eq!(output, sets_output(&tray, &by_points()));
+}
+
+#[test]
+fn solve_summary_test() {
+    let output = stdout(rummikub().args(["solve", "R10 R11 R12 K7 B7 Y7 W R2 B9"]));
+
+    assert_eq!(output.lines().nth(1), Some(" -> 10 Red 11 Red 12 Red W(13 Red) (46 points, run)"));
+    assert!(output.ends_with("\
+Summary:
+ sets found: 10
+ best meld: 46 points
+ best partition: 7 tiles, 67 points
+ dead tiles: 1
+"), "{}", output);
+
+    let output = stdout(rummikub().args(["solve", "R1 B5 K9", "--partition"]));
+    assert_eq!(output, "No plays possible: the tray makes no valid set\n");
+}
+
+#[test]
+fn solve_verbose_test() {
+    let output = rummikub().args(["solve", "--verbose", "R4 R6 R8 W"]).output().unwrap();
+    assert!(output.status.success());
+
+    let tray: Vec<Card> = ["R4", "R6", "R8", "W"].iter().map(|code| code.parse().unwrap()).collect();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), sets_output(&tray, &by_points()));
+    let trace = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(trace, trace_sets(&tray, &SetSearchOptions::default()).to_string());
+    assert!(trace.contains("run of Red from 4 (copy 0), 5 tiles: rejected, 2 missing and 1 wildcards\n"));
+}
+
+#[test]
+fn solve_partition_test() {
+    let output = stdout(rummikub().args(["solve", "B2", "B3", "B4", "R7", "W", "--partition", "--maximal"]));
+
+    assert_eq!(output, "\
+Valid sets:
+ -> 2 Blue 3 Blue 4 Blue W(5 Blue) (14 points, run)
+Best partition:
+ -> 2 Blue 3 Blue 4 Blue W(5 Blue) (14 points, run)
+Left in hand:
+ - 7 Red
+Summary:
+ sets found: 1
+ best meld: 14 points
+ best partition: 4 tiles, 14 points
+ dead tiles: 1
+");
+}
+
+#[test]
+fn solve_order_and_min_len_test() {
+    let output = stdout(rummikub().args(["solve", "R11 R12 R13 K1 Y1 B1 W", "--order", "points", "--min-len", "4"]));
+
+    let tray: Vec<Card> = (11..=13).map(|n| Card::new(n, Color::Red))
+        .chain([Card::new(1, Color::Black), Card::new(1, Color::Yellow), Card::new(1, Color::Blue), Card::Wildcard])
+        .collect();
+    let options = SetSearchOptions { min_len: 4, order: SetOrder::Points, ..SetSearchOptions::default() };
+    assert_eq!(output, sets_output(&tray, &options));
+    assert_eq!(output.lines().nth(1), Some(" -> 11 Red 12 Red 13 Red W(10 Red) (46 points, run)"));
+}
+
+#[test]
+fn solve_unknown_tile_test() {
+    let output = rummikub().args(["solve", "B2 B3 Q4"]).assert().code(2).get_output().clone();
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("unknown tile 'Q4'"), "{}", stderr);
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn solve_from_file_test() {
+    let path = std::env::temp_dir().join(format!("rummikub-hand-{}.txt", std::process::id()));
+    std::fs::write(&path, "# Hand from a real game\nB2 B3\nB4\n\nR7 W\n").unwrap();
+
+    let output = stdout(rummikub().args(["solve", "--file"]).arg(&path));
+    std::fs::remove_file(&path).unwrap();
+
+    let tray = vec![
+        Card::new(2, Color::Blue),
+        Card::new(3, Color::Blue),
+        Card::new(4, Color::Blue),
+        Card::new(7, Color::Red),
+        Card::Wildcard
+    ];
+    assert_eq!(output, sets_output(&tray, &by_points()));
+}
+
+#[test]
+fn solve_position_test() {
+    let path = std::env::temp_dir().join(format!("rummikub-position-{}.txt", std::process::id()));
+    std::fs::write(&path, "table:\nB4-B5-B6-B7-B8\nhand:\nB6 R9\n").unwrap();
+    let output = stdout(rummikub().args(["solve-position", "--theme", "plain", "--file"]).arg(&path));
+    std::fs::write(&path, "table:\nB4-B5-B6\nB4-B5-B6\nhand:\nB6\n").unwrap();
+    let error = rummikub().args(["solve-position", "--file"]).arg(&path).output().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(output, "Play:\n - B6\nTable after the turn:\n -> B4 B5 B6 (15 points, run)\n -> B6 B7 B8 (21 points, run)\nLeft in hand:\n - R9\n");
+    assert_eq!(error.status.code(), Some(2));
+    assert!(String::from_utf8(error.stderr).unwrap().contains("B6 is there more times than the deck holds it"));
+}
+
+#[test]
+fn solve_html_test() {
+    let output = stdout(rummikub().args(["solve", "R7 B7 K7 Y2", "--format", "html"]));
+
+    assert!(output.starts_with("<!DOCTYPE html>\n"));
+    assert!(output.contains("<div class=\"tray\"><span class=\"tile red\" data-sets=\"0\">R7</span>"));
+    assert!(output.contains("<div class=\"set\" data-set=\"0\">"));
+    assert!(!output.contains("data-set=\"1\""));
+
+    let path = std::env::temp_dir().join(format!("rummikub-position-html-{}.txt", std::process::id()));
+    std::fs::write(&path, "table:\nB4-B5-B6-B7-B8\nhand:\nB6 R9\n").unwrap();
+    let output = stdout(rummikub().args(["solve-position", "--format", "html", "--file"]).arg(&path));
+    let themed = rummikub().args(["solve-position", "--format", "html", "--theme", "plain", "--file"]).arg(&path).output().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(output.contains("<div class=\"meld\" data-meld=\"#0\"><span class=\"tile blue\" data-sets=\"0\">B4</span>"));
+    assert!(output.contains("<span class=\"tile blue\" data-sets=\"1\">B6</span><span class=\"tile red\">R9</span>"));
+    assert_eq!(themed.status.code(), Some(2));
+}
+
+#[test]
+fn solve_from_stdin_test() {
+    let output = stdout(rummikub().arg("solve").write_stdin("K5\nK6 # middle\nK7\n"));
+
+    assert!(output.starts_with("Valid sets:\n -> 5 Black 6 Black 7 Black (18 points, run)\nSummary:\n"), "{}", output);
+}
+
+#[test]
+fn solve_file_error_test() {
+    let output = rummikub().args(["solve", "--file", "-"])
+        .write_stdin("R1 R2\n\nR3 R14\n")
+        .assert()
+        .code(2)
+        .get_output()
+        .clone();
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error: <stdin>:3: unknown tile 'R14'"), "{}", stderr);
+}
+
+#[test]
+fn solve_json_test() {
+    let output = stdout(rummikub().args(["solve", "K11 K12 W Y5", "--format", "json"]));
+
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["version"], 1);
+    assert_eq!(json["tray"].as_array().unwrap().len(), 4);
+    assert_eq!(json["tray"][2]["wildcard"], true);
+    assert!(json["tray"][2]["number"].is_null());
+
+    let sets = json["sets"].as_array().unwrap();
+    assert_eq!(sets.len(), 1);
+    assert_eq!(sets[0]["kind"], "run");
+    assert_eq!(sets[0]["points"], 36);
+    let codes: Vec<&str> = sets[0]["cards"].as_array().unwrap()
+        .iter()
+        .map(|c| c["code"].as_str().unwrap())
+        .collect();
+    assert_eq!(codes, ["K11", "K12", "W"]);
+    assert_eq!(sets[0]["cards"][2]["number"], 13);
+    assert_eq!(sets[0]["cards"][2]["color"], "black");
+}
+
+#[test]
+fn solve_json_compact_test() {
+    let pretty = stdout(rummikub().args(["solve", "R1 B1 K1", "--format", "json"]));
+    let compact = stdout(rummikub().args(["solve", "R1 B1 K1", "--format", "json", "--compact"]));
+
+    assert_eq!(compact.lines().count(), 1);
+    assert!(pretty.lines().count() > 1);
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&compact).unwrap(),
+        serde_json::from_str::<serde_json::Value>(&pretty).unwrap()
+    );
+}
+
+#[test]
+fn solve_csv_test() {
+    let output = stdout(rummikub().args(["solve", "R7 B7 K7 Y2", "--format", "csv", "--hand-id", "h1"]));
+
+    assert_eq!(output, "hand_id,kind,length,points,cards\nh1,group,3,21,R7-B7-K7\n");
+}
+
+#[test]
+fn solve_csv_summary_test() {
+    let output = stdout(rummikub().args(["solve", "R7 B7 K7 Y2 W", "--format", "csv-summary"]));
+
+    assert_eq!(output.lines().next(), Some("hand_id,tiles,wildcards,sets,best_partition"));
+    assert!(output.lines().nth(1).unwrap().starts_with("1,5,1,"), "{}", output);
+}
+
+#[test]
+fn play_test() {
+    let tray = Deck::new_seeded(7).pick_tray(14);
+    let output = stdout(rummikub().args(["play", "--seed", "7"]).write_stdin("draw\nsort number\nquit\n"));
+
+    let codes: Vec<String> = tray.iter().map(|c| c.code()).collect();
+    assert!(output.contains(&format!(" {}\n", codes.join(" "))), "{}", output);
+    assert!(output.contains("You drew "));
+    assert!(output.contains("(91 tiles left in the deck)"));
+    assert!(output.ends_with("Bye\n"));
+}
+
+#[test]
+fn play_with_bots_test() {
+    let output = stdout(rummikub().args(["play", "--bots", "3", "--seed", "7"]).write_stdin("draw\nquit\n"));
+
+    assert!(output.contains("(50 tiles left in the deck)"), "{}", output);
+    assert!(output.contains("Bot 3 "));
+    assert!(output.ends_with("Bye\n"));
+
+    rummikub().args(["play", "--bots", "7"]).assert().failure();
+    rummikub().args(["play", "--bots", "1", "--tray-size", "10"]).assert().failure();
+}
+
+#[test]
+fn play_hot_seat_test() {
+    let output = stdout(rummikub().args(["play", "--players", "alice", "bob", "carol", "--seed", "7"]).write_stdin("\ndraw\n\nquit\n"));
+
+    assert!(output.contains("Press enter when alice has the device\n"), "{}", output);
+    assert!(output.contains("(64 tiles left in the deck)"));
+    assert!(output.contains("Press enter when bob has the device\n"));
+    assert!(!output.contains("carol has the device"));
+    assert!(output.ends_with("Bye\n"));
+
+    rummikub().args(["play", "--players", "alice"]).assert().failure();
+    rummikub().args(["play", "--players", "a", "b", "c", "d", "e", "f", "g", "h"]).assert().code(2);
+    rummikub().args(["play", "--players", "alice", "bob", "--bots", "1"]).assert().failure();
+}
+
+#[test]
+fn tournament_test() {
+    let output = stdout(rummikub().args(["tournament", "--games", "2", "--seed", "3", "--json", "greedy", "hightile"]));
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    assert_eq!(json["seed"], 3);
+    assert_eq!(json["strategies"][1]["name"], "hightile");
+    assert_eq!(json["pairings"][0]["games"], 2);
+
+    let text = stdout(rummikub().args(["tournament", "--games", "2", "--seed", "3", "greedy", "random"]));
+    assert!(text.starts_with("2 games per pairing, seed 3\n"));
+    assert!(text.contains("greedy vs random: "));
+    rummikub().args(["tournament", "greedy"]).assert().failure();
+}
+
+#[test]
+fn simulate_test() {
+    let output = stdout(rummikub().args(["simulate", "--hands", "200", "--seed", "3", "--stat", "wildcard-hands", "--json"]));
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    assert_eq!(json["stat"], "wildcard-hands");
+    assert_eq!(json["mean"], 0.25);
+    assert_eq!(json["buckets"][0]["count"], 152);
+
+    let text = stdout(rummikub().args(["simulate", "--hands", "100", "--seed", "3", "--stat", "opening30"]));
+    assert!(text.starts_with("opening30: 100 trays of 14 tiles, seed 3\nmean 0.4200"));
+    rummikub().args(["simulate", "--tray-size", "200", "--stat", "opening30"]).assert().code(2);
+}
+
+#[test]
+fn stats_test() {
+    let output = stdout(rummikub().args(["stats", "--samples", "200", "--seed", "3", "--json"]));
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    assert_eq!(json["median_sets"], 2);
+    assert_eq!(json["p_any_set"], 0.785);
+    assert_eq!(json["set_counts"][0]["count"], 43);
+
+    let text = stdout(rummikub().args(["stats", "--samples", "200", "--seed", "3"]));
+    assert!(text.starts_with("200 trays of 14 tiles, seed 3\nvalid sets: mean 3.99, median 2\nat least one set: 78.5%\n"));
+    assert!(text.contains("      1:      56  28.0%\n"));
+    rummikub().args(["stats", "--tray-size", "200"]).assert().code(2);
+}
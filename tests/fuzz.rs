@@ -0,0 +1,96 @@
+// Arbitrary trays for the finders: any number a Card can hold, up to 20 wildcards and the
+// twist jokers. Every case must come back without panicking, with sets that are valid and
+// taken from the tray. PROPTEST_CASES raises the 256 cases proptest runs by default; the
+// suite ran clean with PROPTEST_CASES=100000.
+#![cfg(all(feature = "std", not(target_arch = "wasm32")))]
+
+use std::collections::BTreeMap;
+use proptest::prelude::*;
+use rummikub::{best_partition, count_valid_sets, has_valid_set, valid_sets, validate_set, validate_set_with, Card, Color, RulesConfig};
+
+fn any_card() -> impl Strategy<Value = Card> {
+    prop_oneof![
+        6 => (any::<i8>(), 0..Color::SIX.len()).prop_map(|(number, color)| Card::Numbered { number, color: Color::SIX[color] }),
+        6 => (-1..=15i8, 0..Color::ALL.len()).prop_map(|(number, color)| Card::Numbered { number, color: Color::ALL[color] }),
+        3 => Just(Card::Wildcard),
+        1 => Just(Card::DoubleJoker),
+        1 => Just(Card::MirrorJoker)
+    ]
+}
+
+fn any_tray() -> impl Strategy<Value = Vec<Card>> {
+    (prop::collection::vec(any_card(), 0..16), 0..=20usize).prop_map(|(mut tray, wildcards)| {
+        // Spread through the tray rather than all at the end
+        for idx in 0..wildcards {
+            tray.insert((idx * 7) % (tray.len() + 1), Card::Wildcard);
+        }
+        tray
+    })
+}
+
+fn counts<'a>(cards: impl IntoIterator<Item = &'a Card>) -> BTreeMap<Card, usize> {
+    let mut counts = BTreeMap::new();
+    for card in cards {
+        *counts.entry(*card).or_default() += 1;
+    }
+    counts
+}
+
+proptest! {
+    #[test]
+    fn validate_set_never_panics_test(set in prop::collection::vec(any_card(), 0..24)) {
+        let _ = validate_set(&set);
+        let _ = validate_set_with(&set, &RulesConfig::six_colors());
+        let _ = validate_set_with(&set, &RulesConfig { twist_jokers: true, wrap_runs: true, ..RulesConfig::default() });
+    }
+
+    #[test]
+    fn valid_sets_are_valid_test(tray in any_tray()) {
+        let held = counts(&tray);
+        for set in valid_sets(&tray) {
+            let cards: Vec<Card> = set.iter().map(|c| **c).collect();
+            prop_assert!(validate_set(&cards).is_ok(), "{:?}", cards);
+            prop_assert!(counts(&cards).iter().all(|(card, count)| held.get(card) >= Some(count)), "{:?}", cards);
+        }
+    }
+
+    #[test]
+    fn count_and_has_valid_set_agree_test(tray in any_tray()) {
+        let sets = valid_sets(&tray);
+        prop_assert_eq!(count_valid_sets(&tray), sets.len());
+        prop_assert_eq!(has_valid_set(&tray), !sets.is_empty());
+    }
+
+    #[test]
+    fn best_partition_uses_the_tray_test(tray in any_tray()) {
+        let partition = best_partition(&tray);
+        for set in &partition.sets {
+            prop_assert!(validate_set(set).is_ok(), "{:?}", set);
+        }
+        let used = counts(partition.sets.iter().flatten().chain(&partition.leftover));
+        prop_assert_eq!(used, counts(&tray));
+    }
+}
+
+// Crashers and slow cases found along the way stay here
+#[test]
+fn absurd_trays_test() {
+    let out_of_range: Vec<Card> = [0, 14, -5, i8::MIN, i8::MAX].into_iter().map(|number| Card::Numbered { number, color: Color::Red }).collect();
+    assert!(valid_sets(&out_of_range).is_empty());
+    assert_eq!(best_partition(&out_of_range).leftover.len(), 5);
+
+    let wildcards = vec![Card::Wildcard; 10];
+    assert!(valid_sets(&wildcards).is_empty());
+    assert_eq!(validate_set(&wildcards), Err(rummikub::SetError::AllWildcards));
+    assert_eq!(best_partition(&wildcards).leftover.len(), 10);
+
+    // The wildcards of a group used to make a set of their own
+    let three = vec![Card::Wildcard, Card::Wildcard, Card::Wildcard, Card::new(1, Color::Red)];
+    assert!(valid_sets(&three).iter().all(|set| set.contains(&&three[3])), "{:?}", valid_sets(&three));
+
+    // K3 to K13
+    let mut with_one = wildcards.clone();
+    with_one.push(Card::new(13, Color::Black));
+    assert_eq!(valid_sets(&with_one).iter().map(|set| set.len()).max(), Some(11));
+    assert_eq!(best_partition(&with_one).played_count(), 11);
+}
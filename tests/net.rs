@@ -0,0 +1,69 @@
+// cargo test --features net --test net
+#![cfg(feature = "net")]
+
+use std::thread;
+use rummikub::net::{Client, Host, HostMessage, NetError};
+use rummikub::{Move, MoveError};
+
+// Draws and ends every turn until the round is over, returning the winner
+fn draw_until_over(mut client: Client) -> usize {
+    let mut played_turn = None;
+    loop {
+        match client.next_message().unwrap() {
+            HostMessage::State(state) if state.current_player == client.player && played_turn != Some(state.turn_number) => {
+                played_turn = Some(state.turn_number);
+                assert_eq!(state.tray.len(), state.players[client.player].tile_count);
+                if state.deck_len > 0 {
+                    client.play(Move::Draw).unwrap();
+                }
+                client.play(Move::EndTurn).unwrap();
+            },
+            HostMessage::GameOver { winner, .. } => return winner,
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn two_clients_test() {
+    let host = Host::bind("127.0.0.1:0").unwrap();
+    let addr = host.local_addr().unwrap();
+    let game = thread::spawn(move || host.run(2, 11));
+
+    // Speaking none of the versions of the host doesn't take a seat
+    let rejected = Client::connect_with_versions(addr, "old", &[0]);
+    assert!(matches!(rejected, Err(NetError::Rejected(_))), "{:?}", rejected.err());
+
+    let first = Client::connect(addr, "Ann").unwrap();
+    let mut second = Client::connect(addr, "Bob").unwrap();
+    assert_eq!((first.player, second.player), (0, 1));
+    assert_eq!(first.version, 1);
+    let first = thread::spawn(move || draw_until_over(first));
+
+    // Bob gets their first turn, draws, and leaves before ending it
+    let state = loop {
+        let state = second.next_state().unwrap();
+        if state.current_player == 1 {
+            break state;
+        }
+    };
+    assert_eq!(state.players[0].name, "Ann");
+    assert_eq!(state.tray.len(), 14);
+    second.play(Move::EndTurn).unwrap();
+    assert_eq!(second.next_message().unwrap(), HostMessage::MoveRejected { reason: MoveError::MustDrawOrPlay.to_string() });
+    second.play(Move::Draw).unwrap();
+    assert_eq!(second.next_state().unwrap().tray.len(), 15);
+    drop(second);
+
+    let winner = first.join().unwrap();
+    let game = game.join().unwrap().unwrap();
+    let result = game.round_result().unwrap();
+    assert_eq!(result.winner, winner);
+    assert_eq!(game.deck_len(), 0);
+    // The draw of Bob was taken back, and the host drew for them
+    let first_turn: Vec<&Move> = game.events().iter()
+        .filter(|event| event.turn == state.turn_number)
+        .map(|event| &event.mv)
+        .collect();
+    assert_eq!(first_turn, vec![&Move::Draw, &Move::EndTurn]);
+}
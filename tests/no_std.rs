@@ -0,0 +1,26 @@
+// cargo test --no-default-features --test no_std
+#![cfg(not(feature = "std"))]
+
+use rummikub::{best_partition, can_go_out, valid_sets, validate_set, Card, Color, Deck, MeldKind};
+
+#[test]
+fn core_without_std_test() {
+    let tray = vec![
+        Card::new(4, Color::Blue),
+        Card::new(5, Color::Blue),
+        Card::new(6, Color::Blue),
+        Card::new(9, Color::Red),
+        Card::new(9, Color::Black),
+        Card::Wildcard
+    ];
+
+    assert_eq!(validate_set(&tray[..3]), Ok(MeldKind::Run));
+    assert!(valid_sets(&tray).len() > 2);
+    assert_eq!(best_partition(&tray).played_count(), 6);
+    assert!(can_go_out(&tray).is_some());
+
+    // The seeded shuffles don't need the standard library
+    let mut deck = Deck::new_seeded(3);
+    assert_eq!(deck.pick_tray(14), Deck::new_seeded(3).pick_tray(14));
+    assert_eq!(deck.len(), 106 - 14);
+}
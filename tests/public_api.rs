@@ -0,0 +1,95 @@
+use rummikub::{
+    best_partition, can_go_out, dead_tiles, find_almost_sets, find_opening_melds, find_runs,
+    find_same_numbers, resolve_wildcards, valid_sets, valid_sets_batch, valid_sets_from_index,
+    valid_sets_maximal, valid_sets_with, validate_set, Card, Color, Deck, MeldKind, SetError,
+    SetSearchOptions, TrayIndex
+};
+
+fn owned(sets: Vec<Vec<&Card>>) -> Vec<Vec<Card>> {
+    sets.into_iter().map(|s| s.into_iter().copied().collect()).collect()
+}
+
+fn test_tray() -> Vec<Card> {
+    vec![
+        Card::new(5, Color::Red),
+        Card::new(6, Color::Red),
+        Card::new(7, Color::Red),
+        Card::new(7, Color::Blue),
+        Card::new(7, Color::Black),
+        Card::new(12, Color::Yellow),
+        Card::Wildcard
+    ]
+}
+
+#[test]
+fn deck_test() {
+    let mut deck = Deck::new_seeded(1);
+    assert_eq!(deck.len(), 106);
+
+    let tray = deck.pick_tray(14);
+
+    assert_eq!(tray.len(), 14);
+    assert_eq!(deck.len(), 92);
+    assert_eq!(Deck::new_seeded(1).pick_tray(14), tray);
+}
+
+#[test]
+fn valid_sets_test() {
+    let test_tray = test_tray();
+
+    let sets = owned(valid_sets(&test_tray));
+
+    assert!(sets.contains(&vec![Card::new(5, Color::Red), Card::new(6, Color::Red), Card::new(7, Color::Red)]));
+    assert!(sets.contains(&vec![Card::new(7, Color::Red), Card::new(7, Color::Blue), Card::new(7, Color::Black)]));
+    for set in &sets {
+        assert!(validate_set(set).is_ok(), "{:?}", set);
+    }
+    assert_eq!(owned(valid_sets_from_index(&TrayIndex::new(&test_tray))), sets);
+    assert_eq!(valid_sets_batch(std::slice::from_ref(&test_tray)), vec![sets]);
+}
+
+#[test]
+fn set_searches_test() {
+    let test_tray = test_tray();
+
+    let runs = find_runs(&test_tray);
+    let groups = find_same_numbers(&test_tray);
+    let only_groups = valid_sets_with(&test_tray, &SetSearchOptions {
+        include_runs: false,
+        ..SetSearchOptions::default()
+    });
+
+    assert!(!runs.is_empty());
+    assert!(!groups.is_empty());
+    assert!(owned(only_groups).iter().all(|s| validate_set(s) == Ok(MeldKind::Group)));
+    assert!(valid_sets_maximal(&test_tray).len() < valid_sets(&test_tray).len());
+}
+
+#[test]
+fn validation_test() {
+    let run = [Card::new(3, Color::Blue), Card::Wildcard, Card::new(5, Color::Blue)];
+    let gap = [Card::new(3, Color::Blue), Card::new(6, Color::Blue), Card::Wildcard];
+
+    assert_eq!(validate_set(&run), Ok(MeldKind::Run));
+    assert_eq!(validate_set(&gap), Err(SetError::GapInRun { missing: 5 }));
+
+    let refs: Vec<&Card> = run.iter().collect();
+    assert_eq!(
+        resolve_wildcards(&refs),
+        Some(vec![(3, Color::Blue), (4, Color::Blue), (5, Color::Blue)])
+    );
+}
+
+#[test]
+fn solvers_test() {
+    let test_tray = test_tray();
+
+    let partition = best_partition(&test_tray);
+    let openings = find_opening_melds(&test_tray);
+
+    assert_eq!(partition.played_count() + partition.leftover.len(), test_tray.len());
+    assert!(can_go_out(&test_tray).is_none());
+    assert!(!openings.is_empty());
+    assert_eq!(dead_tiles(&test_tray), vec![&Card::new(12, Color::Yellow)]);
+    assert!(!find_almost_sets(&test_tray).is_empty());
+}
@@ -0,0 +1,49 @@
+# Run with: maturin develop && pytest tests/python
+import pytest
+
+import rummikub
+from rummikub import Card, Color, Deck
+
+
+def test_card():
+    card = Card("b2")
+
+    assert card.number == 2
+    assert card.color == Color.Blue
+    assert card.code == "B2"
+    assert not card.is_wildcard
+    assert Card("W").is_wildcard and Card("W").color is None
+    assert Card.numbered(2, Color.Blue) == card
+    assert repr(card) == "Card('B2')"
+
+
+def test_card_parse_error():
+    with pytest.raises(ValueError, match="unknown tile 'Q4'"):
+        Card("Q4")
+    with pytest.raises(ValueError, match="unknown tile 'R14'"):
+        rummikub.valid_sets(["R12", "R13", "R14"])
+
+
+def test_deck():
+    deck = Deck.new_seeded(1)
+
+    tray = deck.pick_tray(14)
+
+    assert len(tray) == 14
+    assert len(deck) == 92
+    assert Deck.new_seeded(1).pick_tray(14) == tray
+    assert len(Deck.new_shuffled()) == 106
+
+
+def test_valid_sets():
+    sets = rummikub.valid_sets(["B2", "B3", "B4", "W"])
+
+    assert [Card("B2"), Card("B3"), Card("B4")] in sets
+    assert ["B2", "B3", "B4"] in rummikub.valid_set_codes(["B2", Card("B3"), "b4", "w"])
+
+
+def test_best_partition():
+    sets, leftover = rummikub.best_partition(["R1", "R2", "R3", "Y9"])
+
+    assert sets == [[Card("R1"), Card("R2"), Card("R3")]]
+    assert leftover == [Card("Y9")]
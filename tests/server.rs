@@ -0,0 +1,63 @@
+#![cfg(feature = "server")]
+
+use axum::body::{to_bytes, Body};
+use axum::http::{Request, StatusCode};
+use serde_json::Value;
+use tower::ServiceExt;
+use rummikub::server::{router, MAX_BODY_LEN};
+
+// Posts the body to the router, giving the status and the JSON answer
+async fn post(uri: &str, body: impl Into<Body>) -> (StatusCode, Value) {
+    let request = Request::post(uri).header("content-type", "application/json").body(body.into()).unwrap();
+    let response = router().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn solve_test() {
+    let (status, json) = post("/solve", r#"{"tray": ["R4", "R5", "R6", "R7", "B7", "K7"]}"#).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["tray"].as_array().unwrap().len(), 6);
+    assert!(json["sets"].as_array().unwrap().len() >= 4);
+
+    let (status, json) = post("/solve?min_len=4&maximal=true", r#"{"tray": ["R4", "R5", "R6", "R7", "B7", "K7"]}"#).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["sets"].as_array().unwrap().len(), 1);
+    assert_eq!(json["sets"][0]["points"], 22);
+}
+
+#[tokio::test]
+async fn partition_and_validate_test() {
+    let (status, json) = post("/partition", r#"{"tray": ["R4", "R5", "R6", "B9"]}"#).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["sets"][0]["kind"], "run");
+    assert_eq!(json["leftover"][0]["code"], "B9");
+
+    let (status, json) = post("/validate-set", r#"{"set": ["R7", "W", "B7"]}"#).await;
+    assert_eq!((status, &json["valid"]), (StatusCode::OK, &Value::Bool(true)));
+    let (status, json) = post("/validate-set", r#"{"set": ["R7", "B8"]}"#).await;
+    assert_eq!((status, &json["valid"]), (StatusCode::OK, &Value::Bool(false)));
+}
+
+#[tokio::test]
+async fn malformed_tile_test() {
+    let (status, json) = post("/solve", r#"{"tray": ["R4", "X5"]}"#).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(json["error"].as_str().unwrap().contains("X5"), "{}", json);
+
+    let (status, _) = post("/partition", "not json").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn oversized_request_test() {
+    let tray = format!(r#"{{"tray": [{}]}}"#, vec![r#""R1""#; 50].join(", "));
+    let (status, json) = post("/solve", tray).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert!(json["error"].as_str().unwrap().starts_with("50 tiles"));
+
+    let (status, _) = post("/solve", " ".repeat(MAX_BODY_LEN + 1)).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+}
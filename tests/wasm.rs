@@ -0,0 +1,55 @@
+// Runs on node with wasm-bindgen-test-runner as the cargo runner of wasm32-unknown-unknown:
+// cargo test --target wasm32-unknown-unknown --features wasm --test wasm
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use js_sys::{Array, Error, JsString, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_test::wasm_bindgen_test;
+use rummikub::wasm::{best_partition_json, deal, parse_tray, valid_sets_json};
+
+fn get(value: &JsValue, key: &str) -> JsValue {
+    Reflect::get(value, &JsValue::from_str(key)).unwrap()
+}
+
+fn error_message(error: JsValue) -> String {
+    String::from(error.dyn_into::<Error>().unwrap().message())
+}
+
+#[wasm_bindgen_test]
+fn parse_tray_test() {
+    let codes: Array = parse_tray("b2 B3  w").unwrap().into();
+
+    assert_eq!(codes.to_vec(), vec![JsValue::from("B2"), JsValue::from("B3"), JsValue::from("W")]);
+    assert!(error_message(parse_tray("B2 Q4").unwrap_err()).contains("'Q4'"));
+}
+
+#[wasm_bindgen_test]
+fn valid_sets_json_test() {
+    let output = valid_sets_json("R7 B7 K7 Y2").unwrap();
+
+    assert_eq!(get(&output, "version"), JsValue::from(1));
+    let sets: Array = get(&output, "sets").into();
+    assert_eq!(sets.length(), 1);
+    assert_eq!(get(&sets.get(0), "kind"), JsValue::from("group"));
+    assert!(valid_sets_json("R7 B77").is_err());
+}
+
+#[wasm_bindgen_test]
+fn best_partition_json_test() {
+    let output = best_partition_json("R1 R2 R3 Y9").unwrap();
+
+    let sets: Array = get(&output, "sets").into();
+    let leftover: Array = get(&output, "leftover").into();
+    assert_eq!(sets.length(), 1);
+    assert_eq!(get(&leftover.get(0), "code").dyn_into::<JsString>().unwrap(), "Y9");
+    assert!(best_partition_json("").is_ok());
+}
+
+#[wasm_bindgen_test]
+fn deal_test() {
+    let tray: Array = deal(42, 14).unwrap().into();
+
+    assert_eq!(tray.length(), 14);
+    assert_eq!(Array::from(&deal(42, 14).unwrap()).to_vec(), tray.to_vec());
+    assert!(error_message(deal(42, 107).unwrap_err()).contains("only 106"));
+}